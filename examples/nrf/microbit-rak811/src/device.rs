@@ -7,16 +7,19 @@ use drogue_device::{
         uart::dma::DmaUart,
         uart::*,
     },
-    platform::cortex_m::nrf::{gpiote::*, timer::Timer as HalTimer, uarte::Uarte as HalUart},
+    platform::cortex_m::nrf::{
+        gpiote::*, rtc::RtcClock, timer::Timer as HalTimer, uarte::Uarte as HalUart,
+    },
     prelude::*,
 };
 use hal::gpio::{Input, Output, Pin, PullUp, PushPull};
-use hal::pac::{TIMER0, UARTE0};
+use hal::pac::{RTC0, TIMER0, UARTE0};
 use heapless::consts;
 
 use nrf52833_hal as hal;
 
-pub type AppTimer = Timer<HalTimer<TIMER0>>;
+pub type AppTimer = Timer<HalTimer<TIMER0>, RtcClock<RTC0>>;
+pub type AppClock = ClockInterrupt<RtcClock<RTC0>>;
 pub type AppUart =
     DmaUart<HalUart<UARTE0>, <AppTimer as Package>::Primary, consts::U64, consts::U64>;
 pub type Rak811Lora = rak811::Rak811<
@@ -35,6 +38,7 @@ pub struct LoraDevice {
     pub uart: AppUart,
     pub lora: Rak811Lora,
     pub timer: AppTimer,
+    pub clock: InterruptContext<AppClock>,
     pub app: ActorContext<App<AppLora>>,
 }
 
@@ -45,6 +49,7 @@ impl Device for LoraDevice {
         self.btn_connect.mount(config.event_bus, supervisor);
         self.btn_send.mount(config.event_bus, supervisor);
         let timer = self.timer.mount((), supervisor);
+        self.clock.mount(self.timer.clock(), supervisor);
         let uart = self.uart.mount(timer, supervisor);
         let lora = self.lora.mount((uart, timer), supervisor);
         self.app.mount(lora, supervisor);
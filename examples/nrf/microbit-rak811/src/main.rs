@@ -10,10 +10,11 @@ use drogue_device::{
     api::lora::*,
     driver::lora::*,
     driver::memory::Memory,
-    driver::timer::Timer,
+    driver::timer::{ClockInterrupt, Timer},
     driver::uart::dma::DmaUart,
     platform::cortex_m::nrf::{
         gpiote::*,
+        rtc::RtcClock,
         timer::Timer as HalTimer,
         uarte::{Baudrate, Parity, Pins, Uarte},
     },
@@ -61,7 +62,11 @@ fn main() -> ! {
     );
 
     // Timer
-    let timer = Timer::new(HalTimer::new(device.TIMER0), hal::pac::Interrupt::TIMER0);
+    let timer = Timer::new(
+        HalTimer::new(device.TIMER0),
+        RtcClock::new(device.RTC0),
+        hal::pac::Interrupt::TIMER0,
+    );
 
     // Uart
     let uart = DmaUart::new(
@@ -87,6 +92,7 @@ fn main() -> ! {
         lora: rak811::Rak811::new(port1.p1_02.into_push_pull_output(Level::High).degrade()),
         memory: ActorContext::new(Memory::new()).with_name("memory"),
         timer,
+        clock: InterruptContext::new(ClockInterrupt::new(), hal::pac::Interrupt::RTC0),
         app: ActorContext::new(App::new(
             LoraConfig::new()
                 .band(LoraRegion::EU868)
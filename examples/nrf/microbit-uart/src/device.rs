@@ -2,16 +2,17 @@ use drogue_device::{
     api::{delayer::*, uart::*},
     domain::time::duration::Milliseconds,
     driver::{led::*, timer::*, uart::dma::*},
-    platform::cortex_m::nrf::{gpiote::*, timer::Timer as HalTimer, uarte::Uarte},
+    platform::cortex_m::nrf::{gpiote::*, rtc::RtcClock, timer::Timer as HalTimer, uarte::Uarte},
     prelude::*,
 };
 use hal::gpio::{Input, Output, Pin, PullUp, PushPull};
-use hal::pac::{TIMER0, UARTE0};
+use hal::pac::{RTC0, TIMER0, UARTE0};
 use heapless::consts;
 use nrf52833_hal as hal;
 
 pub type Button = GpioteChannel<MyDevice, Pin<Input<PullUp>>>;
-pub type AppTimer = Timer<HalTimer<TIMER0>>;
+pub type AppTimer = Timer<HalTimer<TIMER0>, RtcClock<RTC0>>;
+pub type AppClock = ClockInterrupt<RtcClock<RTC0>>;
 pub type AppUart = DmaUart<Uarte<UARTE0>, <AppTimer as Package>::Primary, consts::U64, consts::U64>;
 pub type LedMatrix =
     LEDMatrix<Pin<Output<PushPull>>, consts::U5, consts::U5, <AppTimer as Package>::Primary>;
@@ -22,6 +23,7 @@ pub struct MyDevice {
     pub btn_fwd: ActorContext<Button>,
     pub btn_back: ActorContext<Button>,
     pub timer: AppTimer,
+    pub clock: InterruptContext<AppClock>,
     pub uart: AppUart,
     pub app: ActorContext<App<<AppUart as Package>::Primary, <AppTimer as Package>::Primary>>,
 }
@@ -33,6 +35,7 @@ impl Device for MyDevice {
         self.btn_back.mount(config.event_bus, supervisor);
 
         let timer = self.timer.mount((), supervisor);
+        self.clock.mount(self.timer.clock(), supervisor);
         let display = self.led.mount(timer, supervisor);
         let uart = self.uart.mount(timer, supervisor);
 
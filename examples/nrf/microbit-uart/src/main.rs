@@ -8,10 +8,11 @@ use panic_rtt_target as _;
 use cortex_m_rt::{entry, exception};
 use drogue_device::{
     domain::time::rate::Extensions,
-    driver::timer::Timer,
+    driver::timer::{ClockInterrupt, Timer},
     driver::uart::dma::DmaUart,
     platform::cortex_m::nrf::{
         gpiote::*,
+        rtc::RtcClock,
         timer::Timer as HalTimer,
         uarte::{Baudrate, Parity, Pins, Uarte},
     },
@@ -58,7 +59,11 @@ fn main() -> ! {
     );
 
     // Timer
-    let timer = Timer::new(HalTimer::new(device.TIMER0), hal::pac::Interrupt::TIMER0);
+    let timer = Timer::new(
+        HalTimer::new(device.TIMER0),
+        RtcClock::new(device.RTC0),
+        hal::pac::Interrupt::TIMER0,
+    );
 
     // Uart
     let uart = DmaUart::new(
@@ -110,6 +115,7 @@ fn main() -> ! {
         gpiote: InterruptContext::new(gpiote, hal::pac::Interrupt::GPIOTE).with_name("gpiote"),
         led: ActorContext::new(led).with_name("matrix"),
         timer,
+        clock: InterruptContext::new(ClockInterrupt::new(), hal::pac::Interrupt::RTC0),
         uart,
         app: ActorContext::new(App::new()),
     };
@@ -2,14 +2,16 @@ use crate::logic::Logic;
 use drogue_device::prelude::*;
 use drogue_device::domain::time::duration::Milliseconds;
 use drogue_device::driver::sensor::hts221::SensorAcquisition;
-use drogue_device::driver::spi::SpiController;
+use drogue_device::driver::sensor::lps22hb::{Lps22hb, PressureAcquisition, PressureBatch};
+use drogue_device::driver::sensor::lis3mdl::{Lis3mdl, MagneticFieldAcquisition};
+use drogue_device::driver::sensor::lsm6dsl::{Lsm6dsl, MotionAcquisition, MotionEvent};
+use drogue_device::driver::spi::dma::{DmaSpi, DmaSpiController};
 use drogue_device::driver::wifi::eswifi::EsWifi;
 use drogue_device::{
     domain::temperature::Celsius,
     driver::{
         i2c::I2c,
         memory::{Memory, Query},
-        spi::Spi,
     },
     hal::gpio::ActiveHigh,
 };
@@ -18,12 +20,13 @@ use drogue_device::{
         button::{Button, ButtonEvent},
         led::{Blinker, SimpleLED},
         sensor::hts221::Hts221,
-        timer::Timer,
+        timer::{ClockInterrupt, Timer},
     },
-    platform::cortex_m::stm32l4xx::timer::Timer as HardwareTimer,
+    platform::cortex_m::stm32l4xx::{clock::MonotonicTimer, timer::Timer as HardwareTimer},
+    platform::cortex_m::stm32l4xx::spi::DmaSpi3,
     prelude::*,
 };
-use stm32l4xx_hal::gpio::{PB13, PE0, PE1, PE13, PE8};
+use stm32l4xx_hal::gpio::{PB13, PC8, PD10, PD11, PE0, PE1, PE13, PE8};
 use stm32l4xx_hal::{
     gpio::{
         Alternate, Floating, Input, OpenDrain, Output, PullDown, PullUp, PushPull, AF4, AF6, PA5,
@@ -31,9 +34,8 @@ use stm32l4xx_hal::{
     },
     i2c::I2c as HalI2c,
     pac::I2C2,
-    pac::SPI3,
     pac::TIM15,
-    spi::Spi as HalSpi,
+    pac::TIM2,
 };
 
 type Ld1Pin = PA5<Output<PushPull>>;
@@ -48,19 +50,28 @@ type I2cSda = PB11<Alternate<AF4, Output<OpenDrain>>>;
 type I2cPeriph = HalI2c<I2C2, (I2cScl, I2cSda)>;
 type I2cPackage = I2c<I2cPeriph>;
 
-type TimerPackage = Timer<HardwareTimer<TIM15>>;
+type TimerPackage = Timer<HardwareTimer<TIM15>, MonotonicTimer<TIM2>>;
+type ClockInterruptActor = ClockInterrupt<MonotonicTimer<TIM2>>;
 
 type Blinker1Actor = Blinker<Ld1Actor, <TimerPackage as Package>::Primary>;
 type Blinker2Actor = Blinker<Ld2Actor, <TimerPackage as Package>::Primary>;
 
-type Hts221Package = Hts221<MyDevice, PD15<Input<PullDown>>, I2cPeriph>;
+type Hts221Package = Hts221<
+    MyDevice,
+    PD15<Input<PullDown>>,
+    I2cPeriph,
+    <TimerPackage as Package>::Primary,
+>;
+type Lis3mdlPackage = Lis3mdl<MyDevice, PC8<Input<PullDown>>, I2cPeriph>;
+type Lps22hbPackage = Lps22hb<MyDevice, PD10<Input<PullDown>>, I2cPeriph>;
+type Lsm6dslPackage = Lsm6dsl<MyDevice, PD11<Input<PullDown>>, I2cPeriph>;
 
 type SpiClk = PC10<Alternate<AF6, Input<Floating>>>;
 type SpiMiso = PC11<Alternate<AF6, Input<Floating>>>;
 type SpiMosi = PC12<Alternate<AF6, Input<Floating>>>;
 
-type HardwareSpi = HalSpi<SPI3, (SpiClk, SpiMiso, SpiMosi)>;
-type SpiPackage = Spi<HardwareSpi, u8>;
+type HardwareSpi = DmaSpi3<(SpiClk, SpiMiso, SpiMosi)>;
+type SpiPackage = DmaSpi<HardwareSpi>;
 
 type WifiCs = PE0<Output<PushPull>>;
 //type WifiCs = PE10<Output<PullUp>>;
@@ -69,7 +80,7 @@ type WifiReset = PE8<Output<PushPull>>;
 type WifiWakeup = PB13<Output<PushPull>>;
 
 type WifiAdapter = EsWifi<
-    SpiController<HardwareSpi, u8>,
+    DmaSpiController<HardwareSpi>,
     <TimerPackage as Package>::Primary,
     WifiCs,
     WifiReady,
@@ -89,12 +100,17 @@ pub struct MyDevice {
     pub button: InterruptContext<ButtonInterrupt>,
     pub i2c: I2cPackage,
     pub hts221: Hts221Package,
+    pub lis3mdl: Lis3mdlPackage,
+    pub lps22hb: Lps22hbPackage,
+    pub lsm6dsl: Lsm6dslPackage,
     pub timer: TimerPackage,
+    pub clock: InterruptContext<ClockInterruptActor>,
 }
 
 impl Device for MyDevice {
     fn mount(&'static self, config: DeviceConfiguration<Self>, supervisor: &mut Supervisor) {
         let timer_addr = self.timer.mount((), supervisor);
+        self.clock.mount(self.timer.clock(), supervisor);
 
         let spi_addr = self.spi.mount((), supervisor);
 
@@ -109,7 +125,11 @@ impl Device for MyDevice {
         self.blinker1.mount((ld1_addr, timer_addr), supervisor);
         self.blinker2.mount((ld2_addr, timer_addr), supervisor);
 
-        self.hts221.mount((config.event_bus, i2c_addr), supervisor);
+        self.hts221
+            .mount((config.event_bus, i2c_addr, timer_addr), supervisor);
+        self.lis3mdl.mount((config.event_bus, i2c_addr), supervisor);
+        self.lps22hb.mount((config.event_bus, i2c_addr), supervisor);
+        self.lsm6dsl.mount((config.event_bus, i2c_addr), supervisor);
 
         self.button.mount(config.event_bus, supervisor);
     }
@@ -148,3 +168,52 @@ impl EventHandler<SensorAcquisition<Celsius>> for MyDevice {
         self.logic.address().notify( message );
     }
 }
+
+impl EventHandler<MagneticFieldAcquisition> for MyDevice {
+    fn on_event(&'static self, message: MagneticFieldAcquisition)
+    where
+        Self: Sized,
+    {
+        log::trace!("[event-bus] magnetic field={:?}", message);
+    }
+}
+
+impl EventHandler<MotionAcquisition> for MyDevice {
+    fn on_event(&'static self, message: MotionAcquisition)
+    where
+        Self: Sized,
+    {
+        log::trace!("[event-bus] motion={:?}", message);
+    }
+}
+
+impl EventHandler<MotionEvent> for MyDevice {
+    fn on_event(&'static self, message: MotionEvent)
+    where
+        Self: Sized,
+    {
+        log::info!("[event-bus] motion event {:?}", message);
+    }
+}
+
+impl EventHandler<PressureAcquisition> for MyDevice {
+    fn on_event(&'static self, message: PressureAcquisition)
+    where
+        Self: Sized,
+    {
+        log::info!(
+            "[event-bus] pressure={:.2} altitude={:.1}",
+            message.pressure,
+            message.altitude
+        );
+    }
+}
+
+impl EventHandler<PressureBatch> for MyDevice {
+    fn on_event(&'static self, message: PressureBatch)
+    where
+        Self: Sized,
+    {
+        log::info!("[event-bus] {} pressure samples", message.samples.len());
+    }
+}
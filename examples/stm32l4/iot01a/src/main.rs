@@ -9,7 +9,7 @@ use cortex_m_rt::{entry, exception};
 use stm32l4xx_hal::{
     gpio::Edge,
     i2c::I2c as HalI2c,
-    pac::interrupt::{EXTI15_10, TIM15},
+    pac::interrupt::{EXTI15_10, EXTI9_5, TIM15, TIM2},
     prelude::*,
     rcc::RccExt,
     stm32::Peripherals,
@@ -21,7 +21,8 @@ use panic_rtt_target as _;
 use rtt_logger::RTTLogger;
 use rtt_target::rtt_init_print;
 
-use drogue_device::driver::spi::Spi;
+use drogue_device::driver::spi::dma::DmaSpi;
+use drogue_device::platform::cortex_m::stm32l4xx::spi::DmaSpi3;
 use drogue_device::driver::wifi::eswifi::EsWifi;
 use drogue_device::{
     domain::time::duration::Milliseconds,
@@ -30,15 +31,16 @@ use drogue_device::{
         i2c::I2c,
         led::{Blinker, SimpleLED},
         memory::Memory,
-        sensor::hts221::Hts221,
-        timer::Timer,
+        sensor::{hts221::Hts221, lis3mdl::Lis3mdl, lps22hb::Lps22hb, lsm6dsl::Lsm6dsl},
+        timer::{ClockInterrupt, Timer},
     },
-    platform::cortex_m::stm32l4xx::timer::Timer as McuTimer,
+    platform::cortex_m::stm32l4xx::{clock::MonotonicTimer, timer::Timer as McuTimer},
     hal::Active,
     prelude::*,
 };
 use embedded_hal::spi::{Mode, MODE_0};
-use stm32l4xx_hal::pac::Interrupt::{EXTI1, SPI3};
+use stm32l4xx_hal::dma::DmaExt;
+use stm32l4xx_hal::pac::Interrupt::{DMA2_CH1, EXTI1};
 use stm32l4xx_hal::spi::Spi as HalSpi;
 use stm32l4xx_hal::time::MegaHertz;
 use crate::logic::Logic;
@@ -139,10 +141,47 @@ fn main() -> ! {
     // Create the Actor around the HTS221
     let hts221 = Hts221::new(ready, EXTI15_10);
 
+    // == LIS3MDL ==
+
+    let mut ready = gpioc
+        .pc8
+        .into_pull_down_input(&mut gpioc.moder, &mut gpioc.pupdr);
+
+    ready.enable_interrupt(&mut device.EXTI);
+    ready.make_interrupt_source(&mut device.SYSCFG, &mut rcc.apb2);
+    ready.trigger_on_edge(&mut device.EXTI, Edge::RISING);
+
+    let lis3mdl = Lis3mdl::new(ready, EXTI9_5);
+
+    // == LPS22HB ==
+
+    let mut ready = gpiod
+        .pd10
+        .into_pull_down_input(&mut gpiod.moder, &mut gpiod.pupdr);
+
+    ready.enable_interrupt(&mut device.EXTI);
+    ready.make_interrupt_source(&mut device.SYSCFG, &mut rcc.apb2);
+    ready.trigger_on_edge(&mut device.EXTI, Edge::RISING);
+
+    let lps22hb = Lps22hb::new(ready, EXTI15_10);
+
+    // == LSM6DSL ==
+
+    let mut int1 = gpiod
+        .pd11
+        .into_pull_down_input(&mut gpiod.moder, &mut gpiod.pupdr);
+
+    int1.enable_interrupt(&mut device.EXTI);
+    int1.make_interrupt_source(&mut device.SYSCFG, &mut rcc.apb2);
+    int1.trigger_on_edge(&mut device.EXTI, Edge::RISING);
+
+    let lsm6dsl = Lsm6dsl::new(int1, EXTI15_10);
+
     // == Timer ==
 
     let mcu_timer = McuTimer::tim15(device.TIM15, clocks, &mut rcc.apb2);
-    let timer = Timer::new(mcu_timer, TIM15);
+    let clock = MonotonicTimer::tim2(device.TIM2, clocks, &mut rcc.apb1r1);
+    let timer = Timer::new(mcu_timer, clock, TIM15);
 
     // == SPI ==
 
@@ -159,7 +198,8 @@ fn main() -> ! {
         &mut rcc.apb1r1,
     );
 
-    let spi = Spi::new(spi);
+    let dma2 = device.DMA2.split(&mut rcc.ahb1);
+    let spi = DmaSpi::new(DmaSpi3::new(spi, dma2.1, dma2.2, clocks), DMA2_CH1);
 
     // == Wifi ==
 
@@ -201,8 +241,12 @@ fn main() -> ! {
         blinker2: ActorContext::new(blinker2).with_name("blinker2"),
         i2c,
         hts221,
+        lis3mdl,
+        lps22hb,
+        lsm6dsl,
         button: InterruptContext::new(button, EXTI15_10).with_name("button"),
         timer,
+        clock: InterruptContext::new(ClockInterrupt::new(), TIM2).with_name("clock"),
     };
 
     device!( MyDevice = device; 10240 );
@@ -0,0 +1,85 @@
+use drogue_device::{
+    api::uart::{UartReader, UartWriter},
+    domain::time::duration::Milliseconds,
+    driver::{
+        timer::{ClockInterrupt, Timer},
+        uart::usb::UsbSerial,
+    },
+    platform::cortex_m::stm32l4xx::{clock::MonotonicTimer, timer::Timer as HardwareTimer},
+    prelude::*,
+};
+use heapless::consts;
+use stm32l4xx_hal::{
+    pac::{TIM15, TIM2},
+    usb::Peripheral,
+};
+
+pub type AppTimer = Timer<HardwareTimer<TIM15>, MonotonicTimer<TIM2>>;
+pub type AppClock = ClockInterrupt<MonotonicTimer<TIM2>>;
+pub type AppSerial =
+    UsbSerial<Peripheral, <AppTimer as Package>::Primary, consts::U256, consts::U256>;
+
+pub struct MyDevice {
+    pub timer: AppTimer,
+    pub clock: InterruptContext<AppClock>,
+    pub serial: AppSerial,
+    pub echo: ActorContext<Echo<<AppSerial as Package>::Primary>>,
+}
+
+impl Device for MyDevice {
+    fn mount(&'static self, _: DeviceConfiguration<Self>, supervisor: &mut Supervisor) {
+        let timer = self.timer.mount((), supervisor);
+        self.clock.mount(self.timer.clock(), supervisor);
+        let serial = self.serial.mount(timer, supervisor);
+        self.echo.mount(serial, supervisor);
+    }
+}
+
+/// Writes back whatever a terminal on the host sends.
+pub struct Echo<U>
+where
+    U: UartReader + UartWriter + 'static,
+{
+    uart: Option<Address<U>>,
+}
+
+impl<U> Echo<U>
+where
+    U: UartReader + UartWriter + 'static,
+{
+    pub fn new() -> Self {
+        Self { uart: None }
+    }
+}
+
+impl<U> Actor for Echo<U>
+where
+    U: UartReader + UartWriter + 'static,
+{
+    type Configuration = Address<U>;
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.uart.replace(config);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        Completion::defer(async move {
+            let uart = self.uart.unwrap();
+            let mut buf = [0; 64];
+            loop {
+                match uart
+                    .read_with_timeout(&mut buf[..], Milliseconds(100))
+                    .await
+                {
+                    Ok(len) if len > 0 => {
+                        if let Err(e) = uart.write(&buf[..len]).await {
+                            log::error!("Error writing to USB: {:?}", e);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => log::error!("Error reading from USB: {:?}", e),
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,100 @@
+#![no_std]
+#![no_main]
+
+mod device;
+
+use device::{Echo, MyDevice};
+
+use cortex_m_rt::{entry, exception};
+use drogue_device::{
+    driver::{
+        timer::{ClockInterrupt, Timer},
+        uart::usb::UsbConfig,
+        uart::usb::UsbSerial,
+    },
+    platform::cortex_m::stm32l4xx::{clock::MonotonicTimer, timer::Timer as McuTimer},
+    prelude::*,
+};
+use log::LevelFilter;
+use panic_rtt_target as _;
+use rtt_logger::RTTLogger;
+use rtt_target::rtt_init_print;
+use stm32l4xx_hal::{
+    pac::{
+        interrupt::{TIM15, TIM2, USB_FS},
+        Peripherals, CRS, PWR, RCC,
+    },
+    prelude::*,
+    usb::Peripheral,
+};
+
+static LOGGER: RTTLogger = RTTLogger::new(LevelFilter::Info);
+
+/// Trim the HSI48 against the start-of-frame packets of the host, as USB needs.
+fn enable_crs() {
+    let rcc = unsafe { &(*RCC::ptr()) };
+    rcc.apb1enr1.modify(|_, w| w.crsen().set_bit());
+    let crs = unsafe { &(*CRS::ptr()) };
+    crs.cr.modify(|_, w| w.autotrimen().set_bit());
+    crs.cr.modify(|_, w| w.cen().set_bit());
+}
+
+/// Declare the USB supply valid, which it is when powered over USB.
+fn enable_usb_supply() {
+    let rcc = unsafe { &(*RCC::ptr()) };
+    rcc.apb1enr1.modify(|_, w| w.pwren().set_bit());
+    let pwr = unsafe { &*PWR::ptr() };
+    pwr.cr2.modify(|_, w| w.usv().set_bit());
+}
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let device = Peripherals::take().unwrap();
+
+    log::info!("[main] Initializing");
+    let mut flash = device.FLASH.constrain();
+    let mut rcc = device.RCC.constrain();
+    let mut pwr = device.PWR.constrain(&mut rcc.apb1r1);
+    let clocks = rcc
+        .cfgr
+        .hsi48(true)
+        .sysclk(80.mhz())
+        .pclk1(80.mhz())
+        .pclk2(80.mhz())
+        .freeze(&mut flash.acr, &mut pwr);
+
+    enable_crs();
+    enable_usb_supply();
+
+    let mut gpioa = device.GPIOA.split(&mut rcc.ahb2);
+
+    // == Timer ==
+
+    let mcu_timer = McuTimer::tim15(device.TIM15, clocks, &mut rcc.apb2);
+    let clock = MonotonicTimer::tim2(device.TIM2, clocks, &mut rcc.apb1r1);
+    let timer = Timer::new(mcu_timer, clock, TIM15);
+
+    // == USB ==
+
+    let usb = Peripheral {
+        usb: device.USB,
+        pin_dm: gpioa.pa11.into_af10(&mut gpioa.moder, &mut gpioa.afrh),
+        pin_dp: gpioa.pa12.into_af10(&mut gpioa.moder, &mut gpioa.afrh),
+    };
+    let serial = UsbSerial::new(usb, UsbConfig::default(), USB_FS);
+
+    // == Device ==
+
+    let device = MyDevice {
+        timer,
+        clock: InterruptContext::new(ClockInterrupt::new(), TIM2),
+        serial,
+        echo: ActorContext::new(Echo::new()).with_name("echo"),
+    };
+
+    device!( MyDevice = device; 8192 );
+}
@@ -1,38 +1,34 @@
+use crate::api::spi::Deselect;
 use crate::prelude::*;
-use core::cell::RefCell;
+use crate::synchronization::{Priority, WaitQueue, Waiter};
 use core::future::Future;
 use core::pin::Pin;
 use core::sync::atomic::{AtomicBool, Ordering};
-use core::task::{Context, Poll, Waker};
-use heapless::{consts::*, spsc::Queue};
+use core::task::{Context, Poll};
+use heapless::consts::*;
 
 pub struct Shared {
     available: AtomicBool,
-    waiters: RefCell<Queue<Waker, U8>>,
+    waiters: WaitQueue<U8>,
 }
 
 impl Shared {
     fn new() -> Self {
         Self {
             available: AtomicBool::new(false),
-            waiters: RefCell::new(Queue::new()),
+            waiters: WaitQueue::new(),
         }
     }
 
-    fn begin_transaction(&self) -> Result<bool, bool> {
+    fn begin_transaction(&self) -> bool {
         self.available
             .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
     }
 
     fn end_transaction(&self) {
         self.available.store(true, Ordering::Release);
-        if let Some(next) = self.waiters.borrow_mut().dequeue() {
-            next.wake()
-        }
-    }
-
-    fn add_waiter(&self, waker: Waker) {
-        self.waiters.borrow_mut().enqueue(waker).unwrap();
+        self.waiters.wake_one();
     }
 }
 
@@ -134,7 +130,7 @@ where
     }
 }
 
-struct BeginTransaction;
+struct BeginTransaction(Priority);
 struct EndTransaction;
 
 impl<BUS> RequestHandler<BeginTransaction> for BusArbitrator<BUS>
@@ -148,6 +144,7 @@ where
             &self.shared.unwrap(),
             self.address.unwrap(),
             self.bus.unwrap(),
+            message.0,
         );
         Response::immediate_future(self, future)
     }
@@ -165,10 +162,46 @@ where
 
 impl<BUS> Address<BusArbitrator<BUS>>
 where
-    BUS: Actor + 'static,
+    BUS: Bus + 'static,
 {
-    pub async fn begin_transaction(&self) -> BusTransaction<BUS> {
-        self.request(BeginTransaction).await
+    /// Wait for exclusive use of the bus, set up according to `config`. Transactions begin in
+    /// the order they were asked for.
+    pub async fn begin_transaction(&self, config: BUS::Config) -> BusTransaction<BUS> {
+        self.begin_prioritized_transaction(config, Priority::default())
+            .await
+    }
+
+    /// Wait for exclusive use of the bus, ahead of transactions of a lower `priority`.
+    pub async fn begin_prioritized_transaction(
+        &self,
+        config: BUS::Config,
+        priority: Priority,
+    ) -> BusTransaction<BUS> {
+        let transaction = self.request(BeginTransaction(priority)).await;
+        transaction.bus.request(Configure(config)).await;
+        transaction
+    }
+}
+
+/// A bus shared by devices which may each need it set up differently.
+pub trait Bus: Actor {
+    /// The settings particular to a device.
+    type Config: 'static;
+
+    /// Apply the settings of the device about to use the bus.
+    fn configure(self, config: Self::Config) -> Response<Self, ()>;
+}
+
+pub struct Configure<C>(pub C);
+
+impl<B> RequestHandler<Configure<B::Config>> for B
+where
+    B: Bus + 'static,
+{
+    type Response = ();
+
+    fn on_request(self, message: Configure<B::Config>) -> Response<Self, Self::Response> {
+        self.configure(message.0)
     }
 }
 
@@ -178,6 +211,7 @@ where
 {
     arbitrator: Address<BusArbitrator<BUS>>,
     pub(crate) bus: Address<BUS>,
+    pub(crate) deselect: Option<&'static dyn Deselect>,
 }
 
 impl<BUS> BusTransaction<BUS>
@@ -185,7 +219,11 @@ where
     BUS: Actor + 'static,
 {
     fn new(arbitrator: Address<BusArbitrator<BUS>>, bus: Address<BUS>) -> Self {
-        Self { arbitrator, bus }
+        Self {
+            arbitrator,
+            bus,
+            deselect: None,
+        }
     }
 }
 
@@ -194,6 +232,9 @@ where
     BUS: Actor + 'static,
 {
     fn drop(&mut self) {
+        if let Some(device) = self.deselect.take() {
+            device.deselect();
+        }
         self.arbitrator.notify(EndTransaction {});
     }
 }
@@ -202,10 +243,10 @@ pub struct BeginTransactionFuture<BUS>
 where
     BUS: Actor + 'static,
 {
+    waiter: Waiter<'static, U8>,
     shared: &'static Shared,
     arbitrator: Address<BusArbitrator<BUS>>,
     bus: Address<BUS>,
-    waiting: bool,
 }
 
 impl<BUS> BeginTransactionFuture<BUS>
@@ -216,12 +257,13 @@ where
         shared: &'static Shared,
         arbitrator: Address<BusArbitrator<BUS>>,
         bus: Address<BUS>,
+        priority: Priority,
     ) -> Self {
         Self {
+            waiter: shared.waiters.waiter(priority),
             shared,
             arbitrator,
             bus,
-            waiting: false,
         }
     }
 }
@@ -232,15 +274,14 @@ where
     type Output = BusTransaction<BUS>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        if let Ok(val) = self.shared.begin_transaction() {
-            if val {
-                return Poll::Ready(BusTransaction::new(self.arbitrator, self.bus));
+        let shared = self.shared;
+        let (arbitrator, bus) = (self.arbitrator, self.bus);
+        self.waiter.poll_acquire(cx, || {
+            if shared.begin_transaction() {
+                Some(BusTransaction::new(arbitrator, bus))
+            } else {
+                None
             }
-        }
-        if !self.waiting {
-            self.shared.add_waiter(cx.waker().clone());
-            self.waiting = true;
-        }
-        Poll::Pending
+        })
     }
 }
@@ -0,0 +1,326 @@
+//! Bluetooth Low Energy, in the peripheral role: advertising, connections from centrals, and
+//! GATT services with characteristics centrals read, write and subscribe to.
+//!
+//! Connections and writes from centrals are reported as `BleEvent`s to the actor configured
+//! with the peripheral.
+
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+use heapless::{consts::*, Vec};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BleError {
+    /// No characteristic has this handle.
+    InvalidHandle,
+    /// The value or advertising data does not fit.
+    TooLong,
+    /// No room for more services or characteristics.
+    TableFull,
+    NotConnected,
+    /// The characteristic does not allow the operation.
+    NotPermitted,
+    /// The backend does not support the operation.
+    Unsupported,
+    Transport,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Uuid {
+    /// A UUID assigned by the Bluetooth SIG.
+    Uuid16(u16),
+    /// A vendor-specific UUID, least significant byte first as it goes over the air.
+    Uuid128([u8; 16]),
+}
+
+/// Properties of a characteristic, using the bits of its declaration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Properties(pub u8);
+
+impl Properties {
+    pub const READ: Properties = Properties(0x02);
+    pub const WRITE_WITHOUT_RESPONSE: Properties = Properties(0x04);
+    pub const WRITE: Properties = Properties(0x08);
+    pub const NOTIFY: Properties = Properties(0x10);
+
+    pub const fn with(self, other: Properties) -> Self {
+        Properties(self.0 | other.0)
+    }
+
+    pub fn contains(&self, other: Properties) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether centrals may write the value, with or without response.
+    pub fn writable(&self) -> bool {
+        self.0 & (Self::WRITE.0 | Self::WRITE_WITHOUT_RESPONSE.0) != 0
+    }
+}
+
+/// Longest characteristic value, which fits a notification with the default ATT MTU.
+pub type ValueLength = U20;
+pub type Value = Vec<u8, ValueLength>;
+
+/// Most characteristics of one service.
+pub type MaxCharacteristics = U8;
+
+pub struct Characteristic {
+    pub uuid: Uuid,
+    pub properties: Properties,
+}
+
+pub struct Service {
+    pub uuid: Uuid,
+    pub characteristics: &'static [Characteristic],
+}
+
+/// The handle of the value of a characteristic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CharacteristicHandle(pub u16);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Connection(pub u16);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BleEvent {
+    Connected(Connection),
+    Disconnected(Connection),
+    /// A central wrote the value of a characteristic.
+    Written {
+        connection: Connection,
+        handle: CharacteristicHandle,
+        value: Value,
+    },
+    /// A central turned notifications of a characteristic on or off.
+    Subscribed {
+        connection: Connection,
+        handle: CharacteristicHandle,
+        enabled: bool,
+    },
+}
+
+/// Advertising data, as a sequence of AD structures.
+#[derive(Debug, Clone, Default)]
+pub struct AdvertisingData(Vec<u8, U31>);
+
+/// LE General Discoverable Mode, without BR/EDR support.
+pub const GENERAL_DISCOVERABLE: u8 = 0x06;
+
+impl AdvertisingData {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn with_flags(self, flags: u8) -> Result<Self, BleError> {
+        self.with(0x01, &[flags])
+    }
+
+    /// The complete local name.
+    pub fn with_name(self, name: &str) -> Result<Self, BleError> {
+        self.with(0x09, name.as_bytes())
+    }
+
+    /// A complete list of 16-bit service UUIDs, holding one.
+    pub fn with_service_uuid16(self, uuid: u16) -> Result<Self, BleError> {
+        self.with(0x03, &uuid.to_le_bytes())
+    }
+
+    /// Data of the service with a 16-bit UUID.
+    pub fn with_service_data16(self, uuid: u16, data: &[u8]) -> Result<Self, BleError> {
+        let mut value: Vec<u8, U29> = Vec::new();
+        value.extend_from_slice(&uuid.to_le_bytes()).ok();
+        value
+            .extend_from_slice(data)
+            .map_err(|_| BleError::TooLong)?;
+        self.with(0x16, &value)
+    }
+
+    /// Append an AD structure of any type.
+    pub fn with(mut self, ty: u8, data: &[u8]) -> Result<Self, BleError> {
+        if self.0.len() + 2 + data.len() > self.0.capacity() {
+            return Err(BleError::TooLong);
+        }
+        self.0.push(data.len() as u8 + 1).ok();
+        self.0.push(ty).ok();
+        self.0.extend_from_slice(data).ok();
+        Ok(self)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Start advertising every `interval`, until stopped or a central connects.
+pub struct Advertise {
+    pub data: AdvertisingData,
+    pub interval: Milliseconds,
+    pub connectable: bool,
+}
+
+pub struct StopAdvertising;
+
+/// Add a service, returning the handles of its characteristics in order.
+pub struct AddService(pub &'static Service);
+
+/// Update the value of a characteristic, notifying a subscribed central.
+pub struct SetValue<'a>(pub CharacteristicHandle, pub &'a [u8]);
+
+pub struct GetValue(pub CharacteristicHandle);
+
+pub struct Disconnect(pub Connection);
+
+pub trait BlePeripheral: Actor {
+    fn add_service(
+        self,
+        message: AddService,
+    ) -> Response<Self, Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError>>;
+
+    fn advertise(self, message: Advertise) -> Response<Self, Result<(), BleError>>;
+
+    fn stop_advertising(self, message: StopAdvertising) -> Response<Self, ()>;
+
+    fn set_value<'a>(self, message: SetValue<'a>) -> Response<Self, Result<(), BleError>>;
+
+    fn get_value(self, message: GetValue) -> Response<Self, Result<Value, BleError>>;
+
+    fn disconnect(self, message: Disconnect) -> Response<Self, Result<(), BleError>>;
+}
+
+impl<P> RequestHandler<AddService> for P
+where
+    P: BlePeripheral,
+{
+    type Response = Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError>;
+
+    fn on_request(self, message: AddService) -> Response<Self, Self::Response> {
+        self.add_service(message)
+    }
+}
+
+impl<P> RequestHandler<Advertise> for P
+where
+    P: BlePeripheral,
+{
+    type Response = Result<(), BleError>;
+
+    fn on_request(self, message: Advertise) -> Response<Self, Self::Response> {
+        self.advertise(message)
+    }
+}
+
+impl<P> RequestHandler<StopAdvertising> for P
+where
+    P: BlePeripheral,
+{
+    type Response = ();
+
+    fn on_request(self, message: StopAdvertising) -> Response<Self, Self::Response> {
+        self.stop_advertising(message)
+    }
+}
+
+impl<'a, P> RequestHandler<SetValue<'a>> for P
+where
+    P: BlePeripheral,
+{
+    type Response = Result<(), BleError>;
+
+    fn on_request(self, message: SetValue<'a>) -> Response<Self, Self::Response> {
+        self.set_value(message)
+    }
+}
+
+impl<P> RequestHandler<GetValue> for P
+where
+    P: BlePeripheral,
+{
+    type Response = Result<Value, BleError>;
+
+    fn on_request(self, message: GetValue) -> Response<Self, Self::Response> {
+        self.get_value(message)
+    }
+}
+
+impl<P> RequestHandler<Disconnect> for P
+where
+    P: BlePeripheral,
+{
+    type Response = Result<(), BleError>;
+
+    fn on_request(self, message: Disconnect) -> Response<Self, Self::Response> {
+        self.disconnect(message)
+    }
+}
+
+impl<P> Address<P>
+where
+    P: BlePeripheral + 'static,
+{
+    pub async fn ble_add_service(
+        &self,
+        service: &'static Service,
+    ) -> Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError> {
+        self.request(AddService(service)).await
+    }
+
+    pub async fn ble_advertise(
+        &self,
+        data: AdvertisingData,
+        interval: Milliseconds,
+        connectable: bool,
+    ) -> Result<(), BleError> {
+        self.request(Advertise {
+            data,
+            interval,
+            connectable,
+        })
+        .await
+    }
+
+    pub async fn ble_stop_advertising(&self) {
+        self.request(StopAdvertising).await
+    }
+
+    pub async fn ble_set_value(
+        &self,
+        handle: CharacteristicHandle,
+        value: &[u8],
+    ) -> Result<(), BleError> {
+        self.request_panicking(SetValue(handle, value)).await
+    }
+
+    pub async fn ble_get_value(&self, handle: CharacteristicHandle) -> Result<Value, BleError> {
+        self.request(GetValue(handle)).await
+    }
+
+    pub async fn ble_disconnect(&self, connection: Connection) -> Result<(), BleError> {
+        self.request(Disconnect(connection)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advertising_data() {
+        let data = AdvertisingData::new()
+            .with_flags(GENERAL_DISCOVERABLE)
+            .unwrap()
+            .with_name("drogue")
+            .unwrap()
+            .with_service_data16(0x181a, &[0x10, 0x20])
+            .unwrap();
+        assert_eq!(
+            data.as_bytes(),
+            &[
+                0x02, 0x01, 0x06, 0x07, 0x09, b'd', b'r', b'o', b'g', b'u', b'e', 0x05, 0x16, 0x1a,
+                0x18, 0x10, 0x20
+            ]
+        );
+        assert_eq!(
+            data.with_name("a name much too long to fit").err(),
+            Some(BleError::TooLong)
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Displays drawn through a framebuffer, then flushed to the screen in one go.
+//!
+//! With the `graphics` feature, framebuffers are embedded-graphics draw targets:
+//!
+//! ```ignore
+//! display.draw(|buffer| {
+//!     Circle::new(Point::new(64, 32), 16)
+//!         .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+//!         .draw(buffer)
+//!         .ok();
+//! });
+//! display.flush().await?;
+//! ```
+use crate::prelude::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayError {
+    /// The display did not take what was sent to it.
+    Bus,
+}
+
+pub trait Display: Actor {
+    /// What is drawn to.
+    type Buffer;
+
+    /// Draw into the framebuffer, to be shown on the next flush.
+    fn draw<F: FnOnce(&mut Self::Buffer)>(&mut self, draw: F);
+
+    /// Blank the framebuffer.
+    fn clear(&mut self);
+
+    /// Show what was drawn.
+    fn flush(self) -> Response<Self, Result<(), DisplayError>>;
+}
+
+pub struct Draw<F>(pub F);
+
+impl<D, F> NotifyHandler<Draw<F>> for D
+where
+    D: Display + 'static,
+    F: FnOnce(&mut D::Buffer),
+{
+    fn on_notify(mut self, message: Draw<F>) -> Completion<Self> {
+        self.draw(message.0);
+        Completion::immediate(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Clear;
+
+impl<D> NotifyHandler<Clear> for D
+where
+    D: Display + 'static,
+{
+    fn on_notify(mut self, message: Clear) -> Completion<Self> {
+        self.clear();
+        Completion::immediate(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Flush;
+
+impl<D> RequestHandler<Flush> for D
+where
+    D: Display + 'static,
+{
+    type Response = Result<(), DisplayError>;
+
+    fn on_request(self, message: Flush) -> Response<Self, Self::Response> {
+        self.flush()
+    }
+}
+
+impl<D> Address<D>
+where
+    D: Display + 'static,
+{
+    pub fn draw<F: FnOnce(&mut D::Buffer) + 'static>(&self, draw: F) {
+        self.notify(Draw(draw));
+    }
+
+    pub fn clear(&self) {
+        self.notify(Clear);
+    }
+
+    /// Show what was drawn, once the draws sent before are done.
+    pub async fn flush(&self) -> Result<(), DisplayError> {
+        self.request(Flush).await
+    }
+}
@@ -1,3 +1,6 @@
+use crate::api::arbitrator::BusTransaction;
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
 use core::fmt::{Formatter, LowerHex, UpperHex};
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -32,3 +35,112 @@ impl UpperHex for I2cAddress {
         UpperHex::fmt(&self.0, f)
     }
 }
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum I2cError {
+    /// No device acknowledged the address.
+    AddressNack,
+    /// The device did not acknowledge a data byte.
+    DataNack,
+    /// Another master took the bus, or a misplaced start or stop condition was detected.
+    Bus,
+    Overrun,
+    /// The transfer did not complete in time, and the bus was recovered.
+    Timeout,
+    TxBufferTooLong,
+    RxBufferTooLong,
+    BufferNotInRAM,
+}
+
+pub trait I2cBus: Actor {
+    fn transfer(self, transfer: I2cTransfer) -> Response<Self, Result<(), I2cError>>;
+}
+
+impl<'b, B> RequestHandler<I2cTransfer<'b>> for B
+where
+    B: I2cBus + 'static,
+{
+    type Response = Result<(), I2cError>;
+
+    fn on_request(self, message: I2cTransfer<'b>) -> Response<Self, Self::Response> {
+        self.transfer(message)
+    }
+}
+
+/// A write, a read, or a write followed by a read after a repeated start.
+pub struct I2cTransfer<'b> {
+    pub address: I2cAddress,
+    pub write: &'b [u8],
+    pub read: &'b mut [u8],
+    /// Overrides the default timeout of the bus.
+    pub timeout: Option<Milliseconds>,
+}
+
+impl<'b> I2cTransfer<'b> {
+    pub fn write(address: I2cAddress, bytes: &'b [u8]) -> Self {
+        Self {
+            address,
+            write: bytes,
+            read: &mut [],
+            timeout: None,
+        }
+    }
+
+    pub fn read(address: I2cAddress, buffer: &'b mut [u8]) -> Self {
+        Self {
+            address,
+            write: &[],
+            read: buffer,
+            timeout: None,
+        }
+    }
+
+    pub fn write_read(address: I2cAddress, bytes: &'b [u8], buffer: &'b mut [u8]) -> Self {
+        Self {
+            address,
+            write: bytes,
+            read: buffer,
+            timeout: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Milliseconds) -> Self {
+        self.timeout.replace(timeout);
+        self
+    }
+}
+
+impl<I2C> BusTransaction<I2C>
+where
+    I2C: I2cBus,
+{
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `bytes` argument to fall out of scope, otherwise a panic will occur.
+    pub async fn i2c_write(&self, address: I2cAddress, bytes: &[u8]) -> Result<(), I2cError> {
+        self.i2c_transfer(I2cTransfer::write(address, bytes)).await
+    }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `buffer` argument to fall out of scope, otherwise a panic will occur.
+    pub async fn i2c_read(&self, address: I2cAddress, buffer: &mut [u8]) -> Result<(), I2cError> {
+        self.i2c_transfer(I2cTransfer::read(address, buffer)).await
+    }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `bytes` and `buffer` arguments to fall out of scope, otherwise a panic will occur.
+    pub async fn i2c_write_read<'b>(
+        &self,
+        address: I2cAddress,
+        bytes: &'b [u8],
+        buffer: &'b mut [u8],
+    ) -> Result<(), I2cError> {
+        self.i2c_transfer(I2cTransfer::write_read(address, bytes, buffer))
+            .await
+    }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the buffers of the transfer to fall out of scope, otherwise a panic will occur.
+    pub async fn i2c_transfer<'b>(&self, transfer: I2cTransfer<'b>) -> Result<(), I2cError> {
+        self.bus.request_panicking(transfer).await
+    }
+}
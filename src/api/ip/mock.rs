@@ -0,0 +1,98 @@
+//! A network stack whose peer tests script, for clients of its sockets.
+
+extern crate std;
+
+use super::tcp::{TcpError, TcpStack};
+use super::{IpProtocol, SocketAddress};
+use crate::prelude::*;
+use core::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// What the peer has yet to send, and what it was sent.
+#[derive(Default)]
+struct Peer {
+    reads: VecDeque<Vec<u8>>,
+    sent: Vec<Vec<u8>>,
+}
+
+/// Clones of a stack share its peer, so a test keeps one to script the stack it mounts.
+#[derive(Clone, Default)]
+pub struct MockStack {
+    peer: Rc<RefCell<Peer>>,
+}
+
+impl MockStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue what the coming reads return, one read each, where an empty one finds nothing yet.
+    /// Once the queue runs out, reads find nothing.
+    pub fn receive(&self, reads: &[&[u8]]) {
+        let mut peer = self.peer.borrow_mut();
+        peer.reads.extend(reads.iter().map(|read| read.to_vec()));
+    }
+
+    /// What was written so far, one entry per write.
+    pub fn sent(&self) -> Vec<Vec<u8>> {
+        self.peer.borrow().sent.clone()
+    }
+}
+
+impl Actor for MockStack {
+    type Configuration = ();
+}
+
+impl TcpStack for MockStack {
+    type SocketHandle = u8;
+
+    fn open(self) -> Response<Self, Self::SocketHandle> {
+        Response::immediate(self, 0)
+    }
+
+    fn connect(
+        self,
+        handle: Self::SocketHandle,
+        proto: IpProtocol,
+        dst: SocketAddress,
+    ) -> Response<Self, Result<(), TcpError>> {
+        Response::immediate(self, Ok(()))
+    }
+
+    fn write(
+        self,
+        handle: Self::SocketHandle,
+        buf: &[u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        self.peer.borrow_mut().sent.push(buf.to_vec());
+        Response::immediate(self, Ok(buf.len()))
+    }
+
+    fn read(
+        self,
+        handle: Self::SocketHandle,
+        buf: &mut [u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        let mut peer = self.peer.borrow_mut();
+        let len = match peer.reads.pop_front() {
+            Some(mut data) => {
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                // what does not fit is left for the next read
+                if len < data.len() {
+                    peer.reads.push_front(data.split_off(len));
+                }
+                len
+            }
+            None => 0,
+        };
+        drop(peer);
+        Response::immediate(self, Ok(len))
+    }
+
+    fn close(self, handle: Self::SocketHandle) -> Completion<Self> {
+        Completion::immediate(self)
+    }
+}
@@ -1,3 +1,5 @@
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod tcp;
 
 use core::fmt::{Debug, Display, Formatter};
@@ -38,6 +40,7 @@ impl Debug for IpAddressV4 {
     }
 }
 
+#[derive(Copy, Clone)]
 pub struct SocketAddress {
     ip: IpAddress,
     port: u32,
@@ -1,9 +1,13 @@
 //! General APIs
 pub mod arbitrator;
+pub mod ble;
 pub mod delayer;
+pub mod display;
 pub mod i2c;
 pub mod ip;
 pub mod lora;
+pub mod pwm;
+pub mod rtc;
 pub mod scheduler;
 pub mod spi;
 pub mod switchable;
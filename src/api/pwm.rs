@@ -0,0 +1,29 @@
+//! Outputs dimmed by pulse-width modulation, as LEDs are.
+pub use crate::hal::pwm::Duty;
+use crate::prelude::{Actor, Address, Completion, NotifyHandler};
+
+pub trait Pwm: Actor {
+    fn set_duty(&mut self, duty: Duty);
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SetDuty(pub Duty);
+
+impl<P> NotifyHandler<SetDuty> for P
+where
+    P: Pwm + 'static,
+{
+    fn on_notify(mut self, message: SetDuty) -> Completion<Self> {
+        self.set_duty(message.0);
+        Completion::immediate(self)
+    }
+}
+
+impl<P> Address<P>
+where
+    P: Pwm + 'static,
+{
+    pub fn set_duty(&self, duty: Duty) {
+        self.notify(SetDuty(duty));
+    }
+}
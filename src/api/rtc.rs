@@ -0,0 +1,48 @@
+//! Wall-clock time, kept by a real-time clock.
+use crate::domain::time::datetime::DateTime;
+use crate::prelude::*;
+
+pub trait RealTimeClock: Actor {
+    fn get_datetime(self) -> Response<Self, DateTime>;
+    fn set_datetime(self, datetime: DateTime) -> Response<Self, ()>;
+}
+
+pub struct GetDateTime;
+
+impl<R> RequestHandler<GetDateTime> for R
+where
+    R: RealTimeClock + 'static,
+{
+    type Response = DateTime;
+
+    fn on_request(self, message: GetDateTime) -> Response<Self, Self::Response> {
+        self.get_datetime()
+    }
+}
+
+pub struct SetDateTime(pub DateTime);
+
+impl<R> RequestHandler<SetDateTime> for R
+where
+    R: RealTimeClock + 'static,
+{
+    type Response = ();
+
+    fn on_request(self, message: SetDateTime) -> Response<Self, Self::Response> {
+        self.set_datetime(message.0)
+    }
+}
+
+impl<R> Address<R>
+where
+    R: RealTimeClock + 'static,
+{
+    /// The current date and time, in UTC.
+    pub async fn rtc_now(&self) -> DateTime {
+        self.request(GetDateTime).await
+    }
+
+    pub async fn rtc_set(&self, datetime: DateTime) {
+        self.request(SetDateTime(datetime)).await
+    }
+}
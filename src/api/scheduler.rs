@@ -1,5 +1,28 @@
+use crate::arena::AllocError;
 use crate::domain::time::duration::{Duration, Milliseconds};
-use crate::prelude::{Actor, Address, Completion, NotifyHandler};
+use crate::prelude::{
+    Actor, ActorInfo, Address, Completion, NotifyHandler, RequestHandler, Response,
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Identifies a schedule, to cancel or reschedule it later.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ScheduleId(u32);
+
+impl ScheduleId {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    /// The scheduler has no room for another schedule.
+    Full,
+    /// The arena has no room for the schedule.
+    Exhausted(AllocError),
+}
 
 #[derive(Clone)]
 pub struct Schedule<A, DUR, E>
@@ -9,8 +32,11 @@ where
     E: Clone + 'static,
 {
     pub delay: DUR,
+    /// For a periodic schedule, the time between firings after the first.
+    pub period: Option<Milliseconds>,
     pub event: E,
     pub address: Address<A>,
+    id: ScheduleId,
 }
 
 impl<A, DUR, E> Schedule<A, DUR, E>
@@ -19,21 +45,49 @@ where
     DUR: Duration + Into<Milliseconds>,
     E: Clone + 'static,
 {
+    /// Send `event` to `address` once, after `delay`.
     pub fn new(delay: DUR, event: E, address: Address<A>) -> Self {
         Self {
             delay,
+            period: None,
             event,
             address,
+            id: ScheduleId::next(),
         }
     }
+
+    /// Send `event` to `address` every `period`, until cancelled.
+    ///
+    /// Firings are spaced from the previous deadline rather than from when the event was sent,
+    /// so a periodic schedule does not drift.
+    pub fn periodic(period: DUR, event: E, address: Address<A>) -> Self {
+        Self {
+            delay: period,
+            period: Some(period.into()),
+            event,
+            address,
+            id: ScheduleId::next(),
+        }
+    }
+
+    pub fn id(&self) -> ScheduleId {
+        self.id
+    }
 }
 
 pub trait Scheduler: Actor {
-    fn schedule<A, DUR, E>(&mut self, schedule: Schedule<A, DUR, E>)
+    fn schedule<A, DUR, E>(&mut self, schedule: Schedule<A, DUR, E>) -> Result<(), ScheduleError>
     where
         A: Actor + NotifyHandler<E> + 'static,
         DUR: Duration + Into<Milliseconds> + 'static,
         E: Clone + 'static;
+
+    /// Drop the schedule, if it did not fire for the last time yet.
+    fn cancel(&mut self, id: ScheduleId);
+
+    /// Fire the schedule after `delay` instead of at its current deadline. A periodic schedule
+    /// keeps its period from there.
+    fn reschedule(&mut self, id: ScheduleId, delay: Milliseconds);
 }
 
 impl<S, E, A, DUR> NotifyHandler<Schedule<A, DUR, E>> for S
@@ -44,18 +98,148 @@ where
     DUR: Duration + Into<Milliseconds> + 'static,
 {
     fn on_notify(mut self, message: Schedule<A, DUR, E>) -> Completion<Self> {
-        self.schedule(message);
+        if let Err(e) = self.schedule(message) {
+            log::error!("[{}] schedule dropped: {:?}", ActorInfo::name(), e);
+        }
+        Completion::immediate(self)
+    }
+}
+
+/// Schedule, answering whether the scheduler took it.
+pub struct TrySchedule<A, DUR, E>(pub Schedule<A, DUR, E>)
+where
+    A: Actor + NotifyHandler<E> + 'static,
+    DUR: Duration + Into<Milliseconds>,
+    E: Clone + 'static;
+
+impl<S, E, A, DUR> RequestHandler<TrySchedule<A, DUR, E>> for S
+where
+    S: Scheduler + Actor + 'static,
+    E: Clone + 'static,
+    A: Actor + NotifyHandler<E> + 'static,
+    DUR: Duration + Into<Milliseconds> + 'static,
+{
+    type Response = Result<(), ScheduleError>;
+
+    fn on_request(mut self, message: TrySchedule<A, DUR, E>) -> Response<Self, Self::Response> {
+        let result = self.schedule(message.0);
+        Response::immediate(self, result)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Cancel(pub ScheduleId);
+
+impl<S: Scheduler + 'static> NotifyHandler<Cancel> for S {
+    fn on_notify(mut self, message: Cancel) -> Completion<Self> {
+        self.cancel(message.0);
         Completion::immediate(self)
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+pub struct Reschedule(pub ScheduleId, pub Milliseconds);
+
+impl<S: Scheduler + 'static> NotifyHandler<Reschedule> for S {
+    fn on_notify(mut self, message: Reschedule) -> Completion<Self> {
+        self.reschedule(message.0, message.1);
+        Completion::immediate(self)
+    }
+}
+
+/// A schedule handed to a scheduler, to cancel or move it.
+pub struct ScheduleHandle<S: Scheduler + 'static> {
+    id: ScheduleId,
+    scheduler: Address<S>,
+}
+
+impl<S: Scheduler> Clone for ScheduleHandle<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: Scheduler> Copy for ScheduleHandle<S> {}
+
+impl<S: Scheduler> ScheduleHandle<S> {
+    pub fn id(&self) -> ScheduleId {
+        self.id
+    }
+
+    /// Stop the schedule. Cancelling a schedule that already fired for the last time does
+    /// nothing.
+    pub fn cancel(&self) {
+        self.scheduler.notify(Cancel(self.id));
+    }
+
+    /// Fire the schedule after `delay` from now instead. A periodic schedule keeps its period
+    /// from there.
+    pub fn reschedule<DUR: Into<Milliseconds>>(&self, delay: DUR) {
+        self.scheduler.notify(Reschedule(self.id, delay.into()));
+    }
+}
+
 impl<S: Scheduler> Address<S> {
-    pub fn schedule<DUR, E, A>(&self, delay: DUR, event: E, address: Address<A>)
+    pub fn schedule<DUR, E, A>(
+        &self,
+        delay: DUR,
+        event: E,
+        address: Address<A>,
+    ) -> ScheduleHandle<S>
+    where
+        DUR: Duration + Into<Milliseconds> + 'static,
+        E: Clone + 'static,
+        A: Actor + NotifyHandler<E>,
+    {
+        self.schedule_with(Schedule::new(delay, event, address))
+    }
+
+    /// Send `event` to `address` every `period`, until cancelled through the handle.
+    pub fn schedule_periodic<DUR, E, A>(
+        &self,
+        period: DUR,
+        event: E,
+        address: Address<A>,
+    ) -> ScheduleHandle<S>
+    where
+        DUR: Duration + Into<Milliseconds> + 'static,
+        E: Clone + 'static,
+        A: Actor + NotifyHandler<E>,
+    {
+        self.schedule_with(Schedule::periodic(period, event, address))
+    }
+
+    fn schedule_with<DUR, E, A>(&self, schedule: Schedule<A, DUR, E>) -> ScheduleHandle<S>
+    where
+        DUR: Duration + Into<Milliseconds> + 'static,
+        E: Clone + 'static,
+        A: Actor + NotifyHandler<E>,
+    {
+        let handle = ScheduleHandle {
+            id: schedule.id(),
+            scheduler: *self,
+        };
+        self.notify(schedule);
+        handle
+    }
+
+    /// Hand over `schedule`, finding out whether the scheduler had room for it rather than
+    /// having it logged and dropped.
+    pub async fn try_schedule<DUR, E, A>(
+        &self,
+        schedule: Schedule<A, DUR, E>,
+    ) -> Result<ScheduleHandle<S>, ScheduleError>
     where
+        S: 'static,
         DUR: Duration + Into<Milliseconds> + 'static,
         E: Clone + 'static,
         A: Actor + NotifyHandler<E>,
     {
-        self.notify(Schedule::new(delay, event, address));
+        let handle = ScheduleHandle {
+            id: schedule.id(),
+            scheduler: *self,
+        };
+        self.request(TrySchedule(schedule)).await?;
+        Ok(handle)
     }
 }
@@ -1,21 +1,85 @@
-use crate::api::arbitrator::BusTransaction;
+use crate::api::arbitrator::{Bus, BusArbitrator, BusTransaction};
 use crate::api::delayer::Delayer;
 use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::rate::Hertz;
 use crate::prelude::*;
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use embedded_hal::digital::v2::OutputPin;
+pub use embedded_hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
 
 #[derive(Debug)]
 pub enum SpiError {
     Overrun,
     ModeFault,
     Crc,
+    /// The buffer exceeds what the peripheral can move in one transfer.
+    TransferTooLong,
+    BufferNotInRAM,
     Unknown,
 }
 
-pub trait SpiBus: Actor {
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// The settings a device expects of the bus.
+#[derive(Copy, Clone, PartialEq)]
+pub struct SpiConfig {
+    pub mode: Mode,
+    /// The highest clock frequency supported by the device. The bus may run slower.
+    pub frequency: Hertz,
+    pub bit_order: BitOrder,
+}
+
+impl SpiConfig {
+    pub const fn new(mode: Mode, frequency: Hertz) -> Self {
+        Self {
+            mode,
+            frequency,
+            bit_order: BitOrder::MsbFirst,
+        }
+    }
+
+    pub const fn with_bit_order(mut self, bit_order: BitOrder) -> Self {
+        self.bit_order = bit_order;
+        self
+    }
+}
+
+// `Mode` does not implement `Debug`.
+impl core::fmt::Debug for SpiConfig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SpiConfig")
+            .field("polarity", &(self.mode.polarity == Polarity::IdleHigh))
+            .field(
+                "phase",
+                &(self.mode.phase == Phase::CaptureOnSecondTransition),
+            )
+            .field("frequency", &self.frequency)
+            .field("bit_order", &self.bit_order)
+            .finish()
+    }
+}
+
+pub trait SpiBus: Bus<Config = SpiConfig> {
     type Word;
+
+    /// Full-duplex transfer, replacing the words of the buffer with the words received.
     fn transfer(self, transfer: SpiTransfer<Self::Word>) -> Response<Self, Result<(), SpiError>>;
+
+    /// Write words, discarding the words received.
+    fn write(self, write: SpiWrite<Self::Word>) -> Response<Self, Result<(), SpiError>>;
+
+    /// Read words, clocking out filler words.
+    fn read(self, read: SpiRead<Self::Word>) -> Response<Self, Result<(), SpiError>>;
+
+    /// Write words, then read words.
+    fn write_read(
+        self,
+        write_read: SpiWriteRead<Self::Word>,
+    ) -> Response<Self, Result<(), SpiError>>;
 }
 
 impl<'b, B> RequestHandler<SpiTransfer<'b, B::Word>> for B
@@ -29,15 +93,107 @@ where
     }
 }
 
+impl<'b, B> RequestHandler<SpiWrite<'b, B::Word>> for B
+where
+    B: SpiBus + 'static,
+{
+    type Response = Result<(), SpiError>;
+
+    fn on_request(self, message: SpiWrite<'b, B::Word>) -> Response<Self, Self::Response> {
+        self.write(message)
+    }
+}
+
+impl<'b, B> RequestHandler<SpiRead<'b, B::Word>> for B
+where
+    B: SpiBus + 'static,
+{
+    type Response = Result<(), SpiError>;
+
+    fn on_request(self, message: SpiRead<'b, B::Word>) -> Response<Self, Self::Response> {
+        self.read(message)
+    }
+}
+
+impl<'b, B> RequestHandler<SpiWriteRead<'b, B::Word>> for B
+where
+    B: SpiBus + 'static,
+{
+    type Response = Result<(), SpiError>;
+
+    fn on_request(self, message: SpiWriteRead<'b, B::Word>) -> Response<Self, Self::Response> {
+        self.write_read(message)
+    }
+}
+
 pub struct SpiTransfer<'b, W>(pub &'b mut [W]);
 
+pub struct SpiWrite<'b, W>(pub &'b [W]);
+
+pub struct SpiRead<'b, W>(pub &'b mut [W]);
+
+pub struct SpiWriteRead<'b, W>(pub &'b [W], pub &'b mut [W]);
+
 impl<SPI> BusTransaction<SPI>
 where
     SPI: SpiBus,
 {
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `buffer` argument to fall out of scope, otherwise a panic will occur.
     pub async fn spi_transfer<'b>(&self, buffer: &mut [SPI::Word]) -> Result<(), SpiError> {
         self.bus.request_panicking(SpiTransfer(buffer)).await
     }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `words` argument to fall out of scope, otherwise a panic will occur.
+    pub async fn spi_write(&self, words: &[SPI::Word]) -> Result<(), SpiError> {
+        self.bus.request_panicking(SpiWrite(words)).await
+    }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `buffer` argument to fall out of scope, otherwise a panic will occur.
+    pub async fn spi_read(&self, buffer: &mut [SPI::Word]) -> Result<(), SpiError> {
+        self.bus.request_panicking(SpiRead(buffer)).await
+    }
+
+    /// # Panics
+    /// The future *must* be fully `.await`'d before allowing the `words` and `buffer` arguments to fall out of scope, otherwise a panic will occur.
+    pub async fn spi_write_read<'b>(
+        &self,
+        words: &'b [SPI::Word],
+        buffer: &'b mut [SPI::Word],
+    ) -> Result<(), SpiError> {
+        self.bus
+            .request_panicking(SpiWriteRead(words, buffer))
+            .await
+    }
+}
+
+impl<SPI> Address<BusArbitrator<SPI>>
+where
+    SPI: SpiBus,
+{
+    /// Begin a transaction with the device behind `cs`, which stays selected until the
+    /// transaction is dropped.
+    pub async fn begin_selected_transaction<PIN, D>(
+        &self,
+        cs: &'static ChipSelect<PIN, D>,
+        config: SpiConfig,
+    ) -> BusTransaction<SPI>
+    where
+        PIN: OutputPin,
+        D: Delayer,
+    {
+        let mut transaction = self.begin_transaction(config).await;
+        cs.assert().await;
+        transaction.deselect.replace(cs);
+        transaction
+    }
+}
+
+/// Releases a device at the end of a transaction.
+pub trait Deselect {
+    fn deselect(&self);
 }
 
 pub struct ChipSelect<PIN, D>
@@ -47,7 +203,7 @@ where
 {
     select_delay: Milliseconds,
     pin: RefCell<PIN>,
-    delayer: Option<Address<D>>,
+    delayer: Cell<Option<Address<D>>>,
 }
 
 impl<PIN, D> ChipSelect<PIN, D>
@@ -59,20 +215,30 @@ where
         Self {
             select_delay,
             pin: RefCell::new(pin),
-            delayer: None,
+            delayer: Cell::new(None),
         }
     }
 
-    pub(crate) fn set_delayer(&mut self, delayer: Address<D>) {
-        self.delayer.replace(delayer);
+    pub(crate) fn set_delayer(&self, delayer: Address<D>) {
+        self.delayer.set(Some(delayer));
     }
 
     pub async fn select(&self) -> Selected<'_, PIN, D> {
-        self.pin.borrow_mut().set_low().ok().unwrap();
-        self.delayer.unwrap().delay(self.select_delay).await;
+        self.assert().await;
         Selected::new(&self)
     }
 
+    async fn assert(&self) {
+        self.pin.borrow_mut().set_low().ok().unwrap();
+        self.delayer.get().unwrap().delay(self.select_delay).await;
+    }
+}
+
+impl<PIN, D> Deselect for ChipSelect<PIN, D>
+where
+    PIN: OutputPin,
+    D: Delayer,
+{
     fn deselect(&self) {
         self.pin.borrow_mut().set_high().ok().unwrap();
     }
@@ -71,6 +71,56 @@ where
         self.request_panicking(UartReadWithTimeout(rx_buffer, timeout))
             .await
     }
+
+    /// Perform an _async_ read from the uart, up to and including `delimiter`, returning the
+    /// number of bytes read. Bytes received after the delimiter are kept for the next read.
+    /// Fails with `RxBufferTooSmall` if the rx_buffer fills up before the delimiter is read,
+    /// leaving the bytes read so far in the rx_buffer, so a long line can be read in parts.
+    ///
+    /// # Panics
+    ///
+    /// While the rx_buffer may be non-static, the user must
+    /// ensure that the response to the read is fully `.await`'d before returning.
+    /// Leaving an in-flight request dangling while references have gone out of lifetime
+    /// scope will result in a panic.
+    pub async fn read_until<'a>(
+        &'a self,
+        rx_buffer: &'a mut [u8],
+        delimiter: &'a [u8],
+    ) -> Result<usize, Error> {
+        self.request_panicking(UartReadUntil(rx_buffer, delimiter))
+            .await
+    }
+
+    /// Perform an _async_ read of a line from the uart, returning the length of the line
+    /// without its `\n` or `\r\n` ending.
+    ///
+    /// # Panics
+    ///
+    /// While the rx_buffer may be non-static, the user must
+    /// ensure that the response to the read is fully `.await`'d before returning.
+    /// Leaving an in-flight request dangling while references have gone out of lifetime
+    /// scope will result in a panic.
+    pub async fn read_line<'a>(&'a self, rx_buffer: &'a mut [u8]) -> Result<usize, Error> {
+        let len = self.read_until(rx_buffer, b"\n").await?;
+        if rx_buffer[..len].ends_with(b"\r\n") {
+            Ok(len - 2)
+        } else {
+            Ok(len - 1)
+        }
+    }
+
+    /// Perform an _async_ read from the uart, filling the whole rx_buffer.
+    ///
+    /// # Panics
+    ///
+    /// While the rx_buffer may be non-static, the user must
+    /// ensure that the response to the read is fully `.await`'d before returning.
+    /// Leaving an in-flight request dangling while references have gone out of lifetime
+    /// scope will result in a panic.
+    pub async fn read_exact<'a>(&'a self, rx_buffer: &'a mut [u8]) -> Result<(), Error> {
+        self.request_panicking(UartReadExact(rx_buffer)).await
+    }
 }
 
 ///
@@ -88,6 +138,8 @@ pub trait UartReader: Actor {
     ) -> Response<Self, Result<usize, Error>>
     where
         DUR: Duration + Into<Milliseconds> + 'static;
+    fn read_until<'a>(self, message: UartReadUntil<'a>) -> Response<Self, Result<usize, Error>>;
+    fn read_exact<'a>(self, message: UartReadExact<'a>) -> Response<Self, Result<(), Error>>;
 }
 
 /// Message types used by UART implementations
@@ -99,6 +151,10 @@ pub struct UartRead<'a>(pub &'a mut [u8]);
 pub struct UartReadWithTimeout<'a, DUR>(pub &'a mut [u8], pub DUR)
 where
     DUR: Duration + Into<Milliseconds>;
+#[derive(Debug)]
+pub struct UartReadUntil<'a>(pub &'a mut [u8], pub &'a [u8]);
+#[derive(Debug)]
+pub struct UartReadExact<'a>(pub &'a mut [u8]);
 
 /// Request handlers wrapper for the UART trait
 impl<'a, A> RequestHandler<UartWrite<'a>> for A
@@ -131,3 +187,23 @@ where
         self.read_with_timeout(message)
     }
 }
+
+impl<'a, A> RequestHandler<UartReadUntil<'a>> for A
+where
+    A: UartReader + 'static,
+{
+    type Response = Result<usize, Error>;
+    fn on_request(self, message: UartReadUntil<'a>) -> Response<Self, Self::Response> {
+        self.read_until(message)
+    }
+}
+
+impl<'a, A> RequestHandler<UartReadExact<'a>> for A
+where
+    A: UartReader + 'static,
+{
+    type Response = Result<(), Error>;
+    fn on_request(self, message: UartReadExact<'a>) -> Response<Self, Self::Response> {
+        self.read_exact(message)
+    }
+}
@@ -0,0 +1,276 @@
+//! Segregated pools of fixed-size blocks.
+//!
+//! Each pool holds blocks of a single size, the free ones chained through their own memory.
+//! An allocation takes a block from the pool of the smallest blocks it fits, or from a pool
+//! of larger ones once that runs dry. Allocating and freeing take constant time, and a
+//! freed block is as good as new, so long-running churn does not fragment the memory the
+//! way it does a heap. The price is the rounding up of each allocation to a block size.
+
+use crate::arena::{exhausted, AllocError, Allocator, Info};
+use crate::platform::{with_critical_section, Mutex};
+use core::cell::RefCell;
+use core::mem;
+use core::ops::Range;
+use core::ptr::{null_mut, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use heapless::{consts::*, Vec};
+
+/// Blocks are aligned to this, and values needing more cannot be allocated.
+const ALIGN: usize = 8;
+
+/// Precedes each block, naming its pool and how much of it is used.
+#[repr(C, align(8))]
+struct Header {
+    pool: u32,
+    size: u32,
+}
+
+const HEADER: usize = mem::size_of::<Header>();
+
+/// Takes the place of the value in a free block.
+struct FreeBlock {
+    next: *mut FreeBlock,
+}
+
+struct Pool {
+    /// The largest value a block holds.
+    block: usize,
+    /// A block with its header.
+    stride: usize,
+    blocks: usize,
+    available: usize,
+    free: *mut FreeBlock,
+}
+
+struct Pools {
+    pools: Vec<Pool, U8>,
+    used: usize,
+    requested: usize,
+}
+
+pub struct BlockPool {
+    memory: Range<usize>,
+    pools: Mutex<RefCell<Pools>>,
+    high_watermark: AtomicUsize,
+}
+
+impl BlockPool {
+    /// Bytes of memory needed for pools of `(block size, number of blocks)`.
+    pub const fn memory_size(pools: &[(usize, usize)]) -> usize {
+        // leaving room to align the first block
+        let mut size = ALIGN;
+        let mut i = 0;
+        while i < pools.len() {
+            size += pools[i].1 * Self::stride(pools[i].0);
+            i += 1;
+        }
+        size
+    }
+
+    const fn stride(block: usize) -> usize {
+        // a free block holds a link to the next
+        let block = if block < ALIGN { ALIGN } else { block };
+        HEADER + (block + ALIGN - 1) / ALIGN * ALIGN
+    }
+
+    /// Carve `memory` up into pools of `(block size, number of blocks)`, at most eight.
+    ///
+    /// # Panics
+    ///
+    /// When there are too many pools, or `memory` holds less than `memory_size(pools)`.
+    pub fn new(memory: &'static [u8], pools: &[(usize, usize)]) -> Self {
+        assert!(
+            memory.len() >= Self::memory_size(pools),
+            "memory too small for the block pools"
+        );
+        let mut sizes: Vec<(usize, usize), U8> = Vec::new();
+        for pool in pools {
+            sizes
+                .push(*pool)
+                .unwrap_or_else(|_| panic!("too many block pools"));
+        }
+        sizes.sort_unstable_by_key(|(block, _)| *block);
+
+        let start = memory.as_ptr() as usize;
+        let mut next = (start + ALIGN - 1) / ALIGN * ALIGN;
+        let mut carved = Vec::new();
+        for (block, blocks) in sizes.iter() {
+            let stride = Self::stride(*block);
+            let mut free = null_mut();
+            for _ in 0..*blocks {
+                let link = (next + HEADER) as *mut FreeBlock;
+                unsafe { link.write(FreeBlock { next: free }) };
+                free = link;
+                next += stride;
+            }
+            carved
+                .push(Pool {
+                    block: *block,
+                    stride,
+                    blocks: *blocks,
+                    available: *blocks,
+                    free,
+                })
+                .ok();
+        }
+
+        Self {
+            memory: start..start + memory.len(),
+            pools: Mutex::new(RefCell::new(Pools {
+                pools: carved,
+                used: 0,
+                requested: 0,
+            })),
+            high_watermark: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Allocator for BlockPool {
+    fn try_alloc_raw(
+        &mut self,
+        arena: &'static str,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        let taken = with_critical_section(|cs| {
+            if align > ALIGN {
+                return None;
+            }
+            let mut pools = self.pools.borrow(cs).borrow_mut();
+            let index = pools
+                .pools
+                .iter()
+                .position(|pool| pool.block >= size && pool.available > 0)?;
+            let pool = &mut pools.pools[index];
+            let block = pool.free;
+            pool.free = unsafe { (*block).next };
+            pool.available -= 1;
+            let stride = pool.stride;
+            pools.used += stride;
+            pools.requested += HEADER + size;
+            Some((index, block as *mut u8, pools.used))
+        });
+
+        match taken {
+            Some((index, block, used)) => unsafe {
+                (block.sub(HEADER) as *mut Header).write(Header {
+                    pool: index as u32,
+                    size: size as u32,
+                });
+                self.high_watermark.fetch_max(used, Ordering::AcqRel);
+                Ok(NonNull::new_unchecked(block))
+            },
+            None => {
+                let error = AllocError {
+                    arena,
+                    requested: HEADER + size,
+                    free: self.info().free,
+                };
+                log::warn!(
+                    "[ALLOC] no free block: requested={}; free={}",
+                    error.requested,
+                    error.free
+                );
+                exhausted(&error);
+                Err(error)
+            }
+        }
+    }
+
+    unsafe fn dealloc_object(&self, ptr: *mut u8) {
+        let header = (ptr.sub(HEADER) as *const Header).read();
+        with_critical_section(|cs| {
+            let mut pools = self.pools.borrow(cs).borrow_mut();
+            let pool = &mut pools.pools[header.pool as usize];
+            (ptr as *mut FreeBlock).write(FreeBlock { next: pool.free });
+            pool.free = ptr as *mut FreeBlock;
+            pool.available += 1;
+            let stride = pool.stride;
+            pools.used -= stride;
+            pools.requested -= HEADER + header.size as usize;
+        });
+    }
+
+    fn contains(&self, ptr: *const u8) -> bool {
+        self.memory.contains(&(ptr as usize))
+    }
+
+    fn info(&self) -> Info {
+        with_critical_section(|cs| {
+            let pools = self.pools.borrow(cs).borrow();
+            let size: usize = pools
+                .pools
+                .iter()
+                .map(|pool| pool.stride * pool.blocks)
+                .sum();
+            Info {
+                used: pools.used,
+                free: size - pools.used,
+                high_watermark: self.high_watermark.load(Ordering::Acquire),
+                largest_free: pools
+                    .pools
+                    .iter()
+                    .filter(|pool| pool.available > 0)
+                    .map(|pool| pool.stride)
+                    .max()
+                    .unwrap_or(0),
+                free_blocks: pools.pools.iter().map(|pool| pool.available).sum(),
+                wasted: pools.used - pools.requested,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::Arena;
+
+    crate::define_arena!(PoolArena: BlockPool);
+
+    #[test]
+    fn test_pools() {
+        crate::init_arena!(crate::arena::block_pool::tests | PoolArena => [(64, 1), (16, 2)]);
+        let small = BlockPool::stride(16);
+        let large = BlockPool::stride(64);
+
+        let first = PoolArena::try_alloc(1u32).unwrap();
+        let info = PoolArena::info();
+        assert_eq!(info.used, small);
+        assert_eq!(info.wasted, small - HEADER - 4);
+        assert_eq!(info.free_blocks, 2);
+        assert_eq!(info.largest_free, large);
+
+        // the small blocks run out, so the large one is next
+        let second = PoolArena::try_alloc(2u32).unwrap();
+        let third = PoolArena::try_alloc(3u32).unwrap();
+        assert_eq!(PoolArena::info().used, 2 * small + large);
+        assert_eq!(PoolArena::info().largest_free, 0);
+        assert_eq!(*first + *second + *third, 6);
+
+        let error = PoolArena::try_alloc(4u32).unwrap_err();
+        assert_eq!(error.arena, "PoolArena");
+        assert_eq!(error.free, 0);
+        assert!(PoolArena::try_alloc([0u8; 65]).is_err());
+
+        // churn leaves the pools as they were
+        for _ in 0..100 {
+            PoolArena::dealloc(second as *mut _ as *mut u8);
+            let again = PoolArena::try_alloc(2u32).unwrap();
+            assert_eq!(again as *mut u32, second as *mut u32);
+        }
+        PoolArena::dealloc(first as *mut _ as *mut u8);
+        PoolArena::dealloc(second as *mut _ as *mut u8);
+        PoolArena::dealloc(third as *mut _ as *mut u8);
+        let info = PoolArena::info();
+        assert_eq!(info.used, 0);
+        assert_eq!(info.wasted, 0);
+        assert_eq!(info.free_blocks, 3);
+        assert_eq!(info.high_watermark, 2 * small + large);
+        assert_eq!(
+            info.fragmentation(),
+            100 - (large * 100 / (2 * small + large)) as u8
+        );
+    }
+}
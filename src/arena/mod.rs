@@ -1,66 +1,348 @@
 use crate::arena::static_arena::StaticArena;
+use crate::platform::with_critical_section;
 use core::cell::UnsafeCell;
 use core::fmt::{Debug, Formatter};
 use core::future::Future;
 use core::marker::PhantomData;
+use core::mem;
 use core::ops::Deref;
 use core::ops::DerefMut;
 use core::pin::Pin;
-use core::ptr::drop_in_place;
+use core::ptr::{drop_in_place, NonNull};
 use core::task::{Context, Poll};
+use heapless::{consts::*, Vec};
 
+pub mod block_pool;
 pub mod static_arena;
 
 pub struct Info {
     pub used: usize,
     pub free: usize,
     pub high_watermark: usize,
+    /// The largest free block, which an allocation must fit in whole, see `footprint()`.
+    pub largest_free: usize,
+    /// The number of free blocks `free` is split into.
+    pub free_blocks: usize,
+    /// Bytes in use beyond what the allocations asked for, lost to rounding them up.
+    pub wasted: usize,
 }
 
 impl Info {
-    pub fn new(heap: &StaticArena) -> Self {
-        Self {
-            used: heap.used(),
-            free: heap.free(),
-            high_watermark: heap.high_watermark(),
+    /// How much of the free memory is out of reach of a single allocation, in percent.
+    pub fn fragmentation(&self) -> u8 {
+        if self.free == 0 {
+            0
+        } else {
+            (100 - self.largest_free.min(self.free) * 100 / self.free) as u8
         }
     }
 }
 
+/// The memory an arena allocates from, as given to `define_arena!`.
+pub trait Allocator {
+    /// Room for a value of `size` bytes aligned to `align`, or the reason there is none,
+    /// reported to `exhausted()` as well.
+    fn try_alloc_raw(
+        &mut self,
+        arena: &'static str,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError>;
+
+    /// Return the memory of an allocation, without dropping its value.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from `try_alloc_raw` of this allocator, and is no longer valid
+    /// afterwards.
+    unsafe fn dealloc_object(&self, ptr: *mut u8);
+
+    /// Whether `ptr` points into the memory of this allocator.
+    fn contains(&self, ptr: *const u8) -> bool;
+
+    fn info(&self) -> Info;
+
+    /// Move `val` into the allocator. When there is no room, `val` is dropped.
+    fn try_alloc_init<'o, T: 'o>(
+        &mut self,
+        arena: &'static str,
+        val: T,
+    ) -> Result<&'o mut T, AllocError> {
+        let slot = self.try_alloc_raw(arena, mem::size_of::<T>(), mem::align_of::<T>());
+        emplace(slot, val, |val| val).map_err(|(_, e)| e)
+    }
+}
+
+/// Build a `T` from `input` into `slot`, if there is one.
+fn emplace<'o, T: 'o, I, F>(
+    slot: Result<NonNull<u8>, AllocError>,
+    input: I,
+    build: F,
+) -> Result<&'o mut T, (I, AllocError)>
+where
+    F: FnOnce(I) -> T,
+{
+    match slot {
+        Ok(slot) => {
+            let slot = slot.cast::<T>().as_ptr();
+            unsafe {
+                slot.write(build(input));
+                Ok(&mut *slot)
+            }
+        }
+        Err(e) => Err((input, e)),
+    }
+}
+
 //pub static mut HEAP: Option<StaticArena> = None;
 
+/// An arena had no room left for an allocation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AllocError {
+    /// The arena, as named to `define_arena!`.
+    pub arena: &'static str,
+    /// Bytes the allocation needed, see `footprint()`.
+    pub requested: usize,
+    /// Bytes free in the arena, though perhaps not in one piece.
+    pub free: usize,
+}
+
+/// Bytes of arena an allocation of a `T` takes, bookkeeping included.
+///
+/// Summing these over what an actor may have in flight at once gives an estimate of the
+/// arena size a device needs, without trial and error. In a block pool, an allocation
+/// takes a whole block of the smallest size it fits instead.
+pub const fn footprint<T>() -> usize {
+    StaticArena::footprint(mem::size_of::<T>(), mem::align_of::<T>())
+}
+
+static mut EXHAUSTION_HANDLER: Option<fn(&AllocError)> = None;
+
+/// Have `handler` called whenever an arena turns down an allocation for lack of room.
+///
+/// The handler runs wherever the allocation was attempted, possibly within an interrupt
+/// or a critical section, so it should do little more than record or report the failure.
+pub fn on_exhaustion(handler: fn(&AllocError)) {
+    with_critical_section(|_| unsafe {
+        EXHAUSTION_HANDLER = Some(handler);
+    });
+}
+
+/// Stop reporting failed allocations, as before any call to `on_exhaustion()`.
+pub fn clear_exhaustion_handler() {
+    with_critical_section(|_| unsafe {
+        EXHAUSTION_HANDLER = None;
+    });
+}
+
+/// Report a failed allocation to the handler given to `on_exhaustion()`, if any.
+pub fn exhausted(error: &AllocError) {
+    if let Some(handler) = unsafe { EXHAUSTION_HANDLER } {
+        handler(error);
+    }
+}
+
 pub trait Arena: Sized {
-    fn alloc<'o, T: 'o>(val: T) -> Option<&'o mut T>;
+    /// The name given to `define_arena!`.
+    fn name() -> &'static str;
+    /// Tells the arena apart from all others: the address of the allocator it was defined
+    /// with, which no two arenas share, even when named alike.
+    fn id() -> usize;
+    /// Room for a value of `size` bytes aligned to `align`.
+    fn try_alloc_raw(size: usize, align: usize) -> Result<NonNull<u8>, AllocError>;
     fn dealloc(ptr: *mut u8);
+    /// Whether `ptr` points into the memory of this arena.
+    fn contains(ptr: *const u8) -> bool;
     fn info() -> Info;
+
+    /// Move `val` into the arena. When there is no room, `val` is dropped.
+    fn try_alloc<'o, T: 'o>(val: T) -> Result<&'o mut T, AllocError> {
+        Self::try_alloc_with(val, |val| val).map_err(|(_, e)| e)
+    }
+
+    fn alloc<'o, T: 'o>(val: T) -> Option<&'o mut T> {
+        Self::try_alloc(val).ok()
+    }
+
+    /// Make room for a `T` before building it from `input`, so `input` is handed back
+    /// rather than lost when there is no room.
+    fn try_alloc_with<'o, T: 'o, I, F>(input: I, build: F) -> Result<&'o mut T, (I, AllocError)>
+    where
+        F: FnOnce(I) -> T,
+    {
+        let slot = Self::try_alloc_raw(mem::size_of::<T>(), mem::align_of::<T>());
+        emplace(slot, input, build)
+    }
+}
+
+/// An arena chosen at runtime rather than by type.
+#[derive(Copy, Clone)]
+pub struct ArenaRef {
+    id: usize,
+    name: fn() -> &'static str,
+    try_alloc_raw: fn(usize, usize) -> Result<NonNull<u8>, AllocError>,
+    dealloc: fn(*mut u8),
+    contains: fn(*const u8) -> bool,
+    info: fn() -> Info,
 }
 
+impl ArenaRef {
+    pub fn of<A: Arena>() -> Self {
+        Self {
+            id: A::id(),
+            name: A::name,
+            try_alloc_raw: A::try_alloc_raw,
+            dealloc: A::dealloc,
+            contains: A::contains,
+            info: A::info,
+        }
+    }
+
+    /// See `Arena::id()`.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &'static str {
+        (self.name)()
+    }
+
+    pub fn info(&self) -> Info {
+        (self.info)()
+    }
+
+    pub fn contains(&self, ptr: *const u8) -> bool {
+        (self.contains)(ptr)
+    }
+
+    pub fn dealloc(&self, ptr: *mut u8) {
+        (self.dealloc)(ptr)
+    }
+
+    pub fn try_alloc_raw(&self, size: usize, align: usize) -> Result<NonNull<u8>, AllocError> {
+        (self.try_alloc_raw)(size, align)
+    }
+
+    /// See `Arena::try_alloc_with()`.
+    pub fn try_alloc_with<'o, T: 'o, I, F>(
+        &self,
+        input: I,
+        build: F,
+    ) -> Result<&'o mut T, (I, AllocError)>
+    where
+        F: FnOnce(I) -> T,
+    {
+        let slot = self.try_alloc_raw(mem::size_of::<T>(), mem::align_of::<T>());
+        emplace(slot, input, build)
+    }
+
+    /// See `Arena::try_alloc()`.
+    pub fn try_alloc<'o, T: 'o>(&self, val: T) -> Result<&'o mut T, AllocError> {
+        self.try_alloc_with(val, |val| val).map_err(|(_, e)| e)
+    }
+}
+
+impl Debug for ArenaRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl PartialEq for ArenaRef {
+    /// Arenas are the same when their ids are, see `Arena::id()`.
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+static mut ARENAS: Vec<ArenaRef, U8> = Vec(heapless::i::Vec::new());
+
+/// Make `arena` known to `owner()` and `registered()`. Registering an arena again does
+/// nothing.
+///
+/// # Panics
+///
+/// When eight arenas are registered already.
+pub fn register(arena: ArenaRef) {
+    with_critical_section(|_| unsafe {
+        if !ARENAS.iter().any(|known| *known == arena) {
+            ARENAS
+                .push(arena)
+                .unwrap_or_else(|_| panic!("too many arenas"));
+        }
+    });
+}
+
+/// The arenas registered so far.
+pub fn registered() -> Vec<ArenaRef, U8> {
+    with_critical_section(|_| unsafe { ARENAS.clone() })
+}
+
+/// The registered arena `ptr` points into, if any.
+pub fn owner(ptr: *const u8) -> Option<ArenaRef> {
+    with_critical_section(|_| unsafe { ARENAS.iter().find(|arena| arena.contains(ptr)).copied() })
+}
+
+/// Define an arena named `$id`, allocating from a `StaticArena` heap unless another
+/// `Allocator` is given, as in `define_arena!(PoolArena: BlockPool)`.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! define_arena {
     ($id:ident) => {
+        $crate::define_arena!($id: $crate::arena::static_arena::StaticArena);
+    };
+    ($id:ident : $allocator:ty) => {
         pub struct $id;
 
         $crate::paste::paste! {
-            pub static mut [< $id:upper _ARENA >]: Option<$crate::arena::static_arena::StaticArena> = None;
+            pub static mut [< $id:upper _ARENA >]: Option<$allocator> = None;
 
             impl $crate::arena::Arena for $id {
-                fn alloc<'o, T: 'o>(val: T) -> Option<&'o mut T> {
-                    unsafe { [< $id:upper _ARENA >].as_mut().unwrap().alloc_init(val) }
+                fn name() -> &'static str {
+                    stringify!($id)
+                }
+
+                // taking the address of a `static mut` is only safe on newer compilers
+                #[allow(unused_unsafe)]
+                fn id() -> usize {
+                    unsafe { core::ptr::addr_of!([< $id:upper _ARENA >]) as usize }
+                }
+
+                fn try_alloc_raw(
+                    size: usize,
+                    align: usize,
+                ) -> Result<core::ptr::NonNull<u8>, $crate::arena::AllocError> {
+                    unsafe {
+                        $crate::arena::Allocator::try_alloc_raw(
+                            [< $id:upper _ARENA >].as_mut().unwrap(),
+                            stringify!($id),
+                            size,
+                            align,
+                        )
+                    }
                 }
 
                 #[allow(clippy::not_unsafe_ptr_arg_deref)]
                 fn dealloc(ptr: *mut u8) {
                     unsafe {
-                        [< $id:upper _ARENA > ].as_ref()
-                            .unwrap()
-                            .dealloc_object(ptr);
+                        $crate::arena::Allocator::dealloc_object(
+                            [< $id:upper _ARENA >].as_ref().unwrap(),
+                            ptr,
+                        );
+                    }
+                }
+
+                fn contains(ptr: *const u8) -> bool {
+                    unsafe {
+                        [< $id:upper _ARENA >]
+                            .as_ref()
+                            .map_or(false, |arena| $crate::arena::Allocator::contains(arena, ptr))
                     }
                 }
 
                 fn info() -> $crate::arena::Info {
                     unsafe {
-                        $crate::arena::Info::new( [< $id:upper _ARENA >].as_ref().unwrap() )
+                        $crate::arena::Allocator::info([< $id:upper _ARENA >].as_ref().unwrap())
                     }
                 }
             }
@@ -69,6 +351,8 @@ macro_rules! define_arena {
     };
 }
 
+/// Give an arena its memory: a number of bytes for a `StaticArena`, or a list of
+/// `(block size, number of blocks)` for a `BlockPool`.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! init_arena {
@@ -79,7 +363,17 @@ macro_rules! init_arena {
                 $mod::[< $id:upper _ARENA >].replace($crate::arena::static_arena::StaticArena::new(&[< $id:upper _MEMORY >]));
             }
         }
-    }
+    };
+    ($mod:path | $id:ident => [$(($block:literal, $count:literal)),+ $(,)?]) => {
+        $crate::paste::paste! {
+            const [< $id:upper _POOLS >]: &[(usize, usize)] = &[$(($block, $count)),+];
+            static mut [< $id:upper _MEMORY >]: [u8; $crate::arena::block_pool::BlockPool::memory_size([< $id:upper _POOLS >])] =
+                [0; $crate::arena::block_pool::BlockPool::memory_size([< $id:upper _POOLS >])];
+            unsafe {
+                $mod::[< $id:upper _ARENA >].replace($crate::arena::block_pool::BlockPool::new(&[< $id:upper _MEMORY >], [< $id:upper _POOLS >]));
+            }
+        }
+    };
 }
 
 #[repr(transparent)]
@@ -138,7 +432,7 @@ impl<T: ?Sized + Debug, A: Arena> Debug for Box<T, A> {
     }
 }
 
-struct RcBox<T: ?Sized, A: Arena> {
+pub(crate) struct RcBox<T: ?Sized, A: Arena> {
     count: u8,
     arena: PhantomData<A>,
     value: T,
@@ -160,11 +454,15 @@ pub struct Rc<T, A: Arena> {
 
 impl<'m, T: 'm, A: Arena> Rc<T, A> {
     pub fn new(val: T) -> Self {
-        let rc_box = RcBox::new(val);
-        let rc_box = A::alloc(rc_box).unwrap_or_else(|| panic!("oom!"));
-        Self {
+        Self::try_new(val).unwrap_or_else(|e| panic!("oom! {:?}", e))
+    }
+
+    /// Like `new()`, dropping `val` when the arena has no room for it.
+    pub fn try_new(val: T) -> Result<Self, AllocError> {
+        let rc_box = A::try_alloc(RcBox::new(val))?;
+        Ok(Self {
             pointer: UnsafeCell::new(rc_box),
-        }
+        })
     }
 }
 
@@ -202,3 +500,103 @@ impl<T, A: Arena> Drop for Rc<T, A> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    crate::define_arena!(TestArena);
+    crate::define_arena!(FragmentedArena);
+
+    mod first {
+        crate::define_arena!(SameName);
+    }
+
+    mod second {
+        crate::define_arena!(SameName);
+    }
+
+    static EXHAUSTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn test_exhaustion() {
+        // the handler is global, so its tests take turns
+        let _lock = crate::system::mock::lock();
+        crate::init_arena!(crate::arena::tests | TestArena => 256);
+        on_exhaustion(|error| {
+            // other tests run out of their arenas too
+            if error.arena == "TestArena" {
+                EXHAUSTED.store(error.requested, Ordering::SeqCst);
+            }
+        });
+
+        let first = TestArena::try_alloc([0u8; 100]).unwrap();
+        assert_eq!(TestArena::info().used, footprint::<[u8; 100]>());
+
+        // the input survives when there is no room to build from it
+        let result = TestArena::try_alloc_with([1u8; 200], |input| input);
+        let (input, error) = result.unwrap_err();
+        assert_eq!(input, [1u8; 200]);
+        assert_eq!(error.requested, footprint::<[u8; 200]>());
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), error.requested);
+
+        TestArena::dealloc(first as *mut _ as *mut u8);
+        let second = TestArena::try_alloc_with([1u8; 200], |input| input).unwrap();
+        assert_eq!(second[199], 1);
+
+        clear_exhaustion_handler();
+        EXHAUSTED.store(0, Ordering::SeqCst);
+        assert!(TestArena::try_alloc([0u8; 200]).is_err());
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        crate::init_arena!(crate::arena::tests | FragmentedArena => 256);
+        let first = FragmentedArena::try_alloc([0u8; 64]).unwrap();
+        let second = FragmentedArena::try_alloc([0u8; 64]).unwrap();
+        let info = FragmentedArena::info();
+        assert_eq!(info.free_blocks, 1);
+        assert_eq!(info.largest_free, info.free);
+        assert_eq!(info.fragmentation(), 0);
+
+        // a hole before the second allocation, and another after it
+        FragmentedArena::dealloc(first as *mut _ as *mut u8);
+        let info = FragmentedArena::info();
+        assert_eq!(info.free_blocks, 2);
+        assert_eq!(info.largest_free, 256 - 2 * footprint::<[u8; 64]>());
+        assert!(info.fragmentation() > 0);
+
+        FragmentedArena::dealloc(second as *mut _ as *mut u8);
+        assert_eq!(FragmentedArena::info().free_blocks, 1);
+    }
+
+    #[test]
+    fn test_same_name() {
+        {
+            crate::init_arena!(crate::arena::tests::first | SameName => 128);
+        }
+        {
+            crate::init_arena!(crate::arena::tests::second | SameName => 128);
+        }
+        let first = ArenaRef::of::<first::SameName>();
+        let second = ArenaRef::of::<second::SameName>();
+        assert_eq!(first.name(), second.name());
+        assert_ne!(first, second);
+        assert_eq!(first, ArenaRef::of::<first::SameName>());
+
+        register(first);
+        register(second);
+        // and once only, however many times
+        register(ArenaRef::of::<first::SameName>());
+        assert_eq!(
+            registered().iter().filter(|arena| **arena == first).count(),
+            1
+        );
+        let value = second.try_alloc(1u8).unwrap();
+        assert_eq!(owner(value), Some(second));
+        owner(value).unwrap().dealloc(value);
+        assert_eq!(second::SameName::info().used, 0);
+    }
+}
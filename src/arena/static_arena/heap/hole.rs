@@ -81,6 +81,19 @@ impl HoleList {
         size_of::<usize>() * 2
     }
 
+    /// Returns the number of holes and the size of the largest one.
+    pub fn stats(&self) -> (usize, usize) {
+        let mut count = 0;
+        let mut largest = 0;
+        let mut hole = self.first.next.as_deref();
+        while let Some(current) = hole {
+            count += 1;
+            largest = core::cmp::max(largest, current.size);
+            hole = current.next.as_deref();
+        }
+        (count, largest)
+    }
+
     /// Returns information about the first hole for test purposes.
     #[cfg(test)]
     pub fn first_hole(&self) -> Option<(usize, usize)> {
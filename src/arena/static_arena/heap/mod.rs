@@ -109,6 +109,11 @@ impl Heap {
     pub fn free(&self) -> usize {
         self.size - self.used
     }
+
+    /// Returns the number of free blocks and the size of the largest one
+    pub fn holes(&self) -> (usize, usize) {
+        self.holes.stats()
+    }
 }
 
 /// Align downwards. Returns the greatest x with alignment `align`
@@ -12,16 +12,18 @@ use core::cell::RefCell;
 //use core::heap::Layout;
 use core::ptr::NonNull;
 
-use core::mem;
 use core::sync::atomic::{AtomicUsize, Ordering};
+use core::{cmp, mem};
 use heap::layout::Layout;
 use heap::Heap;
 //use static_arena::interrupt::Mutex;
+use crate::arena::{exhausted, AllocError, Allocator, Info};
 use crate::platform::{with_critical_section, Mutex};
 
 pub struct StaticArena {
     heap: Mutex<RefCell<Heap>>,
     high_watermark: AtomicUsize,
+    requested: AtomicUsize,
 }
 
 impl StaticArena {
@@ -71,6 +73,7 @@ impl StaticArena {
         Self {
             heap: Mutex::new(RefCell::new(Heap::new(memory))),
             high_watermark: AtomicUsize::new(0),
+            requested: AtomicUsize::new(0),
         }
 
         //with_critical_section(|cs| {
@@ -94,81 +97,132 @@ impl StaticArena {
         self.high_watermark.load(Ordering::Acquire)
     }
 
-    pub(crate) fn alloc_init<'o, T: 'o>(&mut self, val: T) -> Option<&'o mut T> {
-        let layout = Layout::from_size_align(
-            mem::size_of::<(Layout, T)>(),
-            mem::align_of::<(Layout, T)>(),
-        )
-        .unwrap();
+    /// Bytes an allocation of a value of `size` bytes aligned to `align` takes, see
+    /// `arena::footprint()`.
+    pub const fn footprint(size: usize, align: usize) -> usize {
+        let word = mem::size_of::<usize>();
+        let align = if align < mem::align_of::<Layout>() {
+            mem::align_of::<Layout>()
+        } else {
+            align
+        };
+        let size = Self::value_offset(align) + size;
+        // the heap hands out no less than two words, in whole words
+        let size = if size < 2 * word { 2 * word } else { size };
+        (size + word - 1) / word * word
+    }
+
+    /// Where a value aligned to `align` starts in its allocation, past the header.
+    const fn value_offset(align: usize) -> usize {
+        (mem::size_of::<Layout>() + align - 1) / align * align
+    }
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        with_critical_section(|cs| {
+            self.heap
+                .borrow(cs)
+                .borrow_mut()
+                .allocate_first_fit(layout)
+                .ok()
+                .map_or(core::ptr::null_mut::<u8>(), |allocation| {
+                    allocation.as_ptr()
+                })
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        with_critical_section(|cs| {
+            self.heap
+                .borrow(cs)
+                .borrow_mut()
+                .deallocate(NonNull::new_unchecked(ptr), layout)
+        });
+    }
+}
+
+impl Allocator for StaticArena {
+    fn try_alloc_raw(
+        &mut self,
+        arena: &'static str,
+        size: usize,
+        align: usize,
+    ) -> Result<NonNull<u8>, AllocError> {
+        // the layout goes in a header right before the value
+        let align = cmp::max(align, mem::align_of::<Layout>());
+        let offset = Self::value_offset(align);
+        let layout = Layout::from_size_align(offset + size, align).unwrap();
         log::trace!(
             "[ALLOC] asking for {} aligned {}",
             layout.size(),
             layout.align()
         );
         unsafe {
-            let mut allocation = self.alloc(layout);
+            let allocation = self.alloc(layout);
             if allocation.is_null() {
+                let error = AllocError {
+                    arena,
+                    requested: Self::footprint(size, align),
+                    free: self.free(),
+                };
                 log::warn!(
                     "[ALLOC] allocation failed: requested={}; free={}",
                     layout.size(),
-                    self.free()
+                    error.free
                 );
-                None
+                exhausted(&error);
+                Err(error)
             } else {
-                //let mut allocation = &mut *(allocation as *mut MaybeUninit<T>);
-                //allocation.as_mut_ptr().write( (layout, val ));
-                //Some(&mut *allocation.as_mut_ptr())
                 log::trace!(
                     "[ALLOC] {:x} allocate {} || {} free",
                     allocation as u32,
                     layout.size(),
                     self.free()
                 );
-                (allocation as *mut Layout).write(layout);
-                allocation = (allocation as *mut Layout).add(1) as *mut u8;
-                (allocation as *mut T).write(val);
+                let value = allocation.add(offset);
+                (value as *mut Layout).sub(1).write(layout);
+                self.requested.fetch_add(layout.size(), Ordering::AcqRel);
                 let used = self.used();
                 let high = self.high_watermark.load(Ordering::Acquire);
                 if used > high {
                     self.high_watermark.store(used, Ordering::Release);
                 }
-                Some(&mut *(allocation as *mut _ as *mut T))
-                //Some(&*allocation as *mut T)
+                Ok(NonNull::new_unchecked(value))
             }
         }
     }
 
-    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        with_critical_section(|cs| {
-            self.heap
-                .borrow(cs)
-                .borrow_mut()
-                .allocate_first_fit(layout)
-                .ok()
-                .map_or(core::ptr::null_mut::<u8>(), |allocation| {
-                    allocation.as_ptr()
-                })
-        })
-    }
-
-    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        with_critical_section(|cs| {
-            self.heap
-                .borrow(cs)
-                .borrow_mut()
-                .deallocate(NonNull::new_unchecked(ptr), layout)
-        });
-    }
-
-    pub(crate) unsafe fn dealloc_object(&self, ptr: *mut u8) {
-        let head_ptr = (ptr as *mut Layout).sub(1);
-        let layout = head_ptr.read();
+    unsafe fn dealloc_object(&self, ptr: *mut u8) {
+        let layout = (ptr as *mut Layout).sub(1).read();
+        let allocation = ptr.sub(Self::value_offset(layout.align()));
         log::trace!(
             "[ALLOC] {:x} deallocate {} || {} free",
-            head_ptr as u32,
+            allocation as u32,
             layout.size(),
             self.free()
         );
-        self.dealloc(head_ptr as *mut u8, head_ptr.read());
+        self.requested.fetch_sub(layout.size(), Ordering::AcqRel);
+        self.dealloc(allocation, layout);
+    }
+
+    fn contains(&self, ptr: *const u8) -> bool {
+        with_critical_section(|cs| {
+            let heap = self.heap.borrow(cs).borrow();
+            (heap.bottom()..heap.top()).contains(&(ptr as usize))
+        })
+    }
+
+    fn info(&self) -> Info {
+        with_critical_section(|cs| {
+            let heap = self.heap.borrow(cs).borrow();
+            let (free_blocks, largest_free) = heap.holes();
+            Info {
+                used: heap.used(),
+                free: heap.free(),
+                high_watermark: self.high_watermark(),
+                largest_free,
+                free_blocks,
+                wasted: heap.used() - self.requested.load(Ordering::Acquire),
+            }
+        })
     }
 }
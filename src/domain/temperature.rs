@@ -78,6 +78,11 @@ impl<S: TemperatureScale> Temperature<S> {
             _marker: PhantomData::default(),
         }
     }
+
+    /// The raw value in this temperature's scale.
+    pub fn value(&self) -> f32 {
+        self.value
+    }
 }
 
 impl Temperature<Celsius> {
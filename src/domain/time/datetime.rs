@@ -0,0 +1,296 @@
+//! Calendar date and time of day, in UTC.
+//!
+//! Unlike an [`Instant`](crate::domain::time::Instant), which counts from whenever its clock
+//! started, a [`DateTime`] is wall-clock time, convertible to and from time since the Unix
+//! epoch.
+use core::fmt::{Display, Formatter};
+
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// Day of the week
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// 1 for Monday through 7 for Sunday, as in ISO-8601.
+    pub fn number_from_monday(&self) -> u8 {
+        *self as u8 + 1
+    }
+}
+
+/// A UTC date and time, with millisecond resolution, from 1970 onwards.
+///
+/// Ordering follows time.
+///
+/// ```
+/// use drogue_device::domain::time::datetime::DateTime;
+///
+/// let datetime = DateTime::new(2021, 3, 14, 15, 9, 26).unwrap();
+/// assert_eq!(datetime.unix_seconds(), 1_615_734_566);
+/// assert_eq!(DateTime::from_unix_seconds(1_615_734_566), datetime);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+    millisecond: u16,
+}
+
+impl DateTime {
+    /// 1970-01-01T00:00:00Z
+    pub const UNIX_EPOCH: DateTime = DateTime {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        millisecond: 0,
+    };
+
+    /// The date and time, if it exists and is not before the Unix epoch.
+    pub fn new(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> Option<Self> {
+        if year < 1970
+            || month < 1
+            || month > 12
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+        {
+            return None;
+        }
+        Some(Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            millisecond: 0,
+        })
+    }
+
+    /// The same second, at `millisecond` into it.
+    pub fn with_millisecond(self, millisecond: u16) -> Option<Self> {
+        if millisecond > 999 {
+            return None;
+        }
+        Some(Self {
+            millisecond,
+            ..self
+        })
+    }
+
+    /// Construct from seconds since the Unix epoch.
+    pub fn from_unix_seconds(seconds: u64) -> Self {
+        Self::from_unix_millis(seconds * 1_000)
+    }
+
+    /// Construct from milliseconds since the Unix epoch.
+    pub fn from_unix_millis(millis: u64) -> Self {
+        let (year, month, day) = civil_from_days(millis / MILLIS_PER_DAY);
+        let millis = millis % MILLIS_PER_DAY;
+        Self {
+            year,
+            month,
+            day,
+            hour: (millis / 3_600_000) as u8,
+            minute: (millis / 60_000 % 60) as u8,
+            second: (millis / 1_000 % 60) as u8,
+            millisecond: (millis % 1_000) as u16,
+        }
+    }
+
+    /// Seconds since the Unix epoch, leaving out the milliseconds.
+    pub fn unix_seconds(&self) -> u64 {
+        self.unix_millis() / 1_000
+    }
+
+    /// Milliseconds since the Unix epoch.
+    pub fn unix_millis(&self) -> u64 {
+        days_from_civil(self.year, self.month, self.day) * MILLIS_PER_DAY
+            + self.hour as u64 * 3_600_000
+            + self.minute as u64 * 60_000
+            + self.second as u64 * 1_000
+            + self.millisecond as u64
+    }
+
+    /// The year, as in 2021.
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    /// 1 for January through 12 for December.
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// The day of the month, from 1.
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// The hour of the day, from 0 to 23.
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    /// The minute of the hour, from 0 to 59.
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    /// The second of the minute, from 0 to 59.
+    pub fn second(&self) -> u8 {
+        self.second
+    }
+
+    /// The millisecond of the second, from 0 to 999.
+    pub fn millisecond(&self) -> u16 {
+        self.millisecond
+    }
+
+    /// The day of the week.
+    pub fn weekday(&self) -> Weekday {
+        // the epoch was a Thursday
+        match (days_from_civil(self.year, self.month, self.day) + 3) % 7 {
+            0 => Weekday::Monday,
+            1 => Weekday::Tuesday,
+            2 => Weekday::Wednesday,
+            3 => Weekday::Thursday,
+            4 => Weekday::Friday,
+            5 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+}
+
+impl Display for DateTime {
+    /// ISO-8601, as `2021-03-14T15:09:26Z`, with milliseconds only when there are any.
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.millisecond != 0 {
+            write!(f, ".{:03}", self.millisecond)?;
+        }
+        write!(f, "Z")
+    }
+}
+
+/// Whether February of `year` has 29 days.
+pub fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// The number of days in `month` of `year`.
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Day counting after the civil calendar algorithms of Howard Hinnant, with years starting
+// in March so the leap day comes last.
+
+fn days_from_civil(year: u16, month: u8, day: u8) -> u64 {
+    let year = year as u64 - if month <= 2 { 1 } else { 0 };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month = month as u64;
+    let day_of_year =
+        (153 * if month > 2 { month - 3 } else { month + 9 } + 2) / 5 + day as u64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: u64) -> (u16, u8, u8) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn test_unix_conversions() {
+        assert_eq!(DateTime::from_unix_seconds(0), DateTime::UNIX_EPOCH);
+        let leap_day = DateTime::new(2024, 2, 29, 23, 59, 59).unwrap();
+        assert_eq!(leap_day.unix_seconds(), 1_709_251_199);
+        assert_eq!(
+            DateTime::from_unix_seconds(1_709_251_200),
+            DateTime::new(2024, 3, 1, 0, 0, 0).unwrap()
+        );
+
+        // a day a week over four centuries round-trips, across leap years and their exceptions
+        for days in (0..150_000).step_by(7) {
+            let millis = days * MILLIS_PER_DAY + 45_296_789;
+            assert_eq!(DateTime::from_unix_millis(millis).unix_millis(), millis);
+        }
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(DateTime::new(2100, 2, 29, 0, 0, 0).is_none());
+        assert!(DateTime::new(2000, 2, 29, 0, 0, 0).is_some());
+        assert!(DateTime::new(2021, 4, 31, 0, 0, 0).is_none());
+        assert!(DateTime::new(1969, 12, 31, 23, 59, 59).is_none());
+        assert!(DateTime::new(2021, 1, 1, 24, 0, 0).is_none());
+        assert!(DateTime::UNIX_EPOCH.with_millisecond(1_000).is_none());
+    }
+
+    #[test]
+    fn test_weekday() {
+        assert_eq!(DateTime::UNIX_EPOCH.weekday(), Weekday::Thursday);
+        let datetime = DateTime::new(2021, 3, 14, 0, 0, 0).unwrap();
+        assert_eq!(datetime.weekday(), Weekday::Sunday);
+        assert_eq!(datetime.weekday().number_from_monday(), 7);
+    }
+
+    #[test]
+    fn test_iso8601() {
+        let datetime = DateTime::new(2021, 3, 4, 5, 6, 7).unwrap();
+        assert_eq!(datetime.to_string(), "2021-03-04T05:06:07Z");
+        assert_eq!(
+            datetime.with_millisecond(42).unwrap().to_string(),
+            "2021-03-04T05:06:07.042Z"
+        );
+    }
+}
@@ -259,6 +259,7 @@ impl<Clock: crate::domain::time::Clock> PartialOrd for Instant<Clock> {
     ///
     /// assert!(Instant::<Clock>::new(5) > Instant::<Clock>::new(3));
     /// assert!(Instant::<Clock>::new(5) == Instant::<Clock>::new(5));
+    /// assert!(Instant::<Clock>::new(5) <= Instant::<Clock>::new(5));
     /// assert!(Instant::<Clock>::new(u32::MAX) < Instant::<Clock>::new(u32::MIN));
     /// ```
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -272,10 +273,14 @@ where
     Clock::T: ops::Div<Output = Clock::T>,
 {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.ticks
-            .wrapping_sub(&other.ticks)
-            .cmp(&(<Clock::T as num::Bounded>::max_value() / 2.into()))
-            .reverse()
+        if self.ticks == other.ticks {
+            Ordering::Equal
+        } else {
+            self.ticks
+                .wrapping_sub(&other.ticks)
+                .cmp(&(<Clock::T as num::Bounded>::max_value() / 2.into()))
+                .reverse()
+        }
     }
 }
 
@@ -276,6 +276,7 @@
 #![deny(broken_intra_doc_links)]
 
 pub mod clock;
+pub mod datetime;
 pub mod duration;
 pub mod fixed_point;
 pub mod fraction;
@@ -0,0 +1,189 @@
+use crate::api::ble::{
+    BleError, BleEvent, CharacteristicHandle, Connection, MaxCharacteristics, Properties, Service,
+    Uuid, Value,
+};
+use heapless::{consts::*, Vec};
+
+/// Most attributes of all services together.
+pub type AttributeCount = U32;
+
+enum Attribute {
+    Service(Uuid),
+    /// The declaration of a characteristic, which precedes its value.
+    Declaration(Uuid, Properties),
+    Value(Properties, Value),
+    /// The client characteristic configuration of a notifiable value, which follows it.
+    Configuration(bool),
+}
+
+/// The attributes of the services of a peripheral, laid out the way centrals discover them:
+/// each service declaration is followed by a declaration, a value and, for notifiable
+/// characteristics, a client characteristic configuration per characteristic.
+///
+/// Subscriptions only last as long as the connection, as for a central without a bond.
+pub struct GattServer {
+    attributes: Vec<Attribute, AttributeCount>,
+}
+
+impl GattServer {
+    pub fn new() -> Self {
+        Self {
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn add_service(
+        &mut self,
+        service: &Service,
+    ) -> Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError> {
+        let needed = 1 + service
+            .characteristics
+            .iter()
+            .map(|c| {
+                if c.properties.contains(Properties::NOTIFY) {
+                    3
+                } else {
+                    2
+                }
+            })
+            .sum::<usize>();
+        let mut handles = Vec::new();
+        if self.attributes.len() + needed > self.attributes.capacity()
+            || service.characteristics.len() > handles.capacity()
+        {
+            return Err(BleError::TableFull);
+        }
+
+        self.push(Attribute::Service(service.uuid));
+        for characteristic in service.characteristics {
+            self.push(Attribute::Declaration(
+                characteristic.uuid,
+                characteristic.properties,
+            ));
+            let handle = self.push(Attribute::Value(characteristic.properties, Vec::new()));
+            handles.push(handle).ok();
+            if characteristic.properties.contains(Properties::NOTIFY) {
+                self.push(Attribute::Configuration(false));
+            }
+        }
+        Ok(handles)
+    }
+
+    fn push(&mut self, attribute: Attribute) -> CharacteristicHandle {
+        self.attributes.push(attribute).ok();
+        CharacteristicHandle(self.attributes.len() as u16)
+    }
+
+    fn attribute(&self, handle: u16) -> Option<&Attribute> {
+        self.attributes.get((handle as usize).wrapping_sub(1))
+    }
+
+    fn attribute_mut(&mut self, handle: u16) -> Option<&mut Attribute> {
+        self.attributes.get_mut((handle as usize).wrapping_sub(1))
+    }
+
+    /// Update the value of a characteristic, returning whether the central subscribed to it.
+    pub fn set(&mut self, handle: CharacteristicHandle, value: &[u8]) -> Result<bool, BleError> {
+        match self.attribute_mut(handle.0) {
+            Some(Attribute::Value(_, current)) => {
+                if value.len() > current.capacity() {
+                    return Err(BleError::TooLong);
+                }
+                *current = Vec::new();
+                current.extend_from_slice(value).ok();
+            }
+            _ => return Err(BleError::InvalidHandle),
+        }
+        Ok(matches!(
+            self.attribute(handle.0 + 1),
+            Some(Attribute::Configuration(true))
+        ))
+    }
+
+    pub fn get(&self, handle: CharacteristicHandle) -> Result<&[u8], BleError> {
+        match self.attribute(handle.0) {
+            Some(Attribute::Value(_, value)) => Ok(value),
+            _ => Err(BleError::InvalidHandle),
+        }
+    }
+
+    /// Read an attribute on behalf of a central.
+    pub fn read(&self, handle: u16) -> Result<Vec<u8, U20>, BleError> {
+        let mut data = Vec::new();
+        match self.attribute(handle) {
+            Some(Attribute::Service(uuid)) => encode_uuid(&mut data, uuid),
+            Some(Attribute::Declaration(uuid, properties)) => {
+                data.push(properties.0).ok();
+                data.extend_from_slice(&(handle + 1).to_le_bytes()).ok();
+                encode_uuid(&mut data, uuid);
+            }
+            Some(Attribute::Value(properties, value)) => {
+                if !properties.contains(Properties::READ) {
+                    return Err(BleError::NotPermitted);
+                }
+                data.extend_from_slice(value).ok();
+            }
+            Some(Attribute::Configuration(notify)) => {
+                data.extend_from_slice(&[*notify as u8, 0]).ok();
+            }
+            None => return Err(BleError::InvalidHandle),
+        }
+        Ok(data)
+    }
+
+    /// Write an attribute on behalf of a central, returning what the application should hear
+    /// of it.
+    pub fn write(
+        &mut self,
+        connection: Connection,
+        handle: u16,
+        data: &[u8],
+    ) -> Result<BleEvent, BleError> {
+        match self.attribute_mut(handle) {
+            Some(Attribute::Value(properties, value)) => {
+                if !properties.writable() {
+                    return Err(BleError::NotPermitted);
+                }
+                if data.len() > value.capacity() {
+                    return Err(BleError::TooLong);
+                }
+                *value = Vec::new();
+                value.extend_from_slice(data).ok();
+                Ok(BleEvent::Written {
+                    connection,
+                    handle: CharacteristicHandle(handle),
+                    value: value.clone(),
+                })
+            }
+            Some(Attribute::Configuration(notify)) => {
+                if data.len() != 2 {
+                    return Err(BleError::NotPermitted);
+                }
+                *notify = data[0] & 0x01 != 0;
+                Ok(BleEvent::Subscribed {
+                    connection,
+                    handle: CharacteristicHandle(handle - 1),
+                    enabled: *notify,
+                })
+            }
+            Some(_) => Err(BleError::NotPermitted),
+            None => Err(BleError::InvalidHandle),
+        }
+    }
+
+    /// Forget the subscriptions of a central, once it disconnected.
+    pub fn reset(&mut self) {
+        for attribute in self.attributes.iter_mut() {
+            if let Attribute::Configuration(notify) = attribute {
+                *notify = false;
+            }
+        }
+    }
+}
+
+fn encode_uuid(data: &mut Vec<u8, U20>, uuid: &Uuid) {
+    match uuid {
+        Uuid::Uuid16(uuid) => data.extend_from_slice(&uuid.to_le_bytes()).ok(),
+        Uuid::Uuid128(uuid) => data.extend_from_slice(uuid).ok(),
+    };
+}
@@ -0,0 +1,129 @@
+//! A `Transport` on the host, driven by tests playing the part of a central.
+
+extern crate std;
+
+use super::{Transport, TransportEvent};
+use crate::api::ble::{AdvertisingData, BleError, CharacteristicHandle, Connection};
+use heapless::Vec as Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+#[derive(Default)]
+struct Link {
+    events: VecDeque<TransportEvent>,
+    responses: Vec<Result<Vec<u8>, BleError>>,
+    notifications: Vec<(CharacteristicHandle, Vec<u8>)>,
+    advertisements: usize,
+}
+
+pub struct MockTransport {
+    link: Rc<RefCell<Link>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self {
+            link: Default::default(),
+        }
+    }
+
+    /// The central at the other end of the link.
+    pub fn central(&self) -> Central {
+        Central {
+            link: self.link.clone(),
+        }
+    }
+}
+
+impl Transport for MockTransport {
+    fn advertise(&mut self, _: &AdvertisingData, _: bool) -> Result<(), BleError> {
+        self.link.borrow_mut().advertisements += 1;
+        Ok(())
+    }
+
+    fn notify(
+        &mut self,
+        _: Connection,
+        handle: CharacteristicHandle,
+        value: &[u8],
+    ) -> Result<(), BleError> {
+        self.link
+            .borrow_mut()
+            .notifications
+            .push((handle, value.to_vec()));
+        Ok(())
+    }
+
+    fn respond(&mut self, _: Connection, response: Result<&[u8], BleError>) {
+        self.link
+            .borrow_mut()
+            .responses
+            .push(response.map(|data| data.to_vec()));
+    }
+
+    fn disconnect(&mut self, connection: Connection) -> Result<(), BleError> {
+        self.link
+            .borrow_mut()
+            .events
+            .push_back(TransportEvent::Disconnected(connection));
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Option<TransportEvent> {
+        self.link.borrow_mut().events.pop_front()
+    }
+}
+
+/// Queues what a central does for the peripheral to process, and collects what it gets back.
+pub struct Central {
+    link: Rc<RefCell<Link>>,
+}
+
+impl Central {
+    fn push(&self, event: TransportEvent) {
+        self.link.borrow_mut().events.push_back(event);
+    }
+
+    pub fn connect(&self, connection: Connection) {
+        self.push(TransportEvent::Connected(connection));
+    }
+
+    pub fn disconnect(&self, connection: Connection) {
+        self.push(TransportEvent::Disconnected(connection));
+    }
+
+    pub fn read(&self, handle: u16) {
+        self.push(TransportEvent::Read {
+            connection: Connection(1),
+            handle,
+        });
+    }
+
+    pub fn write(&self, handle: u16, data: &[u8]) {
+        let mut value = Value::new();
+        value.extend_from_slice(data).unwrap();
+        self.push(TransportEvent::Write {
+            connection: Connection(1),
+            handle,
+            value,
+            response: true,
+        });
+    }
+
+    /// Responses to reads and writes since the last call.
+    pub fn responses(&self) -> Vec<Result<Vec<u8>, BleError>> {
+        self.link.borrow_mut().responses.drain(..).collect()
+    }
+
+    /// Notifications since the last call.
+    pub fn notifications(&self) -> Vec<(CharacteristicHandle, Vec<u8>)> {
+        self.link.borrow_mut().notifications.drain(..).collect()
+    }
+
+    /// Advertising events since the last call.
+    pub fn advertisements(&self) -> usize {
+        core::mem::replace(&mut self.link.borrow_mut().advertisements, 0)
+    }
+}
@@ -0,0 +1,527 @@
+//! BLE peripherals, serving GATT services over the radio `Transport` of a backend.
+//!
+//! The backend for the nRF radio, behind the `ble-nrf-advertising` feature, only broadcasts
+//! advertisements; connections, and with them GATT access, need a backend which implements a
+//! link layer.
+
+mod gatt;
+#[cfg(test)]
+pub(crate) mod mock;
+
+pub use gatt::GattServer;
+
+use crate::api::ble::{
+    AddService, Advertise, AdvertisingData, BleError, BleEvent, BlePeripheral,
+    CharacteristicHandle, Connection, Disconnect, GetValue, MaxCharacteristics, Service, SetValue,
+    StopAdvertising, Value,
+};
+use crate::api::scheduler::Scheduler;
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+use cortex_m::interrupt::Nr;
+use heapless::Vec;
+
+/// What a central did, as reported by a `Transport`.
+#[derive(Debug, Clone)]
+pub enum TransportEvent {
+    Connected(Connection),
+    Disconnected(Connection),
+    /// A central reads an attribute, and waits for the response.
+    Read {
+        connection: Connection,
+        handle: u16,
+    },
+    /// A central writes an attribute, and waits for the response unless it wrote without one.
+    Write {
+        connection: Connection,
+        handle: u16,
+        value: Value,
+        response: bool,
+    },
+}
+
+/// The link layer of a backend, which sends advertisements and carries attribute operations
+/// to and from centrals.
+pub trait Transport {
+    /// Send one advertising event, on each of the advertising channels.
+    fn advertise(&mut self, data: &AdvertisingData, connectable: bool) -> Result<(), BleError>;
+
+    /// Notify a central of the value of a characteristic.
+    fn notify(
+        &mut self,
+        connection: Connection,
+        handle: CharacteristicHandle,
+        value: &[u8],
+    ) -> Result<(), BleError>;
+
+    /// Respond to the read or write a central waits on.
+    fn respond(&mut self, connection: Connection, response: Result<&[u8], BleError>);
+
+    fn disconnect(&mut self, connection: Connection) -> Result<(), BleError>;
+
+    /// Take what a central did since the last poll, if anything.
+    fn poll(&mut self) -> Option<TransportEvent>;
+}
+
+/// A peripheral with one connection at a time, independent of the actor system.
+///
+/// Advertising stops once a central connects, and is up to the application to start again
+/// once it disconnects.
+pub struct Peripheral<T>
+where
+    T: Transport,
+{
+    transport: T,
+    gatt: GattServer,
+    connection: Option<Connection>,
+    advertising: Option<(AdvertisingData, bool)>,
+}
+
+impl<T> Peripheral<T>
+where
+    T: Transport,
+{
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            gatt: GattServer::new(),
+            connection: None,
+            advertising: None,
+        }
+    }
+
+    pub fn add_service(
+        &mut self,
+        service: &Service,
+    ) -> Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError> {
+        self.gatt.add_service(service)
+    }
+
+    /// Start advertising, sending the first advertising event right away.
+    pub fn start_advertising(
+        &mut self,
+        data: AdvertisingData,
+        connectable: bool,
+    ) -> Result<(), BleError> {
+        self.transport.advertise(&data, connectable)?;
+        self.advertising.replace((data, connectable));
+        Ok(())
+    }
+
+    pub fn stop_advertising(&mut self) {
+        self.advertising.take();
+    }
+
+    /// Send the next advertising event, returning whether advertising goes on.
+    pub fn advertise(&mut self) -> bool {
+        if self.connection.is_some() {
+            self.advertising.take();
+        }
+        match &self.advertising {
+            Some((data, connectable)) => {
+                if let Err(e) = self.transport.advertise(data, *connectable) {
+                    log::warn!("failed to advertise: {:?}", e);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_value(
+        &mut self,
+        handle: CharacteristicHandle,
+        value: &[u8],
+    ) -> Result<(), BleError> {
+        let subscribed = self.gatt.set(handle, value)?;
+        match self.connection {
+            Some(connection) if subscribed => self.transport.notify(connection, handle, value),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn get_value(&self, handle: CharacteristicHandle) -> Result<Value, BleError> {
+        let mut value = Vec::new();
+        value.extend_from_slice(self.gatt.get(handle)?).ok();
+        Ok(value)
+    }
+
+    pub fn disconnect(&mut self, connection: Connection) -> Result<(), BleError> {
+        if self.connection != Some(connection) {
+            return Err(BleError::NotConnected);
+        }
+        self.transport.disconnect(connection)
+    }
+
+    /// Handle what a central did since the last call, returning the event for the application,
+    /// if any. Returns `None` once the transport has nothing more.
+    pub fn process(&mut self) -> Option<Option<BleEvent>> {
+        let event = match self.transport.poll()? {
+            TransportEvent::Connected(connection) => {
+                self.connection.replace(connection);
+                self.advertising.take();
+                Some(BleEvent::Connected(connection))
+            }
+            TransportEvent::Disconnected(connection) => {
+                self.connection.take();
+                self.gatt.reset();
+                Some(BleEvent::Disconnected(connection))
+            }
+            TransportEvent::Read { connection, handle } => {
+                match self.gatt.read(handle) {
+                    Ok(data) => self.transport.respond(connection, Ok(&data)),
+                    Err(e) => self.transport.respond(connection, Err(e)),
+                }
+                None
+            }
+            TransportEvent::Write {
+                connection,
+                handle,
+                value,
+                response,
+            } => match self.gatt.write(connection, handle, &value) {
+                Ok(event) => {
+                    if response {
+                        self.transport.respond(connection, Ok(&[]));
+                    }
+                    Some(event)
+                }
+                Err(e) => {
+                    if response {
+                        self.transport.respond(connection, Err(e));
+                    }
+                    None
+                }
+            },
+        };
+        Some(event)
+    }
+}
+
+/// A BLE peripheral over the transport `T`, spacing advertising events with the scheduler `S`
+/// and reporting to `H`.
+///
+/// The transport is polled whenever its interrupt fires.
+pub struct Ble<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    controller: InterruptContext<BleController<T, S, H>>,
+}
+
+impl<T, S, H> Ble<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    pub fn new<IRQ: Nr>(transport: T, irq: IRQ) -> Self {
+        Self {
+            controller: InterruptContext::new(BleController::new(transport), irq).with_name("ble"),
+        }
+    }
+}
+
+impl<T, S, H> Package for Ble<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    type Primary = BleController<T, S, H>;
+    type Configuration = (Address<S>, Address<H>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        self.controller.mount(config, supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.controller.address()
+    }
+}
+
+/// Time to send the next advertising event, unless advertising restarted since.
+#[derive(Copy, Clone)]
+pub struct AdvertiseTick(u32);
+
+pub struct BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    peripheral: Peripheral<T>,
+    address: Option<Address<Self>>,
+    scheduler: Option<Address<S>>,
+    handler: Option<Address<H>>,
+    interval: Milliseconds,
+    id: u32,
+}
+
+impl<T, S, H> BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    pub fn new(transport: T) -> Self {
+        Self {
+            peripheral: Peripheral::new(transport),
+            address: None,
+            scheduler: None,
+            handler: None,
+            interval: Milliseconds(100),
+            id: 0,
+        }
+    }
+
+    fn schedule(&self) {
+        self.scheduler.unwrap().schedule(
+            self.interval,
+            AdvertiseTick(self.id),
+            self.address.unwrap(),
+        );
+    }
+}
+
+impl<T, S, H> Actor for BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    type Configuration = (Address<S>, Address<H>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration) {
+        self.address.replace(address);
+        self.scheduler.replace(config.0);
+        self.handler.replace(config.1);
+    }
+}
+
+impl<T, S, H> Interrupt for BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    fn on_interrupt(&mut self) {
+        while let Some(event) = self.peripheral.process() {
+            if let Some(event) = event {
+                log::debug!("[{}] {:?}", ActorInfo::name(), event);
+                self.handler.unwrap().notify(event);
+            }
+        }
+    }
+}
+
+impl<T, S, H> NotifyHandler<AdvertiseTick> for BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    fn on_notify(mut self, message: AdvertiseTick) -> Completion<Self> {
+        if message.0 == self.id && self.peripheral.advertise() {
+            self.schedule();
+        }
+        Completion::immediate(self)
+    }
+}
+
+impl<T, S, H> BlePeripheral for BleController<T, S, H>
+where
+    T: Transport + 'static,
+    S: Scheduler + 'static,
+    H: NotifyHandler<BleEvent> + 'static,
+{
+    fn add_service(
+        mut self,
+        message: AddService,
+    ) -> Response<Self, Result<Vec<CharacteristicHandle, MaxCharacteristics>, BleError>> {
+        let result = self.peripheral.add_service(message.0);
+        Response::immediate(self, result)
+    }
+
+    fn advertise(mut self, message: Advertise) -> Response<Self, Result<(), BleError>> {
+        let result = self
+            .peripheral
+            .start_advertising(message.data, message.connectable);
+        if result.is_ok() {
+            self.id = self.id.wrapping_add(1);
+            self.interval = message.interval;
+            self.schedule();
+        }
+        Response::immediate(self, result)
+    }
+
+    fn stop_advertising(mut self, _: StopAdvertising) -> Response<Self, ()> {
+        self.peripheral.stop_advertising();
+        Response::immediate(self, ())
+    }
+
+    fn set_value<'a>(mut self, message: SetValue<'a>) -> Response<Self, Result<(), BleError>> {
+        let result = self.peripheral.set_value(message.0, message.1);
+        Response::immediate(self, result)
+    }
+
+    fn get_value(self, message: GetValue) -> Response<Self, Result<Value, BleError>> {
+        let result = self.peripheral.get_value(message.0);
+        Response::immediate(self, result)
+    }
+
+    fn disconnect(mut self, message: Disconnect) -> Response<Self, Result<(), BleError>> {
+        let result = self.peripheral.disconnect(message.0);
+        Response::immediate(self, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::mock::MockTransport;
+    use super::*;
+    use crate::api::ble::{Characteristic, Properties, Uuid, GENERAL_DISCOVERABLE};
+
+    static SENSOR: Service = Service {
+        uuid: Uuid::Uuid16(0x181a),
+        characteristics: &[
+            Characteristic {
+                uuid: Uuid::Uuid16(0x2a6e),
+                properties: Properties::READ.with(Properties::NOTIFY),
+            },
+            Characteristic {
+                uuid: Uuid::Uuid16(0x2a6f),
+                properties: Properties::READ.with(Properties::WRITE),
+            },
+        ],
+    };
+
+    fn events(peripheral: &mut Peripheral<MockTransport>) -> std::vec::Vec<BleEvent> {
+        let mut events = std::vec::Vec::new();
+        while let Some(event) = peripheral.process() {
+            events.extend(event);
+        }
+        events
+    }
+
+    #[test]
+    fn test_services() {
+        let mut peripheral = Peripheral::new(MockTransport::new());
+        let handles = peripheral.add_service(&SENSOR).unwrap();
+        assert_eq!(
+            &handles[..],
+            &[CharacteristicHandle(3), CharacteristicHandle(6)]
+        );
+
+        let central = peripheral.transport.central();
+        central.connect(Connection(1));
+        central.read(2);
+        central.read(5);
+        peripheral.set_value(handles[0], &[0x10, 0x08]).unwrap();
+        central.read(3);
+        assert_eq!(
+            events(&mut peripheral),
+            [BleEvent::Connected(Connection(1))]
+        );
+        assert_eq!(
+            central.responses(),
+            [
+                Ok(std::vec![0x12, 0x03, 0x00, 0x6e, 0x2a]),
+                Ok(std::vec![0x0a, 0x06, 0x00, 0x6f, 0x2a]),
+                Ok(std::vec![0x10, 0x08]),
+            ]
+        );
+        assert_eq!(
+            peripheral.set_value(CharacteristicHandle(2), &[]),
+            Err(BleError::InvalidHandle)
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let mut peripheral = Peripheral::new(MockTransport::new());
+        let handles = peripheral.add_service(&SENSOR).unwrap();
+        let central = peripheral.transport.central();
+        central.connect(Connection(1));
+        central.write(handles[1].0, &[0x01]);
+        central.write(handles[0].0, &[0x02]);
+        let mut value: Value = Vec::new();
+        value.push(0x01).unwrap();
+        assert_eq!(
+            events(&mut peripheral),
+            [
+                BleEvent::Connected(Connection(1)),
+                BleEvent::Written {
+                    connection: Connection(1),
+                    handle: handles[1],
+                    value,
+                }
+            ]
+        );
+        assert_eq!(
+            central.responses(),
+            [Ok(std::vec![]), Err(BleError::NotPermitted)]
+        );
+        assert_eq!(&peripheral.get_value(handles[1]).unwrap()[..], &[0x01]);
+    }
+
+    #[test]
+    fn test_notify() {
+        let mut peripheral = Peripheral::new(MockTransport::new());
+        let handles = peripheral.add_service(&SENSOR).unwrap();
+        let central = peripheral.transport.central();
+        central.connect(Connection(1));
+        events(&mut peripheral);
+
+        peripheral.set_value(handles[0], &[0x01]).unwrap();
+        central.write(handles[0].0 + 1, &[0x01, 0x00]);
+        assert_eq!(
+            events(&mut peripheral),
+            [BleEvent::Subscribed {
+                connection: Connection(1),
+                handle: handles[0],
+                enabled: true,
+            }]
+        );
+        peripheral.set_value(handles[0], &[0x02]).unwrap();
+        peripheral.set_value(handles[1], &[0x03]).unwrap();
+        assert_eq!(central.notifications(), [(handles[0], std::vec![0x02])]);
+
+        // subscriptions end with the connection
+        central.disconnect(Connection(1));
+        central.connect(Connection(2));
+        events(&mut peripheral);
+        peripheral.set_value(handles[0], &[0x04]).unwrap();
+        assert!(central.notifications().is_empty());
+    }
+
+    #[test]
+    fn test_advertising() {
+        let mut peripheral = Peripheral::new(MockTransport::new());
+        let central = peripheral.transport.central();
+        let data = AdvertisingData::new()
+            .with_flags(GENERAL_DISCOVERABLE)
+            .unwrap();
+        peripheral.start_advertising(data, true).unwrap();
+        assert!(peripheral.advertise());
+        assert_eq!(central.advertisements(), 2);
+
+        central.connect(Connection(1));
+        events(&mut peripheral);
+        assert!(!peripheral.advertise());
+        assert_eq!(central.advertisements(), 0);
+        assert_eq!(
+            peripheral.disconnect(Connection(2)),
+            Err(BleError::NotConnected)
+        );
+        assert_eq!(peripheral.disconnect(Connection(1)), Ok(()));
+    }
+}
@@ -0,0 +1,586 @@
+//! The commands and URCs of the Quectel BG96.
+
+use crate::api::ip::{IpAddress, IpAddressV4, IpProtocol, SocketAddress};
+use crate::domain::time::duration::Milliseconds;
+use crate::util::at::{final_result, AtError, Command, DataHeader, Urc};
+use crate::util::nom::{parse_u8, parse_usize};
+use core::fmt::Write;
+use heapless::{consts::*, Vec};
+use nom::branch::alt;
+use nom::bytes::streaming::{tag, take, take_until};
+use nom::character::streaming::char;
+use nom::combinator::{flat_map, map};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+/// Most data received with one `AT+QIRD`.
+pub type ChunkLength = U192;
+
+/// `AT+CMEE=1`, for numeric `+CME ERROR` codes.
+pub struct SetNumericErrors;
+
+impl Command for SetNumericErrors {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CMEE=1")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// The state of the SIM, as reported by `AT+CPIN?`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SimState {
+    Ready,
+    PinRequired,
+    PukRequired,
+    Other,
+}
+
+/// `AT+CPIN?`, for the state of the SIM.
+pub struct GetSimState;
+
+impl Command for GetSimState {
+    type Response = SimState;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CPIN?")
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(5_000)
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        sim_state(response)
+            .map(|(_, state)| state)
+            .map_err(|_| AtError::Parse)
+    }
+}
+
+/// `AT+CPIN=<pin>`, to unlock the SIM.
+pub struct EnterPin<'a>(pub &'a str);
+
+impl<'a> Command for EnterPin<'a> {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+CPIN=\"{}\"", self.0)
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(5_000)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// The registration of the modem with an EPS network, from `+CEREG`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Registration {
+    NotRegistered,
+    Home,
+    Searching,
+    Denied,
+    Unknown,
+    Roaming,
+}
+
+impl Registration {
+    fn from_stat(stat: u8) -> Self {
+        match stat {
+            0 => Registration::NotRegistered,
+            1 => Registration::Home,
+            2 => Registration::Searching,
+            3 => Registration::Denied,
+            5 => Registration::Roaming,
+            _ => Registration::Unknown,
+        }
+    }
+
+    pub fn is_registered(&self) -> bool {
+        matches!(self, Registration::Home | Registration::Roaming)
+    }
+}
+
+/// `AT+CEREG=1`, to report changes of registration as URCs.
+pub struct EnableRegistrationEvents;
+
+impl Command for EnableRegistrationEvents {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CEREG=1")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CEREG?`, for the current registration.
+pub struct GetRegistration;
+
+impl Command for GetRegistration {
+    type Response = Registration;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CEREG?")
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        registration(response)
+            .map(|(_, registration)| registration)
+            .map_err(|_| AtError::Parse)
+    }
+}
+
+/// `AT+QICSGP`, to configure the IPv4 PDP context 1 with an access point name.
+pub struct ConfigureContext<'a> {
+    pub apn: &'a str,
+}
+
+impl<'a> Command for ConfigureContext<'a> {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+QICSGP=1,1,\"{}\",\"\",\"\",1", self.apn)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+QIACT=1`, to activate PDP context 1.
+pub struct ActivateContext;
+
+impl Command for ActivateContext {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+QIACT=1")
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(150_000)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+QIACT?`, for the address of the active PDP context.
+pub struct GetContextAddress;
+
+impl Command for GetContextAddress {
+    type Response = IpAddress;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+QIACT?")
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        context_address(response)
+            .map(|(_, ip)| IpAddress::V4(ip))
+            .map_err(|_| AtError::Parse)
+    }
+}
+
+/// `AT+QIOPEN`, to connect a socket in buffer access mode. The outcome follows the `OK` as
+/// `+QIOPEN: <id>,<err>`.
+pub struct OpenSocket {
+    pub id: u8,
+    pub proto: IpProtocol,
+    pub dst: SocketAddress,
+}
+
+impl Command for OpenSocket {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        let proto = match self.proto {
+            IpProtocol::Tcp => "TCP",
+            IpProtocol::Udp => "UDP",
+        };
+        write!(
+            w,
+            "+QIOPEN=1,{},\"{}\",\"{}\",{},0,0",
+            self.id,
+            proto,
+            self.dst.ip(),
+            self.dst.port()
+        )
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(150_000)
+    }
+
+    fn final_result(&self, line: &[u8]) -> Option<Result<(), AtError>> {
+        match open_result(line) {
+            Ok((_, (id, 0))) if id == self.id => Some(Ok(())),
+            Ok((_, (id, err))) if id == self.id => Some(Err(AtError::CmeError(err))),
+            _ => match final_result(line) {
+                Some(Ok(_)) => None,
+                other => other,
+            },
+        }
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+QISEND`, to send data over a socket once prompted.
+pub struct SendData<'a> {
+    pub id: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> Command for SendData<'a> {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+QISEND={},{}", self.id, self.data.len())
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(10_000)
+    }
+
+    fn payload(&self) -> Option<&[u8]> {
+        Some(self.data)
+    }
+
+    fn final_result(&self, line: &[u8]) -> Option<Result<(), AtError>> {
+        match line {
+            b"SEND OK\r\n" => Some(Ok(())),
+            b"SEND FAIL\r\n" => Some(Err(AtError::Error)),
+            _ => final_result(line),
+        }
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+QIRD`, to receive up to `len` bytes buffered for a socket.
+pub struct ReadData {
+    pub id: u8,
+    pub len: usize,
+}
+
+impl Command for ReadData {
+    type Response = Vec<u8, ChunkLength>;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+QIRD={},{}", self.id, self.len)
+    }
+
+    fn data_header(&self) -> Option<DataHeader> {
+        Some(read_data_length)
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        let (_, data) = read_data(response).map_err(|_| AtError::Parse)?;
+        let mut chunk = Vec::new();
+        chunk
+            .extend_from_slice(data)
+            .map_err(|_| AtError::ResponseTooLong)?;
+        Ok(chunk)
+    }
+}
+
+/// `AT+QICLOSE`, to close a socket.
+pub struct CloseSocket {
+    pub id: u8,
+}
+
+impl Command for CloseSocket {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+QICLOSE={}", self.id)
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(10_000)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+QPOWD`, to power down gracefully.
+pub struct PowerDown;
+
+impl Command for PowerDown {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+QPOWD")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// The URCs of the BG96.
+#[derive(Debug, PartialEq)]
+pub enum Bg96Urc {
+    /// `RDY`, once powered up.
+    Ready,
+    PoweredDown,
+    /// `+CEREG: <stat>`
+    Registration(Registration),
+    /// `+QIURC: "recv",<id>`, as data is buffered for a socket.
+    DataAvailable(u8),
+    /// `+QIURC: "closed",<id>`
+    Closed(u8),
+    /// `+QIURC: "pdpdeact",<context>`
+    ContextDeactivated(u8),
+}
+
+impl Urc for Bg96Urc {
+    fn parse(line: &[u8]) -> Option<Self> {
+        match line {
+            b"RDY\r\n" => Some(Bg96Urc::Ready),
+            b"POWERED DOWN\r\n" => Some(Bg96Urc::PoweredDown),
+            _ => urc(line).ok().map(|(_, urc)| urc),
+        }
+    }
+}
+
+fn registration_event(input: &[u8]) -> IResult<&[u8], Bg96Urc> {
+    map(delimited(tag("+CEREG: "), parse_u8, tag("\r\n")), |stat| {
+        Bg96Urc::Registration(Registration::from_stat(stat))
+    })(input)
+}
+
+fn socket_event(input: &[u8]) -> IResult<&[u8], Bg96Urc> {
+    map(
+        pair(
+            delimited(
+                tag("+QIURC: \""),
+                alt((tag("recv"), tag("closed"), tag("pdpdeact"))),
+                tag("\","),
+            ),
+            terminated(parse_u8, tag("\r\n")),
+        ),
+        |(event, id)| match event {
+            b"recv" => Bg96Urc::DataAvailable(id),
+            b"closed" => Bg96Urc::Closed(id),
+            _ => Bg96Urc::ContextDeactivated(id),
+        },
+    )(input)
+}
+
+fn urc(input: &[u8]) -> IResult<&[u8], Bg96Urc> {
+    alt((registration_event, socket_event))(input)
+}
+
+fn sim_state(input: &[u8]) -> IResult<&[u8], SimState> {
+    map(
+        preceded(tag("+CPIN: "), take_until("\r\n")),
+        |state: &[u8]| match state {
+            b"READY" => SimState::Ready,
+            b"SIM PIN" => SimState::PinRequired,
+            b"SIM PUK" => SimState::PukRequired,
+            _ => SimState::Other,
+        },
+    )(input)
+}
+
+// +CEREG: <n>,<stat>
+fn registration(input: &[u8]) -> IResult<&[u8], Registration> {
+    map(
+        preceded(pair(tag("+CEREG: "), pair(parse_u8, char(','))), parse_u8),
+        Registration::from_stat,
+    )(input)
+}
+
+fn ip_addr(input: &[u8]) -> IResult<&[u8], IpAddressV4> {
+    map(
+        tuple((
+            parse_u8,
+            preceded(char('.'), parse_u8),
+            preceded(char('.'), parse_u8),
+            preceded(char('.'), parse_u8),
+        )),
+        |(a, b, c, d)| IpAddressV4::new(a, b, c, d),
+    )(input)
+}
+
+// +QIACT: 1,1,1,"10.1.2.3"
+fn context_address(input: &[u8]) -> IResult<&[u8], IpAddressV4> {
+    delimited(
+        tuple((tag("+QIACT: "), take_until("\""), char('"'))),
+        ip_addr,
+        char('"'),
+    )(input)
+}
+
+// +QIOPEN: <id>,<err>
+fn open_result(input: &[u8]) -> IResult<&[u8], (u8, u16)> {
+    map(
+        delimited(
+            tag("+QIOPEN: "),
+            separated_pair(parse_u8, char(','), parse_usize),
+            tag("\r\n"),
+        ),
+        |(id, err)| (id, err as u16),
+    )(input)
+}
+
+// +QIRD: <len>
+fn read_data_header(input: &[u8]) -> IResult<&[u8], usize> {
+    delimited(tag("+QIRD: "), parse_usize, tag("\r\n"))(input)
+}
+
+fn read_data_length(line: &[u8]) -> Option<(usize, usize)> {
+    read_data_header(line)
+        .ok()
+        .map(|(data, len)| (line.len() - data.len(), len))
+}
+
+// +QIRD: <len>\r\n<data>
+fn read_data(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    flat_map(read_data_header, take)(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::uart::mock::Step::{self, *};
+    use crate::util::at::replay::Replay;
+    use crate::util::at::{Attention, SetEcho};
+    use heapless::String;
+
+    /// Recorded from a BG96 with a locked SIM, attaching to an LTE-M network.
+    const STARTUP: &[Step] = &[
+        Receive(b"\r\nRDY\r\n"),
+        Send(b"AT\r\n"),
+        Receive(b"AT\r\r\nOK\r\n"),
+        Send(b"ATE0\r\n"),
+        Receive(b"ATE0\r\r\nOK\r\n"),
+        Send(b"AT+CMEE=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CPIN?\r\n"),
+        Receive(b"\r\n+CPIN: SIM PIN\r\n\r\nOK\r\n"),
+        Send(b"AT+CPIN=\"1234\"\r\n"),
+        Receive(b"\r\nOK\r\n\r\n+CPIN: READY\r\n"),
+        Send(b"AT+CEREG=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CEREG?\r\n"),
+        Receive(b"\r\n+CEREG: 1,2\r\n\r\nOK\r\n"),
+        Receive(b"\r\n+CEREG: 5\r\n"),
+        Send(b"AT+QICSGP=1,1,\"iot.example\",\"\",\"\",1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+QIACT=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+QIACT?\r\n"),
+        Receive(b"\r\n+QIACT: 1,1,1,\"10.170.33.5\"\r\n\r\nOK\r\n"),
+    ];
+
+    #[test]
+    fn test_startup() {
+        let mut modem: Replay<Bg96Urc> = Replay::new(STARTUP);
+        modem.idle();
+        assert_eq!(modem.urc(), Some(Bg96Urc::Ready));
+        assert!(modem.execute(Attention).is_ok());
+        assert!(modem.execute(SetEcho(false)).is_ok());
+        assert!(modem.execute(SetNumericErrors).is_ok());
+        assert_eq!(modem.execute(GetSimState).unwrap(), SimState::PinRequired);
+        assert!(modem.execute(EnterPin("1234")).is_ok());
+        assert!(modem.execute(EnableRegistrationEvents).is_ok());
+        assert_eq!(
+            modem.execute(GetRegistration).unwrap(),
+            Registration::Searching
+        );
+        modem.idle();
+        assert_eq!(
+            modem.urc(),
+            Some(Bg96Urc::Registration(Registration::Roaming))
+        );
+        let configure = ConfigureContext { apn: "iot.example" };
+        assert!(modem.execute(configure).is_ok());
+        assert!(modem.execute(ActivateContext).is_ok());
+
+        let mut ip: String<U16> = String::new();
+        write!(ip, "{}", modem.execute(GetContextAddress).unwrap()).unwrap();
+        assert_eq!(ip.as_str(), "10.170.33.5");
+        modem.finish();
+    }
+
+    #[test]
+    fn test_socket() {
+        let mut modem: Replay<Bg96Urc> = Replay::new(&[
+            Send(b"AT+QIOPEN=1,0,\"TCP\",\"192.168.1.2\",8080,0,0\r\n"),
+            Receive(b"\r\nOK\r\n\r\n+QIOPEN: 0,0\r\n"),
+            Send(b"AT+QISEND=0,5\r\n"),
+            Receive(b"\r\n> "),
+            Send(b"hello"),
+            Receive(b"\r\nSEND OK\r\n"),
+            Receive(b"\r\n+QIURC: \"recv\",0\r\n"),
+            Send(b"AT+QIRD=0,192\r\n"),
+            Receive(b"\r\n+QIRD: 8\r\nhi\r\n\r\nyo\r\n\r\nOK\r\n"),
+            Send(b"AT+QIRD=0,192\r\n"),
+            Receive(b"\r\n+QIRD: 11\r\nOK\r\nERROR\r\n\r\nOK\r\n"),
+            Send(b"AT+QIRD=0,192\r\n"),
+            Receive(b"\r\n+QIRD: 0\r\n\r\nOK\r\n"),
+            Receive(b"\r\n+QIURC: \"closed\",0\r\n"),
+            Send(b"AT+QICLOSE=0\r\n"),
+            Receive(b"\r\nOK\r\n"),
+        ]);
+        let open = OpenSocket {
+            id: 0,
+            proto: IpProtocol::Tcp,
+            dst: SocketAddress::new(IpAddress::new_v4(192, 168, 1, 2), 8080),
+        };
+        assert!(modem.execute(open).is_ok());
+        let send = SendData {
+            id: 0,
+            data: b"hello",
+        };
+        assert!(modem.execute(send).is_ok());
+        modem.idle();
+        assert_eq!(modem.urc(), Some(Bg96Urc::DataAvailable(0)));
+        let data = modem.execute(ReadData { id: 0, len: 192 }).unwrap();
+        assert_eq!(&data[..], b"hi\r\n\r\nyo");
+        // data looking like a final result is still data
+        let data = modem.execute(ReadData { id: 0, len: 192 }).unwrap();
+        assert_eq!(&data[..], b"OK\r\nERROR\r\n");
+        let data = modem.execute(ReadData { id: 0, len: 192 }).unwrap();
+        assert!(data.is_empty());
+        modem.idle();
+        assert_eq!(modem.urc(), Some(Bg96Urc::Closed(0)));
+        assert!(modem.execute(CloseSocket { id: 0 }).is_ok());
+        modem.finish();
+    }
+
+    #[test]
+    fn test_open_failure() {
+        let mut modem: Replay<Bg96Urc> = Replay::new(&[
+            Send(b"AT+QIOPEN=1,1,\"TCP\",\"192.168.1.2\",8080,0,0\r\n"),
+            Receive(b"\r\nOK\r\n\r\n+QIOPEN: 1,566\r\n"),
+        ]);
+        let open = OpenSocket {
+            id: 1,
+            proto: IpProtocol::Tcp,
+            dst: SocketAddress::new(IpAddress::new_v4(192, 168, 1, 2), 8080),
+        };
+        assert!(matches!(modem.execute(open), Err(AtError::CmeError(566))));
+        modem.finish();
+    }
+}
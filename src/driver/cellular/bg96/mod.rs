@@ -0,0 +1,877 @@
+//! A driver for the Quectel BG96 LTE-M/NB-IoT modem over a UART.
+
+mod commands;
+
+use commands::*;
+
+use crate::api::delayer::Delayer;
+use crate::api::ip::tcp::{TcpError, TcpStack};
+use crate::api::ip::{IpProtocol, SocketAddress};
+use crate::api::scheduler::Scheduler;
+use crate::api::uart::{UartReader, UartWriter};
+use crate::domain::time::duration::Milliseconds;
+use crate::driver::socket_pool::SocketPool;
+use crate::prelude::*;
+use crate::synchronization::{Receiver, Watch};
+use crate::util::at::{AtController, AtError, AtModem, Attention, SetEcho};
+use core::cell::RefCell;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use heapless::spsc::Queue;
+
+pub use commands::{Bg96Urc, Registration, SimState};
+
+/// Most data written with one `AT+QISEND`.
+const MAX_SEND: usize = 1460;
+
+/// How often to check the registration while attaching, 2 seconds apart.
+const REGISTRATION_ATTEMPTS: usize = 90;
+
+/// How often to check STATUS while powering on, 100 milliseconds apart, the modem taking
+/// about 5 seconds.
+const POWER_ON_ATTEMPTS: usize = 100;
+
+/// How often to check STATUS while powering down, 100 milliseconds apart, the modem taking
+/// up to 65 seconds.
+const POWER_DOWN_ATTEMPTS: usize = 700;
+
+/// What the modem needs to attach to a network.
+#[derive(Copy, Clone)]
+pub struct Bg96Config {
+    /// The access point name of the PDP context.
+    pub apn: &'static str,
+    /// The PIN of the SIM, if it is locked.
+    pub pin: Option<&'static str>,
+}
+
+#[derive(Debug)]
+enum Error {
+    PowerOn,
+    Modem(AtError),
+    Sim(SimState),
+    RegistrationDenied,
+    NotRegistered,
+}
+
+impl From<AtError> for Error {
+    fn from(error: AtError) -> Self {
+        Error::Modem(error)
+    }
+}
+
+/// What is known of a socket, updated from URCs as well as by the controller.
+struct Socket {
+    opened: bool,
+    connected: bool,
+    /// Whether the modem reported data that has not been read yet.
+    data_available: bool,
+    /// Bytes received from the modem, but not read yet.
+    buffer: Queue<u8, ChunkLength>,
+}
+
+impl Default for Socket {
+    fn default() -> Self {
+        Self {
+            opened: false,
+            connected: false,
+            data_available: false,
+            buffer: Queue::new(),
+        }
+    }
+}
+
+pub struct Shared {
+    socket_pool: SocketPool,
+    sockets: RefCell<[Socket; 4]>,
+    registration: Watch<Registration>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            socket_pool: SocketPool::new(),
+            sockets: Default::default(),
+            registration: Watch::new(),
+        }
+    }
+}
+
+/// A BG96 over the UART `U`, powered on through `PWR`, which asserts PWRKEY while high, and
+/// `STATUS`, which is high while the modem is powered on.
+///
+/// When started, the modem is powered on unless it is already, its SIM unlocked, and the PDP
+/// context activated once registered with a network, after which it serves as a `TcpStack`.
+/// It may be powered down again before cutting its supply.
+pub struct Bg96<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    shared: Shared,
+    modem: AtModem<U, T, Bg96Urc, Bg96Monitor>,
+    monitor: ActorContext<Bg96Monitor>,
+    controller: ActorContext<Bg96Controller<U, T, PWR, STATUS>>,
+}
+
+impl<U, T, PWR, STATUS> Bg96<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    pub fn new(power_key: PWR, status: STATUS, config: Bg96Config) -> Self {
+        Self {
+            shared: Shared::new(),
+            modem: AtModem::new(),
+            monitor: ActorContext::new(Bg96Monitor::new()).with_name("bg96_monitor"),
+            controller: ActorContext::new(Bg96Controller::new(power_key, status, config))
+                .with_name("bg96"),
+        }
+    }
+
+    /// Follow the registration of the modem with a network.
+    pub fn registration(&'static self) -> Receiver<'static, Registration> {
+        self.shared.registration.receiver()
+    }
+}
+
+impl<U, T, PWR, STATUS> Package for Bg96<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    type Primary = Bg96Controller<U, T, PWR, STATUS>;
+    type Configuration = (Address<U>, Address<T>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let monitor = self.monitor.mount(&self.shared, supervisor);
+        let modem = self.modem.mount((config.0, config.1, monitor), supervisor);
+        self.controller
+            .mount((&self.shared, modem, config.1), supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.controller.address()
+    }
+}
+
+enum State {
+    Uninitialized,
+    Ready,
+}
+
+pub struct Bg96Controller<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    shared: Option<&'static Shared>,
+    modem: Option<Address<AtController<U, T>>>,
+    delayer: Option<Address<T>>,
+    power_key: PWR,
+    status: STATUS,
+    config: Bg96Config,
+    state: State,
+}
+
+impl<U, T, PWR, STATUS> Bg96Controller<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    pub fn new(power_key: PWR, status: STATUS, config: Bg96Config) -> Self {
+        Self {
+            shared: None,
+            modem: None,
+            delayer: None,
+            power_key,
+            status,
+            config,
+            state: State::Uninitialized,
+        }
+    }
+
+    async fn start(mut self) -> Self {
+        log::info!("[{}] start", ActorInfo::name());
+        match self.initialize().await {
+            Ok(_) => {
+                self.state = State::Ready;
+                log::info!("[{}] BG96 modem is ready", ActorInfo::name());
+            }
+            Err(e) => {
+                log::info!("[{}] failed to initialize {:?}", ActorInfo::name(), e);
+            }
+        }
+        self
+    }
+
+    fn is_powered(&self) -> bool {
+        self.status.is_high().unwrap_or(false)
+    }
+
+    /// Pulse PWRKEY for `duration`, which toggles the power of the modem.
+    async fn press_power_key(&mut self, duration: Milliseconds) {
+        self.power_key.set_high().ok();
+        self.delayer.unwrap().delay(duration).await;
+        self.power_key.set_low().ok();
+    }
+
+    /// Wait for STATUS to read `powered`, checking it up to `attempts` times.
+    async fn wait_for_status(&mut self, powered: bool, attempts: usize) -> bool {
+        for _ in 0..attempts {
+            if self.is_powered() == powered {
+                return true;
+            }
+            self.delayer.unwrap().delay(Milliseconds(100u32)).await;
+        }
+        self.is_powered() == powered
+    }
+
+    async fn power_on(&mut self) -> Result<(), Error> {
+        if self.is_powered() {
+            // pressing PWRKEY again would power it down
+            log::info!("[{}] already powered on", ActorInfo::name());
+            return Ok(());
+        }
+        self.press_power_key(Milliseconds(600u32)).await;
+        // the UART is up once STATUS is
+        if self.wait_for_status(true, POWER_ON_ATTEMPTS).await {
+            Ok(())
+        } else {
+            Err(Error::PowerOn)
+        }
+    }
+
+    /// Power the modem down with `AT+QPOWD`, or with PWRKEY if it does not answer, and wait
+    /// for STATUS to drop.
+    async fn power_down(&mut self) -> Result<(), AtError> {
+        if self.is_powered() {
+            if let Err(e) = self.modem.unwrap().execute(PowerDown).await {
+                log::info!("[{}] error powering down {:?}", ActorInfo::name(), e);
+                self.press_power_key(Milliseconds(800u32)).await;
+            }
+            if !self.wait_for_status(false, POWER_DOWN_ATTEMPTS).await {
+                return Err(AtError::Timeout);
+            }
+        }
+        self.state = State::Uninitialized;
+        // the sockets are gone with the power
+        for socket in self.shared.unwrap().sockets.borrow_mut().iter_mut() {
+            socket.opened = false;
+            socket.connected = false;
+            socket.data_available = false;
+        }
+        log::info!("[{}] powered down", ActorInfo::name());
+        Ok(())
+    }
+
+    async fn initialize(&mut self) -> Result<(), Error> {
+        self.power_on().await?;
+        let modem = self.modem.unwrap();
+        let mut attempts = 0;
+        while let Err(e) = modem.execute(Attention).await {
+            attempts += 1;
+            if attempts == 10 {
+                return Err(e.into());
+            }
+        }
+        modem.execute(SetEcho(false)).await?;
+        modem.execute(SetNumericErrors).await?;
+        self.unlock_sim().await?;
+        self.attach().await?;
+        modem
+            .execute(ConfigureContext {
+                apn: self.config.apn,
+            })
+            .await?;
+        modem.execute(ActivateContext).await?;
+        let ip = modem.execute(GetContextAddress).await?;
+        log::info!("[{}] PDP context active as {}", ActorInfo::name(), ip);
+        Ok(())
+    }
+
+    async fn unlock_sim(&mut self) -> Result<(), Error> {
+        let modem = self.modem.unwrap();
+        match modem.execute(GetSimState).await? {
+            SimState::Ready => return Ok(()),
+            SimState::PinRequired if self.config.pin.is_some() => {
+                modem.execute(EnterPin(self.config.pin.unwrap())).await?;
+            }
+            state => return Err(Error::Sim(state)),
+        }
+        // the SIM takes a moment to initialize once unlocked
+        for _ in 0..10 {
+            if let Ok(SimState::Ready) = modem.execute(GetSimState).await {
+                return Ok(());
+            }
+            self.delayer.unwrap().delay(Milliseconds(1_000u32)).await;
+        }
+        Err(Error::Sim(SimState::Other))
+    }
+
+    async fn attach(&mut self) -> Result<(), Error> {
+        let modem = self.modem.unwrap();
+        let registration = &self.shared.unwrap().registration;
+        modem.execute(EnableRegistrationEvents).await?;
+        for _ in 0..REGISTRATION_ATTEMPTS {
+            let current = modem.execute(GetRegistration).await?;
+            registration.set(current);
+            match current {
+                Registration::Denied => return Err(Error::RegistrationDenied),
+                r if r.is_registered() => return Ok(()),
+                _ => {}
+            }
+            self.delayer.unwrap().delay(Milliseconds(2_000u32)).await;
+        }
+        Err(Error::NotRegistered)
+    }
+
+    /// Take up to `buf.len()` bytes received for socket `id`, fetching them from the modem if
+    /// none are buffered.
+    async fn receive(&mut self, id: u8, buf: &mut [u8]) -> Result<usize, TcpError> {
+        let shared = self.shared.unwrap();
+        let fetch = {
+            let sockets = shared.sockets.borrow();
+            let socket = &sockets[id as usize];
+            if socket.buffer.is_empty() && !socket.data_available {
+                return if socket.connected {
+                    Ok(0)
+                } else {
+                    Err(TcpError::SocketClosed)
+                };
+            }
+            if socket.buffer.is_empty() {
+                Some(socket.buffer.capacity())
+            } else {
+                None
+            }
+        };
+
+        if let Some(len) = fetch {
+            let chunk = self
+                .modem
+                .unwrap()
+                .execute(ReadData { id, len })
+                .await
+                .map_err(|_| TcpError::ReadError)?;
+            let mut sockets = shared.sockets.borrow_mut();
+            let socket = &mut sockets[id as usize];
+            if chunk.is_empty() {
+                socket.data_available = false;
+            }
+            for b in chunk {
+                socket.buffer.enqueue(b).ok();
+            }
+        }
+
+        let mut sockets = shared.sockets.borrow_mut();
+        let socket = &mut sockets[id as usize];
+        let mut pos = 0;
+        while pos < buf.len() {
+            match socket.buffer.dequeue() {
+                Some(b) => {
+                    buf[pos] = b;
+                    pos += 1;
+                }
+                None => break,
+            }
+        }
+        Ok(pos)
+    }
+}
+
+impl<U, T, PWR, STATUS> TcpStack for Bg96Controller<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    type SocketHandle = u8;
+
+    fn open(self) -> Response<Self, Self::SocketHandle> {
+        let open_future = self.shared.unwrap().socket_pool.open();
+        Response::immediate_future(self, open_future)
+    }
+
+    fn connect(
+        self,
+        handle: Self::SocketHandle,
+        proto: IpProtocol,
+        dst: SocketAddress,
+    ) -> Response<Self, Result<(), TcpError>> {
+        Response::defer(async move {
+            let command = OpenSocket {
+                id: handle,
+                proto,
+                dst,
+            };
+            let result = match self.modem.unwrap().execute(command).await {
+                Ok(_) => {
+                    let mut sockets = self.shared.unwrap().sockets.borrow_mut();
+                    sockets[handle as usize] = Socket {
+                        opened: true,
+                        connected: true,
+                        ..Default::default()
+                    };
+                    Ok(())
+                }
+                Err(_) => Err(TcpError::ConnectError),
+            };
+            (self, result)
+        })
+    }
+
+    fn write(
+        self,
+        handle: Self::SocketHandle,
+        buf: &[u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        unsafe {
+            Response::defer_unchecked(async move {
+                let result = async {
+                    if !self.shared.unwrap().sockets.borrow()[handle as usize].connected {
+                        return Err(TcpError::SocketClosed);
+                    }
+                    let len = core::cmp::min(buf.len(), MAX_SEND);
+                    let command = SendData {
+                        id: handle,
+                        data: &buf[..len],
+                    };
+                    self.modem
+                        .unwrap()
+                        .execute(command)
+                        .await
+                        .map_err(|_| TcpError::WriteError)?;
+                    Ok(len)
+                }
+                .await;
+                (self, result)
+            })
+        }
+    }
+
+    fn read(
+        mut self,
+        handle: Self::SocketHandle,
+        buf: &mut [u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        unsafe {
+            Response::defer_unchecked(async move {
+                let mut pos = 0;
+                loop {
+                    match self.receive(handle, &mut buf[pos..]).await {
+                        Ok(len) => {
+                            pos += len;
+                            if len == 0 || pos == buf.len() {
+                                return (self, Ok(pos));
+                            }
+                        }
+                        Err(e) => {
+                            if pos == 0 {
+                                return (self, Err(e));
+                            } else {
+                                return (self, Ok(pos));
+                            }
+                        }
+                    }
+                }
+            })
+        }
+    }
+
+    fn close(self, handle: Self::SocketHandle) -> Completion<Self> {
+        Completion::defer(async move {
+            let shared = self.shared.unwrap();
+            let opened = shared.sockets.borrow()[handle as usize].opened;
+            if opened {
+                if let Err(e) = self
+                    .modem
+                    .unwrap()
+                    .execute(CloseSocket { id: handle })
+                    .await
+                {
+                    log::info!("[{}] error closing socket {:?}", ActorInfo::name(), e);
+                }
+            }
+            shared.sockets.borrow_mut()[handle as usize] = Socket::default();
+            shared.socket_pool.close(handle);
+            self
+        })
+    }
+}
+
+impl<U, T, PWR, STATUS> Actor for Bg96Controller<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    type Configuration = (&'static Shared, Address<AtController<U, T>>, Address<T>);
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config.0);
+        self.modem.replace(config.1);
+        self.delayer.replace(config.2);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        Completion::defer(self.start())
+    }
+}
+
+pub struct Shutdown;
+
+impl<U, T, PWR, STATUS> RequestHandler<Shutdown> for Bg96Controller<U, T, PWR, STATUS>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    type Response = Result<(), AtError>;
+
+    fn on_request(mut self, _: Shutdown) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.power_down().await;
+            (self, result)
+        })
+    }
+}
+
+impl<U, T, PWR, STATUS> Address<Bg96Controller<U, T, PWR, STATUS>>
+where
+    U: UartReader + UartWriter + 'static,
+    T: Scheduler + Delayer + 'static,
+    PWR: OutputPin + 'static,
+    STATUS: InputPin + 'static,
+{
+    /// Power the modem down gracefully, such as before cutting its supply, failing with
+    /// `Timeout` if it stays powered. Its sockets are closed, and it stays down until the
+    /// device restarts.
+    pub async fn power_down(&self) -> Result<(), AtError> {
+        self.request(Shutdown).await
+    }
+}
+
+/// Keeps track of the network and sockets as the modem reports on them, while the controller
+/// may be busy.
+pub struct Bg96Monitor {
+    shared: Option<&'static Shared>,
+}
+
+impl Bg96Monitor {
+    pub fn new() -> Self {
+        Self { shared: None }
+    }
+}
+
+impl Actor for Bg96Monitor {
+    type Configuration = &'static Shared;
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config);
+    }
+}
+
+impl NotifyHandler<Bg96Urc> for Bg96Monitor {
+    fn on_notify(self, message: Bg96Urc) -> Completion<Self> {
+        log::debug!("[{}] {:?}", ActorInfo::name(), message);
+        let shared = self.shared.unwrap();
+        let mut sockets = shared.sockets.borrow_mut();
+        match message {
+            Bg96Urc::Registration(registration) => {
+                shared.registration.set(registration);
+            }
+            Bg96Urc::DataAvailable(id) => {
+                if let Some(socket) = sockets.get_mut(id as usize) {
+                    socket.data_available = true;
+                }
+            }
+            Bg96Urc::Closed(id) => {
+                if let Some(socket) = sockets.get_mut(id as usize) {
+                    socket.connected = false;
+                }
+            }
+            Bg96Urc::ContextDeactivated(_) | Bg96Urc::PoweredDown => {
+                log::info!("[{}] connectivity lost", ActorInfo::name());
+                for socket in sockets.iter_mut() {
+                    socket.connected = false;
+                }
+            }
+            Bg96Urc::Ready => {}
+        }
+        drop(sockets);
+        Completion::immediate(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::api::ip::tcp::TcpSocket;
+    use crate::api::ip::IpAddress;
+    use crate::driver::uart::mock::{MockUart, Step, Step::*, Transcript};
+    use crate::hal::gpio::mock::MockPin;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use core::cell::Ref;
+    use std::boxed::Box as StdBox;
+    use std::sync::MutexGuard;
+    use std::vec::Vec;
+
+    type Controller = Bg96Controller<MockUart, MockTimer, MockPin, MockPin>;
+
+    const CONFIG: Bg96Config = Bg96Config {
+        apn: "iot.example",
+        pin: Some("1234"),
+    };
+
+    /// Starting up with the SIM unlocked, and registered already.
+    const STARTUP: &[Step] = &[
+        Send(b"AT\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"ATE0\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CMEE=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CPIN?\r\n"),
+        Receive(b"\r\n+CPIN: READY\r\n\r\nOK\r\n"),
+        Send(b"AT+CEREG=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CEREG?\r\n"),
+        Receive(b"\r\n+CEREG: 1,1\r\n\r\nOK\r\n"),
+        Send(b"AT+QICSGP=1,1,\"iot.example\",\"\",\"\",1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+QIACT=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+QIACT?\r\n"),
+        Receive(b"\r\n+QIACT: 1,1,1,\"10.170.33.5\"\r\n\r\nOK\r\n"),
+    ];
+
+    const CONNECT: &[Step] = &[
+        Send(b"AT+QIOPEN=1,0,\"TCP\",\"192.168.1.2\",8080,0,0\r\n"),
+        Receive(b"\r\nOK\r\n\r\n+QIOPEN: 0,0\r\n"),
+    ];
+
+    struct TestDevice {
+        runtime: MockRuntime,
+        shared: &'static Shared,
+        bg96: Address<Controller>,
+        transcript: Transcript,
+        power_key: MockPin,
+        status: MockPin,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    /// A BG96 powered on already or not, playing `steps`.
+    fn start(powered: bool, steps: &[&'static [Step]]) -> TestDevice {
+        let lock = mock::lock();
+        let script: Vec<Step> = steps.concat();
+        let mut runtime = MockRuntime::new();
+        let uart = MockUart::new(StdBox::leak(script.into_boxed_slice()));
+        let transcript = uart.transcript();
+        let uart = runtime.mount(ActorContext::new(uart), ());
+        let timer = runtime.timer();
+        let power_key = MockPin::new();
+        let status = MockPin::new();
+        status.set(powered);
+        let package = StdBox::leak(StdBox::new(Bg96::new(
+            power_key.clone(),
+            status.clone(),
+            CONFIG,
+        )));
+        let bg96 = package.mount((uart, timer), runtime.supervisor());
+        runtime.start();
+        runtime.run();
+        TestDevice {
+            runtime,
+            shared: &package.shared,
+            bg96,
+            transcript,
+            power_key,
+            status,
+            _lock: lock,
+        }
+    }
+
+    impl TestDevice {
+        /// What is known of the socket the tests use.
+        fn socket(&self) -> Ref<'static, Socket> {
+            Ref::map(self.shared.sockets.borrow(), |sockets| &sockets[0])
+        }
+    }
+
+    fn connect(device: &mut TestDevice) -> TcpSocket<Controller> {
+        let bg96 = device.bg96;
+        device.runtime.block_on(async move {
+            let mut socket = bg96.tcp_open().await;
+            let dst = SocketAddress::new(IpAddress::new_v4(192, 168, 1, 2), 8080);
+            assert!(socket.connect(IpProtocol::Tcp, dst).await.is_ok());
+            socket
+        })
+    }
+
+    #[test]
+    fn test_power_on() {
+        let mut device = start(
+            false,
+            &[
+                &[
+                    Pause,
+                    Receive(b"\r\nRDY\r\n"),
+                    Send(b"AT\r\n"),
+                    Receive(b"AT\r\r\nOK\r\n"),
+                    Send(b"ATE0\r\n"),
+                    Receive(b"ATE0\r\r\nOK\r\n"),
+                    Send(b"AT+CMEE=1\r\n"),
+                    Receive(b"\r\nOK\r\n"),
+                    Send(b"AT+CPIN?\r\n"),
+                    Receive(b"\r\n+CPIN: SIM PIN\r\n\r\nOK\r\n"),
+                    Send(b"AT+CPIN=\"1234\"\r\n"),
+                    Receive(b"\r\nOK\r\n"),
+                    Send(b"AT+CPIN?\r\n"),
+                    Receive(b"\r\n+CPIN: READY\r\n\r\nOK\r\n"),
+                    Send(b"AT+CEREG=1\r\n"),
+                    Receive(b"\r\nOK\r\n"),
+                    Send(b"AT+CEREG?\r\n"),
+                    Receive(b"\r\n+CEREG: 1,2\r\n\r\nOK\r\n"),
+                    Send(b"AT+CEREG?\r\n"),
+                    Receive(b"\r\n+CEREG: 1,5\r\n\r\nOK\r\n"),
+                ],
+                &STARTUP[12..],
+            ],
+        );
+        assert_eq!(device.power_key.take_history(), [true]);
+        device.runtime.sleep(Milliseconds(700u32));
+        assert_eq!(device.power_key.take_history(), [false]);
+
+        // STATUS rises about 5 seconds after PWRKEY was pressed
+        device.runtime.sleep(Milliseconds(4_250u32));
+        device.transcript.resume();
+        device.status.set(true);
+        // registration is checked again after 2 seconds
+        device.runtime.sleep(Milliseconds(3_000u32));
+        device.transcript.finish();
+        assert!(device.power_key.take_history().is_empty());
+    }
+
+    #[test]
+    fn test_power_on_failure() {
+        let mut device = start(false, &[]);
+        device.runtime.sleep(Milliseconds(60_000u32));
+        // never talks to a modem which did not power on
+        assert_eq!(device.power_key.take_history(), [true, false]);
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_already_powered() {
+        let device = start(true, &[STARTUP]);
+        // pressing PWRKEY would power the modem down
+        assert!(device.power_key.take_history().is_empty());
+        assert_eq!(device.runtime.elapsed(), Milliseconds(0u32));
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_socket() {
+        let mut device = start(
+            true,
+            &[
+                STARTUP,
+                CONNECT,
+                &[
+                    Send(b"AT+QISEND=0,5\r\n"),
+                    Receive(b"\r\n> "),
+                    Send(b"hello"),
+                    Receive(b"\r\nSEND OK\r\n"),
+                    Receive(b"\r\n+QIURC: \"recv\",0\r\n"),
+                    Send(b"AT+QIRD=0,192\r\n"),
+                    Receive(b"\r\n+QIRD: 6\r\n\x00\r\nOK\r\r\n\r\nOK\r\n"),
+                    Send(b"AT+QIRD=0,192\r\n"),
+                    Receive(b"\r\n+QIRD: 0\r\n\r\nOK\r\n"),
+                    Receive(b"\r\n+QIURC: \"closed\",0\r\n"),
+                    Send(b"AT+QICLOSE=0\r\n"),
+                    Receive(b"\r\nOK\r\n"),
+                ],
+            ],
+        );
+        let mut socket = connect(&mut device);
+        let written = device.runtime.block_on(socket.write(b"hello"));
+        assert!(matches!(written, Ok(5)));
+        device.runtime.run();
+        assert!(device.socket().data_available);
+
+        let mut buf = [0; 16];
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Ok(6)));
+        assert_eq!(&buf[..6], b"\x00\r\nOK\r");
+        assert!(!device.socket().data_available);
+
+        device.runtime.run();
+        assert!(!device.socket().connected);
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Err(TcpError::SocketClosed)));
+        let written = device.runtime.block_on(socket.write(b"hello"));
+        assert!(matches!(written, Err(TcpError::SocketClosed)));
+
+        // closed by the peer, but still to be closed on the modem
+        drop(socket);
+        device.runtime.run();
+        assert!(!device.socket().opened);
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_power_down() {
+        let mut device = start(
+            true,
+            &[
+                STARTUP,
+                CONNECT,
+                &[
+                    Send(b"AT+QPOWD\r\n"),
+                    Receive(b"\r\nOK\r\n"),
+                    Receive(b"\r\nPOWERED DOWN\r\n"),
+                ],
+            ],
+        );
+        let mut socket = connect(&mut device);
+        let bg96 = device.bg96;
+        let status = device.status.clone();
+        let timer = device.runtime.timer();
+        let drop_status = async {
+            timer.delay(Milliseconds(1_500u32)).await;
+            status.set(false);
+        };
+        let (result, _) = device
+            .runtime
+            .block_on(futures::future::join(bg96.power_down(), drop_status));
+        assert!(result.is_ok());
+        assert_eq!(device.runtime.elapsed(), Milliseconds(1_500u32));
+        assert!(device.power_key.take_history().is_empty());
+
+        assert!(!device.socket().connected);
+        let written = device.runtime.block_on(socket.write(b"hello"));
+        assert!(matches!(written, Err(TcpError::SocketClosed)));
+        // nothing to close on a modem powered down
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+}
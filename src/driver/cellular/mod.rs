@@ -0,0 +1,3 @@
+//! Cellular modems.
+
+pub mod bg96;
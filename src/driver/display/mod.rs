@@ -0,0 +1,3 @@
+//! Displays drawn through a framebuffer.
+
+pub mod ssd1306;
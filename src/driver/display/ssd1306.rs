@@ -0,0 +1,569 @@
+//! Monochrome OLEDs of the SSD1306 controller, over I2C or SPI.
+//!
+//! The whole screen is kept in a framebuffer and sent over on each flush, so drawing does not
+//! touch the bus.
+use crate::api::arbitrator::BusArbitrator;
+use crate::api::delayer::Delayer;
+use crate::api::display::{Display, DisplayError};
+use crate::api::i2c::I2cAddress;
+use crate::api::spi::{ChipSelect, SpiBus, SpiConfig, MODE_0};
+use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::rate::Hertz;
+use crate::driver::i2c::I2cPeripheral;
+use crate::prelude::*;
+use embedded_hal::blocking::i2c::Write;
+use embedded_hal::digital::v2::OutputPin;
+
+/// The address of the controller, unless its `SA0` pin is pulled high.
+pub const ADDR: u8 = 0x3C;
+
+const WIDTH: usize = 128;
+
+/// Bytes of commands or data per I2C write, after the control byte.
+const CHUNK: usize = 16;
+
+const SPI_CONFIG: SpiConfig = SpiConfig::new(MODE_0, Hertz(8_000_000));
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplaySize {
+    Display128x64,
+    Display128x32,
+}
+
+impl DisplaySize {
+    fn height(&self) -> usize {
+        match self {
+            DisplaySize::Display128x64 => 64,
+            DisplaySize::Display128x32 => 32,
+        }
+    }
+
+    /// Rows of eight pixels, a byte per column.
+    fn pages(&self) -> usize {
+        self.height() / 8
+    }
+
+    fn init(&self) -> [u8; 25] {
+        let com_pins = match self {
+            DisplaySize::Display128x64 => 0x12,
+            DisplaySize::Display128x32 => 0x02,
+        };
+        [
+            // display off
+            0xAE,
+            // clock divider and oscillator frequency
+            0xD5,
+            0x80,
+            // multiplex ratio
+            0xA8,
+            self.height() as u8 - 1,
+            // no display offset, start at line 0
+            0xD3,
+            0x00,
+            0x40,
+            // charge pump on
+            0x8D,
+            0x14,
+            // horizontal addressing, so a flush is a single run of data
+            0x20,
+            0x00,
+            // column 0 on the left, row 0 on top
+            0xA1,
+            0xC8,
+            0xDA,
+            com_pins,
+            // contrast
+            0x81,
+            0xCF,
+            // pre-charge period
+            0xD9,
+            0xF1,
+            // deselect level
+            0xDB,
+            0x40,
+            // show the RAM, not inverted
+            0xA4,
+            0xA6,
+            // display on
+            0xAF,
+        ]
+    }
+
+    /// Commands to write the whole of the RAM next.
+    fn window(&self) -> [u8; 6] {
+        [0x21, 0, WIDTH as u8 - 1, 0x22, 0, self.pages() as u8 - 1]
+    }
+}
+
+/// The pixels of the screen, in pages of eight rows with a byte per column, as the
+/// controller takes them.
+pub struct Framebuffer {
+    buffer: [u8; WIDTH * 8],
+    size: DisplaySize,
+}
+
+impl Framebuffer {
+    fn new(size: DisplaySize) -> Self {
+        Self {
+            buffer: [0; WIDTH * 8],
+            size,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        self.size.height()
+    }
+
+    /// Light the pixel or not. Pixels off the screen are left out.
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        if x >= WIDTH || y >= self.height() {
+            return;
+        }
+        let byte = &mut self.buffer[y / 8 * WIDTH + x];
+        if on {
+            *byte |= 1 << (y % 8);
+        } else {
+            *byte &= !(1 << (y % 8));
+        }
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        x < WIDTH && y < self.height() && self.buffer[y / 8 * WIDTH + x] & (1 << (y % 8)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        for byte in self.buffer.iter_mut() {
+            *byte = 0;
+        }
+    }
+
+    fn data(&self) -> &[u8] {
+        &self.buffer[..self.size.pages() * WIDTH]
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl embedded_graphics::DrawTarget<embedded_graphics::pixelcolor::BinaryColor> for Framebuffer {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(
+        &mut self,
+        pixel: embedded_graphics::drawable::Pixel<embedded_graphics::pixelcolor::BinaryColor>,
+    ) -> Result<(), Self::Error> {
+        let embedded_graphics::drawable::Pixel(point, color) = pixel;
+        if point.x >= 0 && point.y >= 0 {
+            self.set_pixel(point.x as usize, point.y as usize, color.is_on());
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> embedded_graphics::geometry::Size {
+        embedded_graphics::geometry::Size::new(WIDTH as u32, self.height() as u32)
+    }
+}
+
+/// An SSD1306 display, on the interface `IF`.
+pub struct Ssd1306<IF> {
+    interface: IF,
+    buffer: Framebuffer,
+}
+
+impl<IF> Ssd1306<IF> {
+    fn with_interface(interface: IF, size: DisplaySize) -> Self {
+        Self {
+            interface,
+            buffer: Framebuffer::new(size),
+        }
+    }
+}
+
+// ------------------------------------------------------------------------
+// I2C
+// ------------------------------------------------------------------------
+
+/// Commands and data over I2C, each write telling which it is by its first byte.
+pub struct I2cInterface<I>
+where
+    I: 'static,
+{
+    i2c: Option<Address<I2cPeripheral<I>>>,
+    address: I2cAddress,
+}
+
+impl<I> I2cInterface<I>
+where
+    I: Write + 'static,
+{
+    async fn commands(&self, commands: &[u8]) -> Result<(), DisplayError> {
+        self.send(0x00, commands).await
+    }
+
+    async fn data(&self, data: &[u8]) -> Result<(), DisplayError> {
+        self.send(0x40, data).await
+    }
+
+    async fn send(&self, control: u8, bytes: &[u8]) -> Result<(), DisplayError> {
+        let i2c = self.i2c.ok_or(DisplayError::Bus)?;
+        let mut write = [control; CHUNK + 1];
+        for chunk in bytes.chunks(CHUNK) {
+            write[1..=chunk.len()].copy_from_slice(chunk);
+            i2c.write(self.address, &write[..=chunk.len()])
+                .await
+                .map_err(|_| DisplayError::Bus)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> Ssd1306<I2cInterface<I>>
+where
+    I: Write + 'static,
+{
+    pub fn new(size: DisplaySize) -> Self {
+        Self::with_interface(
+            I2cInterface {
+                i2c: None,
+                address: I2cAddress::new(ADDR),
+            },
+            size,
+        )
+    }
+
+    /// Use `address` rather than the default of `0x3C`.
+    pub fn with_address(mut self, address: I2cAddress) -> Self {
+        self.interface.address = address;
+        self
+    }
+}
+
+impl<I> Actor for Ssd1306<I2cInterface<I>>
+where
+    I: Write + 'static,
+{
+    type Configuration = Address<I2cPeripheral<I>>;
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.interface.i2c.replace(config);
+    }
+
+    fn on_initialize(self) -> Completion<Self> {
+        Completion::defer(async move {
+            let init = self.buffer.size.init();
+            if let Err(e) = self.interface.commands(&init).await {
+                log::error!("[{}] initialization failed: {:?}", ActorInfo::name(), e);
+            }
+            self
+        })
+    }
+}
+
+impl<I> Display for Ssd1306<I2cInterface<I>>
+where
+    I: Write + 'static,
+{
+    type Buffer = Framebuffer;
+
+    fn draw<F: FnOnce(&mut Self::Buffer)>(&mut self, draw: F) {
+        draw(&mut self.buffer);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn flush(self) -> Response<Self, Result<(), DisplayError>> {
+        Response::defer(async move {
+            let window = self.buffer.size.window();
+            let mut result = self.interface.commands(&window).await;
+            if result.is_ok() {
+                result = self.interface.data(self.buffer.data()).await;
+            }
+            (self, result)
+        })
+    }
+}
+
+// ------------------------------------------------------------------------
+// SPI
+// ------------------------------------------------------------------------
+
+/// Commands and data over SPI, told apart by the level of the data/command pin.
+pub struct SpiInterface<SPI, CS, DC, T>
+where
+    SPI: SpiBus<Word = u8> + 'static,
+    CS: OutputPin + 'static,
+    DC: OutputPin + 'static,
+    T: Delayer + 'static,
+{
+    spi: Option<Address<BusArbitrator<SPI>>>,
+    cs: Option<&'static ChipSelect<CS, T>>,
+    dc: DC,
+}
+
+impl<SPI, CS, DC, T> SpiInterface<SPI, CS, DC, T>
+where
+    SPI: SpiBus<Word = u8> + 'static,
+    CS: OutputPin + 'static,
+    DC: OutputPin + 'static,
+    T: Delayer + 'static,
+{
+    async fn commands(&mut self, commands: &[u8]) -> Result<(), DisplayError> {
+        self.dc.set_low().ok();
+        self.send(commands).await
+    }
+
+    async fn data(&mut self, data: &[u8]) -> Result<(), DisplayError> {
+        self.dc.set_high().ok();
+        self.send(data).await
+    }
+
+    async fn send(&self, bytes: &[u8]) -> Result<(), DisplayError> {
+        let (spi, cs) = match (self.spi, self.cs) {
+            (Some(spi), Some(cs)) => (spi, cs),
+            _ => return Err(DisplayError::Bus),
+        };
+        let spi = spi.begin_selected_transaction(cs, SPI_CONFIG).await;
+        spi.spi_write(bytes).await.map_err(|_| DisplayError::Bus)
+    }
+}
+
+/// An SSD1306 display on a shared SPI bus, along with its chip select.
+pub struct Ssd1306Spi<SPI, CS, DC, T>
+where
+    SPI: SpiBus<Word = u8> + 'static,
+    CS: OutputPin + 'static,
+    DC: OutputPin + 'static,
+    T: Delayer + 'static,
+{
+    cs: ChipSelect<CS, T>,
+    display: ActorContext<Ssd1306<SpiInterface<SPI, CS, DC, T>>>,
+}
+
+impl<SPI, CS, DC, T> Ssd1306Spi<SPI, CS, DC, T>
+where
+    SPI: SpiBus<Word = u8>,
+    CS: OutputPin,
+    DC: OutputPin + 'static,
+    T: Delayer,
+{
+    pub fn new(cs: CS, dc: DC, size: DisplaySize) -> Self {
+        let interface = SpiInterface {
+            spi: None,
+            cs: None,
+            dc,
+        };
+        Self {
+            cs: ChipSelect::new(cs, Milliseconds(0u32)),
+            display: ActorContext::new(Ssd1306::with_interface(interface, size))
+                .with_name("ssd1306"),
+        }
+    }
+}
+
+impl<SPI, CS, DC, T> Package for Ssd1306Spi<SPI, CS, DC, T>
+where
+    SPI: SpiBus<Word = u8>,
+    CS: OutputPin,
+    DC: OutputPin + 'static,
+    T: Delayer,
+{
+    type Primary = Ssd1306<SpiInterface<SPI, CS, DC, T>>;
+    type Configuration = (Address<BusArbitrator<SPI>>, Address<T>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        self.cs.set_delayer(config.1);
+        self.display.mount((config.0, &self.cs), supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.display.address()
+    }
+}
+
+impl<SPI, CS, DC, T> Actor for Ssd1306<SpiInterface<SPI, CS, DC, T>>
+where
+    SPI: SpiBus<Word = u8>,
+    CS: OutputPin,
+    DC: OutputPin + 'static,
+    T: Delayer,
+{
+    type Configuration = (Address<BusArbitrator<SPI>>, &'static ChipSelect<CS, T>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.interface.spi.replace(config.0);
+        self.interface.cs.replace(config.1);
+    }
+
+    fn on_initialize(mut self) -> Completion<Self> {
+        Completion::defer(async move {
+            let init = self.buffer.size.init();
+            if let Err(e) = self.interface.commands(&init).await {
+                log::error!("[{}] initialization failed: {:?}", ActorInfo::name(), e);
+            }
+            self
+        })
+    }
+}
+
+impl<SPI, CS, DC, T> Display for Ssd1306<SpiInterface<SPI, CS, DC, T>>
+where
+    SPI: SpiBus<Word = u8>,
+    CS: OutputPin,
+    DC: OutputPin + 'static,
+    T: Delayer,
+{
+    type Buffer = Framebuffer;
+
+    fn draw<F: FnOnce(&mut Self::Buffer)>(&mut self, draw: F) {
+        draw(&mut self.buffer);
+    }
+
+    fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn flush(mut self) -> Response<Self, Result<(), DisplayError>> {
+        Response::defer(async move {
+            let window = self.buffer.size.window();
+            let mut result = self.interface.commands(&window).await;
+            if result.is_ok() {
+                result = self.interface.data(self.buffer.data()).await;
+            }
+            (self, result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::i2c::I2c;
+    use crate::driver::sensor::mock::{AutoIncrement, MockI2c, SharedI2c, Transaction};
+    use crate::system::mock::{self, MockRuntime};
+
+    type TestDisplay = Ssd1306<I2cInterface<SharedI2c>>;
+
+    /// The display, initialized, with what it wrote to the bus so far.
+    fn start(size: DisplaySize) -> (MockRuntime, Address<TestDisplay>, SharedI2c) {
+        let i2c = SharedI2c::new(MockI2c::new(ADDR, AutoIncrement::Always));
+        let mut runtime = MockRuntime::new();
+        let peripheral = runtime.mount_package(I2c::new(i2c.clone()), ());
+        let display = runtime.mount(ActorContext::new(TestDisplay::new(size)), peripheral);
+        runtime.start();
+        runtime.run();
+        (runtime, display, i2c)
+    }
+
+    #[test]
+    fn test_initialize() {
+        let _lock = mock::lock();
+        for size in [DisplaySize::Display128x64, DisplaySize::Display128x32].iter() {
+            let (_, _, i2c) = start(*size);
+            let init = size.init();
+            // commands, sixteen at a time
+            assert_eq!(
+                i2c.borrow_mut().log,
+                [
+                    Transaction::Write(0x00, init[..16].to_vec()),
+                    Transaction::Write(0x00, init[16..].to_vec()),
+                ]
+            );
+        }
+        assert_eq!(DisplaySize::Display128x64.init()[4], 63);
+        assert_eq!(DisplaySize::Display128x32.init()[4], 31);
+    }
+
+    #[test]
+    fn test_flush() {
+        let _lock = mock::lock();
+        let (mut runtime, display, i2c) = start(DisplaySize::Display128x32);
+        i2c.borrow_mut().log.clear();
+        display.draw(|buffer: &mut Framebuffer| {
+            buffer.set_pixel(0, 0, true);
+            buffer.set_pixel(127, 31, true);
+        });
+        assert_eq!(runtime.block_on(display.flush()), Ok(()));
+
+        let i2c = i2c.borrow_mut();
+        // the window of the whole screen, then its four pages of data, sixteen bytes at a time
+        assert_eq!(
+            i2c.log[0],
+            Transaction::Write(0x00, [0x21, 0, 127, 0x22, 0, 3].to_vec())
+        );
+        assert_eq!(i2c.log.len(), 1 + 512 / CHUNK);
+        let mut data = [0; 16];
+        data[0] = 0x01;
+        assert_eq!(i2c.log[1], Transaction::Write(0x40, data.to_vec()));
+        let mut data = [0; 16];
+        data[15] = 0x80;
+        assert_eq!(i2c.log[32], Transaction::Write(0x40, data.to_vec()));
+        assert!(i2c.log[2..32]
+            .iter()
+            .all(|write| *write == Transaction::Write(0x40, [0; 16].to_vec())));
+    }
+
+    #[test]
+    fn test_framebuffer() {
+        let mut buffer = Framebuffer::new(DisplaySize::Display128x32);
+        buffer.set_pixel(3, 9, true);
+        buffer.set_pixel(127, 31, true);
+        // off the screen
+        buffer.set_pixel(128, 0, true);
+        buffer.set_pixel(0, 32, true);
+
+        assert!(buffer.is_set(3, 9));
+        assert_eq!(buffer.data().len(), 512);
+        assert_eq!(buffer.data()[WIDTH + 3], 0b10);
+        assert_eq!(buffer.data()[3 * WIDTH + 127], 0x80);
+        assert_eq!(buffer.data().iter().filter(|byte| **byte != 0).count(), 2);
+
+        buffer.set_pixel(3, 9, false);
+        assert!(!buffer.is_set(3, 9));
+        buffer.clear();
+        assert!(buffer.data().iter().all(|byte| *byte == 0));
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn test_draw_target() {
+        use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+
+        let mut buffer = Framebuffer::new(DisplaySize::Display128x64);
+        assert_eq!(buffer.size(), Size::new(128, 64));
+        Pixel(Point::new(3, 9), BinaryColor::On)
+            .draw(&mut buffer)
+            .unwrap();
+        Pixel(Point::new(127, 63), BinaryColor::On)
+            .draw(&mut buffer)
+            .unwrap();
+        // off the screen, on either side
+        Pixel(Point::new(-1, 0), BinaryColor::On)
+            .draw(&mut buffer)
+            .unwrap();
+        Pixel(Point::new(0, 64), BinaryColor::On)
+            .draw(&mut buffer)
+            .unwrap();
+        assert!(buffer.is_set(3, 9));
+        assert!(buffer.is_set(127, 63));
+        assert_eq!(buffer.data().iter().filter(|byte| **byte != 0).count(), 2);
+
+        Pixel(Point::new(3, 9), BinaryColor::Off)
+            .draw(&mut buffer)
+            .unwrap();
+        assert!(!buffer.is_set(3, 9));
+    }
+}
@@ -0,0 +1,422 @@
+use crate::prelude::*;
+
+use crate::api::arbitrator::{Arbitrator, Bus};
+use crate::api::i2c::{I2cBus, I2cError, I2cTransfer};
+use crate::api::scheduler::{ScheduleHandle, Scheduler};
+use crate::domain::time::duration::Milliseconds;
+use crate::hal::i2c::dma::DmaI2cHal;
+use crate::synchronization::Signal;
+
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::task::{Context, Poll};
+use cortex_m::interrupt::Nr;
+
+const DEFAULT_TIMEOUT: Milliseconds = Milliseconds(100);
+
+/// State shared between the controller and the interrupt. Whichever of the interrupt and the
+/// timeout ends a transfer first reports its result, the other one is ignored.
+pub struct Shared {
+    in_flight: AtomicBool,
+    transfer: AtomicU32,
+    done: Signal<Result<(), I2cError>>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            in_flight: AtomicBool::new(false),
+            transfer: AtomicU32::new(0),
+            done: Signal::new(),
+        }
+    }
+
+    /// Mark a new transfer as in flight, returning its id.
+    fn begin(&self) -> u32 {
+        self.done.reset();
+        let id = self.transfer.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+        self.in_flight.store(true, Ordering::Release);
+        id
+    }
+
+    /// Take ownership of ending the transfer in flight, if any.
+    fn end(&self) -> bool {
+        self.in_flight.swap(false, Ordering::AcqRel)
+    }
+
+    /// Take ownership of ending transfer `id`, if it is still in flight.
+    fn expire(&self, id: u32) -> bool {
+        self.transfer.load(Ordering::Acquire) == id && self.end()
+    }
+
+    fn complete(&self, result: Result<(), I2cError>) {
+        self.done.signal(result);
+    }
+}
+
+/// An I2C bus driven by interrupts, with transfers serialized through an `Arbitrator`.
+pub struct DmaI2c<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    i2c: I,
+    timeout: Milliseconds,
+    shared: Shared,
+    arbitrator: Arbitrator<I2cController<I, S>>,
+    controller: ActorContext<I2cController<I, S>>,
+    interrupt: InterruptContext<I2cInterrupt<I>>,
+}
+
+impl<I, S> DmaI2c<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    pub fn new<IRQ>(i2c: I, irq: IRQ) -> Self
+    where
+        IRQ: Nr,
+    {
+        Self {
+            i2c,
+            timeout: DEFAULT_TIMEOUT,
+            shared: Shared::new(),
+            arbitrator: Arbitrator::new(),
+            controller: ActorContext::new(I2cController::new()).with_name("i2c_controller"),
+            interrupt: InterruptContext::new(I2cInterrupt::new(), irq).with_name("i2c_interrupt"),
+        }
+    }
+
+    /// Timeout of transfers not specifying their own.
+    pub fn with_timeout(mut self, timeout: Milliseconds) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<I, S> Package for DmaI2c<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    type Primary = <Arbitrator<I2cController<I, S>> as Package>::Primary;
+    type Configuration = Address<S>;
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let controller = self
+            .controller
+            .mount((&self.i2c, &self.shared, config, self.timeout), supervisor);
+        self.interrupt.mount((&self.i2c, &self.shared), supervisor);
+        self.arbitrator.mount(controller, supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.arbitrator.primary()
+    }
+}
+
+pub struct I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    me: Option<Address<Self>>,
+    i2c: Option<&'static I>,
+    shared: Option<&'static Shared>,
+    scheduler: Option<Address<S>>,
+    timeout: Milliseconds,
+}
+
+impl<I, S> I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    fn new() -> Self {
+        Self {
+            me: None,
+            i2c: None,
+            shared: None,
+            scheduler: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl<I, S> Actor for I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    type Configuration = (&'static I, &'static Shared, Address<S>, Milliseconds);
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.me.replace(me);
+        self.i2c.replace(config.0);
+        self.shared.replace(config.1);
+        self.scheduler.replace(config.2);
+        self.timeout = config.3;
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        // A device may have been left holding SDA by a reset in the middle of a transfer.
+        self.i2c.unwrap().recover();
+        Completion::immediate(self)
+    }
+}
+
+impl<I, S> Bus for I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    type Config = ();
+
+    fn configure(self, config: Self::Config) -> Response<Self, ()> {
+        Response::immediate(self, ())
+    }
+}
+
+impl<I, S> I2cBus for I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    /// The buffers of the transfer must be available until the returned future is await'ed.
+    fn transfer(self, transfer: I2cTransfer) -> Response<Self, Result<(), I2cError>> {
+        let i2c = self.i2c.unwrap();
+        let shared = self.shared.unwrap();
+
+        if let Err(e) = i2c.prepare_transfer(transfer.address.into(), transfer.write, transfer.read)
+        {
+            return Response::immediate(self, Err(e));
+        }
+
+        let id = shared.begin();
+        i2c.start_transfer();
+        let timeout = self.scheduler.unwrap().schedule(
+            transfer.timeout.unwrap_or(self.timeout),
+            TransferTimeout(id),
+            self.me.unwrap(),
+        );
+        Response::immediate_future(self, TransferFuture::new(shared, timeout))
+    }
+}
+
+impl<I, S> NotifyHandler<TransferTimeout> for I2cController<I, S>
+where
+    I: DmaI2cHal + 'static,
+    S: Scheduler + 'static,
+{
+    fn on_notify(self, message: TransferTimeout) -> Completion<Self> {
+        let shared = self.shared.unwrap();
+        if shared.expire(message.0) {
+            log::warn!("[I2C] transfer timed out, recovering bus");
+            let i2c = self.i2c.unwrap();
+            i2c.cancel_transfer();
+            i2c.recover();
+            shared.complete(Err(I2cError::Timeout));
+        }
+        Completion::immediate(self)
+    }
+}
+
+pub struct I2cInterrupt<I>
+where
+    I: DmaI2cHal + 'static,
+{
+    i2c: Option<&'static I>,
+    shared: Option<&'static Shared>,
+}
+
+impl<I> I2cInterrupt<I>
+where
+    I: DmaI2cHal + 'static,
+{
+    fn new() -> Self {
+        Self {
+            i2c: None,
+            shared: None,
+        }
+    }
+}
+
+impl<I> Actor for I2cInterrupt<I>
+where
+    I: DmaI2cHal + 'static,
+{
+    type Configuration = (&'static I, &'static Shared);
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.i2c.replace(config.0);
+        self.shared.replace(config.1);
+    }
+}
+
+impl<I> Interrupt for I2cInterrupt<I>
+where
+    I: DmaI2cHal + 'static,
+{
+    fn on_interrupt(&mut self) {
+        let i2c = self.i2c.unwrap();
+        let shared = self.shared.unwrap();
+
+        // Events of a cancelled transfer are cleared, but not reported.
+        if i2c.process_interrupts() && shared.end() {
+            let result = i2c.finish_transfer();
+            log::trace!("[I2C ISR] transfer done: {:?}", result);
+            if result.is_err() {
+                i2c.recover();
+            }
+            shared.complete(result);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TransferTimeout(u32);
+
+/// Resolves when the transfer ends, cancelling its timeout so that it does not hold a slot of
+/// the timer until it would have fired.
+struct TransferFuture<S: Scheduler + 'static> {
+    shared: &'static Shared,
+    timeout: ScheduleHandle<S>,
+}
+
+impl<S: Scheduler> TransferFuture<S> {
+    fn new(shared: &'static Shared, timeout: ScheduleHandle<S>) -> Self {
+        Self { shared, timeout }
+    }
+}
+
+impl<S: Scheduler> Future for TransferFuture<S> {
+    type Output = Result<(), I2cError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = self.shared.done.poll_wait(cx);
+        if result.is_ready() {
+            // a no-op when it was the timeout which ended the transfer
+            self.timeout.cancel();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::api::i2c::I2cAddress;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use core::cell::Cell;
+    use futures::future::{join, poll_fn};
+    use std::boxed::Box;
+
+    /// Ends each transfer it is started on when its interrupt is raised.
+    #[derive(Default)]
+    struct TestHal {
+        started: Cell<bool>,
+    }
+
+    impl DmaI2cHal for TestHal {
+        fn prepare_transfer(&self, _: u8, _: &[u8], _: &mut [u8]) -> Result<(), I2cError> {
+            Ok(())
+        }
+
+        fn start_transfer(&self) {
+            self.started.set(true);
+        }
+
+        fn finish_transfer(&self) -> Result<(), I2cError> {
+            Ok(())
+        }
+
+        fn cancel_transfer(&self) {
+            self.started.set(false);
+        }
+
+        fn recover(&self) {}
+
+        fn process_interrupts(&self) -> bool {
+            self.started.replace(false)
+        }
+    }
+
+    #[test]
+    fn test_timeouts_cancelled() {
+        let _lock = mock::lock();
+        let hal: &'static TestHal = Box::leak(Box::new(TestHal::default()));
+        let shared: &'static Shared = Box::leak(Box::new(Shared::new()));
+        let mut runtime = MockRuntime::new();
+        let timer = runtime.timer();
+        let controller = runtime.mount(
+            ActorContext::new(I2cController::<TestHal, MockTimer>::new()),
+            (hal, shared, timer, DEFAULT_TIMEOUT),
+        );
+        runtime.start();
+        runtime.run();
+        let mut interrupt = I2cInterrupt {
+            i2c: Some(hal),
+            shared: Some(shared),
+        };
+
+        // more transfers than the timer has slots, each ended by its interrupt right away
+        for _ in 0..40 {
+            let (result, _) = runtime.block_on(join(
+                controller.request(I2cTransfer::write(I2cAddress::new(0x10), &[0x01])),
+                poll_fn(|cx| {
+                    if hal.started.get() {
+                        interrupt.on_interrupt();
+                        Poll::Ready(())
+                    } else {
+                        cx.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                }),
+            ));
+            assert_eq!(result, Ok(()));
+        }
+        assert_eq!(runtime.elapsed(), Milliseconds(0u32));
+
+        // the timeouts left no deadlines behind, so one is still there to end a transfer
+        // never answered
+        let result = runtime
+            .block_on(controller.request(I2cTransfer::write(I2cAddress::new(0x10), &[0x01])));
+        assert_eq!(result, Err(I2cError::Timeout));
+        assert_eq!(runtime.elapsed(), DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn test_interrupt_ends_transfer() {
+        let shared = Shared::new();
+        let id = shared.begin();
+        assert!(shared.end());
+        // the timeout of the completed transfer fires later
+        assert!(!shared.expire(id));
+    }
+
+    #[test]
+    fn test_timeout_ends_transfer() {
+        let shared = Shared::new();
+        let id = shared.begin();
+        assert!(shared.expire(id));
+        // the interrupt raised by cancelling is not reported
+        assert!(!shared.end());
+    }
+
+    #[test]
+    fn test_stale_timeout() {
+        let shared = Shared::new();
+        let first = shared.begin();
+        assert!(shared.end());
+        let second = shared.begin();
+        assert!(!shared.expire(first));
+        assert!(shared.expire(second));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod dma;
+
 use crate::api::i2c::I2cAddress;
 use crate::prelude::*;
 use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
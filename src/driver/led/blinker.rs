@@ -1,4 +1,4 @@
-use crate::api::scheduler::Scheduler;
+use crate::api::scheduler::{ScheduleHandle, Scheduler};
 use crate::api::switchable::Switchable;
 use crate::domain::time::duration::Milliseconds;
 use crate::prelude::*;
@@ -12,6 +12,8 @@ where
     timer: Option<Address<T>>,
     delay: Milliseconds,
     address: Option<Address<Self>>,
+    on: bool,
+    schedule: Option<ScheduleHandle<T>>,
 }
 
 impl<S, T> Blinker<S, T>
@@ -25,8 +27,18 @@ where
             timer: None,
             delay: delay.into(),
             address: None,
+            on: false,
+            schedule: None,
         }
     }
+
+    fn start(&mut self) {
+        let handle =
+            self.timer
+                .unwrap()
+                .schedule_periodic(self.delay, Toggle, self.address.unwrap());
+        self.schedule.replace(handle);
+    }
 }
 
 impl<S, T> Actor for Blinker<S, T>
@@ -45,39 +57,26 @@ where
         self.timer.replace(config.1);
     }
 
-    fn on_start(self) -> Completion<Self> {
-        self.timer
-            .unwrap()
-            .schedule(self.delay, State::On, self.address.unwrap());
+    fn on_start(mut self) -> Completion<Self> {
+        self.start();
         Completion::immediate(self)
     }
 }
 
 #[derive(Copy, Clone, Debug)]
-enum State {
-    On,
-    Off,
-}
+struct Toggle;
 
-impl<S, T> NotifyHandler<State> for Blinker<S, T>
+impl<S, T> NotifyHandler<Toggle> for Blinker<S, T>
 where
     S: Switchable,
     T: Scheduler,
 {
-    fn on_notify(self, message: State) -> Completion<Self> {
-        match message {
-            State::On => {
-                self.led.unwrap().turn_on();
-                self.timer
-                    .unwrap()
-                    .schedule(self.delay, State::Off, self.address.unwrap());
-            }
-            State::Off => {
-                self.led.unwrap().turn_off();
-                self.timer
-                    .unwrap()
-                    .schedule(self.delay, State::On, self.address.unwrap());
-            }
+    fn on_notify(mut self, message: Toggle) -> Completion<Self> {
+        self.on = !self.on;
+        if self.on {
+            self.led.unwrap().turn_on();
+        } else {
+            self.led.unwrap().turn_off();
         }
         Completion::immediate(self)
     }
@@ -92,6 +91,10 @@ where
 {
     fn on_notify(mut self, message: AdjustDelay) -> Completion<Self> {
         self.delay = message.0;
+        if let Some(schedule) = self.schedule.take() {
+            schedule.cancel();
+            self.start();
+        }
         Completion::immediate(self)
     }
 }
@@ -0,0 +1,265 @@
+use crate::api::pwm::{Duty, Pwm};
+use crate::api::switchable::{Off, On, Switchable};
+use crate::hal::pwm::Pwm as HalPwm;
+use crate::hal::Active;
+use crate::prelude::*;
+
+/// An LED on a PWM channel, turned on at a brightness that steps through a number of levels.
+pub struct DimmableLED<P>
+where
+    P: HalPwm,
+{
+    pwm: P,
+    active: Active,
+    levels: u8,
+    level: u8,
+    on: bool,
+}
+
+impl<P> DimmableLED<P>
+where
+    P: HalPwm,
+{
+    /// An LED going from off through 8 levels of brightness, at full brightness to begin with.
+    pub fn new(pwm: P, active: Active) -> Self {
+        Self::with_levels(pwm, active, 8)
+    }
+
+    pub fn with_levels(pwm: P, active: Active, levels: u8) -> Self {
+        let levels = if levels == 0 { 1 } else { levels };
+        Self {
+            pwm,
+            active,
+            levels,
+            level: levels,
+            on: false,
+        }
+    }
+
+    /// Brightness out of 255, as perceived.
+    fn brightness(&self) -> u8 {
+        (self.level as u16 * 255 / self.levels as u16) as u8
+    }
+
+    fn output(&mut self, duty: Duty) {
+        let duty = match self.active {
+            Active::High => duty,
+            Active::Low => duty.inverted(),
+        };
+        self.pwm.set_duty(duty);
+    }
+
+    fn update(&mut self) {
+        let duty = if self.on {
+            Duty::perceived(self.brightness())
+        } else {
+            Duty::OFF
+        };
+        self.output(duty);
+    }
+}
+
+impl<P> Actor for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    type Configuration = ();
+
+    fn on_start(mut self) -> Completion<Self> {
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+impl<P> Switchable for DimmableLED<P> where P: HalPwm + 'static {}
+
+impl<P> Pwm for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    /// Drive the LED at `duty` directly, until it is next turned on or off.
+    fn set_duty(&mut self, duty: Duty) {
+        self.output(duty);
+    }
+}
+
+impl<P> NotifyHandler<On> for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    fn on_notify(mut self, message: On) -> Completion<Self> {
+        self.on = true;
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+impl<P> NotifyHandler<Off> for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    fn on_notify(mut self, message: Off) -> Completion<Self> {
+        self.on = false;
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+/// Set the level of brightness, from 0 to the number of levels, for whenever the LED is on.
+#[derive(Copy, Clone, Debug)]
+pub struct SetLevel(pub u8);
+
+impl<P> NotifyHandler<SetLevel> for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    fn on_notify(mut self, message: SetLevel) -> Completion<Self> {
+        self.level = if message.0 > self.levels {
+            self.levels
+        } else {
+            message.0
+        };
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Brighter;
+
+impl<P> NotifyHandler<Brighter> for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    fn on_notify(mut self, message: Brighter) -> Completion<Self> {
+        if self.level < self.levels {
+            self.level += 1;
+        }
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Dimmer;
+
+impl<P> NotifyHandler<Dimmer> for DimmableLED<P>
+where
+    P: HalPwm + 'static,
+{
+    fn on_notify(mut self, message: Dimmer) -> Completion<Self> {
+        self.level = self.level.saturating_sub(1);
+        self.update();
+        Completion::immediate(self)
+    }
+}
+
+impl<P> Address<DimmableLED<P>>
+where
+    P: HalPwm + 'static,
+{
+    pub fn set_level(&self, level: u8) {
+        self.notify(SetLevel(level));
+    }
+
+    pub fn brighter(&self) {
+        self.notify(Brighter);
+    }
+
+    pub fn dimmer(&self) {
+        self.notify(Dimmer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::system::mock::{self, MockRuntime};
+    use core::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    /// Keeps the duties it is set to, for the test to take.
+    #[derive(Clone, Default)]
+    struct MockPwm(Rc<RefCell<Vec<Duty>>>);
+
+    impl MockPwm {
+        fn take(&self) -> Vec<Duty> {
+            self.0.borrow_mut().drain(..).collect()
+        }
+    }
+
+    impl HalPwm for MockPwm {
+        fn set_duty(&mut self, duty: Duty) {
+            self.0.borrow_mut().push(duty);
+        }
+    }
+
+    fn start(active: Active) -> (MockRuntime, Address<DimmableLED<MockPwm>>, MockPwm) {
+        let mut runtime = MockRuntime::new();
+        let pwm = MockPwm::default();
+        let led = DimmableLED::with_levels(pwm.clone(), active, 4);
+        let address = runtime.mount(ActorContext::new(led), ());
+        runtime.start();
+        runtime.run();
+        (runtime, address, pwm)
+    }
+
+    #[test]
+    fn test_levels() {
+        let _lock = mock::lock();
+        let (mut runtime, led, pwm) = start(Active::High);
+        assert_eq!(pwm.take(), [Duty::OFF]);
+
+        led.turn_on();
+        led.dimmer();
+        led.set_level(9);
+        led.set_level(2);
+        runtime.run();
+        assert_eq!(
+            pwm.take(),
+            [
+                Duty::perceived(255),
+                Duty::perceived(191),
+                // past the top level is the top level
+                Duty::perceived(255),
+                Duty::perceived(127),
+            ]
+        );
+
+        // the level stays put while off
+        led.turn_off();
+        led.brighter();
+        led.turn_on();
+        led.dimmer();
+        led.dimmer();
+        led.dimmer();
+        runtime.run();
+        assert_eq!(
+            pwm.take(),
+            [
+                Duty::OFF,
+                Duty::OFF,
+                Duty::perceived(191),
+                Duty::perceived(127),
+                Duty::perceived(63),
+                Duty::OFF,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_active_low() {
+        let _lock = mock::lock();
+        let (mut runtime, led, pwm) = start(Active::Low);
+        led.turn_on();
+        led.dimmer();
+        runtime.run();
+        assert_eq!(
+            pwm.take(),
+            [Duty::FULL, Duty::OFF, Duty::perceived(191).inverted()]
+        );
+    }
+}
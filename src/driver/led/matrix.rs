@@ -1,12 +1,26 @@
-use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::duration::{Microseconds, Milliseconds};
 use crate::domain::time::rate::{Hertz, Rate};
 
+use crate::api::display::{Display, DisplayError};
 use crate::api::scheduler::Scheduler;
+use crate::arena::Rc;
 use crate::prelude::*;
+use crate::synchronization::Signal;
+use crate::system::ActorArena;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
 use embedded_hal::digital::v2::OutputPin;
+#[cfg(feature = "fonts")]
+use heapless::{consts::*, String};
 use heapless::{ArrayLength, Vec};
 
 // Led matrix driver supporting up to 32x32 led matrices.
+//
+// Rows are lit one after the other, one on each render. With more than one level of
+// brightness, each row stays lit for as many renders as there are levels, its dimmer LEDs
+// switched off for some of them, so the refresh rate must go up by as much to keep from
+// flickering.
 pub struct LEDMatrix<P, ROWS, COLS, S>
 where
     P: OutputPin + 'static,
@@ -18,20 +32,28 @@ where
     pin_rows: Vec<P, ROWS>,
     pin_cols: Vec<P, COLS>,
     frame_buffer: Frame,
+    brightness: [[u8; 32]; 32],
+    levels: u8,
     row_p: usize,
+    level_p: u8,
     timer: Option<Address<S>>,
     refresh_rate: Hertz,
+    #[cfg(feature = "fonts")]
+    scroll_speed: Milliseconds,
+    show: Option<Show>,
 }
 
 /**
  * A 32x32 bitmap that can be displayed on a LED matrix.
  */
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Frame {
     bitmap: [u32; 32],
 }
 
 impl Frame {
-    fn new(bitmap: [u32; 32]) -> Self {
+    /// A frame of rows, the first column of each in its lowest bit.
+    pub const fn new(bitmap: [u32; 32]) -> Self {
         Self { bitmap }
     }
 
@@ -54,6 +76,65 @@ impl Frame {
     }
 }
 
+impl ToFrame for Frame {
+    fn to_frame(&self) -> Frame {
+        *self
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl embedded_graphics::DrawTarget<embedded_graphics::pixelcolor::BinaryColor> for Frame {
+    type Error = core::convert::Infallible;
+
+    fn draw_pixel(
+        &mut self,
+        pixel: embedded_graphics::drawable::Pixel<embedded_graphics::pixelcolor::BinaryColor>,
+    ) -> Result<(), Self::Error> {
+        let embedded_graphics::drawable::Pixel(point, color) = pixel;
+        if (0..32).contains(&point.x) && (0..32).contains(&point.y) {
+            // rows first
+            if color.is_on() {
+                self.set(point.y as usize, point.x as usize);
+            } else {
+                self.unset(point.y as usize, point.x as usize);
+            }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> embedded_graphics::geometry::Size {
+        embedded_graphics::geometry::Size::new(32, 32)
+    }
+}
+
+/// How text scrolling or an animation ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Playback {
+    /// It played to the end.
+    Completed,
+    /// Something else was displayed before it got to the end.
+    Interrupted,
+}
+
+/// Text scrolling or an animation, stepping along as the matrix renders.
+struct Show {
+    content: Content,
+    /// Microseconds between steps.
+    interval: u32,
+    elapsed: u32,
+    done: Option<Rc<Signal<Playback>, ActorArena>>,
+}
+
+enum Content {
+    #[cfg(feature = "fonts")]
+    Text { text: String<U128>, offset: usize },
+    Animation {
+        frames: &'static [Frame],
+        index: usize,
+        repeat: bool,
+    },
+}
+
 impl<P, ROWS, COLS, S> LEDMatrix<P, ROWS, COLS, S>
 where
     P: OutputPin,
@@ -67,12 +148,32 @@ where
             pin_rows,
             pin_cols,
             frame_buffer: Frame::new([0; 32]),
+            brightness: [[u8::MAX; 32]; 32],
+            levels: 1,
             row_p: 0,
+            level_p: 0,
             refresh_rate,
             timer: None,
+            #[cfg(feature = "fonts")]
+            scroll_speed: Milliseconds(150),
+            show: None,
         }
     }
 
+    /// Tell apart `levels` of brightness rather than just on and off. Each row then takes
+    /// `levels` renders, so the refresh rate has to be as many times higher.
+    pub fn with_brightness_levels(mut self, levels: u8) -> Self {
+        self.levels = if levels == 0 { 1 } else { levels };
+        self
+    }
+
+    /// Scroll text along by a column every `speed`, 150ms unless set.
+    #[cfg(feature = "fonts")]
+    pub fn with_scroll_speed<DUR: Into<Milliseconds>>(mut self, speed: DUR) -> Self {
+        self.scroll_speed = speed.into();
+        self
+    }
+
     pub fn clear(&mut self) {
         self.frame_buffer.clear();
     }
@@ -85,6 +186,11 @@ where
         self.frame_buffer.unset(x, y);
     }
 
+    /// Set how bright the LED is when on, out of 255.
+    pub fn set_brightness(&mut self, x: usize, y: usize, brightness: u8) {
+        self.brightness[x][y] = brightness;
+    }
+
     pub fn apply(&mut self, frame: Frame) {
         self.frame_buffer = frame;
     }
@@ -95,14 +201,108 @@ where
         }
 
         for (cid, col) in self.pin_cols.iter_mut().enumerate() {
-            if self.frame_buffer.is_set(self.row_p, cid) {
+            // lit for as many of the row's renders as the brightness takes, rounding up
+            let renders =
+                (self.brightness[self.row_p][cid] as u32 * self.levels as u32 + 254) / 255;
+            if self.frame_buffer.is_set(self.row_p, cid) && (self.level_p as u32) < renders {
                 col.set_low().ok();
             } else {
                 col.set_high().ok();
             }
         }
         self.pin_rows[self.row_p].set_high().ok();
-        self.row_p = (self.row_p + 1) % self.pin_rows.len();
+        self.level_p += 1;
+        if self.level_p >= self.levels {
+            self.level_p = 0;
+            self.row_p = (self.row_p + 1) % self.pin_rows.len();
+        }
+    }
+
+    fn play(
+        &mut self,
+        content: Content,
+        interval: Milliseconds,
+    ) -> Rc<Signal<Playback>, ActorArena> {
+        self.stop();
+        let done = Rc::new(Signal::new());
+        self.show.replace(Show {
+            content,
+            interval: interval.0 * 1_000,
+            elapsed: 0,
+            done: Some(done.clone()),
+        });
+        self.step();
+        done
+    }
+
+    /// Stop scrolling or animating, if either is going on.
+    fn stop(&mut self) {
+        self.end(Playback::Interrupted);
+    }
+
+    fn end(&mut self, playback: Playback) {
+        if let Some(done) = self.show.take().and_then(|show| show.done) {
+            done.signal(playback);
+        }
+    }
+
+    /// Count the time of a render towards the next step of the show.
+    fn advance(&mut self) {
+        let period = self
+            .refresh_rate
+            .to_duration::<Microseconds>()
+            .map(|period| period.0)
+            .unwrap_or(0);
+        if let Some(show) = self.show.as_mut() {
+            show.elapsed += period;
+            if show.elapsed >= show.interval {
+                show.elapsed -= show.interval;
+                self.step();
+            }
+        }
+    }
+
+    fn step(&mut self) {
+        let width = self.pin_cols.len();
+        let frame = match self.show.as_mut().map(|show| &mut show.content) {
+            #[cfg(feature = "fonts")]
+            Some(Content::Text { text, offset }) => {
+                if *offset > fonts::text_width(text) + width {
+                    None
+                } else {
+                    *offset += 1;
+                    Some(fonts::scrolled(text, *offset, width))
+                }
+            }
+            Some(Content::Animation {
+                frames,
+                index,
+                repeat,
+            }) => {
+                if *index >= frames.len() && *repeat {
+                    *index = 0;
+                }
+                *index += 1;
+                frames.get(*index - 1).copied()
+            }
+            None => return,
+        };
+        match frame {
+            Some(frame) => self.frame_buffer = frame,
+            None => self.end(Playback::Completed),
+        }
+    }
+
+    #[cfg(feature = "fonts")]
+    fn text(text: &str) -> String<U128> {
+        let mut copy = String::new();
+        for c in text.chars() {
+            if copy.push(c).is_err() {
+                log::warn!("[{}] text cut short to scroll", ActorInfo::name());
+                break;
+            }
+        }
+        copy
     }
 }
 
@@ -123,7 +323,7 @@ where
     fn on_start(self) -> Completion<Self> {
         if let Some(address) = self.address {
             if let Some(timer) = self.timer {
-                timer.schedule(
+                timer.schedule_periodic(
                     self.refresh_rate.to_duration::<Milliseconds>().unwrap(),
                     Render,
                     address,
@@ -143,6 +343,7 @@ where
     F: ToFrame,
 {
     fn on_notify(mut self, message: Apply<F>) -> Completion<Self> {
+        self.stop();
         self.apply(message.0.to_frame());
         Completion::immediate(self)
     }
@@ -174,6 +375,19 @@ where
     }
 }
 
+impl<P, ROWS, COLS, S> NotifyHandler<SetBrightness> for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+{
+    fn on_notify(mut self, message: SetBrightness) -> Completion<Self> {
+        self.set_brightness(message.0, message.1, message.2);
+        Completion::immediate(self)
+    }
+}
+
 impl<P, ROWS, COLS, S> NotifyHandler<Clear> for LEDMatrix<P, ROWS, COLS, S>
 where
     P: OutputPin,
@@ -182,6 +396,7 @@ where
     S: Scheduler,
 {
     fn on_notify(mut self, message: Clear) -> Completion<Self> {
+        self.stop();
         self.clear();
         Completion::immediate(self)
     }
@@ -196,21 +411,148 @@ where
 {
     fn on_notify(mut self, message: Render) -> Completion<Self> {
         self.render();
-        if let Some(address) = self.address {
-            self.timer.unwrap().schedule(
-                self.refresh_rate.to_duration::<Milliseconds>().unwrap(),
-                Render,
-                address,
-            );
-        }
+        self.advance();
+        Completion::immediate(self)
+    }
+}
+
+#[cfg(feature = "fonts")]
+impl<P, ROWS, COLS, S, T> NotifyHandler<ScrollText<T>> for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+    T: AsRef<str>,
+{
+    fn on_notify(mut self, message: ScrollText<T>) -> Completion<Self> {
+        let text = Self::text(message.0.as_ref());
+        self.play(Content::Text { text, offset: 0 }, self.scroll_speed);
         Completion::immediate(self)
     }
 }
 
+#[cfg(feature = "fonts")]
+impl<P, ROWS, COLS, S, T> RequestHandler<ScrollText<T>> for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+    T: AsRef<str>,
+{
+    type Response = Playback;
+
+    fn on_request(mut self, message: ScrollText<T>) -> Response<Self, Self::Response> {
+        let text = Self::text(message.0.as_ref());
+        let done = self.play(Content::Text { text, offset: 0 }, self.scroll_speed);
+        Response::immediate_future(self, PlaybackFuture { done })
+    }
+}
+
+impl<P, ROWS, COLS, S> NotifyHandler<Animate> for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+{
+    fn on_notify(mut self, message: Animate) -> Completion<Self> {
+        self.play(message.content(), message.interval);
+        Completion::immediate(self)
+    }
+}
+
+impl<P, ROWS, COLS, S> RequestHandler<Animate> for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+{
+    type Response = Playback;
+
+    fn on_request(mut self, message: Animate) -> Response<Self, Self::Response> {
+        let done = self.play(message.content(), message.interval);
+        Response::immediate_future(self, PlaybackFuture { done })
+    }
+}
+
+impl<P, ROWS, COLS, S> Display for LEDMatrix<P, ROWS, COLS, S>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+{
+    type Buffer = Frame;
+
+    /// Draw on what is shown, which stops text scrolling or an animation.
+    fn draw<F: FnOnce(&mut Self::Buffer)>(&mut self, draw: F) {
+        self.stop();
+        draw(&mut self.frame_buffer);
+    }
+
+    fn clear(&mut self) {
+        self.stop();
+        LEDMatrix::clear(self);
+    }
+
+    /// The matrix shows the frame as it is drawn, so there is nothing to flush.
+    fn flush(self) -> Response<Self, Result<(), DisplayError>> {
+        Response::immediate(self, Ok(()))
+    }
+}
+
+impl<P, ROWS, COLS, S> Address<LEDMatrix<P, ROWS, COLS, S>>
+where
+    P: OutputPin,
+    ROWS: ArrayLength<P>,
+    COLS: ArrayLength<P>,
+    S: Scheduler,
+{
+    /// Scroll `text` across the matrix, from right to left, until it is gone.
+    #[cfg(feature = "fonts")]
+    pub async fn scroll_text<T: AsRef<str> + 'static>(&self, text: T) -> Playback {
+        self.request(ScrollText(text)).await
+    }
+
+    /// Show `frames` one after the other, every `interval`.
+    pub async fn animate<DUR: Into<Milliseconds>>(
+        &self,
+        frames: &'static [Frame],
+        interval: DUR,
+        repeat: bool,
+    ) -> Playback {
+        self.request(Animate {
+            frames,
+            interval: interval.into(),
+            repeat,
+        })
+        .await
+    }
+}
+
+/// Resolves when a show ends.
+struct PlaybackFuture {
+    done: Rc<Signal<Playback>, ActorArena>,
+}
+
+impl Future for PlaybackFuture {
+    type Output = Playback;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.done.poll_wait(cx)
+    }
+}
+
 #[derive(Debug)]
 pub struct On(pub usize, pub usize);
 #[derive(Debug)]
 pub struct Off(pub usize, pub usize);
+/// Set the brightness of the LED in a row and column, out of 255.
+#[derive(Debug)]
+pub struct SetBrightness(pub usize, pub usize, pub u8);
 #[derive(Debug)]
 pub struct Clear;
 #[derive(Debug, Clone)]
@@ -219,6 +561,29 @@ pub struct Render;
 pub struct Apply<F>(pub F)
 where
     F: ToFrame;
+/// Scroll text across the matrix.
+#[cfg(feature = "fonts")]
+#[derive(Debug)]
+pub struct ScrollText<T>(pub T)
+where
+    T: AsRef<str>;
+/// Show frames one after the other, once or over and over.
+#[derive(Debug)]
+pub struct Animate {
+    pub frames: &'static [Frame],
+    pub interval: Milliseconds,
+    pub repeat: bool,
+}
+
+impl Animate {
+    fn content(&self) -> Content {
+        Content::Animation {
+            frames: self.frames,
+            index: 0,
+            repeat: self.repeat,
+        }
+    }
+}
 
 pub trait ToFrame: Copy + Clone + core::fmt::Debug {
     fn to_frame(&self) -> Frame;
@@ -247,6 +612,34 @@ pub mod fonts {
         Frame::new(bitmap)
     }
 
+    /// Columns a character takes, with the gap after it.
+    const GLYPH_WIDTH: usize = 6;
+
+    pub(crate) fn text_width(text: &str) -> usize {
+        text.chars().count() * GLYPH_WIDTH
+    }
+
+    /// `text` as it shows `width` columns wide, scrolled in from the right by `offset` columns.
+    pub(crate) fn scrolled(text: &str, offset: usize, width: usize) -> Frame {
+        let mut frame = Frame::new([0; 32]);
+        for column in 0..width {
+            // the column of text showing here, if any yet
+            let t = match (offset + column).checked_sub(width) {
+                Some(t) if t % GLYPH_WIDTH < GLYPH_WIDTH - 1 => t,
+                _ => continue,
+            };
+            if let Some(c) = text.chars().nth(t / GLYPH_WIDTH) {
+                let glyph = c.to_frame();
+                for row in 0..5 {
+                    if glyph.is_set(row, t % GLYPH_WIDTH) {
+                        frame.set(row, column);
+                    }
+                }
+            }
+        }
+        frame
+    }
+
     // These are for 5x5 only
     impl ToFrame for char {
         #[rustfmt::skip]
@@ -491,5 +884,185 @@ pub mod fonts {
             assert!(frame.is_set(4, 3));
             assert!(!frame.is_set(4, 4));
         }
+
+        #[test]
+        fn test_scrolled() {
+            // the first column of the D comes in on the right
+            let frame = scrolled("DO", 1, 5);
+            for row in 0..5 {
+                assert!(frame.is_set(row, 4));
+                assert!(!frame.is_set(row, 3));
+            }
+            assert_eq!(scrolled("DO", 5, 5), 'D'.to_frame());
+            // after the D, a column of space and the O
+            let frame = scrolled("DO", 7, 5);
+            assert!(!frame.is_set(0, 3));
+            assert!(frame.is_set(0, 4));
+            assert_eq!(scrolled("DO", text_width("DO") + 5, 5), Frame::new([0; 32]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal::gpio::mock::MockPin;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use futures::future::join;
+    use heapless::consts::U2;
+
+    type Matrix = LEDMatrix<MockPin, U2, U2, MockTimer>;
+
+    /// Both rows lit in turn, the first column, then the second, then neither.
+    static FRAMES: [Frame; 3] = [
+        Frame::new(frame(0b01)),
+        Frame::new(frame(0b10)),
+        Frame::new(frame(0b00)),
+    ];
+
+    const fn frame(row: u32) -> [u32; 32] {
+        let mut bitmap = [0; 32];
+        bitmap[0] = row;
+        bitmap[1] = row;
+        bitmap
+    }
+
+    fn pins() -> (Vec<MockPin, U2>, [MockPin; 2]) {
+        let pins = [MockPin::new(), MockPin::new()];
+        let mut vec = Vec::new();
+        for pin in pins.iter() {
+            vec.push(pin.clone()).ok();
+        }
+        (vec, pins)
+    }
+
+    fn start(refresh_rate: u32) -> (MockRuntime, Address<Matrix>, [MockPin; 2]) {
+        let mut runtime = MockRuntime::new();
+        let (pin_rows, _) = pins();
+        let (pin_cols, cols) = pins();
+        let timer = runtime.timer();
+        let matrix = runtime.mount(
+            ActorContext::new(Matrix::new(pin_rows, pin_cols, Hertz(refresh_rate))),
+            timer,
+        );
+        runtime.start();
+        runtime.run();
+        (runtime, matrix, cols)
+    }
+
+    /// Which columns are lit, going by their (active low) pins.
+    fn lit(cols: &[MockPin; 2]) -> [bool; 2] {
+        [!cols[0].level(), !cols[1].level()]
+    }
+
+    #[test]
+    fn test_brightness() {
+        let (pin_rows, rows) = pins();
+        let (pin_cols, cols) = pins();
+        let mut matrix = Matrix::new(pin_rows, pin_cols, Hertz(400)).with_brightness_levels(4);
+        matrix.on(0, 0);
+        matrix.on(0, 1);
+        matrix.on(1, 0);
+        // a quarter, which takes one of the four renders, and two of them when rounded up
+        matrix.set_brightness(0, 1, 64);
+        matrix.set_brightness(1, 0, 0);
+
+        let mut shown = [[false; 2]; 4];
+        for levels in shown.iter_mut() {
+            matrix.render();
+            assert!(rows[0].level());
+            assert!(!rows[1].level());
+            *levels = lit(&cols);
+        }
+        assert_eq!(
+            shown,
+            [[true, true], [true, true], [true, false], [true, false]]
+        );
+
+        // the second row after its four renders, an LED at no brightness never lit
+        for _ in 0..4 {
+            matrix.render();
+            assert!(!rows[0].level());
+            assert!(rows[1].level());
+            assert_eq!(lit(&cols), [false, false]);
+        }
+        matrix.render();
+        assert!(rows[0].level());
+    }
+
+    #[test]
+    fn test_animate() {
+        let _lock = mock::lock();
+        let (mut runtime, matrix, cols) = start(100);
+        matrix.notify(Animate {
+            frames: &FRAMES,
+            interval: Milliseconds(30),
+            repeat: false,
+        });
+        runtime.sleep(Milliseconds(25u32));
+        assert_eq!(lit(&cols), [true, false]);
+        runtime.sleep(Milliseconds(30u32));
+        assert_eq!(lit(&cols), [false, true]);
+        runtime.sleep(Milliseconds(30u32));
+        assert_eq!(lit(&cols), [false, false]);
+        // the last frame stays up
+        runtime.sleep(Milliseconds(100u32));
+        assert_eq!(lit(&cols), [false, false]);
+    }
+
+    #[test]
+    fn test_animate_repeat() {
+        let _lock = mock::lock();
+        let (mut runtime, matrix, cols) = start(100);
+        matrix.notify(Animate {
+            frames: &FRAMES,
+            interval: Milliseconds(30),
+            repeat: true,
+        });
+        runtime.sleep(Milliseconds(85u32));
+        assert_eq!(lit(&cols), [false, false]);
+        runtime.sleep(Milliseconds(30u32));
+        assert_eq!(lit(&cols), [true, false]);
+        runtime.sleep(Milliseconds(30u32));
+        assert_eq!(lit(&cols), [false, true]);
+    }
+
+    #[test]
+    fn test_playback_completed() {
+        let _lock = mock::lock();
+        let (mut runtime, matrix, _) = start(100);
+        let playback = runtime.block_on(matrix.animate(&FRAMES, Milliseconds(30u32), false));
+        assert_eq!(playback, Playback::Completed);
+        // a step for each frame, and one more to find there are none left
+        assert_eq!(runtime.elapsed(), Milliseconds(90u32));
+    }
+
+    #[test]
+    fn test_playback_interrupted() {
+        let _lock = mock::lock();
+        let (mut runtime, matrix, cols) = start(100);
+        let timer = runtime.timer();
+        let (playback, _) = runtime.block_on(join(
+            matrix.animate(&FRAMES, Milliseconds(30u32), true),
+            async {
+                timer.delay(Milliseconds(45u32)).await;
+                matrix.notify(Apply(Frame::new(frame(0b11))));
+            },
+        ));
+        assert_eq!(playback, Playback::Interrupted);
+
+        // and the animation no longer steps
+        runtime.sleep(Milliseconds(100u32));
+        assert_eq!(lit(&cols), [true, true]);
+
+        let (first, second) = runtime.block_on(join(
+            matrix.animate(&FRAMES, Milliseconds(30u32), true),
+            async {
+                timer.delay(Milliseconds(45u32)).await;
+                matrix.animate(&FRAMES, Milliseconds(30u32), false).await
+            },
+        ));
+        assert_eq!(first, Playback::Interrupted);
+        assert_eq!(second, Playback::Completed);
     }
 }
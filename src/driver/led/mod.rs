@@ -1,7 +1,15 @@
 pub mod blinker;
+pub mod dimmable;
 pub mod matrix;
+pub mod pattern;
 pub mod simple;
 
 pub use blinker::Blinker;
-pub use matrix::{Apply, Clear, LEDMatrix, Off, On, Render, ToFrame};
+pub use dimmable::DimmableLED;
+#[cfg(feature = "fonts")]
+pub use matrix::ScrollText;
+pub use matrix::{
+    Animate, Apply, Clear, Frame, LEDMatrix, Off, On, Playback, Render, SetBrightness, ToFrame,
+};
+pub use pattern::{Pattern, PatternPlayer};
 pub use simple::SimpleLED;
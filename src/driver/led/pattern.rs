@@ -0,0 +1,558 @@
+//! Light patterns played on a PWM output, telling apart states by the look of an LED.
+//!
+//! A [`PatternPlayer`] steps through a [`Pattern`] with the scheduler, so playing one does
+//! not hold up anything else. A new pattern takes over from the one playing.
+use crate::api::pwm::{Duty, Pwm};
+use crate::api::scheduler::{ScheduleHandle, Scheduler};
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+
+/// How often fades and breathing change brightness.
+const FRAME: u32 = 20;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Pattern {
+    /// Stay at a brightness, out of 255.
+    Solid(u8),
+    /// Go from one brightness to another, and stay there.
+    Fade {
+        from: u8,
+        to: u8,
+        duration: Milliseconds,
+    },
+    /// Brighten and dim again, every `period`.
+    Breathe { period: Milliseconds },
+    /// Blink a number of times, on and off for `interval` each, and again after `pause` if
+    /// there is one.
+    Blink {
+        times: u8,
+        interval: Milliseconds,
+        pause: Option<Milliseconds>,
+    },
+    /// Spell out letters and digits in Morse code, a dot lasting `unit`, and again after
+    /// `pause` if there is one. Other characters are left out.
+    Morse {
+        text: &'static str,
+        unit: Milliseconds,
+        pause: Option<Milliseconds>,
+    },
+}
+
+/// A brightness, out of 255, to hold for a while.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub brightness: u8,
+    /// Milliseconds until the next step.
+    pub hold: u32,
+}
+
+impl Step {
+    fn new(brightness: u8, hold: u32) -> Self {
+        Self { brightness, hold }
+    }
+}
+
+/// The steps of a pattern, one after another.
+pub struct Sequence {
+    pattern: Pattern,
+    step: u32,
+    morse: Morse,
+}
+
+impl Sequence {
+    pub fn new(pattern: Pattern) -> Self {
+        let text = match pattern {
+            Pattern::Morse { text, .. } => text,
+            _ => "",
+        };
+        Self {
+            pattern,
+            step: 0,
+            morse: Morse::new(text),
+        }
+    }
+}
+
+impl Iterator for Sequence {
+    type Item = Step;
+
+    fn next(&mut self) -> Option<Step> {
+        let step = self.step;
+        self.step += 1;
+        match self.pattern {
+            Pattern::Solid(brightness) if step == 0 => Some(Step::new(brightness, 0)),
+            Pattern::Solid(_) => None,
+            Pattern::Fade { from, to, duration } => {
+                let frames = frames(duration.0, 1);
+                if step > frames {
+                    return None;
+                }
+                let delta = (to as i32 - from as i32) * step as i32 / frames as i32;
+                Some(Step::new((from as i32 + delta) as u8, FRAME))
+            }
+            Pattern::Breathe { period } => {
+                let frames = frames(period.0, 2);
+                let phase = (2 * (step % frames)) as i32 - frames as i32;
+                let brightness = 255 * (frames as i32 - phase.abs()) / frames as i32;
+                Some(Step::new(brightness as u8, period.0 / frames))
+            }
+            Pattern::Blink {
+                times,
+                interval,
+                pause,
+            } => {
+                let steps = 2 * times as u32;
+                if times == 0 || (pause.is_none() && step >= steps) {
+                    return None;
+                }
+                let step = step % steps;
+                if step % 2 == 0 {
+                    Some(Step::new(255, interval.0))
+                } else if step == steps - 1 {
+                    Some(Step::new(0, interval.0 + pause.map_or(0, |pause| pause.0)))
+                } else {
+                    Some(Step::new(0, interval.0))
+                }
+            }
+            Pattern::Morse { text, unit, pause } => {
+                let (lit, units) = match self.morse.next() {
+                    Some(symbol) => symbol,
+                    // nothing to repeat when nothing was spelled out
+                    None if pause.is_none() || step == 0 => return None,
+                    None => {
+                        self.morse = Morse::new(text);
+                        self.step = 1;
+                        self.morse.next()?
+                    }
+                };
+                if lit {
+                    Some(Step::new(255, units * unit.0))
+                } else if self.morse.finished() {
+                    Some(Step::new(0, pause.map_or(0, |pause| pause.0)))
+                } else {
+                    Some(Step::new(0, units * unit.0))
+                }
+            }
+        }
+    }
+}
+
+/// The number of frames in `millis`, at least `min`.
+fn frames(millis: u32, min: u32) -> u32 {
+    let frames = millis / FRAME;
+    if frames < min {
+        min
+    } else {
+        frames
+    }
+}
+
+/// Walks text as lit and dark spells, lasting a number of units.
+struct Morse {
+    text: &'static [u8],
+    char: usize,
+    symbol: usize,
+    lit: bool,
+}
+
+impl Morse {
+    fn new(text: &'static str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            char: 0,
+            symbol: 0,
+            lit: false,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.char >= self.text.len()
+    }
+
+    fn next(&mut self) -> Option<(bool, u32)> {
+        let symbols = loop {
+            match code(*self.text.get(self.char)?) {
+                Some(code) => break code.as_bytes(),
+                None => self.char += 1,
+            }
+        };
+        if !self.lit {
+            self.lit = true;
+            return Some((true, if symbols[self.symbol] == b'-' { 3 } else { 1 }));
+        }
+
+        self.lit = false;
+        self.symbol += 1;
+        if self.symbol < symbols.len() {
+            return Some((false, 1));
+        }
+        self.symbol = 0;
+        self.char += 1;
+        let mut gap = 3;
+        while let Some(c) = self.text.get(self.char) {
+            if code(*c).is_some() {
+                break;
+            }
+            if *c == b' ' {
+                gap = 7;
+            }
+            self.char += 1;
+        }
+        Some((false, gap))
+    }
+}
+
+fn code(c: u8) -> Option<&'static str> {
+    const LETTERS: [&str; 26] = [
+        ".-", "-...", "-.-.", "-..", ".", "..-.", "--.", "....", "..", ".---", "-.-", ".-..", "--",
+        "-.", "---", ".--.", "--.-", ".-.", "...", "-", "..-", "...-", ".--", "-..-", "-.--",
+        "--..",
+    ];
+    const DIGITS: [&str; 10] = [
+        "-----", ".----", "..---", "...--", "....-", ".....", "-....", "--...", "---..", "----.",
+    ];
+    match c {
+        b'a'..=b'z' => Some(LETTERS[(c - b'a') as usize]),
+        b'A'..=b'Z' => Some(LETTERS[(c - b'A') as usize]),
+        b'0'..=b'9' => Some(DIGITS[(c - b'0') as usize]),
+        _ => None,
+    }
+}
+
+/// Plays patterns on a PWM output, an LED mostly.
+pub struct PatternPlayer<P, T>
+where
+    P: Pwm + 'static,
+    T: Scheduler + 'static,
+{
+    output: Option<Address<P>>,
+    timer: Option<Address<T>>,
+    address: Option<Address<Self>>,
+    sequence: Option<Sequence>,
+    /// Bumped with each pattern, so the steps of one do not carry over into the next.
+    generation: u32,
+    schedule: Option<ScheduleHandle<T>>,
+}
+
+impl<P, T> PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    pub fn new() -> Self {
+        Self {
+            output: None,
+            timer: None,
+            address: None,
+            sequence: None,
+            generation: 0,
+            schedule: None,
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(schedule) = self.schedule.take() {
+            schedule.cancel();
+        }
+        self.sequence.take();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn advance(&mut self) {
+        let step = match self.sequence.as_mut().and_then(|sequence| sequence.next()) {
+            Some(step) => step,
+            None => {
+                self.stop();
+                return;
+            }
+        };
+        self.output
+            .unwrap()
+            .set_duty(Duty::perceived(step.brightness));
+        let handle = self.timer.unwrap().schedule(
+            Milliseconds(step.hold),
+            Advance(self.generation),
+            self.address.unwrap(),
+        );
+        self.schedule.replace(handle);
+    }
+}
+
+impl<P, T> Default for PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, T> Actor for PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    type Configuration = (Address<P>, Address<T>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.address.replace(address);
+        self.output.replace(config.0);
+        self.timer.replace(config.1);
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Play(pub Pattern);
+
+impl<P, T> NotifyHandler<Play> for PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    fn on_notify(mut self, message: Play) -> Completion<Self> {
+        self.stop();
+        self.sequence.replace(Sequence::new(message.0));
+        self.advance();
+        Completion::immediate(self)
+    }
+}
+
+/// Stop playing, and turn the output off.
+#[derive(Copy, Clone, Debug)]
+pub struct Stop;
+
+impl<P, T> NotifyHandler<Stop> for PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    fn on_notify(mut self, message: Stop) -> Completion<Self> {
+        self.stop();
+        self.output.unwrap().set_duty(Duty::OFF);
+        Completion::immediate(self)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Advance(u32);
+
+impl<P, T> NotifyHandler<Advance> for PatternPlayer<P, T>
+where
+    P: Pwm,
+    T: Scheduler,
+{
+    fn on_notify(mut self, message: Advance) -> Completion<Self> {
+        if message.0 == self.generation {
+            self.schedule.take();
+            self.advance();
+        }
+        Completion::immediate(self)
+    }
+}
+
+impl<P, T> Address<PatternPlayer<P, T>>
+where
+    Self: 'static,
+    P: Pwm,
+    T: Scheduler,
+{
+    pub fn play(&self, pattern: Pattern) {
+        self.notify(Play(pattern))
+    }
+
+    pub fn stop(&self) {
+        self.notify(Stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use core::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    /// Keeps the duties it is set to, for the test to take.
+    #[derive(Clone, Default)]
+    struct Output(Rc<RefCell<Vec<Duty>>>);
+
+    impl Output {
+        fn take(&self) -> Vec<Duty> {
+            self.0.borrow_mut().drain(..).collect()
+        }
+    }
+
+    impl Actor for Output {
+        type Configuration = ();
+    }
+
+    impl Pwm for Output {
+        fn set_duty(&mut self, duty: Duty) {
+            self.0.borrow_mut().push(duty);
+        }
+    }
+
+    fn start() -> (
+        MockRuntime,
+        Address<PatternPlayer<Output, MockTimer>>,
+        Output,
+    ) {
+        let mut runtime = MockRuntime::new();
+        let output = Output::default();
+        let address = runtime.mount(ActorContext::new(output.clone()), ());
+        let timer = runtime.timer();
+        let player = runtime.mount(ActorContext::new(PatternPlayer::new()), (address, timer));
+        runtime.start();
+        runtime.run();
+        (runtime, player, output)
+    }
+
+    fn blink(interval: u32, pause: Option<u32>) -> Pattern {
+        Pattern::Blink {
+            times: 1,
+            interval: Milliseconds(interval),
+            pause: pause.map(Milliseconds),
+        }
+    }
+
+    fn steps(pattern: Pattern, count: usize) -> Vec<(u8, u32)> {
+        Sequence::new(pattern)
+            .take(count)
+            .map(|step| (step.brightness, step.hold))
+            .collect()
+    }
+
+    #[test]
+    fn test_blink() {
+        let once = Pattern::Blink {
+            times: 2,
+            interval: Milliseconds(100),
+            pause: None,
+        };
+        assert_eq!(
+            steps(once, 10),
+            [(255, 100), (0, 100), (255, 100), (0, 100)]
+        );
+
+        let repeated = Pattern::Blink {
+            times: 2,
+            interval: Milliseconds(100),
+            pause: Some(Milliseconds(1_000)),
+        };
+        assert_eq!(
+            steps(repeated, 6),
+            [
+                (255, 100),
+                (0, 100),
+                (255, 100),
+                (0, 1_100),
+                (255, 100),
+                (0, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_morse() {
+        let sos = Pattern::Morse {
+            text: "s o",
+            unit: Milliseconds(10),
+            pause: Some(Milliseconds(500)),
+        };
+        assert_eq!(
+            steps(sos, 13),
+            [
+                (255, 10),
+                (0, 10),
+                (255, 10),
+                (0, 10),
+                (255, 10),
+                // between words
+                (0, 70),
+                (255, 30),
+                (0, 10),
+                (255, 30),
+                (0, 10),
+                (255, 30),
+                (0, 500),
+                (255, 10),
+            ]
+        );
+
+        let nothing = Pattern::Morse {
+            text: "?!",
+            unit: Milliseconds(10),
+            pause: Some(Milliseconds(500)),
+        };
+        assert!(steps(nothing, 1).is_empty());
+    }
+
+    #[test]
+    fn test_fade_and_breathe() {
+        let fade = steps(
+            Pattern::Fade {
+                from: 200,
+                to: 0,
+                duration: Milliseconds(100),
+            },
+            10,
+        );
+        assert_eq!(fade.len(), 6);
+        assert_eq!(fade[0], (200, FRAME));
+        assert_eq!(fade[5], (0, FRAME));
+
+        let breathe = steps(
+            Pattern::Breathe {
+                period: Milliseconds(200),
+            },
+            21,
+        );
+        assert_eq!(breathe[0], (0, 20));
+        assert_eq!(breathe[5], (255, 20));
+        assert_eq!(breathe[10], (0, 20));
+        assert_eq!(breathe[20], (0, 20));
+    }
+
+    #[test]
+    fn test_play_takes_over() {
+        let _lock = mock::lock();
+        let (mut runtime, player, output) = start();
+        player.play(blink(100, Some(1_000)));
+        runtime.sleep(Milliseconds(50u32));
+        assert_eq!(output.take(), [Duty::FULL]);
+
+        // the first pattern would have gone dark at 100ms, had its advance not been cancelled,
+        // or ignored when already on its way
+        player.play(blink(300, None));
+        player.notify(Advance(1));
+        runtime.sleep(Milliseconds(250u32));
+        assert_eq!(output.take(), [Duty::FULL]);
+
+        runtime.sleep(Milliseconds(100u32));
+        assert_eq!(output.take(), [Duty::OFF]);
+        // and it played once
+        runtime.sleep(Milliseconds(2_000u32));
+        assert!(output.take().is_empty());
+    }
+
+    #[test]
+    fn test_stop() {
+        let _lock = mock::lock();
+        let (mut runtime, player, output) = start();
+        player.play(blink(100, Some(1_000)));
+        runtime.sleep(Milliseconds(150u32));
+        assert_eq!(output.take(), [Duty::FULL, Duty::OFF]);
+
+        player.play(Pattern::Solid(255));
+        player.stop();
+        runtime.sleep(Milliseconds(2_000u32));
+        assert_eq!(output.take(), [Duty::FULL, Duty::OFF]);
+    }
+}
@@ -367,13 +367,26 @@ where
     }
 
     async fn process(&mut self) -> Result<(), LoraError> {
-        let uart = self.uart.as_ref().unwrap();
+        let uart = self.uart.unwrap();
+
+        let mut buf = [0; 128];
 
-        let mut buf = [0; 1];
+        // Lines longer than `buf`, such as `at+recv=` with a payload, are handed to the
+        // parser in parts: a read which fills up `buf` has still consumed those bytes.
+        loop {
+            match uart.read_until(&mut buf[..], b"\r\n").await {
+                Ok(len) => return self.write(&buf[..len]),
+                Err(UartError::RxBufferTooSmall) => self.write(&buf[..])?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
 
-        let len = uart.read(&mut buf[..]).await?;
-        for b in &buf[..len] {
-            self.parse_buffer.write(*b).unwrap();
+    fn write(&mut self, data: &[u8]) -> Result<(), LoraError> {
+        for b in data {
+            self.parse_buffer
+                .write(*b)
+                .map_err(|_| LoraError::RecvError)?;
         }
         Ok(())
     }
@@ -1,6 +1,6 @@
 use crate::prelude::*;
 
-use crate::arena::{Arena, Info};
+use crate::arena::{registered, Arena, ArenaRef, Info};
 use crate::system::SystemArena;
 use core::marker::PhantomData;
 //use crate::arena::HEAP;
@@ -37,16 +37,28 @@ impl<A: Arena + 'static> RequestHandler<Query> for Memory<A> {
     }
 }
 
+/// Logs the usage of arena `A`, and of every other arena actors were given.
 impl<A: Arena + 'static> NotifyHandler<Query> for Memory<A> {
     fn on_notify(self, message: Query) -> Completion<Self> {
-        let info = A::info();
-        log::info!(
-            "[{}] used={}, free={} || high={}",
-            ActorInfo::name(),
-            info.used,
-            info.free,
-            info.high_watermark,
-        );
+        let arena = ArenaRef::of::<A>();
+        log(arena);
+        for other in registered().iter().filter(|other| **other != arena) {
+            log(*other);
+        }
         Completion::immediate(self)
     }
 }
+
+fn log(arena: ArenaRef) {
+    let info = arena.info();
+    log::info!(
+        "[{}] {}: used={}, free={} || high={} || largest={}, fragmentation={}%",
+        ActorInfo::name(),
+        arena.name(),
+        info.used,
+        info.free,
+        info.high_watermark,
+        info.largest_free,
+        info.fragmentation(),
+    );
+}
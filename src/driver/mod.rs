@@ -1,11 +1,16 @@
 //! Device drivers.
 
+pub mod ble;
 pub mod button;
+pub mod cellular;
+pub mod display;
 pub mod i2c;
 pub mod led;
 pub mod lora;
 pub mod memory;
+pub mod rtc;
 pub mod sensor;
+pub(crate) mod socket_pool;
 pub mod spi;
 pub mod timer;
 pub mod uart;
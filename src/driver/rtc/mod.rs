@@ -0,0 +1,35 @@
+//! Real-time clocks, and keeping them in sync with network time.
+use crate::api::rtc::RealTimeClock;
+use crate::domain::time::datetime::DateTime;
+use crate::hal::rtc::Rtc as HalRtc;
+use crate::prelude::*;
+
+pub mod sntp;
+pub mod sync;
+
+/// A real-time clock actor, over the calendar clock of a chip.
+pub struct Rtc<R: HalRtc> {
+    rtc: R,
+}
+
+impl<R: HalRtc> Rtc<R> {
+    pub fn new(rtc: R) -> Self {
+        Self { rtc }
+    }
+}
+
+impl<R: HalRtc> Actor for Rtc<R> {
+    type Configuration = ();
+}
+
+impl<R: HalRtc + 'static> RealTimeClock for Rtc<R> {
+    fn get_datetime(mut self) -> Response<Self, DateTime> {
+        let datetime = self.rtc.get_datetime();
+        Response::immediate(self, datetime)
+    }
+
+    fn set_datetime(mut self, datetime: DateTime) -> Response<Self, ()> {
+        self.rtc.set_datetime(datetime);
+        Response::immediate(self, ())
+    }
+}
@@ -0,0 +1,275 @@
+//! A Simple Network Time Protocol client, as of RFC 4330.
+use crate::api::delayer::Delayer;
+use crate::api::ip::tcp::{TcpSocket, TcpStack};
+use crate::api::ip::{IpProtocol, SocketAddress};
+use crate::domain::time::datetime::DateTime;
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+
+/// The port servers listen on.
+pub const NTP_PORT: u32 = 123;
+
+const PACKET_SIZE: usize = 48;
+
+/// Seconds from the NTP epoch, 1900, to the Unix epoch.
+const UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Leap indicator 0, version 4, client mode.
+const REQUEST: u8 = 0b00_100_011;
+
+const MODE_SERVER: u8 = 4;
+
+/// Requests sent before giving up on a server, as UDP may lose either the request or the reply.
+const ATTEMPTS: usize = 3;
+
+/// How long to wait for a reply to each request.
+const REPLY_TIMEOUT: Milliseconds = Milliseconds(5_000);
+
+/// How often to read the socket while waiting for the reply.
+const POLL_INTERVAL: Milliseconds = Milliseconds(100);
+
+/// Offset of the transmit timestamp, when the server sent its reply.
+const TRANSMIT_TIMESTAMP: usize = 40;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SntpError {
+    /// The exchange with the server failed.
+    Network,
+    /// The reply is not a valid server reply.
+    InvalidResponse,
+    /// The server does not know the time itself.
+    Unsynchronized,
+    /// The server asked to be left alone, in a kiss-o'-death reply.
+    Denied,
+    /// No reply arrived to any of the requests.
+    Timeout,
+}
+
+/// Queries a time server, over a UDP socket of the stack `S`, waiting for replies with `D`.
+pub struct SntpClient<S, D>
+where
+    S: TcpStack + 'static,
+    D: Delayer + 'static,
+{
+    stack: Address<S>,
+    delayer: Address<D>,
+}
+
+impl<S, D> SntpClient<S, D>
+where
+    S: TcpStack + 'static,
+    D: Delayer + 'static,
+{
+    pub fn new(stack: Address<S>, delayer: Address<D>) -> Self {
+        Self { stack, delayer }
+    }
+
+    /// The current time at `server`.
+    ///
+    /// This is the time the server replied at, without making up for the time the reply took to
+    /// get here, which is close enough for timestamps to the second. A request that goes
+    /// unanswered for a while is sent again, a few times.
+    pub async fn query(&self, server: SocketAddress) -> Result<DateTime, SntpError> {
+        let mut socket = self.stack.tcp_open().await;
+        socket
+            .connect(IpProtocol::Udp, server)
+            .await
+            .map_err(|_| SntpError::Network)?;
+        for _ in 0..ATTEMPTS {
+            socket
+                .write(&request())
+                .await
+                .map_err(|_| SntpError::Network)?;
+            let mut buf = [0; PACKET_SIZE];
+            if self.receive(&mut socket, &mut buf).await? {
+                return parse(&buf);
+            }
+        }
+        Err(SntpError::Timeout)
+    }
+
+    /// Read until the reply fills `buf`, however many reads it arrives in, or until the reply
+    /// timeout passes, returning whether it arrived.
+    async fn receive(&self, socket: &mut TcpSocket<S>, buf: &mut [u8]) -> Result<bool, SntpError> {
+        let mut len = 0;
+        let mut waited = Milliseconds(0);
+        while len < buf.len() {
+            let read = socket
+                .read(&mut buf[len..])
+                .await
+                .map_err(|_| SntpError::Network)?;
+            len += read;
+            if read == 0 {
+                if waited.0 >= REPLY_TIMEOUT.0 {
+                    return Ok(false);
+                }
+                self.delayer.delay(POLL_INTERVAL).await;
+                waited.0 += POLL_INTERVAL.0;
+            }
+        }
+        Ok(true)
+    }
+}
+
+pub fn request() -> [u8; PACKET_SIZE] {
+    let mut packet = [0; PACKET_SIZE];
+    packet[0] = REQUEST;
+    packet
+}
+
+/// The transmit time of a server reply.
+pub fn parse(packet: &[u8]) -> Result<DateTime, SntpError> {
+    if packet.len() < PACKET_SIZE || packet[0] & 0x07 != MODE_SERVER {
+        return Err(SntpError::InvalidResponse);
+    }
+    let stratum = packet[1];
+    if stratum == 0 {
+        return Err(SntpError::Denied);
+    }
+    if packet[0] >> 6 == 3 || stratum > 15 {
+        return Err(SntpError::Unsynchronized);
+    }
+
+    let timestamp = &packet[TRANSMIT_TIMESTAMP..PACKET_SIZE];
+    let seconds = u32::from_be_bytes([timestamp[0], timestamp[1], timestamp[2], timestamp[3]]);
+    let fraction = u32::from_be_bytes([timestamp[4], timestamp[5], timestamp[6], timestamp[7]]);
+    if seconds == 0 && fraction == 0 {
+        return Err(SntpError::InvalidResponse);
+    }
+    unix_millis(seconds, fraction)
+        .map(DateTime::from_unix_millis)
+        .ok_or(SntpError::InvalidResponse)
+}
+
+/// Milliseconds since the Unix epoch of an NTP timestamp, if it is not before the epoch.
+fn unix_millis(seconds: u32, fraction: u32) -> Option<u64> {
+    let mut seconds = seconds as u64;
+    // timestamps wrap in 2036, and those of 1968 to 2036 have the most significant bit set
+    if seconds & 0x8000_0000 == 0 {
+        seconds += 1 << 32;
+    }
+    let seconds = seconds.checked_sub(UNIX_OFFSET)?;
+    Some(seconds * 1_000 + ((fraction as u64 * 1_000) >> 32))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::api::ip::mock::MockStack;
+    use crate::api::ip::IpAddress;
+    use crate::system::mock::{self, MockRuntime};
+    use std::vec::Vec;
+
+    fn reply(seconds: u32, fraction: u32) -> [u8; PACKET_SIZE] {
+        let mut packet = [0; PACKET_SIZE];
+        packet[0] = 0b00_100_100;
+        packet[1] = 2;
+        packet[40..44].copy_from_slice(&seconds.to_be_bytes());
+        packet[44..48].copy_from_slice(&fraction.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_request() {
+        let packet = request();
+        assert_eq!(packet[0], 0x23);
+        assert!(packet[1..].iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn test_parse() {
+        // 2021-03-14T15:09:26.500Z
+        let datetime = parse(&reply(3_824_723_366, 1 << 31)).unwrap();
+        assert_eq!(datetime.unix_seconds(), 1_615_734_566);
+        assert_eq!(datetime.millisecond(), 500);
+
+        // after the NTP era rolls over, in 2036
+        let datetime = parse(&reply(10, 0)).unwrap();
+        assert_eq!(datetime.unix_seconds(), (1 << 32) + 10 - UNIX_OFFSET);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(&[0x24; 20]), Err(SntpError::InvalidResponse));
+
+        let mut packet = reply(3_824_723_366, 0);
+        packet[0] = REQUEST;
+        assert_eq!(parse(&packet), Err(SntpError::InvalidResponse));
+
+        let mut packet = reply(3_824_723_366, 0);
+        packet[1] = 0;
+        assert_eq!(parse(&packet), Err(SntpError::Denied));
+
+        let mut packet = reply(3_824_723_366, 0);
+        packet[0] |= 0b11_000_000;
+        assert_eq!(parse(&packet), Err(SntpError::Unsynchronized));
+
+        assert_eq!(parse(&reply(0, 0)), Err(SntpError::InvalidResponse));
+
+        // in the era before 2036, but before the Unix epoch
+        assert_eq!(
+            parse(&reply(0x8000_0000, 0)),
+            Err(SntpError::InvalidResponse)
+        );
+        assert_eq!(
+            parse(&reply(UNIX_OFFSET as u32 - 1, 0)),
+            Err(SntpError::InvalidResponse)
+        );
+        assert_eq!(
+            parse(&reply(UNIX_OFFSET as u32, 0)).unwrap().unix_seconds(),
+            0
+        );
+    }
+
+    fn query(stack: &MockStack) -> (Result<DateTime, SntpError>, Milliseconds) {
+        let _lock = mock::lock();
+        let mut runtime = MockRuntime::new();
+        let address = runtime.mount(ActorContext::new(stack.clone()), ());
+        let timer = runtime.timer();
+        runtime.start();
+
+        let client = SntpClient::new(address, timer);
+        let server = SocketAddress::new(IpAddress::new_v4(192, 0, 2, 1), NTP_PORT);
+        let result = runtime.block_on(client.query(server));
+        (result, runtime.elapsed())
+    }
+
+    #[test]
+    fn test_query() {
+        let stack = MockStack::new();
+        let packet = reply(3_824_723_366, 0);
+        // the reply comes late, and in pieces
+        stack.receive(&[&[], &[], &packet[..20], &[], &packet[20..]]);
+
+        let (result, elapsed) = query(&stack);
+        assert_eq!(result.unwrap().unix_seconds(), 1_615_734_566);
+        assert_eq!(elapsed, Milliseconds(300u32));
+        assert_eq!(stack.sent(), [request().to_vec()]);
+    }
+
+    #[test]
+    fn test_query_retries() {
+        let stack = MockStack::new();
+        let polls = (REPLY_TIMEOUT.0 / POLL_INTERVAL.0) as usize + 1;
+        let packet = reply(3_824_723_366, 0);
+        let mut reads: Vec<&[u8]> = std::vec![&[]; polls];
+        reads.push(&packet);
+        stack.receive(&reads);
+
+        let (result, elapsed) = query(&stack);
+        assert!(result.is_ok());
+        assert_eq!(elapsed, REPLY_TIMEOUT);
+        assert_eq!(stack.sent().len(), 2);
+    }
+
+    #[test]
+    fn test_query_timeout() {
+        let stack = MockStack::new();
+        let (result, elapsed) = query(&stack);
+        assert_eq!(result, Err(SntpError::Timeout));
+        assert_eq!(elapsed, Milliseconds(REPLY_TIMEOUT.0 * ATTEMPTS as u32));
+        assert_eq!(stack.sent().len(), ATTEMPTS);
+    }
+}
@@ -0,0 +1,191 @@
+use super::sntp::SntpClient;
+use crate::api::delayer::Delayer;
+use crate::api::ip::tcp::TcpStack;
+use crate::api::ip::SocketAddress;
+use crate::api::rtc::RealTimeClock;
+use crate::api::scheduler::Scheduler;
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+
+/// Keeps a real-time clock in sync with a time server, setting it at start and then every
+/// `interval`. The timer schedules the syncs, and bounds the wait for each reply.
+pub struct TimeSync<S, R, T>
+where
+    S: TcpStack + 'static,
+    R: RealTimeClock + 'static,
+    T: Scheduler + Delayer + 'static,
+{
+    server: SocketAddress,
+    interval: Milliseconds,
+    me: Option<Address<Self>>,
+    stack: Option<Address<S>>,
+    rtc: Option<Address<R>>,
+    timer: Option<Address<T>>,
+}
+
+impl<S, R, T> TimeSync<S, R, T>
+where
+    S: TcpStack,
+    R: RealTimeClock,
+    T: Scheduler + Delayer,
+{
+    pub fn new<DUR: Into<Milliseconds>>(server: SocketAddress, interval: DUR) -> Self {
+        Self {
+            server,
+            interval: interval.into(),
+            me: None,
+            stack: None,
+            rtc: None,
+            timer: None,
+        }
+    }
+}
+
+impl<S, R, T> Actor for TimeSync<S, R, T>
+where
+    S: TcpStack,
+    R: RealTimeClock,
+    T: Scheduler + Delayer,
+{
+    type Configuration = (Address<S>, Address<R>, Address<T>);
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.me.replace(me);
+        self.stack.replace(config.0);
+        self.rtc.replace(config.1);
+        self.timer.replace(config.2);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        let me = self.me.unwrap();
+        let timer = self.timer.unwrap();
+        // through the timer, as the actor cannot be notified while it starts
+        timer.schedule(Milliseconds(0), Sync, me);
+        timer.schedule_periodic(self.interval, Sync, me);
+        Completion::immediate(self)
+    }
+}
+
+impl<S, R, T> NotifyHandler<Sync> for TimeSync<S, R, T>
+where
+    S: TcpStack,
+    R: RealTimeClock,
+    T: Scheduler + Delayer,
+{
+    fn on_notify(self, message: Sync) -> Completion<Self> {
+        Completion::defer(async move {
+            let client = SntpClient::new(self.stack.unwrap(), self.timer.unwrap());
+            match client.query(self.server).await {
+                Ok(datetime) => {
+                    self.rtc.unwrap().rtc_set(datetime).await;
+                    log::info!("[{}] clock set to {}", ActorInfo::name(), datetime);
+                }
+                Err(e) => {
+                    // the clock keeps running on its own until the next attempt
+                    log::warn!("[{}] time sync failed: {:?}", ActorInfo::name(), e);
+                }
+            }
+            self
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Sync;
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::api::ip::mock::MockStack;
+    use crate::api::ip::IpAddress;
+    use crate::api::rtc::RealTimeClock;
+    use crate::domain::time::datetime::DateTime;
+    use crate::driver::rtc::sntp::NTP_PORT;
+    use crate::system::mock::{self, MockRuntime};
+    use core::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::MutexGuard;
+
+    /// 2021-03-14T15:09:26Z, in NTP seconds and Unix seconds.
+    const NTP_SECONDS: u32 = 3_824_723_366;
+    const UNIX_SECONDS: u64 = 1_615_734_566;
+
+    #[derive(Clone, Default)]
+    struct MockRtc(Rc<Cell<Option<DateTime>>>);
+
+    impl Actor for MockRtc {
+        type Configuration = ();
+    }
+
+    impl RealTimeClock for MockRtc {
+        fn get_datetime(self) -> Response<Self, DateTime> {
+            let datetime = self.0.get().unwrap();
+            Response::immediate(self, datetime)
+        }
+
+        fn set_datetime(self, datetime: DateTime) -> Response<Self, ()> {
+            self.0.set(Some(datetime));
+            Response::immediate(self, ())
+        }
+    }
+
+    fn reply(seconds: u32) -> [u8; 48] {
+        let mut packet = [0; 48];
+        packet[0] = 0b00_100_100;
+        packet[1] = 2;
+        packet[40..44].copy_from_slice(&seconds.to_be_bytes());
+        packet
+    }
+
+    fn start(stack: &MockStack, rtc: &MockRtc) -> (MockRuntime, MutexGuard<'static, ()>) {
+        let lock = mock::lock();
+        let mut runtime = MockRuntime::new();
+        let stack = runtime.mount(ActorContext::new(stack.clone()), ());
+        let rtc = runtime.mount(ActorContext::new(rtc.clone()), ());
+        let timer = runtime.timer();
+        let server = SocketAddress::new(IpAddress::new_v4(192, 0, 2, 1), NTP_PORT);
+        runtime.mount(
+            ActorContext::new(TimeSync::new(server, Milliseconds(60_000))),
+            (stack, rtc, timer),
+        );
+        runtime.start();
+        runtime.sleep(Milliseconds(100u32));
+        (runtime, lock)
+    }
+
+    #[test]
+    fn test_sync() {
+        let stack = MockStack::new();
+        let rtc = MockRtc::default();
+        stack.receive(&[&reply(NTP_SECONDS)]);
+        let (mut runtime, _lock) = start(&stack, &rtc);
+        assert_eq!(rtc.0.get().unwrap().unix_seconds(), UNIX_SECONDS);
+
+        // and again once the interval passes
+        stack.receive(&[&reply(NTP_SECONDS + 60)]);
+        runtime.sleep(Milliseconds(59_800u32));
+        assert_eq!(stack.sent().len(), 1);
+        runtime.sleep(Milliseconds(200u32));
+        assert_eq!(stack.sent().len(), 2);
+        assert_eq!(rtc.0.get().unwrap().unix_seconds(), UNIX_SECONDS + 60);
+    }
+
+    #[test]
+    fn test_sync_failure() {
+        let stack = MockStack::new();
+        let rtc = MockRtc::default();
+        let (mut runtime, _lock) = start(&stack, &rtc);
+
+        // the server does not answer, and the clock is left alone until the next sync
+        runtime.sleep(Milliseconds(19_900u32));
+        assert_eq!(stack.sent().len(), 3);
+        assert!(rtc.0.get().is_none());
+
+        stack.receive(&[&reply(NTP_SECONDS)]);
+        runtime.sleep(Milliseconds(40_100u32));
+        assert_eq!(stack.sent().len(), 4);
+        assert_eq!(rtc.0.get().unwrap().unix_seconds(), UNIX_SECONDS);
+    }
+}
@@ -16,6 +16,14 @@ pub struct SensorAcquisition<S: TemperatureScale> {
     pub relative_humidity: f32,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorError {
+    NotInitialized,
+    NotCalibrated,
+    I2c,
+    Timeout,
+}
+
 impl<S: TemperatureScale> Debug for SensorAcquisition<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("SensorAcquisition")
@@ -24,3 +32,277 @@ impl<S: TemperatureScale> Debug for SensorAcquisition<S> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::register::calibration::Calibration;
+    use super::register::{
+        AvConf, BlockDataUpdate, Ctrl1, Ctrl2, HumidityAverage, OutputDataRate, Power, Status,
+        TemperatureAverage, WhoAmI, IDENTITY,
+    };
+    use super::sensor::ADDR;
+    use super::*;
+    use crate::domain::temperature::Celsius;
+    use crate::domain::time::duration::Milliseconds;
+    use crate::driver::i2c::I2c;
+    use crate::driver::sensor::mock::{AutoIncrement, MockI2c, SharedI2c, Transaction};
+    use crate::driver::sensor::register::Register;
+    use crate::prelude::*;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use embedded_hal::blocking::i2c::WriteRead;
+
+    const AV_CONF: u8 = 0x10;
+    const CTRL_REG1: u8 = 0x20;
+    const CTRL_REG2: u8 = 0x21;
+    const STATUS: u8 = 0x27;
+    const H_OUT: u8 = 0x28;
+    const T_OUT: u8 = 0x2A;
+
+    // H0 = 40%rH, H1 = 80%rH, T0 = 36°C, T1 = 60°C (both using the MSB bits)
+    const CALIBRATION: [u8; 16] = [
+        80, 160, 0x20, 0xE0, 0x00, 0b0101, 0x00, 0x00, 0x00, 0x00, 0x10, 0x27, 0x00, 0x00, 0xE8,
+        0x03,
+    ];
+
+    /// Reboots and one-shot conversions complete immediately.
+    fn mock() -> MockI2c {
+        MockI2c::new(ADDR, AutoIncrement::Msb)
+            .with_register(AV_CONF, 0x1B)
+            .on_write(|registers, reg| {
+                if reg == CTRL_REG2 as usize {
+                    registers[reg] &= !0x80;
+                    if (registers[reg] & 0b1) != 0 {
+                        registers[reg] &= !0b1;
+                        registers[STATUS as usize] = 0b11;
+                    }
+                }
+            })
+    }
+
+    #[test]
+    fn test_calibration() {
+        let mut i2c = mock();
+        i2c.registers[0x30..0x40].copy_from_slice(&CALIBRATION);
+
+        let mut buf = [0; 16];
+        i2c.write_read(ADDR, &[0xB0], &mut buf).unwrap();
+        assert_eq!(CALIBRATION, buf);
+
+        let calibration: Calibration = buf.into();
+        assert_eq!(36.0, calibration.temperature.t0_degc.value());
+        assert_eq!(60.0, calibration.temperature.t1_degc.value());
+        assert_eq!(48.0, calibration.calibrated_temperature(500).value());
+        assert_eq!(36.0, calibration.calibrated_temperature(0).value());
+        assert_eq!(40.0, calibration.calibrated_humidity(0));
+        assert_eq!(60.0, calibration.calibrated_humidity(5000));
+        assert_eq!(80.0, calibration.calibrated_humidity(10000));
+    }
+
+    #[test]
+    fn test_ctrl1_modify() {
+        let mut i2c = mock().with_register(CTRL_REG1, 0b1000_0101);
+
+        let mut reg: Ctrl1 = i2c.read_typed();
+        assert_eq!(Power::Active, reg.get_power());
+        assert_eq!(BlockDataUpdate::MsbLsbReading, reg.get_block_data_update());
+        assert_eq!(OutputDataRate::Hz1, reg.get_output_data_rate());
+        reg.output_data_rate(OutputDataRate::Hz12p5);
+        i2c.write_typed(reg);
+        assert_eq!(0b1000_0111, i2c.registers[CTRL_REG1 as usize]);
+
+        let mut reg: Ctrl1 = i2c.read_typed();
+        reg.power(Power::PowerDown)
+            .block_data_update(BlockDataUpdate::Continuous)
+            .output_data_rate(OutputDataRate::OneShot);
+        i2c.write_typed(reg);
+        assert_eq!(0, i2c.registers[CTRL_REG1 as usize]);
+
+        assert_eq!(
+            i2c.log,
+            [
+                Transaction::Read(CTRL_REG1, 1),
+                Transaction::Write(CTRL_REG1, [0b1000_0111].to_vec()),
+                Transaction::Read(CTRL_REG1, 1),
+                Transaction::Write(CTRL_REG1, [0].to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_one_shot() {
+        let mut i2c = mock();
+        let status: Status = i2c.read_typed();
+        assert!(!status.any_available());
+
+        let mut reg: Ctrl2 = i2c.read_typed();
+        reg.heater(true).one_shot(true);
+        assert_eq!(0b0000_0011, reg.into_value());
+        i2c.write_typed(reg);
+
+        let status: Status = i2c.read_typed();
+        assert!(status.get_temperature_available());
+        assert!(status.get_humidity_available());
+        // heater remains on, one-shot bit self-clears
+        assert_eq!(0b0000_0010, i2c.registers[CTRL_REG2 as usize]);
+    }
+
+    #[test]
+    fn test_av_conf() {
+        let mut i2c = mock();
+        let mut reg: AvConf = i2c.read_typed();
+        assert_eq!(0x1B, reg.into_value());
+        assert_eq!(TemperatureAverage::Avg16, reg.get_temperature_average());
+        assert_eq!(HumidityAverage::Avg32, reg.get_humidity_average());
+
+        reg.temperature_average(TemperatureAverage::Avg256)
+            .humidity_average(HumidityAverage::Avg4);
+        assert_eq!(0b0011_1000, reg.into_value());
+
+        reg.temperature_average(TemperatureAverage::Avg2)
+            .humidity_average(HumidityAverage::Avg512);
+        assert_eq!(0b0000_0111, reg.into_value());
+    }
+
+    #[test]
+    fn test_who_am_i() {
+        let mut i2c = mock().with_register(0x0F, IDENTITY);
+        let who_am_i: WhoAmI = i2c.read_typed();
+        assert_eq!(IDENTITY, who_am_i.get_identity());
+    }
+
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn mount(&'static self, config: DeviceConfiguration<Self>, supervisor: &mut Supervisor) {}
+    }
+
+    impl EventHandler<SensorAcquisition<Celsius>> for TestDevice {}
+
+    type TestSensor = Sensor<TestDevice, SharedI2c, MockTimer>;
+
+    /// A calibrated sensor reading 48°C and 60%rH, started with `output_data_rate`.
+    fn start(
+        mut i2c: MockI2c,
+        output_data_rate: OutputDataRate,
+    ) -> (MockRuntime, Address<TestSensor>, SharedI2c) {
+        i2c.registers[0x30..0x40].copy_from_slice(&CALIBRATION);
+        i2c.registers[H_OUT as usize..][..2].copy_from_slice(&5000u16.to_le_bytes());
+        i2c.registers[T_OUT as usize..][..2].copy_from_slice(&500u16.to_le_bytes());
+        let i2c = SharedI2c::new(i2c);
+
+        let mut runtime = MockRuntime::new();
+        let bus = runtime.event_bus(TestDevice);
+        let peripheral = runtime.mount_package(I2c::new(i2c.clone()), ());
+        let timer = runtime.timer();
+        let sensor = runtime.mount(
+            ActorContext::new(Sensor::new().with_output_data_rate(output_data_rate)),
+            (bus, peripheral, timer),
+        );
+        runtime.start();
+        i2c.borrow_mut().log.clear();
+        (runtime, sensor, i2c)
+    }
+
+    #[test]
+    fn test_initialize() {
+        let _lock = mock::lock();
+        let (_, _, i2c) = start(mock(), OutputDataRate::Hz7);
+        // active, block data update, 7Hz, and data ready enabled
+        let i2c = i2c.borrow_mut();
+        assert_eq!(i2c.registers[CTRL_REG1 as usize], 0b1000_0110);
+        assert_eq!(i2c.registers[0x22], 0b0000_0100);
+    }
+
+    #[test]
+    fn test_take_reading() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock(), OutputDataRate::OneShot);
+
+        let reading = runtime.block_on(sensor.take_reading()).unwrap();
+        assert_eq!(reading.temperature.value(), 48.0);
+        assert_eq!(reading.relative_humidity, 60.0);
+        assert_eq!(
+            i2c.borrow_mut().log,
+            [
+                Transaction::Read(CTRL_REG2, 1),
+                Transaction::Write(CTRL_REG2, [0b1].to_vec()),
+                Transaction::Read(STATUS, 1),
+                Transaction::Read(T_OUT | 0x80, 2),
+                Transaction::Read(H_OUT | 0x80, 2),
+            ]
+        );
+
+        // in continuous mode the latest output is read without triggering a conversion
+        runtime
+            .block_on(sensor.set_output_data_rate(OutputDataRate::Hz1))
+            .unwrap();
+        i2c.borrow_mut().log.clear();
+        runtime.block_on(sensor.take_reading()).unwrap();
+        assert_eq!(
+            i2c.borrow_mut().log,
+            [
+                Transaction::Read(T_OUT | 0x80, 2),
+                Transaction::Read(H_OUT | 0x80, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_take_reading_timeout() {
+        let _lock = mock::lock();
+        // the one-shot conversion never completes
+        let (mut runtime, sensor, i2c) = start(
+            MockI2c::new(ADDR, AutoIncrement::Msb),
+            OutputDataRate::OneShot,
+        );
+
+        let result = runtime.block_on(sensor.take_reading());
+        assert_eq!(result.unwrap_err(), SensorError::Timeout);
+        assert_eq!(runtime.elapsed(), Milliseconds(500u32));
+        let polls = i2c
+            .borrow_mut()
+            .log
+            .iter()
+            .filter(|t| **t == Transaction::Read(STATUS, 1))
+            .count();
+        assert_eq!(polls, 101);
+    }
+
+    #[test]
+    fn test_output_data_rate_and_power() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock(), OutputDataRate::Hz1);
+
+        runtime
+            .block_on(sensor.set_output_data_rate(OutputDataRate::Hz12p5))
+            .unwrap();
+        runtime.block_on(sensor.power_down()).unwrap();
+        assert_eq!(i2c.borrow_mut().registers[CTRL_REG1 as usize], 0b0000_0111);
+        runtime.block_on(sensor.power_up()).unwrap();
+        assert_eq!(
+            i2c.borrow_mut().log,
+            [
+                Transaction::Read(CTRL_REG1, 1),
+                Transaction::Write(CTRL_REG1, [0b1000_0111].to_vec()),
+                Transaction::Read(CTRL_REG1, 1),
+                Transaction::Write(CTRL_REG1, [0b0000_0111].to_vec()),
+                Transaction::Read(CTRL_REG1, 1),
+                Transaction::Write(CTRL_REG1, [0b1000_0111].to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_averaging_and_heater() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock(), OutputDataRate::Hz1);
+
+        runtime
+            .block_on(sensor.set_averaging(TemperatureAverage::Avg2, HumidityAverage::Avg512))
+            .unwrap();
+        runtime.block_on(sensor.set_heater(true)).unwrap();
+        let i2c = i2c.borrow_mut();
+        assert_eq!(i2c.registers[AV_CONF as usize], 0b0000_0111);
+        assert_eq!(i2c.registers[CTRL_REG2 as usize], 0b0000_0010);
+    }
+}
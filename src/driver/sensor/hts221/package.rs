@@ -1,6 +1,8 @@
+use crate::api::delayer::Delayer;
 use crate::domain::temperature::Celsius;
 use crate::driver::i2c::I2cPeripheral;
 use crate::driver::sensor::hts221::ready::Ready;
+use crate::driver::sensor::hts221::register::OutputDataRate;
 use crate::driver::sensor::hts221::sensor::Sensor;
 use crate::driver::sensor::hts221::SensorAcquisition;
 use crate::hal::gpio::InterruptPin;
@@ -9,25 +11,38 @@ use cortex_m::interrupt::Nr;
 use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
 use embedded_hal::digital::v2::InputPin;
 
-pub struct Hts221<D, P, I>
+pub struct Hts221<D, P, I, T>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>> + 'static,
     P: InputPin + InterruptPin + 'static,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
-    sensor: ActorContext<Sensor<D, I>>,
-    ready: InterruptContext<Ready<D, P, I>>,
+    sensor: ActorContext<Sensor<D, I, T>>,
+    ready: InterruptContext<Ready<D, P, I, T>>,
 }
 
-impl<D, P, I> Hts221<D, P, I>
+impl<D, P, I, T> Hts221<D, P, I, T>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>>,
     P: InputPin + InterruptPin,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
     pub fn new<N: Nr>(ready: P, irq: N) -> Self {
+        Self::with_output_data_rate(ready, irq, OutputDataRate::Hz1)
+    }
+
+    /// Create the package with a specific output data rate. Using `OutputDataRate::OneShot`
+    /// disables continuous conversion, so readings are only taken on request.
+    pub fn with_output_data_rate<N: Nr>(
+        ready: P,
+        irq: N,
+        output_data_rate: OutputDataRate,
+    ) -> Self {
         Self {
-            sensor: ActorContext::new(Sensor::new()).with_name("hts221-sensor"),
+            sensor: ActorContext::new(Sensor::new().with_output_data_rate(output_data_rate))
+                .with_name("hts221-sensor"),
             ready: InterruptContext::new(Ready::new(ready), irq).with_name("hts221-irq"),
         }
     }
@@ -37,14 +52,15 @@ where
     //}
 }
 
-impl<D, P, I> Package for Hts221<D, P, I>
+impl<D, P, I, T> Package for Hts221<D, P, I, T>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>>,
     P: InputPin + InterruptPin,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
-    type Primary = Sensor<D, I>;
-    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+    type Primary = Sensor<D, I, T>;
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>, Address<T>);
 
     fn mount(
         &'static self,
@@ -1,3 +1,4 @@
+use crate::api::delayer::Delayer;
 use crate::domain::temperature::Celsius;
 use crate::driver::sensor::hts221::sensor::Sensor;
 use crate::driver::sensor::hts221::SensorAcquisition;
@@ -8,34 +9,37 @@ use embedded_hal::digital::v2::InputPin;
 
 pub struct DataReady;
 
-pub struct Ready<D, P, I>
+pub struct Ready<D, P, I, T>
 where
     D: Device + 'static,
     P: InputPin + InterruptPin + 'static,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
     pin: P,
-    sensor: Option<Address<Sensor<D, I>>>,
+    sensor: Option<Address<Sensor<D, I, T>>>,
 }
 
-impl<D, P, I> Ready<D, P, I>
+impl<D, P, I, T> Ready<D, P, I, T>
 where
     D: Device,
     P: InputPin + InterruptPin,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
     pub fn new(pin: P) -> Self {
         Self { pin, sensor: None }
     }
 }
 
-impl<D, P, I> Actor for Ready<D, P, I>
+impl<D, P, I, T> Actor for Ready<D, P, I, T>
 where
     D: Device,
     P: InputPin + InterruptPin,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
-    type Configuration = Address<Sensor<D, I>>;
+    type Configuration = Address<Sensor<D, I, T>>;
 
     fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
     where
@@ -45,11 +49,12 @@ where
     }
 }
 
-impl<D, P, I> Interrupt for Ready<D, P, I>
+impl<D, P, I, T> Interrupt for Ready<D, P, I, T>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>> + 'static,
     P: InputPin + InterruptPin,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
     fn on_interrupt(&mut self) {
         if self.pin.check_interrupt() {
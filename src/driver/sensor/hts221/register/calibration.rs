@@ -1,12 +1,57 @@
 use crate::api::i2c::I2cAddress;
 use crate::domain::temperature::{Celsius, Temperature};
 use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::register::{Register, RegisterValue};
 use crate::prelude::Address;
+use crate::register;
 use embedded_hal::blocking::i2c::WriteRead;
 
-// 16-byte block of calibration at 0x30 with high bit for auto-increment
+// The calibration registers span 0x30 to 0x3F and are read as one 16-byte block, with the
+// high bit of each address set for auto-increment.
 const CALIBRATION_16: u8 = 0xB0;
 
+register! {
+    pub RhCalibration: ro u16 @ 0xB0 {
+        h1_rh_x2: u8 = [15:8],
+        h0_rh_x2: u8 = [7:0],
+    }
+}
+
+register! {
+    /// Temperature calibration points in 1/8 °C, 10 bits each with the top two bits apart.
+    pub DegCCalibration: ro u32 @ 0xB2 {
+        t1_msb: u16 = [27:26],
+        t0_msb: u16 = [25:24],
+        t1_degc_x8: u16 = [15:8],
+        t0_degc_x8: u16 = [7:0],
+    }
+}
+
+register! {
+    pub H0T0Out: ro u16 @ 0xB6 {
+        h0_t0_out: i16 = [15:0],
+    }
+}
+
+register! {
+    pub H1T0Out: ro u16 @ 0xBA {
+        h1_t0_out: i16 = [15:0],
+    }
+}
+
+register! {
+    pub TOutCalibration: ro u32 @ 0xBC {
+        t1_out: i16 = [31:16],
+        t0_out: i16 = [15:0],
+    }
+}
+
+/// Decode a calibration register from its place within the 16-byte block.
+fn decode<R: Register>(block: &[u8; 16]) -> R {
+    let offset = (R::ADDRESS - CALIBRATION_16) as usize;
+    R::from_value(RegisterValue::from_le(&block[offset..]))
+}
+
 pub struct Calibration {
     pub temperature: TemperatureCalibration,
     pub humidity: HumidityCalibration,
@@ -18,7 +63,7 @@ impl Calibration {
         i2c: Address<I2cPeripheral<I>>,
     ) -> Result<Calibration, I::Error> {
         let mut buf = [0; 16];
-        let result = i2c.write_read(address, &[CALIBRATION_16], &mut buf).await?;
+        i2c.write_read(address, &[CALIBRATION_16], &mut buf).await?;
         Ok(buf.into())
     }
 
@@ -61,20 +106,19 @@ impl HumidityCalibration {
 
 impl Into<Calibration> for [u8; 16] {
     fn into(self) -> Calibration {
-        let t0_out = i16::from_le_bytes([self[12], self[13]]);
+        let rh: RhCalibration = decode(&self);
+        let degc: DegCCalibration = decode(&self);
+        let h0_out = decode::<H0T0Out>(&self).get_h0_t0_out();
+        let h1_out = decode::<H1T0Out>(&self).get_h1_t0_out();
+        let t_out: TOutCalibration = decode(&self);
 
-        let t1_out = i16::from_le_bytes([self[14], self[15]]);
+        let t0_out = t_out.get_t0_out();
+        let t1_out = t_out.get_t1_out();
 
-        let t0_degc = self[2];
-        let t1_degc = self[3];
-
-        let t_msb = self[5];
-
-        let t0_msb = (t_msb & 0b00000011) >> 2;
-        let t1_msb = (t_msb & 0b00001100) >> 2;
-
-        let t0_degc = (i16::from_le_bytes([t0_degc, t0_msb]) as f32 / 8.0).into();
-        let t1_degc = (i16::from_le_bytes([t1_degc, t1_msb]) as f32 / 8.0).into();
+        let t0_degc = (degc.get_t0_msb() << 8 | degc.get_t0_degc_x8()) as f32 / 8.0;
+        let t1_degc = (degc.get_t1_msb() << 8 | degc.get_t1_degc_x8()) as f32 / 8.0;
+        let t0_degc: Temperature<Celsius> = t0_degc.into();
+        let t1_degc: Temperature<Celsius> = t1_degc.into();
 
         let slope = (t1_degc - t0_degc) / ((t1_out - t0_out) as f32);
 
@@ -86,12 +130,8 @@ impl Into<Calibration> for [u8; 16] {
             slope,
         };
 
-        let h0_rh = self[0] as f32 / 2.0;
-        let h1_rh = self[1] as f32 / 2.0;
-
-        let h0_out = i16::from_le_bytes([self[6], self[7]]);
-
-        let h1_out = i16::from_le_bytes([self[10], self[11]]);
+        let h0_rh = rh.get_h0_rh_x2() as f32 / 2.0;
+        let h1_rh = rh.get_h1_rh_x2() as f32 / 2.0;
 
         let slope = (h1_rh - h0_rh) / ((h1_out - h0_out) as f32);
 
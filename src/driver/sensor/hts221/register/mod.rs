@@ -1,13 +1,149 @@
+use crate::{register, register_field};
+
 pub mod calibration;
-pub mod ctrl1;
-pub mod ctrl2;
-pub mod ctrl3;
-pub mod h_out;
-pub mod status;
-pub mod t_out;
-pub mod who_am_i;
-
-pub enum ModifyError<R, W> {
-    Read(R),
-    Write(W),
+
+pub use crate::driver::sensor::register::ModifyError;
+
+/// Fixed value of the `WHO_AM_I` register.
+pub const IDENTITY: u8 = 0xBC;
+
+register_field! {
+    /// Number of internal temperature samples averaged per output value.
+    pub enum TemperatureAverage {
+        Avg2 = 0b000,
+        Avg4 = 0b001,
+        Avg8 = 0b010,
+        Avg16 = 0b011,
+        Avg32 = 0b100,
+        Avg64 = 0b101,
+        Avg128 = 0b110,
+        Avg256 = 0b111,
+        _ => Avg2,
+    }
+}
+
+register_field! {
+    /// Number of internal humidity samples averaged per output value.
+    pub enum HumidityAverage {
+        Avg4 = 0b000,
+        Avg8 = 0b001,
+        Avg16 = 0b010,
+        Avg32 = 0b011,
+        Avg64 = 0b100,
+        Avg128 = 0b101,
+        Avg256 = 0b110,
+        Avg512 = 0b111,
+        _ => Avg4,
+    }
+}
+
+register_field! {
+    pub enum Power {
+        PowerDown = 0b0,
+        Active = 0b1,
+        _ => PowerDown,
+    }
+}
+
+register_field! {
+    pub enum BlockDataUpdate {
+        Continuous = 0b0,
+        /// Output registers are not updated until both the MSB and LSB have been read.
+        MsbLsbReading = 0b1,
+        _ => Continuous,
+    }
+}
+
+register_field! {
+    pub enum OutputDataRate {
+        /// No continuous conversion, conversions only happen on a one-shot request.
+        OneShot = 0b00,
+        Hz1 = 0b01,
+        Hz7 = 0b10,
+        Hz12p5 = 0b11,
+        _ => OneShot,
+    }
+}
+
+register_field! {
+    pub enum ActiveState {
+        High = 0b0,
+        Low = 0b1,
+        _ => High,
+    }
+}
+
+register_field! {
+    pub enum ReadyMode {
+        PushPull = 0b0,
+        OpenDrain = 0b1,
+        _ => PushPull,
+    }
+}
+
+register! {
+    pub WhoAmI: ro u8 @ 0x0F {
+        identity: u8 = [7:0],
+    }
+}
+
+register! {
+    pub AvConf: rw u8 @ 0x10 {
+        temperature_average: TemperatureAverage = [5:3],
+        humidity_average: HumidityAverage = [2:0],
+    }
+}
+
+register! {
+    pub Ctrl1: rw u8 @ 0x20 {
+        power: Power = [7],
+        block_data_update: BlockDataUpdate = [2],
+        output_data_rate: OutputDataRate = [1:0],
+    }
+}
+
+register! {
+    pub Ctrl2: rw u8 @ 0x21 {
+        /// Reload the calibration from flash, the bit self-clears once done.
+        boot: bool = [7],
+        heater: bool = [1],
+        /// Trigger a single conversion, the bit self-clears once it completes.
+        one_shot: bool = [0],
+    }
+}
+
+register! {
+    /// Configuration of the DRDY pin.
+    pub Ctrl3: rw u8 @ 0x22 {
+        active_state: ActiveState = [7],
+        ready_mode: ReadyMode = [6],
+        data_ready: bool = [2],
+    }
+}
+
+register! {
+    pub Status: ro u8 @ 0x27 {
+        humidity_available: bool = [1],
+        temperature_available: bool = [0],
+    }
+}
+
+impl Status {
+    pub fn any_available(&self) -> bool {
+        self.temperature_available || self.humidity_available
+    }
+}
+
+// Registers wider than a byte are addressed with the MSB set for auto-increment.
+
+register! {
+    pub Hout: ro u16 @ 0xA8 {
+        humidity: i16 = [15:0],
+    }
+}
+
+register! {
+    pub Tout: ro u16 @ 0xAA {
+        temperature: i16 = [15:0],
+    }
 }
@@ -1,62 +1,81 @@
+use crate::api::delayer::Delayer;
 use crate::api::i2c::I2cAddress;
 use crate::domain::temperature::Celsius;
+use crate::domain::time::duration::Milliseconds;
 use crate::driver::i2c::I2cPeripheral;
 use crate::driver::sensor::hts221::ready::DataReady;
 use crate::driver::sensor::hts221::register::calibration::*;
-use crate::driver::sensor::hts221::register::ctrl1::{BlockDataUpdate, Ctrl1, OutputDataRate};
-use crate::driver::sensor::hts221::register::ctrl2::Ctrl2;
-use crate::driver::sensor::hts221::register::ctrl3::Ctrl3;
-use crate::driver::sensor::hts221::register::h_out::Hout;
-use crate::driver::sensor::hts221::register::status::Status;
-use crate::driver::sensor::hts221::register::t_out::Tout;
-use crate::driver::sensor::hts221::SensorAcquisition;
+use crate::driver::sensor::hts221::register::{
+    AvConf, BlockDataUpdate, Ctrl1, Ctrl2, Ctrl3, Hout, HumidityAverage, OutputDataRate, Power,
+    Status, TemperatureAverage, Tout,
+};
+use crate::driver::sensor::hts221::{SensorAcquisition, SensorError};
 use crate::prelude::*;
 use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
 
 pub const ADDR: u8 = 0x5F;
 
-pub struct Sensor<D, I>
+/// How long a one-shot conversion may take, with the most averaging.
+const ONE_SHOT_TIMEOUT: Milliseconds = Milliseconds(500);
+/// How long to wait between checks for a one-shot conversion to complete.
+const ONE_SHOT_POLL: Milliseconds = Milliseconds(5);
+
+pub struct Sensor<D, I, T>
 where
     D: Device + 'static,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
     address: I2cAddress,
+    output_data_rate: OutputDataRate,
     i2c: Option<Address<I2cPeripheral<I>>>,
+    delayer: Option<Address<T>>,
     calibration: Option<Calibration>,
     bus: Option<Address<EventBus<D>>>,
 }
 
-impl<D, I> Sensor<D, I>
+impl<D, I, T> Sensor<D, I, T>
 where
     D: Device,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
     pub fn new() -> Self {
         Self {
             address: I2cAddress::new(ADDR),
+            output_data_rate: OutputDataRate::Hz1,
             i2c: None,
+            delayer: None,
             calibration: None,
             bus: None,
         }
     }
+
+    /// Use the provided output data rate once initialized instead of the default 1Hz.
+    pub fn with_output_data_rate(mut self, output_data_rate: OutputDataRate) -> Self {
+        self.output_data_rate = output_data_rate;
+        self
+    }
 }
 
-impl<D, I> Default for Sensor<D, I>
+impl<D, I, T> Default for Sensor<D, I, T>
 where
     D: Device,
     I: WriteRead + Read + Write + 'static,
+    T: Delayer + 'static,
 {
     fn default() -> Self {
         Sensor::new()
     }
 }
 
-impl<D, I> Actor for Sensor<D, I>
+impl<D, I, T> Actor for Sensor<D, I, T>
 where
     D: Device,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
-    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>, Address<T>);
 
     fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
     where
@@ -64,27 +83,29 @@ where
     {
         self.bus.replace(config.0);
         self.i2c.replace(config.1);
+        self.delayer.replace(config.2);
     }
 
     fn on_initialize(self) -> Completion<Self> {
         Completion::defer(async move {
             if let Some(i2c) = self.i2c {
                 Ctrl2::modify(self.address, i2c, |reg| {
-                    reg.boot();
+                    reg.boot(true);
                 })
                 .await
                 .ok();
 
+                let output_data_rate = self.output_data_rate;
                 Ctrl1::modify(self.address, i2c, |reg| {
-                    reg.power_active()
-                        .output_data_rate(OutputDataRate::Hz1)
+                    reg.power(Power::Active)
+                        .output_data_rate(output_data_rate)
                         .block_data_update(BlockDataUpdate::MsbLsbReading);
                 })
                 .await
                 .ok();
 
                 Ctrl3::modify(self.address, i2c, |reg| {
-                    reg.enable(true);
+                    reg.data_ready(true);
                 })
                 .await
                 .ok();
@@ -116,10 +137,11 @@ where
     }
 }
 
-impl<D, I> NotifyHandler<DataReady> for Sensor<D, I>
+impl<D, I, T> NotifyHandler<DataReady> for Sensor<D, I, T>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>>,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
     fn on_notify(self, message: DataReady) -> Completion<Self> {
         Completion::defer(async move {
@@ -128,10 +150,12 @@ where
 
                 if let Some(ref calibration) = self.calibration {
                     if let Ok(t_out) = Tout::read(self.address, i2c).await {
-                        let temperature = calibration.calibrated_temperature(t_out);
+                        let temperature =
+                            calibration.calibrated_temperature(t_out.get_temperature());
 
                         if let Ok(h_out) = Hout::read(self.address, i2c).await {
-                            let relative_humidity = calibration.calibrated_humidity(h_out);
+                            let relative_humidity =
+                                calibration.calibrated_humidity(h_out.get_humidity());
 
                             self.bus.unwrap().publish(SensorAcquisition {
                                 temperature,
@@ -148,11 +172,268 @@ where
     }
 }
 
+impl<D, I, T> Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    async fn acquire(&self) -> Result<SensorAcquisition<Celsius>, SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+        let calibration = self
+            .calibration
+            .as_ref()
+            .ok_or(SensorError::NotCalibrated)?;
+
+        if let OutputDataRate::OneShot = self.output_data_rate {
+            Ctrl2::modify(self.address, i2c, |reg| {
+                reg.one_shot(true);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+            let delayer = self.delayer.ok_or(SensorError::NotInitialized)?;
+            let mut waited = 0;
+            loop {
+                let status = Status::read(self.address, i2c)
+                    .await
+                    .map_err(|_| SensorError::I2c)?;
+                if status.get_temperature_available() && status.get_humidity_available() {
+                    break;
+                }
+                if waited >= ONE_SHOT_TIMEOUT.0 {
+                    return Err(SensorError::Timeout);
+                }
+                delayer.delay(ONE_SHOT_POLL).await;
+                waited += ONE_SHOT_POLL.0;
+            }
+        }
+
+        let t_out = Tout::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        let h_out = Hout::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+        Ok(SensorAcquisition {
+            temperature: calibration.calibrated_temperature(t_out.get_temperature()),
+            relative_humidity: calibration.calibrated_humidity(h_out.get_humidity()),
+        })
+    }
+}
+
+/// Request a single acquisition. In one-shot mode this triggers a conversion,
+/// otherwise the most recent output of the continuous conversion is returned.
+#[derive(Debug)]
+pub struct TakeReading;
+
+impl<D, I, T> RequestHandler<TakeReading> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<SensorAcquisition<Celsius>, SensorError>;
+
+    fn on_request(self, message: TakeReading) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.acquire().await;
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetOutputDataRate(pub OutputDataRate);
+
+impl<D, I, T> RequestHandler<SetOutputDataRate> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(mut self, message: SetOutputDataRate) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = if let Some(i2c) = self.i2c {
+                Ctrl1::modify(self.address, i2c, |reg| {
+                    reg.output_data_rate(message.0);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)
+            } else {
+                Err(SensorError::NotInitialized)
+            };
+            if result.is_ok() {
+                self.output_data_rate = message.0;
+            }
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetAveraging(pub TemperatureAverage, pub HumidityAverage);
+
+impl<D, I, T> RequestHandler<SetAveraging> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(self, message: SetAveraging) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = if let Some(i2c) = self.i2c {
+                AvConf::modify(self.address, i2c, |reg| {
+                    reg.temperature_average(message.0)
+                        .humidity_average(message.1);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)
+            } else {
+                Err(SensorError::NotInitialized)
+            };
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetHeater(pub bool);
+
+impl<D, I, T> RequestHandler<SetHeater> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(self, message: SetHeater) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = if let Some(i2c) = self.i2c {
+                Ctrl2::modify(self.address, i2c, |reg| {
+                    reg.heater(message.0);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)
+            } else {
+                Err(SensorError::NotInitialized)
+            };
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct PowerDown;
+
+impl<D, I, T> RequestHandler<PowerDown> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(self, message: PowerDown) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = if let Some(i2c) = self.i2c {
+                Ctrl1::modify(self.address, i2c, |reg| {
+                    reg.power(Power::PowerDown);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)
+            } else {
+                Err(SensorError::NotInitialized)
+            };
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct PowerUp;
+
+impl<D, I, T> RequestHandler<PowerUp> for Sensor<D, I, T>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(self, message: PowerUp) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = if let Some(i2c) = self.i2c {
+                Ctrl1::modify(self.address, i2c, |reg| {
+                    reg.power(Power::Active);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)
+            } else {
+                Err(SensorError::NotInitialized)
+            };
+            (self, result)
+        })
+    }
+}
+
+impl<D, I, T> Address<Sensor<D, I, T>>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write,
+    T: Delayer,
+{
+    /// Take a single temperature and humidity reading.
+    pub async fn take_reading(&self) -> Result<SensorAcquisition<Celsius>, SensorError> {
+        self.request(TakeReading).await
+    }
+
+    /// Change the output data rate. `OutputDataRate::OneShot` stops continuous
+    /// conversion, after which readings are only taken through `take_reading()`.
+    pub async fn set_output_data_rate(
+        &self,
+        output_data_rate: OutputDataRate,
+    ) -> Result<(), SensorError> {
+        self.request(SetOutputDataRate(output_data_rate)).await
+    }
+
+    /// Configure the number of internal samples averaged for each output value.
+    pub async fn set_averaging(
+        &self,
+        temperature: TemperatureAverage,
+        humidity: HumidityAverage,
+    ) -> Result<(), SensorError> {
+        self.request(SetAveraging(temperature, humidity)).await
+    }
+
+    /// Turn the internal heater on or off.
+    pub async fn set_heater(&self, on: bool) -> Result<(), SensorError> {
+        self.request(SetHeater(on)).await
+    }
+
+    /// Put the sensor into power-down mode.
+    pub async fn power_down(&self) -> Result<(), SensorError> {
+        self.request(PowerDown).await
+    }
+
+    /// Return the sensor to active mode after `power_down()`.
+    pub async fn power_up(&self) -> Result<(), SensorError> {
+        self.request(PowerUp).await
+    }
+}
+
 #[doc(hidden)]
-impl<D, I> Address<Sensor<D, I>>
+impl<D, I, T> Address<Sensor<D, I, T>>
 where
     D: Device + EventHandler<SensorAcquisition<Celsius>> + 'static,
     I: WriteRead + Read + Write,
+    T: Delayer,
 {
     pub fn signal_data_ready(&self) {
         self.notify(DataReady)
@@ -0,0 +1,231 @@
+pub mod package;
+pub mod ready;
+pub mod register;
+pub mod sensor;
+
+pub use package::Lis3mdl;
+pub use ready::Ready;
+pub use sensor::Sensor;
+
+use crate::driver::sensor::lis3mdl::register::FullScale;
+use core::fmt::{Debug, Formatter};
+
+/// Magnetic field strength, in gauss, along each axis.
+#[derive(Copy, Clone)]
+pub struct MagneticFieldAcquisition {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl MagneticFieldAcquisition {
+    /// Convert raw axis outputs using the sensitivity of the configured full scale.
+    pub fn from_raw(raw: [i16; 3], full_scale: FullScale) -> Self {
+        let sensitivity = full_scale.sensitivity();
+        Self {
+            x: raw[0] as f32 / sensitivity,
+            y: raw[1] as f32 / sensitivity,
+            z: raw[2] as f32 / sensitivity,
+        }
+    }
+}
+
+impl Debug for MagneticFieldAcquisition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MagneticFieldAcquisition")
+            .field("x", &self.x)
+            .field("y", &self.y)
+            .field("z", &self.z)
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorError {
+    NotInitialized,
+    I2c,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::register::out::axes;
+    use super::register::{
+        CtrlReg1, CtrlReg2, CtrlReg3, CtrlReg4, DataRate, FullScale, MeasurementMode,
+        OperativeMode, Status,
+    };
+    use super::sensor::{Sensor, ADDR};
+    use super::{MagneticFieldAcquisition, SensorError};
+    use crate::driver::i2c::I2c;
+    use crate::driver::sensor::mock::{AutoIncrement, MockI2c, SharedI2c, Transaction};
+    use crate::driver::sensor::register::Register;
+    use crate::prelude::*;
+    use crate::system::mock::{self, MockRuntime};
+    use embedded_hal::blocking::i2c::WriteRead;
+
+    const CTRL_REG1: u8 = 0x20;
+    const CTRL_REG2: u8 = 0x21;
+    const CTRL_REG3: u8 = 0x22;
+    const CTRL_REG4: u8 = 0x23;
+    const CTRL_REG5: u8 = 0x24;
+    const STATUS_REG: u8 = 0x27;
+    const OUT_XYZ: u8 = 0xA8;
+
+    fn mock() -> MockI2c {
+        // power-on defaults
+        MockI2c::new(ADDR, AutoIncrement::Msb)
+            .with_register(0x0F, 0x3D)
+            .with_register(CTRL_REG1, 0x10)
+            .with_register(CTRL_REG3, 0x03)
+    }
+
+    #[test]
+    fn test_ctrl_reg1() {
+        let mut i2c = mock();
+        let mut reg: CtrlReg1 = i2c.read_typed();
+        assert_eq!(0x10, reg.into_value());
+        assert_eq!(DataRate::Hz10, reg.get_data_rate());
+        assert_eq!(OperativeMode::LowPower, reg.get_xy_mode());
+
+        reg.xy_mode(OperativeMode::UltraHighPerformance)
+            .data_rate(DataRate::Hz80)
+            .temperature_enable(true);
+        i2c.write_typed(reg);
+        assert_eq!(0b1111_1100, i2c.registers[CTRL_REG1 as usize]);
+
+        let reg: CtrlReg1 = i2c.read_typed();
+        assert_eq!(0b1111_1100, reg.into_value());
+    }
+
+    #[test]
+    fn test_ctrl_reg2_to_4() {
+        let mut i2c = mock();
+
+        let mut reg: CtrlReg2 = i2c.read_typed();
+        reg.full_scale(FullScale::Gauss12);
+        i2c.write_typed(reg);
+        assert_eq!(0b0100_0000, i2c.registers[CTRL_REG2 as usize]);
+
+        let mut reg: CtrlReg3 = i2c.read_typed();
+        assert_eq!(MeasurementMode::PowerDown, reg.get_mode());
+        reg.mode(MeasurementMode::Continuous);
+        i2c.write_typed(reg);
+        assert_eq!(0, i2c.registers[CTRL_REG3 as usize]);
+
+        let mut reg: CtrlReg4 = i2c.read_typed();
+        reg.z_mode(OperativeMode::HighPerformance);
+        i2c.write_typed(reg);
+        assert_eq!(0b0000_1000, i2c.registers[CTRL_REG4 as usize]);
+
+        assert_eq!(
+            i2c.log,
+            [
+                Transaction::Read(CTRL_REG2, 1),
+                Transaction::Write(CTRL_REG2, [0b0100_0000].to_vec()),
+                Transaction::Read(CTRL_REG3, 1),
+                Transaction::Write(CTRL_REG3, [0].to_vec()),
+                Transaction::Read(CTRL_REG4, 1),
+                Transaction::Write(CTRL_REG4, [0b0000_1000].to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_out() {
+        let mut i2c = mock().with_register(STATUS_REG, 0b0000_1000);
+        let status: Status = i2c.read_typed();
+        assert!(status.get_xyz_available());
+        assert!(!status.get_xyz_overrun());
+
+        // x = 6842, y = -3421, z = 0
+        i2c.registers[0x28..0x2E].copy_from_slice(&[0xBA, 0x1A, 0xA3, 0xF2, 0x00, 0x00]);
+        let mut buf = [0; 6];
+        i2c.write_read(ADDR, &[0xA8], &mut buf).unwrap();
+        assert_eq!([6842, -3421, 0], axes(&buf));
+
+        let field = MagneticFieldAcquisition::from_raw(axes(&buf), FullScale::Gauss4);
+        assert_eq!(1.0, field.x);
+        assert_eq!(-0.5, field.y);
+        assert_eq!(0.0, field.z);
+
+        let field = MagneticFieldAcquisition::from_raw(axes(&buf), FullScale::Gauss8);
+        assert_eq!(2.0, field.x);
+    }
+
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn mount(&'static self, config: DeviceConfiguration<Self>, supervisor: &mut Supervisor) {}
+    }
+
+    impl EventHandler<MagneticFieldAcquisition> for TestDevice {}
+
+    type TestSensor = Sensor<TestDevice, SharedI2c>;
+
+    fn start(i2c: MockI2c) -> (MockRuntime, Address<TestSensor>, SharedI2c) {
+        let i2c = SharedI2c::new(i2c);
+        let mut runtime = MockRuntime::new();
+        let bus = runtime.event_bus(TestDevice);
+        let peripheral = runtime.mount_package(I2c::new(i2c.clone()), ());
+        let sensor = runtime.mount(ActorContext::new(Sensor::new()), (bus, peripheral));
+        runtime.start();
+        (runtime, sensor, i2c)
+    }
+
+    fn out_reads(i2c: &SharedI2c) -> usize {
+        i2c.borrow_mut()
+            .log
+            .iter()
+            .filter(|t| **t == Transaction::Read(OUT_XYZ, 6))
+            .count()
+    }
+
+    #[test]
+    fn test_initialize() {
+        let _lock = mock::lock();
+        let (_, _, i2c) = start(mock());
+        let i2c = i2c.borrow_mut();
+        // ultra-high performance at 10Hz, ±4 gauss, continuous, with block data update
+        assert_eq!(0b0111_0000, i2c.registers[CTRL_REG1 as usize]);
+        assert_eq!(0, i2c.registers[CTRL_REG2 as usize]);
+        assert_eq!(0, i2c.registers[CTRL_REG3 as usize]);
+        assert_eq!(0b0000_1100, i2c.registers[CTRL_REG4 as usize]);
+        assert_eq!(0b0100_0000, i2c.registers[CTRL_REG5 as usize]);
+        assert_eq!(i2c.log.last(), Some(&Transaction::Read(STATUS_REG, 1)));
+    }
+
+    #[test]
+    fn test_initialize_drain_is_bounded() {
+        let _lock = mock::lock();
+        // data stays available however often the output is read
+        let (_, _, i2c) = start(mock().with_register(STATUS_REG, 0b0000_1000));
+        assert_eq!(4, out_reads(&i2c));
+    }
+
+    #[test]
+    fn test_initialize_without_status() {
+        let _lock = mock::lock();
+        let (_, _, i2c) = start(mock().failing(STATUS_REG));
+        assert_eq!(0, out_reads(&i2c));
+        assert_eq!(
+            i2c.borrow_mut().log.last(),
+            Some(&Transaction::Read(STATUS_REG, 1))
+        );
+    }
+
+    #[test]
+    fn test_take_reading() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock());
+        i2c.borrow_mut().registers[0x28..0x2E]
+            .copy_from_slice(&[0xBA, 0x1A, 0xA3, 0xF2, 0x00, 0x00]);
+
+        let field = runtime.block_on(sensor.take_reading()).unwrap();
+        assert_eq!(1.0, field.x);
+        assert_eq!(-0.5, field.y);
+        assert_eq!(0.0, field.z);
+
+        i2c.borrow_mut().failing = Some(OUT_XYZ);
+        let result = runtime.block_on(sensor.take_reading());
+        assert_eq!(SensorError::I2c, result.unwrap_err());
+    }
+}
@@ -0,0 +1,64 @@
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lis3mdl::ready::Ready;
+use crate::driver::sensor::lis3mdl::register::DataRate;
+use crate::driver::sensor::lis3mdl::sensor::Sensor;
+use crate::driver::sensor::lis3mdl::MagneticFieldAcquisition;
+use crate::hal::gpio::InterruptPin;
+use crate::prelude::*;
+use cortex_m::interrupt::Nr;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+pub struct Lis3mdl<D, P, I>
+where
+    D: Device + EventHandler<MagneticFieldAcquisition> + 'static,
+    P: InputPin + InterruptPin + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    sensor: ActorContext<Sensor<D, I>>,
+    ready: InterruptContext<Ready<D, P, I>>,
+}
+
+impl<D, P, I> Lis3mdl<D, P, I>
+where
+    D: Device + EventHandler<MagneticFieldAcquisition>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    pub fn new<N: Nr>(ready: P, irq: N) -> Self {
+        Self::with_data_rate(ready, irq, DataRate::Hz10)
+    }
+
+    /// Create the package with a specific data rate.
+    pub fn with_data_rate<N: Nr>(ready: P, irq: N, data_rate: DataRate) -> Self {
+        Self {
+            sensor: ActorContext::new(Sensor::new().with_data_rate(data_rate))
+                .with_name("lis3mdl-sensor"),
+            ready: InterruptContext::new(Ready::new(ready), irq).with_name("lis3mdl-irq"),
+        }
+    }
+}
+
+impl<D, P, I> Package for Lis3mdl<D, P, I>
+where
+    D: Device + EventHandler<MagneticFieldAcquisition>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    type Primary = Sensor<D, I>;
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let sensor_addr = self.sensor.mount(config, supervisor);
+        self.ready.mount(sensor_addr, supervisor);
+        sensor_addr
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.sensor.address()
+    }
+}
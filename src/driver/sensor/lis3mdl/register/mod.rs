@@ -0,0 +1,114 @@
+use crate::{register, register_field};
+
+pub mod out;
+
+pub use crate::driver::sensor::register::ModifyError;
+
+/// Expected contents of the `WHO_AM_I` register.
+pub const IDENTITY: u8 = 0x3D;
+
+register_field! {
+    pub enum OperativeMode {
+        LowPower = 0b00,
+        MediumPerformance = 0b01,
+        HighPerformance = 0b10,
+        UltraHighPerformance = 0b11,
+        _ => LowPower,
+    }
+}
+
+register_field! {
+    pub enum DataRate {
+        Hz0p625 = 0b000,
+        Hz1p25 = 0b001,
+        Hz2p5 = 0b010,
+        Hz5 = 0b011,
+        Hz10 = 0b100,
+        Hz20 = 0b101,
+        Hz40 = 0b110,
+        Hz80 = 0b111,
+        _ => Hz0p625,
+    }
+}
+
+register_field! {
+    pub enum FullScale {
+        Gauss4 = 0b00,
+        Gauss8 = 0b01,
+        Gauss12 = 0b10,
+        Gauss16 = 0b11,
+        _ => Gauss4,
+    }
+}
+
+impl FullScale {
+    /// Sensitivity in LSB per gauss.
+    pub fn sensitivity(&self) -> f32 {
+        match self {
+            FullScale::Gauss4 => 6842.0,
+            FullScale::Gauss8 => 3421.0,
+            FullScale::Gauss12 => 2281.0,
+            FullScale::Gauss16 => 1711.0,
+        }
+    }
+}
+
+register_field! {
+    pub enum MeasurementMode {
+        Continuous = 0b00,
+        Single = 0b01,
+        PowerDown = 0b11,
+        _ => PowerDown,
+    }
+}
+
+register! {
+    pub WhoAmI: ro u8 @ 0x0F {
+        identity: u8 = [7:0],
+    }
+}
+
+register! {
+    pub CtrlReg1: rw u8 @ 0x20 {
+        temperature_enable: bool = [7],
+        xy_mode: OperativeMode = [6:5],
+        data_rate: DataRate = [4:2],
+        fast_odr: bool = [1],
+        self_test: bool = [0],
+    }
+}
+
+register! {
+    pub CtrlReg2: rw u8 @ 0x21 {
+        full_scale: FullScale = [6:5],
+        reboot: bool = [3],
+        soft_reset: bool = [2],
+    }
+}
+
+register! {
+    pub CtrlReg3: rw u8 @ 0x22 {
+        low_power: bool = [5],
+        mode: MeasurementMode = [1:0],
+    }
+}
+
+register! {
+    pub CtrlReg4: rw u8 @ 0x23 {
+        z_mode: OperativeMode = [3:2],
+    }
+}
+
+register! {
+    pub CtrlReg5: rw u8 @ 0x24 {
+        fast_read: bool = [7],
+        block_data_update: bool = [6],
+    }
+}
+
+register! {
+    pub Status: ro u8 @ 0x27 {
+        xyz_overrun: bool = [7],
+        xyz_available: bool = [3],
+    }
+}
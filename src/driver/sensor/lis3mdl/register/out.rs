@@ -0,0 +1,30 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::prelude::Address;
+use embedded_hal::blocking::i2c::WriteRead;
+
+// auto-increment variant of OUT_X_L through OUT_Z_H
+const OUT_XYZ: u8 = 0xA8;
+
+pub struct Out;
+
+impl Out {
+    /// Read the raw x, y and z outputs.
+    pub async fn read<I: WriteRead>(
+        address: I2cAddress,
+        i2c: Address<I2cPeripheral<I>>,
+    ) -> Result<[i16; 3], I::Error> {
+        let mut buf = [0; 6];
+        i2c.write_read(address, &[OUT_XYZ], &mut buf).await?;
+        Ok(axes(&buf))
+    }
+}
+
+/// Decode little-endian x, y and z outputs.
+pub(crate) fn axes(buf: &[u8; 6]) -> [i16; 3] {
+    [
+        i16::from_le_bytes([buf[0], buf[1]]),
+        i16::from_le_bytes([buf[2], buf[3]]),
+        i16::from_le_bytes([buf[4], buf[5]]),
+    ]
+}
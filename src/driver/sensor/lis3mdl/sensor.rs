@@ -0,0 +1,224 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lis3mdl::ready::DataReady;
+use crate::driver::sensor::lis3mdl::register::out::Out;
+use crate::driver::sensor::lis3mdl::register::{
+    CtrlReg1, CtrlReg2, CtrlReg3, CtrlReg4, CtrlReg5, DataRate, FullScale, MeasurementMode,
+    OperativeMode, Status, WhoAmI, IDENTITY,
+};
+use crate::driver::sensor::lis3mdl::{MagneticFieldAcquisition, SensorError};
+use crate::prelude::*;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+pub const ADDR: u8 = 0x1E;
+
+// Samples read while emptying the output at startup before giving up on the device.
+const DRAIN_ATTEMPTS: u8 = 4;
+
+pub struct Sensor<D, I>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    address: I2cAddress,
+    data_rate: DataRate,
+    full_scale: FullScale,
+    i2c: Option<Address<I2cPeripheral<I>>>,
+    bus: Option<Address<EventBus<D>>>,
+}
+
+impl<D, I> Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            address: I2cAddress::new(ADDR),
+            data_rate: DataRate::Hz10,
+            full_scale: FullScale::Gauss4,
+            i2c: None,
+            bus: None,
+        }
+    }
+
+    /// Use the provided data rate once initialized instead of the default 10Hz.
+    pub fn with_data_rate(mut self, data_rate: DataRate) -> Self {
+        self.data_rate = data_rate;
+        self
+    }
+
+    /// Use the provided full scale once initialized instead of the default ±4 gauss.
+    pub fn with_full_scale(mut self, full_scale: FullScale) -> Self {
+        self.full_scale = full_scale;
+        self
+    }
+}
+
+impl<D, I> Default for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    fn default() -> Self {
+        Sensor::new()
+    }
+}
+
+impl<D, I> Actor for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.bus.replace(config.0);
+        self.i2c.replace(config.1);
+    }
+
+    fn on_initialize(self) -> Completion<Self> {
+        Completion::defer(async move {
+            if let Some(i2c) = self.i2c {
+                match WhoAmI::read(self.address, i2c)
+                    .await
+                    .map(|reg| reg.get_identity())
+                {
+                    Ok(IDENTITY) => {}
+                    Ok(identity) => {
+                        log::warn!("[lis3mdl] unexpected identity {:x}", identity)
+                    }
+                    Err(_) => log::warn!("[lis3mdl] unable to read identity"),
+                }
+
+                let full_scale = self.full_scale;
+                CtrlReg2::modify(self.address, i2c, |reg| {
+                    reg.full_scale(full_scale);
+                })
+                .await
+                .ok();
+
+                let data_rate = self.data_rate;
+                CtrlReg1::modify(self.address, i2c, |reg| {
+                    reg.xy_mode(OperativeMode::UltraHighPerformance)
+                        .data_rate(data_rate);
+                })
+                .await
+                .ok();
+
+                CtrlReg4::modify(self.address, i2c, |reg| {
+                    reg.z_mode(OperativeMode::UltraHighPerformance);
+                })
+                .await
+                .ok();
+
+                CtrlReg5::modify(self.address, i2c, |reg| {
+                    reg.block_data_update(true);
+                })
+                .await
+                .ok();
+
+                CtrlReg3::modify(self.address, i2c, |reg| {
+                    reg.low_power(false).mode(MeasurementMode::Continuous);
+                })
+                .await
+                .ok();
+
+                // Ensure status is emptied, so the data ready line goes low again
+                let mut drained = 0;
+                loop {
+                    match Status::read(self.address, i2c).await {
+                        Ok(status) if !status.get_xyz_available() => break,
+                        Ok(_) if drained == DRAIN_ATTEMPTS => {
+                            log::warn!("[lis3mdl] data still available after draining");
+                            break;
+                        }
+                        Ok(_) => {
+                            Out::read(self.address, i2c).await.ok();
+                            drained += 1;
+                        }
+                        Err(_) => {
+                            log::warn!("[lis3mdl] unable to read status");
+                            break;
+                        }
+                    }
+                }
+            }
+            self
+        })
+    }
+}
+
+impl<D, I> NotifyHandler<DataReady> for Sensor<D, I>
+where
+    D: Device + EventHandler<MagneticFieldAcquisition>,
+    I: WriteRead + Read + Write,
+{
+    fn on_notify(self, message: DataReady) -> Completion<Self> {
+        Completion::defer(async move {
+            match self.acquire().await {
+                Ok(acquisition) => self.bus.unwrap().publish(acquisition),
+                Err(e) => log::warn!("[lis3mdl] acquisition failed: {:?}", e),
+            }
+            self
+        })
+    }
+}
+
+impl<D, I> Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    async fn acquire(&self) -> Result<MagneticFieldAcquisition, SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+        let out = Out::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        Ok(MagneticFieldAcquisition::from_raw(out, self.full_scale))
+    }
+}
+
+/// Request the most recent magnetic field output.
+#[derive(Debug)]
+pub struct TakeReading;
+
+impl<D, I> RequestHandler<TakeReading> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<MagneticFieldAcquisition, SensorError>;
+
+    fn on_request(self, message: TakeReading) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.acquire().await;
+            (self, result)
+        })
+    }
+}
+
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write,
+{
+    /// Read the most recent magnetic field output.
+    pub async fn take_reading(&self) -> Result<MagneticFieldAcquisition, SensorError> {
+        self.request(TakeReading).await
+    }
+}
+
+#[doc(hidden)]
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + EventHandler<MagneticFieldAcquisition> + 'static,
+    I: WriteRead + Read + Write,
+{
+    pub fn signal_data_ready(&self) {
+        self.notify(DataReady)
+    }
+}
@@ -0,0 +1,233 @@
+pub mod package;
+pub mod ready;
+pub mod register;
+pub mod sensor;
+
+pub use package::Lps22hb;
+pub use ready::Ready;
+pub use sensor::Sensor;
+
+use crate::domain::temperature::{Celsius, Temperature};
+use crate::driver::sensor::lps22hb::register::DataRate;
+use core::fmt::{Debug, Formatter};
+use heapless::{consts::*, Vec};
+
+/// Standard atmospheric pressure at sea level, in hPa.
+pub const SEA_LEVEL_PRESSURE: f32 = 1013.25;
+
+/// Size of the hardware FIFO, in samples.
+pub const FIFO_DEPTH: usize = 32;
+
+/// How conversions are triggered and delivered.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mode {
+    /// Conversions only happen on request through `take_reading()`.
+    OneShot,
+    /// Every conversion is published as a `PressureAcquisition`.
+    Continuous(DataRate),
+    /// Conversions are buffered in the FIFO, and published as a `PressureBatch` once
+    /// `watermark` (1 to 31) samples are stored.
+    Fifo { data_rate: DataRate, watermark: u8 },
+}
+
+#[derive(Copy, Clone)]
+pub struct PressureAcquisition {
+    /// Pressure in hPa.
+    pub pressure: f32,
+    pub temperature: Temperature<Celsius>,
+    /// Altitude in meters relative to the reference pressure.
+    pub altitude: f32,
+}
+
+impl PressureAcquisition {
+    /// Convert raw outputs, computing the altitude against `reference` (in hPa).
+    pub fn from_raw(pressure: i32, temperature: i16, reference: f32) -> Self {
+        let pressure = pressure as f32 / 4096.0;
+        Self {
+            pressure,
+            temperature: Temperature::new(temperature as f32 / 100.0),
+            altitude: altitude(pressure, reference),
+        }
+    }
+}
+
+impl Debug for PressureAcquisition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PressureAcquisition")
+            .field("pressure", &self.pressure)
+            .field("temperature", &self.temperature)
+            .field("altitude", &self.altitude)
+            .finish()
+    }
+}
+
+/// Samples drained from the FIFO, oldest first.
+#[derive(Debug, Clone)]
+pub struct PressureBatch {
+    pub samples: Vec<PressureAcquisition, U32>,
+    /// Whether samples were lost because the FIFO filled up before being drained.
+    pub overrun: bool,
+}
+
+/// Altitude in meters at `pressure`, relative to the altitude where the pressure is `reference`,
+/// using the international barometric formula.
+pub fn altitude(pressure: f32, reference: f32) -> f32 {
+    44330.0 * (1.0 - libm::powf(pressure / reference, 1.0 / 5.255))
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorError {
+    NotInitialized,
+    I2c,
+    Timeout,
+    /// The FIFO is enabled, samples are only delivered as batches.
+    FifoEnabled,
+    /// The FIFO watermark must be between 1 and 31.
+    InvalidWatermark,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::register::out::decode;
+    use super::register::{
+        CtrlReg1, CtrlReg2, CtrlReg3, DataRate, FifoCtrl, FifoMode, FifoStatus, Rpds, WhoAmI,
+    };
+    use super::sensor::ADDR;
+    use super::{altitude, PressureAcquisition, SEA_LEVEL_PRESSURE};
+    use crate::driver::sensor::mock::{AutoIncrement, MockI2c, Transaction};
+    use crate::driver::sensor::register::Register;
+    use embedded_hal::blocking::i2c::WriteRead;
+
+    const CTRL_REG1: u8 = 0x10;
+    const CTRL_REG2: u8 = 0x11;
+    const CTRL_REG3: u8 = 0x12;
+    const FIFO_CTRL: u8 = 0x14;
+    const FIFO_STATUS: u8 = 0x26;
+    const STATUS: u8 = 0x27;
+
+    /// One-shot conversions complete immediately.
+    fn mock() -> MockI2c {
+        MockI2c::new(ADDR, AutoIncrement::Always)
+            .with_register(0x0F, 0xB1)
+            .with_register(CTRL_REG2, 0x10)
+            .on_write(|registers, reg| {
+                if reg == CTRL_REG2 as usize && (registers[reg] & 0b1) != 0 {
+                    registers[reg] &= !0b1;
+                    registers[STATUS as usize] = 0b11;
+                }
+            })
+    }
+
+    #[test]
+    fn test_ctrl_regs() {
+        let mut i2c = mock();
+
+        let mut reg: CtrlReg1 = i2c.read_typed();
+        reg.data_rate(DataRate::Hz25).block_data_update(true);
+        i2c.write_typed(reg);
+        assert_eq!(0b0011_0010, i2c.registers[CTRL_REG1 as usize]);
+
+        let mut reg: CtrlReg2 = i2c.read_typed();
+        reg.fifo_enable(true);
+        i2c.write_typed(reg);
+        // auto-increment remains enabled
+        assert_eq!(0b0101_0000, i2c.registers[CTRL_REG2 as usize]);
+
+        let mut reg: CtrlReg3 = i2c.read_typed();
+        reg.fifo_watermark(true).data_ready(false);
+        i2c.write_typed(reg);
+        assert_eq!(0b0001_0000, i2c.registers[CTRL_REG3 as usize]);
+    }
+
+    #[test]
+    fn test_fifo() {
+        let mut i2c = mock().with_register(FIFO_STATUS, 0b1001_0000);
+
+        let mut reg: FifoCtrl = i2c.read_typed();
+        reg.mode(FifoMode::Stream).watermark(16);
+        i2c.write_typed(reg);
+        assert_eq!(0b0101_0000, i2c.registers[FIFO_CTRL as usize]);
+
+        reg.mode(FifoMode::DynamicStream).watermark(0xFF);
+        assert_eq!(0b1101_1111, reg.into_value());
+
+        let status: FifoStatus = i2c.read_typed();
+        assert!(status.get_watermark_reached());
+        assert!(!status.get_overrun());
+        assert_eq!(16, status.get_stored());
+    }
+
+    #[test]
+    fn test_one_shot() {
+        let mut i2c = mock();
+        // 1013.25 hPa, 25.50°C
+        i2c.registers[0x28..0x2D].copy_from_slice(&[0x00, 0x54, 0x3F, 0xF6, 0x09]);
+
+        let mut reg: CtrlReg2 = i2c.read_typed();
+        reg.one_shot(true);
+        i2c.write_typed(reg);
+        assert_eq!(0b0001_0000, i2c.registers[CTRL_REG2 as usize]);
+        assert_eq!(0b11, i2c.registers[STATUS as usize]);
+
+        let mut buf = [0; 5];
+        i2c.write_read(ADDR, &[0x28], &mut buf).unwrap();
+        let (pressure, temperature) = decode(&buf);
+        assert_eq!(4_150_272, pressure);
+        assert_eq!(2550, temperature);
+
+        let acquisition = PressureAcquisition::from_raw(pressure, temperature, SEA_LEVEL_PRESSURE);
+        assert_eq!(1013.25, acquisition.pressure);
+        assert_eq!(25.5, acquisition.temperature.value());
+        assert_eq!(0.0, acquisition.altitude);
+
+        assert_eq!(
+            i2c.log,
+            [
+                Transaction::Read(CTRL_REG2, 1),
+                Transaction::Write(CTRL_REG2, [0b0001_0001].to_vec()),
+                Transaction::Read(0x28, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_identity_and_offset() {
+        let mut i2c = mock();
+        let who_am_i: WhoAmI = i2c.read_typed();
+        assert_eq!(super::register::IDENTITY, who_am_i.get_identity());
+
+        let mut reg: Rpds = i2c.read_typed();
+        reg.offset(0x1234);
+        i2c.write_typed(reg);
+        assert_eq!([0x34, 0x12], i2c.registers[0x18..0x1A]);
+        let reg: Rpds = i2c.read_typed();
+        assert_eq!(0x1234, reg.get_offset());
+
+        assert_eq!(
+            i2c.log,
+            [
+                Transaction::Read(0x0F, 1),
+                Transaction::Read(0x18, 2),
+                Transaction::Write(0x18, [0x34, 0x12].to_vec()),
+                Transaction::Read(0x18, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negative_output() {
+        let (pressure, temperature) = decode(&[0xFF, 0xFF, 0xFF, 0x9C, 0xFF]);
+        assert_eq!(-1, pressure);
+        assert_eq!(-100, temperature);
+    }
+
+    #[test]
+    fn test_altitude() {
+        assert_eq!(0.0, altitude(SEA_LEVEL_PRESSURE, SEA_LEVEL_PRESSURE));
+        // roughly 111m at 1000 hPa and 988m at 900 hPa in the standard atmosphere
+        assert!((altitude(1000.0, SEA_LEVEL_PRESSURE) - 111.0).abs() < 1.0);
+        assert!((altitude(900.0, SEA_LEVEL_PRESSURE) - 988.0).abs() < 1.0);
+        // relative to a reference pressure of a higher position, altitude is negative
+        assert!(altitude(1000.0, 990.0) < 0.0);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lps22hb::ready::Ready;
+use crate::driver::sensor::lps22hb::register::DataRate;
+use crate::driver::sensor::lps22hb::sensor::Sensor;
+use crate::driver::sensor::lps22hb::{Mode, PressureAcquisition, PressureBatch};
+use crate::hal::gpio::InterruptPin;
+use crate::prelude::*;
+use cortex_m::interrupt::Nr;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+pub struct Lps22hb<D, P, I>
+where
+    D: Device + EventHandler<PressureAcquisition> + EventHandler<PressureBatch> + 'static,
+    P: InputPin + InterruptPin + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    sensor: ActorContext<Sensor<D, I>>,
+    ready: InterruptContext<Ready<D, P, I>>,
+}
+
+impl<D, P, I> Lps22hb<D, P, I>
+where
+    D: Device + EventHandler<PressureAcquisition> + EventHandler<PressureBatch>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    pub fn new<N: Nr>(ready: P, irq: N) -> Self {
+        Self::with_mode(ready, irq, Mode::Continuous(DataRate::Hz1))
+    }
+
+    /// Create the package with a specific acquisition mode.
+    pub fn with_mode<N: Nr>(ready: P, irq: N, mode: Mode) -> Self {
+        Self {
+            sensor: ActorContext::new(Sensor::new().with_mode(mode)).with_name("lps22hb-sensor"),
+            ready: InterruptContext::new(Ready::new(ready), irq).with_name("lps22hb-irq"),
+        }
+    }
+}
+
+impl<D, P, I> Package for Lps22hb<D, P, I>
+where
+    D: Device + EventHandler<PressureAcquisition> + EventHandler<PressureBatch>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    type Primary = Sensor<D, I>;
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let sensor_addr = self.sensor.mount(config, supervisor);
+        self.ready.mount(sensor_addr, supervisor);
+        sensor_addr
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.sensor.address()
+    }
+}
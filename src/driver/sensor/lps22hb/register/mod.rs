@@ -0,0 +1,112 @@
+use crate::{register, register_field};
+
+pub mod out;
+
+/// Fixed value of the `WHO_AM_I` register.
+pub const IDENTITY: u8 = 0xB1;
+
+register_field! {
+    pub enum DataRate {
+        /// Power-down, conversions only happen on a one-shot request.
+        PowerDown = 0b000,
+        Hz1 = 0b001,
+        Hz10 = 0b010,
+        Hz25 = 0b011,
+        Hz50 = 0b100,
+        Hz75 = 0b101,
+        _ => PowerDown,
+    }
+}
+
+register_field! {
+    pub enum FifoMode {
+        Bypass = 0b000,
+        Fifo = 0b001,
+        Stream = 0b010,
+        StreamToFifo = 0b011,
+        BypassToStream = 0b100,
+        DynamicStream = 0b110,
+        BypassToFifo = 0b111,
+        _ => Bypass,
+    }
+}
+
+register! {
+    /// Pressure offset for one-point calibration, in 1/256 hPa.
+    pub Rpds: rw u16 @ 0x18 {
+        offset: u16 = [15:0],
+    }
+}
+
+register! {
+    pub WhoAmI: ro u8 @ 0x0F {
+        identity: u8 = [7:0],
+    }
+}
+
+register! {
+    pub CtrlReg1: rw u8 @ 0x10 {
+        data_rate: DataRate = [6:4],
+        low_pass_filter: bool = [3],
+        /// Use a bandwidth of ODR/20 instead of ODR/9 for the low-pass filter.
+        low_pass_narrow: bool = [2],
+        block_data_update: bool = [1],
+    }
+}
+
+register! {
+    pub CtrlReg2: rw u8 @ 0x11 {
+        boot: bool = [7],
+        fifo_enable: bool = [6],
+        stop_on_watermark: bool = [5],
+        auto_increment: bool = [4],
+        i2c_disable: bool = [3],
+        software_reset: bool = [2],
+        /// Trigger a single conversion, the bit self-clears once it completes.
+        one_shot: bool = [0],
+    }
+}
+
+register! {
+    /// Signals routed to the INT_DRDY pin.
+    pub CtrlReg3: rw u8 @ 0x12 {
+        active_low: bool = [7],
+        open_drain: bool = [6],
+        fifo_full: bool = [5],
+        fifo_watermark: bool = [4],
+        fifo_overrun: bool = [3],
+        data_ready: bool = [2],
+    }
+}
+
+register! {
+    pub FifoCtrl: rw u8 @ 0x14 {
+        mode: FifoMode = [7:5],
+        /// FIFO level, up to 31 samples, at which the watermark flag is raised.
+        watermark: u8 = [4:0],
+    }
+}
+
+register! {
+    pub FifoStatus: ro u8 @ 0x26 {
+        watermark_reached: bool = [7],
+        overrun: bool = [6],
+        /// Number of unread samples in the FIFO.
+        stored: u8 = [5:0],
+    }
+}
+
+register! {
+    pub Status: ro u8 @ 0x27 {
+        temperature_overrun: bool = [5],
+        pressure_overrun: bool = [4],
+        temperature_available: bool = [1],
+        pressure_available: bool = [0],
+    }
+}
+
+impl Status {
+    pub fn any_available(&self) -> bool {
+        self.temperature_available || self.pressure_available
+    }
+}
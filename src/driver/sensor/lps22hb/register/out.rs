@@ -0,0 +1,31 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::prelude::Address;
+use embedded_hal::blocking::i2c::WriteRead;
+
+// PRESS_OUT_XL through TEMP_OUT_H, read in one go relying on IF_ADD_INC.
+// With the FIFO enabled each read pops the oldest sample.
+const PRESS_OUT_XL: u8 = 0x28;
+
+pub struct Out;
+
+impl Out {
+    /// Read the raw pressure and temperature outputs.
+    pub async fn read<I: WriteRead>(
+        address: I2cAddress,
+        i2c: Address<I2cPeripheral<I>>,
+    ) -> Result<(i32, i16), I::Error> {
+        let mut buf = [0; 5];
+        i2c.write_read(address, &[PRESS_OUT_XL], &mut buf).await?;
+        Ok(decode(&buf))
+    }
+}
+
+/// Decode the 24-bit two's complement pressure and the 16-bit temperature.
+pub(crate) fn decode(buf: &[u8; 5]) -> (i32, i16) {
+    let sign = if buf[2] & 0x80 != 0 { 0xFF } else { 0x00 };
+    (
+        i32::from_le_bytes([buf[0], buf[1], buf[2], sign]),
+        i16::from_le_bytes([buf[3], buf[4]]),
+    )
+}
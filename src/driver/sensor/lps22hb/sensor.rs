@@ -0,0 +1,372 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lps22hb::ready::DataReady;
+use crate::driver::sensor::lps22hb::register::out::Out;
+use crate::driver::sensor::lps22hb::register::{
+    CtrlReg1, CtrlReg2, CtrlReg3, DataRate, FifoCtrl, FifoMode, FifoStatus, Status, WhoAmI,
+    IDENTITY,
+};
+use crate::driver::sensor::lps22hb::{
+    Mode, PressureAcquisition, PressureBatch, SensorError, FIFO_DEPTH, SEA_LEVEL_PRESSURE,
+};
+use crate::prelude::*;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use heapless::Vec;
+
+pub const ADDR: u8 = 0x5D;
+
+// Upper bound on status polls while waiting for a one-shot conversion.
+const ONE_SHOT_ATTEMPTS: usize = 1000;
+
+pub struct Sensor<D, I>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    address: I2cAddress,
+    mode: Mode,
+    reference_pressure: f32,
+    i2c: Option<Address<I2cPeripheral<I>>>,
+    bus: Option<Address<EventBus<D>>>,
+}
+
+impl<D, I> Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            address: I2cAddress::new(ADDR),
+            mode: Mode::Continuous(DataRate::Hz1),
+            reference_pressure: SEA_LEVEL_PRESSURE,
+            i2c: None,
+            bus: None,
+        }
+    }
+
+    /// Use the provided mode once initialized instead of continuous conversion at 1Hz.
+    pub fn with_mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    async fn configure(&self, mode: Mode) -> Result<(), SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+
+        let (data_rate, fifo) = match mode {
+            Mode::OneShot => (DataRate::PowerDown, None),
+            Mode::Continuous(data_rate) => (data_rate, None),
+            Mode::Fifo {
+                data_rate,
+                watermark,
+            } => {
+                if watermark == 0 || watermark as usize >= FIFO_DEPTH {
+                    return Err(SensorError::InvalidWatermark);
+                }
+                (data_rate, Some(watermark))
+            }
+        };
+
+        // Stop conversions and reset the FIFO before switching modes
+        CtrlReg1::modify(self.address, i2c, |reg| {
+            reg.data_rate(DataRate::PowerDown);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        FifoCtrl::modify(self.address, i2c, |reg| {
+            reg.mode(FifoMode::Bypass);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        CtrlReg2::modify(self.address, i2c, |reg| {
+            reg.fifo_enable(fifo.is_some()).stop_on_watermark(false);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        if let Some(watermark) = fifo {
+            FifoCtrl::modify(self.address, i2c, |reg| {
+                reg.mode(FifoMode::Stream).watermark(watermark);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        }
+
+        CtrlReg3::modify(self.address, i2c, |reg| {
+            reg.data_ready(matches!(mode, Mode::Continuous(_)))
+                .fifo_watermark(fifo.is_some());
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        CtrlReg1::modify(self.address, i2c, |reg| {
+            reg.data_rate(data_rate).block_data_update(true);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        // Ensure a pending data-ready is cleared
+        Out::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        Ok(())
+    }
+
+    async fn acquire(&self) -> Result<PressureAcquisition, SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+
+        match self.mode {
+            Mode::Fifo { .. } => return Err(SensorError::FifoEnabled),
+            Mode::OneShot => {
+                CtrlReg2::modify(self.address, i2c, |reg| {
+                    reg.one_shot(true);
+                })
+                .await
+                .map_err(|_| SensorError::I2c)?;
+
+                let mut attempts = 0;
+                loop {
+                    let status = Status::read(self.address, i2c)
+                        .await
+                        .map_err(|_| SensorError::I2c)?;
+                    if status.get_pressure_available() && status.get_temperature_available() {
+                        break;
+                    }
+                    attempts += 1;
+                    if attempts >= ONE_SHOT_ATTEMPTS {
+                        return Err(SensorError::Timeout);
+                    }
+                }
+            }
+            Mode::Continuous(_) => {}
+        }
+
+        let (pressure, temperature) = Out::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        Ok(PressureAcquisition::from_raw(
+            pressure,
+            temperature,
+            self.reference_pressure,
+        ))
+    }
+
+    async fn drain(&self) -> Result<PressureBatch, SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+        let status = FifoStatus::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+        let mut batch = PressureBatch {
+            samples: Vec::new(),
+            overrun: status.get_overrun(),
+        };
+        for _ in 0..(status.get_stored() as usize).min(FIFO_DEPTH) {
+            let (pressure, temperature) = Out::read(self.address, i2c)
+                .await
+                .map_err(|_| SensorError::I2c)?;
+            batch
+                .samples
+                .push(PressureAcquisition::from_raw(
+                    pressure,
+                    temperature,
+                    self.reference_pressure,
+                ))
+                .ok();
+        }
+        Ok(batch)
+    }
+}
+
+impl<D, I> Default for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    fn default() -> Self {
+        Sensor::new()
+    }
+}
+
+impl<D, I> Actor for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.bus.replace(config.0);
+        self.i2c.replace(config.1);
+    }
+
+    fn on_initialize(self) -> Completion<Self> {
+        Completion::defer(async move {
+            if let Some(i2c) = self.i2c {
+                match WhoAmI::read(self.address, i2c)
+                    .await
+                    .map(|reg| reg.get_identity())
+                {
+                    Ok(IDENTITY) => {}
+                    Ok(identity) => {
+                        log::warn!("[lps22hb] unexpected identity {:x}", identity)
+                    }
+                    Err(_) => log::warn!("[lps22hb] unable to read identity"),
+                }
+
+                if let Err(e) = self.configure(self.mode).await {
+                    log::warn!("[lps22hb] configuration failed: {:?}", e);
+                }
+            }
+            self
+        })
+    }
+}
+
+impl<D, I> NotifyHandler<DataReady> for Sensor<D, I>
+where
+    D: Device + EventHandler<PressureAcquisition> + EventHandler<PressureBatch>,
+    I: WriteRead + Read + Write,
+{
+    fn on_notify(self, message: DataReady) -> Completion<Self> {
+        Completion::defer(async move {
+            if let Some(bus) = self.bus {
+                match self.mode {
+                    Mode::Continuous(_) => match self.acquire().await {
+                        Ok(acquisition) => bus.publish(acquisition),
+                        Err(e) => log::warn!("[lps22hb] acquisition failed: {:?}", e),
+                    },
+                    Mode::Fifo { .. } => match self.drain().await {
+                        Ok(batch) => bus.publish(batch),
+                        Err(e) => log::warn!("[lps22hb] FIFO read failed: {:?}", e),
+                    },
+                    Mode::OneShot => {}
+                }
+            }
+            self
+        })
+    }
+}
+
+/// Request a single acquisition. In one-shot mode this triggers a conversion,
+/// otherwise the most recent output of the continuous conversion is returned.
+#[derive(Debug)]
+pub struct TakeReading;
+
+impl<D, I> RequestHandler<TakeReading> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<PressureAcquisition, SensorError>;
+
+    fn on_request(self, message: TakeReading) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.acquire().await;
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetMode(pub Mode);
+
+impl<D, I> RequestHandler<SetMode> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(mut self, message: SetMode) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.configure(message.0).await;
+            if result.is_ok() {
+                self.mode = message.0;
+            }
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetReferencePressure(pub f32);
+
+impl<D, I> RequestHandler<SetReferencePressure> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = ();
+
+    fn on_request(mut self, message: SetReferencePressure) -> Response<Self, Self::Response> {
+        self.reference_pressure = message.0;
+        Response::immediate(self, ())
+    }
+}
+
+#[derive(Debug)]
+pub struct ZeroAltitude;
+
+impl<D, I> RequestHandler<ZeroAltitude> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<f32, SensorError>;
+
+    fn on_request(mut self, message: ZeroAltitude) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.acquire().await.map(|acquisition| acquisition.pressure);
+            if let Ok(pressure) = result {
+                self.reference_pressure = pressure;
+            }
+            (self, result)
+        })
+    }
+}
+
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write,
+{
+    /// Take a single pressure and temperature reading.
+    pub async fn take_reading(&self) -> Result<PressureAcquisition, SensorError> {
+        self.request(TakeReading).await
+    }
+
+    /// Switch between one-shot, continuous and FIFO acquisition.
+    pub async fn set_mode(&self, mode: Mode) -> Result<(), SensorError> {
+        self.request(SetMode(mode)).await
+    }
+
+    /// Set the pressure, in hPa, at which the reported altitude is zero. Defaults to
+    /// `SEA_LEVEL_PRESSURE`; use the local sea-level pressure for absolute altitude.
+    pub async fn set_reference_pressure(&self, pressure: f32) {
+        self.request(SetReferencePressure(pressure)).await
+    }
+
+    /// Use the current pressure as reference, so that altitude is reported relative to
+    /// the current position. Returns the new reference pressure.
+    pub async fn zero_altitude(&self) -> Result<f32, SensorError> {
+        self.request(ZeroAltitude).await
+    }
+}
+
+#[doc(hidden)]
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + EventHandler<PressureAcquisition> + EventHandler<PressureBatch> + 'static,
+    I: WriteRead + Read + Write,
+{
+    pub fn signal_data_ready(&self) {
+        self.notify(DataReady)
+    }
+}
@@ -0,0 +1,332 @@
+pub mod package;
+pub mod ready;
+pub mod register;
+pub mod sensor;
+
+pub use package::Lsm6dsl;
+pub use ready::Ready;
+pub use sensor::Sensor;
+
+use crate::driver::sensor::lsm6dsl::register::{AccelerationScale, AngularRateScale};
+use core::fmt::{Debug, Formatter};
+
+/// Acceleration, in g, and angular rate, in degrees per second, along the x, y and z axes.
+#[derive(Copy, Clone)]
+pub struct MotionAcquisition {
+    pub acceleration: [f32; 3],
+    pub angular_rate: [f32; 3],
+}
+
+impl MotionAcquisition {
+    /// Convert raw axis outputs using the sensitivity of the configured scales.
+    pub fn from_raw(
+        acceleration: [i16; 3],
+        acceleration_scale: AccelerationScale,
+        angular_rate: [i16; 3],
+        angular_rate_scale: AngularRateScale,
+    ) -> Self {
+        let xl = acceleration_scale.sensitivity() / 1000.0;
+        let g = angular_rate_scale.sensitivity() / 1000.0;
+        Self {
+            acceleration: [
+                acceleration[0] as f32 * xl,
+                acceleration[1] as f32 * xl,
+                acceleration[2] as f32 * xl,
+            ],
+            angular_rate: [
+                angular_rate[0] as f32 * g,
+                angular_rate[1] as f32 * g,
+                angular_rate[2] as f32 * g,
+            ],
+        }
+    }
+}
+
+impl Debug for MotionAcquisition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MotionAcquisition")
+            .field("acceleration", &self.acceleration)
+            .field("angular_rate", &self.angular_rate)
+            .finish()
+    }
+}
+
+/// Events recognized by the embedded functions of the sensor.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MotionEvent {
+    SingleTap,
+    DoubleTap,
+    FreeFall,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SensorError {
+    NotInitialized,
+    I2c,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::register::out::split;
+    use super::register::{
+        AccelerationScale, AngularRateScale, Ctrl1Xl, Ctrl2G, Ctrl3C, DataRate, FreeFall, IntDur2,
+        Md1Cfg, TapCfg, TapSrc, TapThs6d, WakeUpSrc, WakeUpThs,
+    };
+    use super::sensor::{Sensor, ADDR};
+    use super::{MotionAcquisition, MotionEvent, SensorError};
+    use crate::driver::i2c::I2c;
+    use crate::driver::sensor::mock::{AutoIncrement, MockI2c, SharedI2c, Transaction};
+    use crate::driver::sensor::register::Register;
+    use crate::prelude::*;
+    use crate::system::mock::{self, MockRuntime};
+    use embedded_hal::blocking::i2c::WriteRead;
+
+    const INT1_CTRL: u8 = 0x0D;
+    const CTRL1_XL: u8 = 0x10;
+    const CTRL2_G: u8 = 0x11;
+    const CTRL3_C: u8 = 0x12;
+    const TAP_SRC: u8 = 0x1C;
+    const STATUS_REG: u8 = 0x1E;
+    const OUTX_L_G: u8 = 0x22;
+    const WAKE_UP_SRC: u8 = 0x1B;
+    const TAP_CFG: u8 = 0x58;
+    const TAP_THS_6D: u8 = 0x59;
+    const INT_DUR2: u8 = 0x5A;
+    const WAKE_UP_THS: u8 = 0x5B;
+    const FREE_FALL: u8 = 0x5D;
+    const MD1_CFG: u8 = 0x5E;
+
+    fn mock() -> MockI2c {
+        // power-on defaults
+        MockI2c::new(ADDR, AutoIncrement::Always)
+            .with_register(0x0F, 0x6A)
+            .with_register(CTRL3_C, 0x04)
+    }
+
+    #[test]
+    fn test_ctrl() {
+        let mut i2c = mock();
+
+        let mut reg: Ctrl3C = i2c.read_typed();
+        reg.block_data_update(true);
+        i2c.write_typed(reg);
+        assert_eq!(0b0100_0100, i2c.registers[CTRL3_C as usize]);
+
+        let mut reg: Ctrl1Xl = i2c.read_typed();
+        reg.data_rate(DataRate::Hz104).scale(AccelerationScale::G16);
+        i2c.write_typed(reg);
+        assert_eq!(0b0100_0100, i2c.registers[CTRL1_XL as usize]);
+
+        let mut reg: Ctrl2G = i2c.read_typed();
+        reg.data_rate(DataRate::Hz416)
+            .scale(AngularRateScale::Dps2000);
+        i2c.write_typed(reg);
+        assert_eq!(0b0110_1100, i2c.registers[CTRL2_G as usize]);
+
+        reg.scale(AngularRateScale::Dps125);
+        assert_eq!(0b0110_0010, reg.into_value());
+        let reg = Ctrl2G::from_value(0b0110_0010);
+        assert_eq!(AngularRateScale::Dps125, reg.get_scale());
+        assert_eq!(0b0110_0010, reg.into_value());
+        // FS_125 takes precedence over FS_G
+        let reg = Ctrl2G::from_value(0b0110_1110);
+        assert_eq!(AngularRateScale::Dps125, reg.get_scale());
+    }
+
+    #[test]
+    fn test_tap_configuration() {
+        let mut i2c = mock();
+
+        let mut reg: TapCfg = i2c.read_typed();
+        reg.interrupts_enable(true)
+            .tap_x(true)
+            .tap_y(true)
+            .tap_z(true)
+            .latched(false);
+        i2c.write_typed(reg);
+
+        let mut reg: TapThs6d = i2c.read_typed();
+        reg.d4d_enable(true).tap_threshold(0x0C);
+        i2c.write_typed(reg);
+
+        let mut reg: IntDur2 = i2c.read_typed();
+        reg.duration(7).quiet(3).shock(3);
+        i2c.write_typed(reg);
+
+        let mut reg: WakeUpThs = i2c.read_typed();
+        reg.double_tap(true);
+        i2c.write_typed(reg);
+
+        let mut reg: Md1Cfg = i2c.read_typed();
+        reg.single_tap(true).double_tap(true);
+        i2c.write_typed(reg);
+
+        // values from ST's single and double tap example configuration
+        assert_eq!(0x8E, i2c.registers[TAP_CFG as usize]);
+        assert_eq!(0x8C, i2c.registers[TAP_THS_6D as usize]);
+        assert_eq!(0x7F, i2c.registers[INT_DUR2 as usize]);
+        assert_eq!(0x80, i2c.registers[WAKE_UP_THS as usize]);
+        assert_eq!(0x48, i2c.registers[MD1_CFG as usize]);
+    }
+
+    #[test]
+    fn test_free_fall_configuration() {
+        let mut i2c = mock().with_register(MD1_CFG, 0x48);
+
+        let mut reg: FreeFall = i2c.read_typed();
+        reg.duration(0x06).threshold(0x03);
+        i2c.write_typed(reg);
+        assert_eq!(0x33, i2c.registers[FREE_FALL as usize]);
+
+        let mut reg: Md1Cfg = i2c.read_typed();
+        reg.free_fall(true);
+        i2c.write_typed(reg);
+        assert_eq!(0x58, i2c.registers[MD1_CFG as usize]);
+    }
+
+    #[test]
+    fn test_event_sources() {
+        let mut i2c = mock()
+            .with_register(TAP_SRC, 0b0101_0000)
+            .with_register(WAKE_UP_SRC, 0b0010_0000);
+
+        let tap: TapSrc = i2c.read_typed();
+        assert!(tap.get_tap());
+        assert!(tap.get_double_tap());
+        assert!(!tap.get_single_tap());
+
+        let wake_up: WakeUpSrc = i2c.read_typed();
+        assert!(wake_up.get_free_fall());
+        assert!(!wake_up.get_wake_up());
+    }
+
+    #[test]
+    fn test_read_out() {
+        let mut i2c = mock();
+        // gyroscope 8.75dps, -17.5dps, 0; accelerometer 1g (at 16g scale), 0, -0.0488g
+        i2c.registers[0x22..0x2E].copy_from_slice(&[
+            0xE8, 0x03, 0x30, 0xF8, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x9C, 0xFF,
+        ]);
+
+        let mut buf = [0; 12];
+        i2c.write_read(ADDR, &[0x22], &mut buf).unwrap();
+        assert_eq!(i2c.log, [Transaction::Read(0x22, 12)]);
+
+        let (angular_rate, acceleration) = split(&buf);
+        assert_eq!([1000, -2000, 0], angular_rate);
+        assert_eq!([2048, 0, -100], acceleration);
+
+        let motion = MotionAcquisition::from_raw(
+            acceleration,
+            AccelerationScale::G16,
+            angular_rate,
+            AngularRateScale::Dps250,
+        );
+        assert!((motion.acceleration[0] - 0.999424).abs() < 1e-5);
+        assert_eq!(0.0, motion.acceleration[1]);
+        assert!((motion.acceleration[2] + 0.0488).abs() < 1e-5);
+        assert!((motion.angular_rate[0] - 8.75).abs() < 1e-5);
+        assert!((motion.angular_rate[1] + 17.5).abs() < 1e-5);
+    }
+
+    struct TestDevice;
+
+    impl Device for TestDevice {
+        fn mount(&'static self, config: DeviceConfiguration<Self>, supervisor: &mut Supervisor) {}
+    }
+
+    impl EventHandler<MotionAcquisition> for TestDevice {}
+    impl EventHandler<MotionEvent> for TestDevice {}
+
+    type TestSensor = Sensor<TestDevice, SharedI2c>;
+
+    fn start(i2c: MockI2c) -> (MockRuntime, Address<TestSensor>, SharedI2c) {
+        let i2c = SharedI2c::new(i2c);
+        let mut runtime = MockRuntime::new();
+        let bus = runtime.event_bus(TestDevice);
+        let peripheral = runtime.mount_package(I2c::new(i2c.clone()), ());
+        let sensor = runtime.mount(ActorContext::new(Sensor::new()), (bus, peripheral));
+        runtime.start();
+        (runtime, sensor, i2c)
+    }
+
+    fn out_reads(i2c: &SharedI2c) -> usize {
+        i2c.borrow_mut()
+            .log
+            .iter()
+            .filter(|t| **t == Transaction::Read(OUTX_L_G, 12))
+            .count()
+    }
+
+    #[test]
+    fn test_initialize() {
+        let _lock = mock::lock();
+        let (_, _, i2c) = start(mock());
+        let i2c = i2c.borrow_mut();
+        // 104Hz at ±2g and ±250dps, block data update, data ready on INT1
+        assert_eq!(0b0100_0000, i2c.registers[CTRL1_XL as usize]);
+        assert_eq!(0b0100_0000, i2c.registers[CTRL2_G as usize]);
+        assert_eq!(0b0100_0100, i2c.registers[CTRL3_C as usize]);
+        assert_eq!(0b0000_0001, i2c.registers[INT1_CTRL as usize]);
+        assert_eq!(i2c.log.last(), Some(&Transaction::Read(STATUS_REG, 1)));
+    }
+
+    #[test]
+    fn test_initialize_drain_is_bounded() {
+        let _lock = mock::lock();
+        // data stays available however often the outputs are read
+        let (_, _, i2c) = start(mock().with_register(STATUS_REG, 0b0000_0011));
+        assert_eq!(4, out_reads(&i2c));
+    }
+
+    #[test]
+    fn test_initialize_without_status() {
+        let _lock = mock::lock();
+        let (_, _, i2c) = start(mock().failing(STATUS_REG));
+        assert_eq!(0, out_reads(&i2c));
+        assert_eq!(
+            i2c.borrow_mut().log.last(),
+            Some(&Transaction::Read(STATUS_REG, 1))
+        );
+    }
+
+    #[test]
+    fn test_take_reading() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock());
+        // gyroscope 8.75dps, accelerometer 0.125g
+        i2c.borrow_mut().registers[0x22..0x2E].copy_from_slice(&[
+            0xE8, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,
+        ]);
+
+        let motion = runtime.block_on(sensor.take_reading()).unwrap();
+        assert!((motion.angular_rate[0] - 8.75).abs() < 1e-5);
+        assert!((motion.acceleration[0] - 0.124928).abs() < 1e-5);
+
+        i2c.borrow_mut().failing = Some(OUTX_L_G);
+        let result = runtime.block_on(sensor.take_reading());
+        assert_eq!(SensorError::I2c, result.unwrap_err());
+    }
+
+    #[test]
+    fn test_set_tap_detection() {
+        let _lock = mock::lock();
+        let (mut runtime, sensor, i2c) = start(mock());
+
+        runtime.block_on(sensor.set_tap_detection(true)).unwrap();
+        assert_eq!(0x0C, i2c.borrow_mut().registers[TAP_THS_6D as usize]);
+        assert_eq!(
+            0b1000_0000,
+            i2c.borrow_mut().registers[WAKE_UP_THS as usize]
+        );
+
+        i2c.borrow_mut().failing = Some(TAP_CFG);
+        let result = runtime.block_on(sensor.set_tap_detection(false));
+        assert_eq!(SensorError::I2c, result.unwrap_err());
+        // left enabled, as it was never turned off
+        assert_eq!(
+            0b1000_0000,
+            i2c.borrow_mut().registers[WAKE_UP_THS as usize]
+        );
+    }
+}
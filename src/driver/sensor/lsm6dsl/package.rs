@@ -0,0 +1,65 @@
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lsm6dsl::ready::Ready;
+use crate::driver::sensor::lsm6dsl::register::DataRate;
+use crate::driver::sensor::lsm6dsl::sensor::Sensor;
+use crate::driver::sensor::lsm6dsl::{MotionAcquisition, MotionEvent};
+use crate::hal::gpio::InterruptPin;
+use crate::prelude::*;
+use cortex_m::interrupt::Nr;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::v2::InputPin;
+
+/// LSM6DSL accelerometer and gyroscope, with data-ready and motion events routed to INT1.
+pub struct Lsm6dsl<D, P, I>
+where
+    D: Device + EventHandler<MotionAcquisition> + EventHandler<MotionEvent> + 'static,
+    P: InputPin + InterruptPin + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    sensor: ActorContext<Sensor<D, I>>,
+    ready: InterruptContext<Ready<D, P, I>>,
+}
+
+impl<D, P, I> Lsm6dsl<D, P, I>
+where
+    D: Device + EventHandler<MotionAcquisition> + EventHandler<MotionEvent>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    pub fn new<N: Nr>(int1: P, irq: N) -> Self {
+        Self::with_data_rate(int1, irq, DataRate::Hz104)
+    }
+
+    /// Create the package with a specific data rate for both accelerometer and gyroscope.
+    pub fn with_data_rate<N: Nr>(int1: P, irq: N, data_rate: DataRate) -> Self {
+        Self {
+            sensor: ActorContext::new(Sensor::new().with_data_rate(data_rate))
+                .with_name("lsm6dsl-sensor"),
+            ready: InterruptContext::new(Ready::new(int1), irq).with_name("lsm6dsl-irq"),
+        }
+    }
+}
+
+impl<D, P, I> Package for Lsm6dsl<D, P, I>
+where
+    D: Device + EventHandler<MotionAcquisition> + EventHandler<MotionEvent>,
+    P: InputPin + InterruptPin,
+    I: WriteRead + Read + Write,
+{
+    type Primary = Sensor<D, I>;
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let sensor_addr = self.sensor.mount(config, supervisor);
+        self.ready.mount(sensor_addr, supervisor);
+        sensor_addr
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.sensor.address()
+    }
+}
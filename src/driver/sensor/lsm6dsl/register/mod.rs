@@ -0,0 +1,214 @@
+use crate::{register, register_field};
+
+pub mod out;
+
+pub use crate::driver::sensor::register::ModifyError;
+
+/// Fixed value of the `WHO_AM_I` register.
+pub const IDENTITY: u8 = 0x6A;
+
+register_field! {
+    /// Output data rate shared by the accelerometer and gyroscope.
+    pub enum DataRate {
+        PowerDown = 0b0000,
+        Hz12p5 = 0b0001,
+        Hz26 = 0b0010,
+        Hz52 = 0b0011,
+        Hz104 = 0b0100,
+        Hz208 = 0b0101,
+        Hz416 = 0b0110,
+        Hz833 = 0b0111,
+        Hz1660 = 0b1000,
+        Hz3330 = 0b1001,
+        Hz6660 = 0b1010,
+        _ => PowerDown,
+    }
+}
+
+register_field! {
+    pub enum AccelerationScale {
+        G2 = 0b00,
+        G16 = 0b01,
+        G4 = 0b10,
+        G8 = 0b11,
+        _ => G2,
+    }
+}
+
+impl AccelerationScale {
+    /// Sensitivity in milli-g per LSB.
+    pub fn sensitivity(&self) -> f32 {
+        match self {
+            AccelerationScale::G2 => 0.061,
+            AccelerationScale::G4 => 0.122,
+            AccelerationScale::G8 => 0.244,
+            AccelerationScale::G16 => 0.488,
+        }
+    }
+}
+
+register_field! {
+    /// Gyroscope full scale, from `FS_G` and the `FS_125` bit below it, which takes
+    /// precedence when set.
+    pub enum AngularRateScale {
+        Dps250 = 0b000,
+        Dps125 = 0b001,
+        Dps500 = 0b010,
+        Dps1000 = 0b100,
+        Dps2000 = 0b110,
+        _ => Dps125,
+    }
+}
+
+impl AngularRateScale {
+    /// Sensitivity in milli-degrees per second per LSB.
+    pub fn sensitivity(&self) -> f32 {
+        match self {
+            AngularRateScale::Dps125 => 4.375,
+            AngularRateScale::Dps250 => 8.75,
+            AngularRateScale::Dps500 => 17.5,
+            AngularRateScale::Dps1000 => 35.0,
+            AngularRateScale::Dps2000 => 70.0,
+        }
+    }
+}
+
+register! {
+    /// Routing of data-ready signals to the INT1 pin.
+    pub Int1Ctrl: rw u8 @ 0x0D {
+        gyroscope_data_ready: bool = [1],
+        accelerometer_data_ready: bool = [0],
+    }
+}
+
+register! {
+    pub WhoAmI: ro u8 @ 0x0F {
+        identity: u8 = [7:0],
+    }
+}
+
+register! {
+    pub Ctrl1Xl: rw u8 @ 0x10 {
+        data_rate: DataRate = [7:4],
+        scale: AccelerationScale = [3:2],
+    }
+}
+
+register! {
+    pub Ctrl2G: rw u8 @ 0x11 {
+        data_rate: DataRate = [7:4],
+        scale: AngularRateScale = [3:1],
+    }
+}
+
+register! {
+    pub Ctrl3C: rw u8 @ 0x12 {
+        boot: bool = [7],
+        block_data_update: bool = [6],
+        interrupt_active_low: bool = [5],
+        open_drain: bool = [4],
+        auto_increment: bool = [2],
+        software_reset: bool = [0],
+    }
+}
+
+register! {
+    /// Wake-up and free-fall event source. Reading it clears a latched interrupt.
+    pub WakeUpSrc: ro u8 @ 0x1B {
+        free_fall: bool = [5],
+        sleep_state: bool = [4],
+        wake_up: bool = [3],
+    }
+}
+
+register! {
+    /// Tap event source. Reading it clears a latched interrupt.
+    pub TapSrc: ro u8 @ 0x1C {
+        tap: bool = [6],
+        single_tap: bool = [5],
+        double_tap: bool = [4],
+    }
+}
+
+register! {
+    pub Status: ro u8 @ 0x1E {
+        temperature_available: bool = [2],
+        gyroscope_available: bool = [1],
+        accelerometer_available: bool = [0],
+    }
+}
+
+impl Status {
+    pub fn any_available(&self) -> bool {
+        self.gyroscope_available || self.accelerometer_available
+    }
+}
+
+register! {
+    pub TapCfg: rw u8 @ 0x58 {
+        interrupts_enable: bool = [7],
+        tap_x: bool = [3],
+        tap_y: bool = [2],
+        tap_z: bool = [1],
+        latched: bool = [0],
+    }
+}
+
+register! {
+    pub TapThs6d: rw u8 @ 0x59 {
+        d4d_enable: bool = [7],
+        /// Raw 6D/4D orientation threshold selection.
+        sixd_threshold: u8 = [6:5],
+        /// Tap threshold, 1 LSB = full scale / 32.
+        tap_threshold: u8 = [4:0],
+    }
+}
+
+register! {
+    pub IntDur2: rw u8 @ 0x5A {
+        /// Maximum time between two taps of a double tap, 1 LSB = 32 / ODR.
+        duration: u8 = [7:4],
+        /// Quiet time after a tap, 1 LSB = 4 / ODR.
+        quiet: u8 = [3:2],
+        /// Maximum duration of an over-threshold event, 1 LSB = 8 / ODR.
+        shock: u8 = [1:0],
+    }
+}
+
+register! {
+    pub WakeUpThs: rw u8 @ 0x5B {
+        /// Enable double tap in addition to single tap recognition.
+        double_tap: bool = [7],
+        inactivity: bool = [6],
+        /// Wake-up threshold, 1 LSB = full scale / 64.
+        wake_up_threshold: u8 = [5:0],
+    }
+}
+
+register! {
+    pub WakeUpDur: rw u8 @ 0x5C {
+        /// Bit 5 of the free-fall duration, see `FreeFall`.
+        free_fall_duration_msb: bool = [7],
+        wake_up_duration: u8 = [6:5],
+        timer_high_resolution: bool = [4],
+        sleep_duration: u8 = [3:0],
+    }
+}
+
+register! {
+    pub FreeFall: rw u8 @ 0x5D {
+        /// Lower 5 bits of the free-fall duration, 1 LSB = 1 / ODR.
+        duration: u8 = [7:3],
+        /// Free-fall threshold, from 156mg (0) to 500mg (7).
+        threshold: u8 = [2:0],
+    }
+}
+
+register! {
+    /// Routing of embedded function events to the INT1 pin.
+    pub Md1Cfg: rw u8 @ 0x5E {
+        single_tap: bool = [6],
+        free_fall: bool = [4],
+        double_tap: bool = [3],
+    }
+}
@@ -0,0 +1,27 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::prelude::Address;
+use embedded_hal::blocking::i2c::WriteRead;
+
+// OUTX_L_G through OUTZ_H_XL, read in one go relying on IF_INC
+const OUTX_L_G: u8 = 0x22;
+
+pub struct Out;
+
+impl Out {
+    /// Read the raw gyroscope and accelerometer outputs, as `(angular_rate, acceleration)`.
+    pub async fn read<I: WriteRead>(
+        address: I2cAddress,
+        i2c: Address<I2cPeripheral<I>>,
+    ) -> Result<([i16; 3], [i16; 3]), I::Error> {
+        let mut buf = [0; 12];
+        i2c.write_read(address, &[OUTX_L_G], &mut buf).await?;
+        Ok(split(&buf))
+    }
+}
+
+/// Decode the little-endian gyroscope axes followed by the accelerometer axes.
+pub(crate) fn split(buf: &[u8; 12]) -> ([i16; 3], [i16; 3]) {
+    let axis = |i: usize| i16::from_le_bytes([buf[i], buf[i + 1]]);
+    ([axis(0), axis(2), axis(4)], [axis(6), axis(8), axis(10)])
+}
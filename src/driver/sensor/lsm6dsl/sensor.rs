@@ -0,0 +1,393 @@
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::lsm6dsl::ready::DataReady;
+use crate::driver::sensor::lsm6dsl::register::out::Out;
+use crate::driver::sensor::lsm6dsl::register::{
+    AccelerationScale, AngularRateScale, Ctrl1Xl, Ctrl2G, Ctrl3C, DataRate, FreeFall, Int1Ctrl,
+    IntDur2, Md1Cfg, Status, TapCfg, TapSrc, TapThs6d, WakeUpDur, WakeUpSrc, WakeUpThs, WhoAmI,
+    IDENTITY,
+};
+use crate::driver::sensor::lsm6dsl::{MotionAcquisition, MotionEvent, SensorError};
+use crate::prelude::*;
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+
+pub const ADDR: u8 = 0x6A;
+
+// Tap recognition settings recommended by ST (AN5040).
+const TAP_THRESHOLD: u8 = 0x0C;
+const TAP_DURATION: u8 = 0x07;
+const TAP_QUIET: u8 = 0x03;
+const TAP_SHOCK: u8 = 0x03;
+
+// Free-fall recognition settings recommended by ST (AN5040), 6 samples below 312mg.
+const FREE_FALL_DURATION: u8 = 0x06;
+const FREE_FALL_THRESHOLD: u8 = 0x03;
+
+// Samples read while emptying the outputs at startup before giving up on the device.
+const DRAIN_ATTEMPTS: u8 = 4;
+
+pub struct Sensor<D, I>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write + 'static,
+{
+    address: I2cAddress,
+    data_rate: DataRate,
+    acceleration_scale: AccelerationScale,
+    angular_rate_scale: AngularRateScale,
+    tap_detection: bool,
+    free_fall_detection: bool,
+    i2c: Option<Address<I2cPeripheral<I>>>,
+    bus: Option<Address<EventBus<D>>>,
+}
+
+impl<D, I> Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            address: I2cAddress::new(ADDR),
+            data_rate: DataRate::Hz104,
+            acceleration_scale: AccelerationScale::G2,
+            angular_rate_scale: AngularRateScale::Dps250,
+            tap_detection: false,
+            free_fall_detection: false,
+            i2c: None,
+            bus: None,
+        }
+    }
+
+    /// Use the provided data rate once initialized instead of the default 104Hz.
+    pub fn with_data_rate(mut self, data_rate: DataRate) -> Self {
+        self.data_rate = data_rate;
+        self
+    }
+
+    /// Use the provided scales once initialized instead of the default ±2g and ±250dps.
+    pub fn with_scale(
+        mut self,
+        acceleration_scale: AccelerationScale,
+        angular_rate_scale: AngularRateScale,
+    ) -> Self {
+        self.acceleration_scale = acceleration_scale;
+        self.angular_rate_scale = angular_rate_scale;
+        self
+    }
+}
+
+impl<D, I> Default for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write + 'static,
+{
+    fn default() -> Self {
+        Sensor::new()
+    }
+}
+
+impl<D, I> Actor for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Configuration = (Address<EventBus<D>>, Address<I2cPeripheral<I>>);
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.bus.replace(config.0);
+        self.i2c.replace(config.1);
+    }
+
+    fn on_initialize(self) -> Completion<Self> {
+        Completion::defer(async move {
+            if let Some(i2c) = self.i2c {
+                match WhoAmI::read(self.address, i2c)
+                    .await
+                    .map(|reg| reg.get_identity())
+                {
+                    Ok(IDENTITY) => {}
+                    Ok(identity) => {
+                        log::warn!("[lsm6dsl] unexpected identity {:x}", identity)
+                    }
+                    Err(_) => log::warn!("[lsm6dsl] unable to read identity"),
+                }
+
+                Ctrl3C::modify(self.address, i2c, |reg| {
+                    reg.block_data_update(true).auto_increment(true);
+                })
+                .await
+                .ok();
+
+                let data_rate = self.data_rate;
+                let acceleration_scale = self.acceleration_scale;
+                Ctrl1Xl::modify(self.address, i2c, |reg| {
+                    reg.data_rate(data_rate).scale(acceleration_scale);
+                })
+                .await
+                .ok();
+
+                let angular_rate_scale = self.angular_rate_scale;
+                Ctrl2G::modify(self.address, i2c, |reg| {
+                    reg.data_rate(data_rate).scale(angular_rate_scale);
+                })
+                .await
+                .ok();
+
+                Int1Ctrl::modify(self.address, i2c, |reg| {
+                    reg.accelerometer_data_ready(true);
+                })
+                .await
+                .ok();
+
+                // Ensure status is emptied, so the data ready line goes low again
+                let mut drained = 0;
+                loop {
+                    match Status::read(self.address, i2c).await {
+                        Ok(status) if !status.any_available() => break,
+                        Ok(_) if drained == DRAIN_ATTEMPTS => {
+                            log::warn!("[lsm6dsl] data still available after draining");
+                            break;
+                        }
+                        Ok(_) => {
+                            Out::read(self.address, i2c).await.ok();
+                            drained += 1;
+                        }
+                        Err(_) => {
+                            log::warn!("[lsm6dsl] unable to read status");
+                            break;
+                        }
+                    }
+                }
+            }
+            self
+        })
+    }
+}
+
+impl<D, I> NotifyHandler<DataReady> for Sensor<D, I>
+where
+    D: Device + EventHandler<MotionAcquisition> + EventHandler<MotionEvent>,
+    I: WriteRead + Read + Write,
+{
+    fn on_notify(self, message: DataReady) -> Completion<Self> {
+        Completion::defer(async move {
+            if let (Some(i2c), Some(bus)) = (self.i2c, self.bus) {
+                if self.tap_detection {
+                    if let Ok(tap) = TapSrc::read(self.address, i2c).await {
+                        if tap.get_double_tap() {
+                            bus.publish(MotionEvent::DoubleTap);
+                        } else if tap.get_single_tap() {
+                            bus.publish(MotionEvent::SingleTap);
+                        }
+                    }
+                }
+
+                if self.free_fall_detection {
+                    if let Ok(wake_up) = WakeUpSrc::read(self.address, i2c).await {
+                        if wake_up.get_free_fall() {
+                            bus.publish(MotionEvent::FreeFall);
+                        }
+                    }
+                }
+
+                if let Ok(status) = Status::read(self.address, i2c).await {
+                    if status.any_available() {
+                        match self.acquire().await {
+                            Ok(acquisition) => bus.publish(acquisition),
+                            Err(e) => log::warn!("[lsm6dsl] acquisition failed: {:?}", e),
+                        }
+                    }
+                }
+            }
+            self
+        })
+    }
+}
+
+impl<D, I> Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    async fn acquire(&self) -> Result<MotionAcquisition, SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+        let (angular_rate, acceleration) = Out::read(self.address, i2c)
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        Ok(MotionAcquisition::from_raw(
+            acceleration,
+            self.acceleration_scale,
+            angular_rate,
+            self.angular_rate_scale,
+        ))
+    }
+
+    async fn configure_tap_detection(&self, enable: bool) -> Result<(), SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+
+        if enable {
+            TapThs6d::modify(self.address, i2c, |reg| {
+                reg.tap_threshold(TAP_THRESHOLD);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+            IntDur2::modify(self.address, i2c, |reg| {
+                reg.duration(TAP_DURATION).quiet(TAP_QUIET).shock(TAP_SHOCK);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        }
+
+        TapCfg::modify(self.address, i2c, |reg| {
+            reg.interrupts_enable(true)
+                .latched(true)
+                .tap_x(enable)
+                .tap_y(enable)
+                .tap_z(enable);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        WakeUpThs::modify(self.address, i2c, |reg| {
+            reg.double_tap(enable);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)?;
+
+        Md1Cfg::modify(self.address, i2c, |reg| {
+            reg.single_tap(enable).double_tap(enable);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)
+    }
+
+    async fn configure_free_fall_detection(&self, enable: bool) -> Result<(), SensorError> {
+        let i2c = self.i2c.ok_or(SensorError::NotInitialized)?;
+
+        if enable {
+            TapCfg::modify(self.address, i2c, |reg| {
+                reg.interrupts_enable(true).latched(true);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+            WakeUpDur::modify(self.address, i2c, |reg| {
+                reg.free_fall_duration_msb(false);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+
+            FreeFall::modify(self.address, i2c, |reg| {
+                reg.duration(FREE_FALL_DURATION)
+                    .threshold(FREE_FALL_THRESHOLD);
+            })
+            .await
+            .map_err(|_| SensorError::I2c)?;
+        }
+
+        Md1Cfg::modify(self.address, i2c, |reg| {
+            reg.free_fall(enable);
+        })
+        .await
+        .map_err(|_| SensorError::I2c)
+    }
+}
+
+/// Request the most recent accelerometer and gyroscope outputs.
+#[derive(Debug)]
+pub struct TakeReading;
+
+impl<D, I> RequestHandler<TakeReading> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<MotionAcquisition, SensorError>;
+
+    fn on_request(self, message: TakeReading) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.acquire().await;
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetTapDetection(pub bool);
+
+impl<D, I> RequestHandler<SetTapDetection> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(mut self, message: SetTapDetection) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.configure_tap_detection(message.0).await;
+            if result.is_ok() {
+                self.tap_detection = message.0;
+            }
+            (self, result)
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SetFreeFallDetection(pub bool);
+
+impl<D, I> RequestHandler<SetFreeFallDetection> for Sensor<D, I>
+where
+    D: Device,
+    I: WriteRead + Read + Write,
+{
+    type Response = Result<(), SensorError>;
+
+    fn on_request(mut self, message: SetFreeFallDetection) -> Response<Self, Self::Response> {
+        Response::defer(async move {
+            let result = self.configure_free_fall_detection(message.0).await;
+            if result.is_ok() {
+                self.free_fall_detection = message.0;
+            }
+            (self, result)
+        })
+    }
+}
+
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + 'static,
+    I: WriteRead + Read + Write,
+{
+    /// Read the most recent accelerometer and gyroscope outputs.
+    pub async fn take_reading(&self) -> Result<MotionAcquisition, SensorError> {
+        self.request(TakeReading).await
+    }
+
+    /// Enable or disable single and double tap recognition, published as `MotionEvent`s.
+    /// Tap recognition works best with a data rate of at least 416Hz.
+    pub async fn set_tap_detection(&self, enable: bool) -> Result<(), SensorError> {
+        self.request(SetTapDetection(enable)).await
+    }
+
+    /// Enable or disable free-fall recognition, published as `MotionEvent::FreeFall`.
+    pub async fn set_free_fall_detection(&self, enable: bool) -> Result<(), SensorError> {
+        self.request(SetFreeFallDetection(enable)).await
+    }
+}
+
+#[doc(hidden)]
+impl<D, I> Address<Sensor<D, I>>
+where
+    D: Device + EventHandler<MotionAcquisition> + EventHandler<MotionEvent> + 'static,
+    I: WriteRead + Read + Write,
+{
+    pub fn signal_data_ready(&self) {
+        self.notify(DataReady)
+    }
+}
@@ -0,0 +1,185 @@
+//! Register-file backed mock of a blocking `embedded_hal` I2C peripheral for sensor tests.
+
+extern crate std;
+
+use crate::driver::sensor::register::{Readable, RegisterValue, Writable};
+use core::cell::{RefCell, RefMut};
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use std::rc::Rc;
+use std::vec::Vec;
+
+#[derive(Debug, PartialEq)]
+pub enum Transaction {
+    Write(u8, Vec<u8>),
+    Read(u8, usize),
+}
+
+/// How the emulated device advances the register pointer on multi-byte transfers.
+#[derive(Copy, Clone)]
+pub enum AutoIncrement {
+    /// The pointer only advances if the MSB of the sub-address is set (HTS221, LIS3MDL).
+    Msb,
+    /// The pointer always advances (LSM6DSL with `IF_INC` set).
+    Always,
+}
+
+pub struct MockI2c {
+    address: u8,
+    auto_increment: AutoIncrement,
+    on_write: Option<fn(&mut [u8; 256], usize)>,
+    pub failing: Option<u8>,
+    pointer: usize,
+    pub registers: [u8; 256],
+    pub log: Vec<Transaction>,
+}
+
+impl MockI2c {
+    pub fn new(address: u8, auto_increment: AutoIncrement) -> Self {
+        Self {
+            address,
+            auto_increment,
+            on_write: None,
+            failing: None,
+            pointer: 0,
+            registers: [0; 256],
+            log: Vec::new(),
+        }
+    }
+
+    /// Emulate device side-effects, called with the index of each register written.
+    pub fn on_write(mut self, f: fn(&mut [u8; 256], usize)) -> Self {
+        self.on_write.replace(f);
+        self
+    }
+
+    /// Fail every read starting at `register`, as a device which stopped answering would.
+    pub fn failing(mut self, register: u8) -> Self {
+        self.failing.replace(register);
+        self
+    }
+
+    pub fn with_register(mut self, register: u8, value: u8) -> Self {
+        self.registers[register as usize] = value;
+        self
+    }
+
+    fn start(&self, register: u8) -> (usize, bool) {
+        match self.auto_increment {
+            AutoIncrement::Msb => ((register & 0x7F) as usize, register & 0x80 != 0),
+            AutoIncrement::Always => (register as usize, true),
+        }
+    }
+
+    /// Blocking read of a typed register, transferred the same way as `register::i2c::read`.
+    pub fn read_typed<R: Readable>(&mut self) -> R {
+        let width = <R::Value as RegisterValue>::WIDTH;
+        let mut buf = [0; 4];
+        let address = self.address;
+        self.write_read(address, &[R::ADDRESS], &mut buf[..width])
+            .unwrap();
+        R::from_value(RegisterValue::from_le(&buf[..width]))
+    }
+
+    /// Blocking write of a typed register, transferred the same way as `register::i2c::write`.
+    pub fn write_typed<R: Writable>(&mut self, reg: R) {
+        let width = <R::Value as RegisterValue>::WIDTH;
+        let mut buf = [0; 5];
+        buf[0] = R::ADDRESS;
+        reg.into_value().to_le(&mut buf[1..]);
+        let address = self.address;
+        self.write(address, &buf[..=width]).unwrap();
+    }
+}
+
+impl Write for MockI2c {
+    type Error = ();
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+        assert_eq!(self.address, address);
+        let (mut reg, increment) = self.start(bytes[0]);
+        self.log
+            .push(Transaction::Write(bytes[0], bytes[1..].to_vec()));
+        for b in &bytes[1..] {
+            self.registers[reg] = *b;
+            if let Some(on_write) = self.on_write {
+                on_write(&mut self.registers, reg);
+            }
+            if increment {
+                reg += 1;
+            }
+        }
+        self.pointer = reg;
+        Ok(())
+    }
+}
+
+impl Read for MockI2c {
+    type Error = ();
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), ()> {
+        assert_eq!(self.address, address);
+        for b in buffer.iter_mut() {
+            *b = self.registers[self.pointer];
+            self.pointer += 1;
+        }
+        Ok(())
+    }
+}
+
+impl WriteRead for MockI2c {
+    type Error = ();
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+        assert_eq!(self.address, address);
+        let (mut reg, increment) = self.start(bytes[0]);
+        self.log.push(Transaction::Read(bytes[0], buffer.len()));
+        if self.failing == Some(bytes[0]) {
+            return Err(());
+        }
+        for b in buffer.iter_mut() {
+            *b = self.registers[reg];
+            if increment {
+                reg += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `MockI2c` handed to an actor, which the test keeps a handle on to look at afterwards.
+#[derive(Clone)]
+pub struct SharedI2c(Rc<RefCell<MockI2c>>);
+
+impl SharedI2c {
+    pub fn new(i2c: MockI2c) -> Self {
+        Self(Rc::new(RefCell::new(i2c)))
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, MockI2c> {
+        self.0.borrow_mut()
+    }
+}
+
+impl Write for SharedI2c {
+    type Error = ();
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+        self.0.borrow_mut().write(address, bytes)
+    }
+}
+
+impl Read for SharedI2c {
+    type Error = ();
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), ()> {
+        self.0.borrow_mut().read(address, buffer)
+    }
+}
+
+impl WriteRead for SharedI2c {
+    type Error = ();
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+        self.0.borrow_mut().write_read(address, bytes, buffer)
+    }
+}
@@ -1 +1,8 @@
 pub mod hts221;
+pub mod lis3mdl;
+pub mod lps22hb;
+pub mod lsm6dsl;
+pub mod register;
+
+#[cfg(test)]
+pub(crate) mod mock;
@@ -0,0 +1,43 @@
+//! Register access over the I2C actor.
+
+use crate::api::i2c::I2cAddress;
+use crate::driver::i2c::I2cPeripheral;
+use crate::driver::sensor::register::{ModifyError, Readable, RegisterValue, Writable};
+use crate::prelude::Address;
+use embedded_hal::blocking::i2c::{Write, WriteRead};
+
+// Enough for the widest supported register, plus the sub-address when writing.
+const BUFFER_SIZE: usize = 5;
+
+pub async fn read<I: WriteRead, R: Readable>(
+    address: I2cAddress,
+    i2c: Address<I2cPeripheral<I>>,
+) -> Result<R, I::Error> {
+    let width = <R::Value as RegisterValue>::WIDTH;
+    let mut buf = [0; BUFFER_SIZE];
+    i2c.write_read(address, &[R::ADDRESS], &mut buf[..width])
+        .await?;
+    Ok(R::from_value(RegisterValue::from_le(&buf[..width])))
+}
+
+pub async fn write<I: Write, R: Writable>(
+    address: I2cAddress,
+    i2c: Address<I2cPeripheral<I>>,
+    reg: R,
+) -> Result<(), I::Error> {
+    let width = <R::Value as RegisterValue>::WIDTH;
+    let mut buf = [0; BUFFER_SIZE];
+    buf[0] = R::ADDRESS;
+    reg.into_value().to_le(&mut buf[1..]);
+    Ok(i2c.write(address, &buf[..=width]).await?)
+}
+
+pub async fn modify<I: WriteRead + Write, R: Readable + Writable, F: FnOnce(&mut R)>(
+    address: I2cAddress,
+    i2c: Address<I2cPeripheral<I>>,
+    modify: F,
+) -> Result<(), ModifyError<<I as WriteRead>::Error, <I as Write>::Error>> {
+    let mut reg = read(address, i2c).await.map_err(ModifyError::Read)?;
+    modify(&mut reg);
+    write(address, i2c, reg).await.map_err(ModifyError::Write)
+}
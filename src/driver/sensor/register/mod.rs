@@ -0,0 +1,452 @@
+//! Typed registers for sensor drivers.
+//!
+//! Registers are declared once with the [`register!`](crate::register) macro, giving their
+//! address, raw width, access mode and bitfields. The macro generates the register type with
+//! builder-style setters and getters for each field, along with async `read`/`write`/`modify`
+//! operations over the I2C actor and `spi_read`/`spi_write`/`spi_modify` over an SPI
+//! `BusTransaction`. Fields may be `bool`, raw integers, or enums declared with
+//! [`register_field!`](crate::register_field).
+//!
+//! ```ignore
+//! register_field! {
+//!     pub enum DataRate {
+//!         PowerDown = 0b000,
+//!         Hz1 = 0b001,
+//!         Hz10 = 0b010,
+//!         _ => PowerDown,
+//!     }
+//! }
+//!
+//! register! {
+//!     pub CtrlReg1: rw u8 @ 0x10 {
+//!         data_rate: DataRate = [6:4],
+//!         block_data_update: bool = [1],
+//!     }
+//! }
+//!
+//! CtrlReg1::modify(address, i2c, |reg| {
+//!     reg.data_rate(DataRate::Hz10).block_data_update(true);
+//! })
+//! .await?;
+//! ```
+
+pub mod i2c;
+pub mod spi;
+
+#[doc(hidden)]
+pub use embedded_hal::blocking::i2c::{Write as I2cWrite, WriteRead as I2cWriteRead};
+
+#[derive(Debug)]
+pub enum ModifyError<R, W> {
+    Read(R),
+    Write(W),
+}
+
+/// Raw storage of a register, transferred least significant byte first.
+pub trait RegisterValue: Copy {
+    /// Width of the register, in bytes.
+    const WIDTH: usize;
+
+    fn from_le(bytes: &[u8]) -> Self;
+    fn to_le(self, bytes: &mut [u8]);
+    fn from_bits(bits: u32) -> Self;
+    fn into_bits(self) -> u32;
+}
+
+macro_rules! register_value {
+    ($ty:ty, $width:literal) => {
+        impl RegisterValue for $ty {
+            const WIDTH: usize = $width;
+
+            fn from_le(bytes: &[u8]) -> Self {
+                let mut buf = [0; $width];
+                buf.copy_from_slice(&bytes[..$width]);
+                <$ty>::from_le_bytes(buf)
+            }
+
+            fn to_le(self, bytes: &mut [u8]) {
+                bytes[..$width].copy_from_slice(&<$ty>::to_le_bytes(self));
+            }
+
+            fn from_bits(bits: u32) -> Self {
+                bits as $ty
+            }
+
+            fn into_bits(self) -> u32 {
+                self as u32
+            }
+        }
+    };
+}
+
+register_value!(u8, 1);
+register_value!(u16, 2);
+register_value!(u32, 4);
+
+/// The value of a bitfield within a register.
+pub trait FieldValue: Copy {
+    fn from_bits(bits: u32) -> Self;
+    fn into_bits(self) -> u32;
+}
+
+impl FieldValue for bool {
+    fn from_bits(bits: u32) -> Self {
+        bits != 0
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl FieldValue for u8 {
+    fn from_bits(bits: u32) -> Self {
+        bits as u8
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl FieldValue for u16 {
+    fn from_bits(bits: u32) -> Self {
+        bits as u16
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u32
+    }
+}
+
+impl FieldValue for i16 {
+    fn from_bits(bits: u32) -> Self {
+        bits as u16 as i16
+    }
+
+    fn into_bits(self) -> u32 {
+        self as u16 as u32
+    }
+}
+
+/// A register type, usually declared through [`register!`](crate::register).
+pub trait Register: Copy {
+    type Value: RegisterValue;
+
+    /// Sub-address sent to the device. For registers wider than a byte this must select
+    /// auto-increment where the device needs it, such as setting the MSB on the HTS221.
+    const ADDRESS: u8;
+
+    fn from_value(value: Self::Value) -> Self;
+    fn into_value(self) -> Self::Value;
+}
+
+/// Marker for registers that may be read.
+pub trait Readable: Register {}
+
+/// Marker for registers that may be written.
+pub trait Writable: Register {}
+
+/// Mask covering the bits `hi` down to `lo`, shifted down to bit 0.
+#[doc(hidden)]
+pub const fn field_mask(hi: u32, lo: u32) -> u32 {
+    ((1u64 << (hi - lo + 1)) - 1) as u32
+}
+
+/// Declare an enum usable as a register field.
+///
+/// Each variant is given its raw bit pattern, and a fallback variant must be named for
+/// patterns that are reserved by the device.
+#[macro_export]
+macro_rules! register_field {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$vattr:meta])*
+                $variant:ident = $bits:literal,
+            )+
+            _ => $fallback:ident $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        $vis enum $name {
+            $(
+                $(#[$vattr])*
+                $variant,
+            )+
+        }
+
+        impl $crate::driver::sensor::register::FieldValue for $name {
+            fn from_bits(bits: u32) -> Self {
+                match bits {
+                    $( $bits => $name::$variant, )+
+                    _ => $name::$fallback,
+                }
+            }
+
+            fn into_bits(self) -> u32 {
+                match self {
+                    $( $name::$variant => $bits, )+
+                }
+            }
+        }
+    };
+}
+
+/// Declare a typed register.
+///
+/// The declaration gives the register name, its access mode (`ro`, `wo` or `rw`), its raw
+/// width (`u8`, `u16` or `u32`), its address and the bitfields it holds, each as `[hi:lo]`
+/// or `[bit]`. Writable registers get a builder-style setter per field, and readable
+/// registers a getter of the same name prefixed with `get_`.
+#[macro_export]
+macro_rules! register {
+    (@lo $hi:literal) => { $hi };
+    (@lo $hi:literal : $lo:literal) => { $lo };
+
+    (@access ro $name:ident; $( $(#[$fattr:meta])* $field:ident: $fty:ty ),*) => {
+        $crate::register!(@read $name);
+        $crate::register!(@getters $name; $( $(#[$fattr])* $field: $fty ),*);
+    };
+
+    (@access wo $name:ident; $( $(#[$fattr:meta])* $field:ident: $fty:ty ),*) => {
+        $crate::register!(@write $name);
+        $crate::register!(@setters $name; $( $(#[$fattr])* $field: $fty ),*);
+    };
+
+    (@access rw $name:ident; $( $(#[$fattr:meta])* $field:ident: $fty:ty ),*) => {
+        $crate::register!(@read $name);
+        $crate::register!(@write $name);
+        $crate::register!(@setters $name; $( $(#[$fattr])* $field: $fty ),*);
+        $crate::register!(@getters $name; $( $(#[$fattr])* $field: $fty ),*);
+
+        impl $name {
+            pub async fn modify<I, F>(
+                address: $crate::api::i2c::I2cAddress,
+                i2c: $crate::prelude::Address<$crate::driver::i2c::I2cPeripheral<I>>,
+                modify: F,
+            ) -> Result<
+                (),
+                $crate::driver::sensor::register::ModifyError<
+                    <I as $crate::driver::sensor::register::I2cWriteRead>::Error,
+                    <I as $crate::driver::sensor::register::I2cWrite>::Error,
+                >,
+            >
+            where
+                I: $crate::driver::sensor::register::I2cWriteRead
+                    + $crate::driver::sensor::register::I2cWrite,
+                F: FnOnce(&mut Self),
+            {
+                $crate::driver::sensor::register::i2c::modify(address, i2c, modify).await
+            }
+
+            pub async fn spi_modify<SPI, F>(
+                transaction: &$crate::api::arbitrator::BusTransaction<SPI>,
+                modify: F,
+            ) -> Result<(), $crate::api::spi::SpiError>
+            where
+                SPI: $crate::api::spi::SpiBus<Word = u8>,
+                F: FnOnce(&mut Self),
+            {
+                $crate::driver::sensor::register::spi::modify(transaction, modify).await
+            }
+        }
+    };
+
+    (@read $name:ident) => {
+        impl $crate::driver::sensor::register::Readable for $name {}
+
+        impl $name {
+            pub async fn read<I: $crate::driver::sensor::register::I2cWriteRead>(
+                address: $crate::api::i2c::I2cAddress,
+                i2c: $crate::prelude::Address<$crate::driver::i2c::I2cPeripheral<I>>,
+            ) -> Result<Self, I::Error> {
+                $crate::driver::sensor::register::i2c::read(address, i2c).await
+            }
+
+            pub async fn spi_read<SPI: $crate::api::spi::SpiBus<Word = u8>>(
+                transaction: &$crate::api::arbitrator::BusTransaction<SPI>,
+            ) -> Result<Self, $crate::api::spi::SpiError> {
+                $crate::driver::sensor::register::spi::read(transaction).await
+            }
+        }
+    };
+
+    (@write $name:ident) => {
+        impl $crate::driver::sensor::register::Writable for $name {}
+
+        impl $name {
+            pub async fn write<I: $crate::driver::sensor::register::I2cWrite>(
+                address: $crate::api::i2c::I2cAddress,
+                i2c: $crate::prelude::Address<$crate::driver::i2c::I2cPeripheral<I>>,
+                reg: Self,
+            ) -> Result<(), I::Error> {
+                $crate::driver::sensor::register::i2c::write(address, i2c, reg).await
+            }
+
+            pub async fn spi_write<SPI: $crate::api::spi::SpiBus<Word = u8>>(
+                transaction: &$crate::api::arbitrator::BusTransaction<SPI>,
+                reg: Self,
+            ) -> Result<(), $crate::api::spi::SpiError> {
+                $crate::driver::sensor::register::spi::write(transaction, reg).await
+            }
+        }
+    };
+
+    (@setters $name:ident; $( $(#[$fattr:meta])* $field:ident: $fty:ty ),*) => {
+        impl $name {
+            $(
+                $(#[$fattr])*
+                pub fn $field(&mut self, $field: $fty) -> &mut Self {
+                    self.$field = $field;
+                    self
+                }
+            )*
+        }
+    };
+
+    (@getters $name:ident; $( $(#[$fattr:meta])* $field:ident: $fty:ty ),*) => {
+        $crate::paste::paste! {
+            impl $name {
+                $(
+                    $(#[$fattr])*
+                    pub fn [<get_ $field>](&self) -> $fty {
+                        self.$field
+                    }
+                )*
+            }
+        }
+    };
+
+    (
+        $(#[$attr:meta])*
+        $vis:vis $name:ident: $access:ident $value:ident @ $address:literal {
+            $(
+                $(#[$fattr:meta])*
+                $field:ident: $fty:ty = [$hi:literal $(: $lo:literal)?]
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Copy, Clone)]
+        $vis struct $name {
+            $( $field: $fty, )*
+        }
+
+        impl $crate::driver::sensor::register::Register for $name {
+            type Value = $value;
+            const ADDRESS: u8 = $address;
+
+            fn from_value(value: $value) -> Self {
+                let bits = $crate::driver::sensor::register::RegisterValue::into_bits(value);
+                Self {
+                    $(
+                        $field: <$fty as $crate::driver::sensor::register::FieldValue>::from_bits(
+                            (bits >> $crate::register!(@lo $hi $(: $lo)?))
+                                & $crate::driver::sensor::register::field_mask(
+                                    $hi,
+                                    $crate::register!(@lo $hi $(: $lo)?),
+                                ),
+                        ),
+                    )*
+                }
+            }
+
+            fn into_value(self) -> $value {
+                let mut bits = 0u32;
+                $(
+                    bits |= ($crate::driver::sensor::register::FieldValue::into_bits(self.$field)
+                        & $crate::driver::sensor::register::field_mask(
+                            $hi,
+                            $crate::register!(@lo $hi $(: $lo)?),
+                        ))
+                        << $crate::register!(@lo $hi $(: $lo)?);
+                )*
+                <$value as $crate::driver::sensor::register::RegisterValue>::from_bits(bits)
+            }
+        }
+
+        $crate::register!(@access $access $name; $( $(#[$fattr])* $field: $fty ),*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{field_mask, FieldValue, Register};
+
+    register_field! {
+        enum Mode {
+            Off = 0b00,
+            Low = 0b01,
+            High = 0b11,
+            _ => Off,
+        }
+    }
+
+    register! {
+        Config: rw u16 @ 0x20 {
+            enable: bool = [15],
+            mode: Mode = [9:8],
+            threshold: u8 = [7:0],
+        }
+    }
+
+    register! {
+        Flags: ro u8 @ 0x21 {
+            ready: bool = [0],
+            count: u8 = [5:1],
+        }
+    }
+
+    register! {
+        Command: wo u8 @ 0x22 {
+            reset: bool = [7],
+        }
+    }
+
+    #[test]
+    fn test_field_mask() {
+        assert_eq!(0b1, field_mask(0, 0));
+        assert_eq!(0b111, field_mask(6, 4));
+        assert_eq!(0xFFFF_FFFF, field_mask(31, 0));
+    }
+
+    #[test]
+    fn test_field_enum() {
+        assert_eq!(Mode::Low, Mode::from_bits(0b01));
+        assert_eq!(Mode::High, Mode::from_bits(0b11));
+        // reserved pattern
+        assert_eq!(Mode::Off, Mode::from_bits(0b10));
+        assert_eq!(0b11, Mode::High.into_bits());
+    }
+
+    #[test]
+    fn test_rw_register() {
+        assert_eq!(0x20, Config::ADDRESS);
+
+        let mut reg = Config::from_value(0b1000_0001_0010_0011);
+        assert!(reg.get_enable());
+        assert_eq!(Mode::Low, reg.get_mode());
+        assert_eq!(0x23, reg.get_threshold());
+
+        reg.enable(false).mode(Mode::High).threshold(0xFF);
+        assert_eq!(0b0000_0011_1111_1111, reg.into_value());
+
+        // unused bits are not retained
+        let reg = Config::from_value(0xFFFF);
+        assert_eq!(0b1000_0011_1111_1111, reg.into_value());
+    }
+
+    #[test]
+    fn test_ro_and_wo_registers() {
+        let flags = Flags::from_value(0b0000_1011);
+        assert!(flags.get_ready());
+        assert_eq!(5, flags.get_count());
+
+        let mut command = Command::from_value(0);
+        command.reset(true);
+        assert_eq!(0x80, command.into_value());
+    }
+}
@@ -0,0 +1,44 @@
+//! Register access over an SPI `BusTransaction`.
+//!
+//! This follows the common convention of setting bit 7 of the address byte for reads and
+//! clearing it for writes. The transaction must select the device, as obtained through
+//! `begin_selected_transaction`.
+
+use crate::api::arbitrator::BusTransaction;
+use crate::api::spi::{SpiBus, SpiError};
+use crate::driver::sensor::register::{Readable, RegisterValue, Writable};
+
+const READ: u8 = 0x80;
+
+// Enough for the widest supported register, plus the address byte.
+const BUFFER_SIZE: usize = 5;
+
+pub async fn read<SPI: SpiBus<Word = u8>, R: Readable>(
+    transaction: &BusTransaction<SPI>,
+) -> Result<R, SpiError> {
+    let width = <R::Value as RegisterValue>::WIDTH;
+    let mut buf = [0; BUFFER_SIZE];
+    buf[0] = R::ADDRESS | READ;
+    transaction.spi_transfer(&mut buf[..=width]).await?;
+    Ok(R::from_value(RegisterValue::from_le(&buf[1..])))
+}
+
+pub async fn write<SPI: SpiBus<Word = u8>, R: Writable>(
+    transaction: &BusTransaction<SPI>,
+    reg: R,
+) -> Result<(), SpiError> {
+    let width = <R::Value as RegisterValue>::WIDTH;
+    let mut buf = [0; BUFFER_SIZE];
+    buf[0] = R::ADDRESS & !READ;
+    reg.into_value().to_le(&mut buf[1..]);
+    transaction.spi_transfer(&mut buf[..=width]).await
+}
+
+pub async fn modify<SPI: SpiBus<Word = u8>, R: Readable + Writable, F: FnOnce(&mut R)>(
+    transaction: &BusTransaction<SPI>,
+    modify: F,
+) -> Result<(), SpiError> {
+    let mut reg = read(transaction).await?;
+    modify(&mut reg);
+    write(transaction, reg).await
+}
@@ -0,0 +1,76 @@
+//! Sockets of a network adapter, handed out as they are closed.
+
+use crate::synchronization::{Priority, WaitQueue, Waiter};
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use heapless::consts::*;
+
+enum SocketState {
+    Closed,
+    Open,
+    Connected,
+}
+
+impl Default for SocketState {
+    fn default() -> Self {
+        Self::Closed
+    }
+}
+
+pub(crate) struct SocketPool {
+    sockets: RefCell<[SocketState; 4]>,
+    waiters: WaitQueue<U8>,
+}
+
+impl SocketPool {
+    pub(crate) fn new() -> Self {
+        Self {
+            sockets: Default::default(),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    pub(crate) fn open(&'static self) -> OpenFuture {
+        OpenFuture::new(self)
+    }
+
+    pub(crate) fn close(&self, handle: u8) {
+        self.sockets.borrow_mut()[handle as usize] = SocketState::Closed;
+        self.waiters.wake_one();
+    }
+
+    fn try_open(&self) -> Option<u8> {
+        let mut sockets = self.sockets.borrow_mut();
+        let index = sockets
+            .iter()
+            .position(|s| matches!(s, SocketState::Closed))?;
+        sockets[index] = SocketState::Open;
+        Some(index as u8)
+    }
+}
+
+pub(crate) struct OpenFuture {
+    pool: &'static SocketPool,
+    waiter: Waiter<'static, U8>,
+}
+
+impl OpenFuture {
+    fn new(pool: &'static SocketPool) -> Self {
+        Self {
+            pool,
+            waiter: pool.waiters.waiter(Priority::default()),
+        }
+    }
+}
+
+impl Future for OpenFuture {
+    type Output = u8;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let pool = self.pool;
+        self.waiter.poll_acquire(cx, || pool.try_open())
+    }
+}
@@ -0,0 +1,260 @@
+use crate::prelude::*;
+
+use crate::api::arbitrator::{Arbitrator, Bus};
+use crate::api::spi::{SpiBus, SpiConfig, SpiError, SpiRead, SpiTransfer, SpiWrite, SpiWriteRead};
+use crate::hal::spi::dma::DmaSpiHal;
+use crate::synchronization::Signal;
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use cortex_m::interrupt::Nr;
+
+/// An SPI bus driven by DMA, with transfers serialized through an `Arbitrator`.
+pub struct DmaSpi<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    spi: S,
+    done: Signal<Result<(), SpiError>>,
+    arbitrator: Arbitrator<DmaSpiController<S>>,
+    controller: ActorContext<DmaSpiController<S>>,
+    interrupt: InterruptContext<DmaSpiInterrupt<S>>,
+}
+
+impl<S> DmaSpi<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    pub fn new<IRQ>(spi: S, irq: IRQ) -> Self
+    where
+        IRQ: Nr,
+    {
+        Self {
+            spi,
+            done: Signal::new(),
+            arbitrator: Arbitrator::new(),
+            controller: ActorContext::new(DmaSpiController::new()).with_name("spi_controller"),
+            interrupt: InterruptContext::new(DmaSpiInterrupt::new(), irq)
+                .with_name("spi_interrupt"),
+        }
+    }
+}
+
+impl<S> Package for DmaSpi<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Primary = <Arbitrator<DmaSpiController<S>> as Package>::Primary;
+    type Configuration = ();
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let controller = self.controller.mount((&self.spi, &self.done), supervisor);
+        self.interrupt.mount((&self.spi, &self.done), supervisor);
+        self.arbitrator.mount(controller, supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.arbitrator.primary()
+    }
+}
+
+pub struct DmaSpiController<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    spi: Option<&'static S>,
+    done: Option<&'static Signal<Result<(), SpiError>>>,
+    config: Option<SpiConfig>,
+}
+
+impl<S> DmaSpiController<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    fn new() -> Self {
+        Self {
+            spi: None,
+            done: None,
+            config: None,
+        }
+    }
+
+    /// Start a prepared transfer, and wait for it, followed by `then` if any.
+    fn start(
+        self,
+        prepared: Result<(), SpiError>,
+        then: Option<&mut [u8]>,
+    ) -> Response<Self, Result<(), SpiError>> {
+        if let Err(e) = prepared {
+            return Response::immediate(self, Err(e));
+        }
+        let spi = self.spi.unwrap();
+        let done = self.done.unwrap();
+        done.reset();
+        spi.start_transfer();
+        let future = TransferFuture::new(spi, done, then);
+        Response::immediate_future(self, future)
+    }
+}
+
+impl<S> Actor for DmaSpiController<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Configuration = (&'static S, &'static Signal<Result<(), SpiError>>);
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.spi.replace(config.0);
+        self.done.replace(config.1);
+    }
+}
+
+impl<S> Bus for DmaSpiController<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Config = SpiConfig;
+
+    fn configure(mut self, config: Self::Config) -> Response<Self, ()> {
+        if self.config != Some(config) {
+            self.spi.unwrap().configure(&config);
+            self.config.replace(config);
+        }
+        Response::immediate(self, ())
+    }
+}
+
+// The buffers of each message must be available until the returned future is await'ed.
+impl<S> SpiBus for DmaSpiController<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Word = u8;
+
+    fn transfer(self, transfer: SpiTransfer<Self::Word>) -> Response<Self, Result<(), SpiError>> {
+        let prepared = self.spi.unwrap().prepare_transfer(transfer.0);
+        self.start(prepared, None)
+    }
+
+    fn write(self, write: SpiWrite<Self::Word>) -> Response<Self, Result<(), SpiError>> {
+        let prepared = self.spi.unwrap().prepare_write(write.0);
+        self.start(prepared, None)
+    }
+
+    fn read(self, read: SpiRead<Self::Word>) -> Response<Self, Result<(), SpiError>> {
+        let prepared = self.spi.unwrap().prepare_read(read.0);
+        self.start(prepared, None)
+    }
+
+    fn write_read(
+        self,
+        write_read: SpiWriteRead<Self::Word>,
+    ) -> Response<Self, Result<(), SpiError>> {
+        let prepared = self.spi.unwrap().prepare_write(write_read.0);
+        self.start(prepared, Some(write_read.1))
+    }
+}
+
+pub struct DmaSpiInterrupt<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    spi: Option<&'static S>,
+    done: Option<&'static Signal<Result<(), SpiError>>>,
+}
+
+impl<S> DmaSpiInterrupt<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    fn new() -> Self {
+        Self {
+            spi: None,
+            done: None,
+        }
+    }
+}
+
+impl<S> Actor for DmaSpiInterrupt<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Configuration = (&'static S, &'static Signal<Result<(), SpiError>>);
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.spi.replace(config.0);
+        self.done.replace(config.1);
+    }
+}
+
+impl<S> Interrupt for DmaSpiInterrupt<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    fn on_interrupt(&mut self) {
+        let spi = self.spi.unwrap();
+        if spi.process_interrupts() {
+            let result = spi.finish_transfer();
+            log::trace!("[SPI ISR] transfer done: {:?}", result);
+            self.done.unwrap().signal(result);
+        }
+    }
+}
+
+struct TransferFuture<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    spi: &'static S,
+    done: &'static Signal<Result<(), SpiError>>,
+    // The read following a write, kept as a raw slice since the request outlives this future.
+    then: Option<(*mut u8, usize)>,
+}
+
+impl<S> TransferFuture<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    fn new(
+        spi: &'static S,
+        done: &'static Signal<Result<(), SpiError>>,
+        then: Option<&mut [u8]>,
+    ) -> Self {
+        Self {
+            spi,
+            done,
+            then: then.map(|buffer| (buffer.as_mut_ptr(), buffer.len())),
+        }
+    }
+}
+
+impl<S> Future for TransferFuture<S>
+where
+    S: DmaSpiHal + 'static,
+{
+    type Output = Result<(), SpiError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match self.done.poll_wait(cx) {
+                Poll::Ready(Ok(_)) => {
+                    if let Some((ptr, len)) = self.then.take() {
+                        let buffer = unsafe { core::slice::from_raw_parts_mut(ptr, len) };
+                        if let Err(e) = self.spi.prepare_read(buffer) {
+                            return Poll::Ready(Err(e));
+                        }
+                        self.spi.start_transfer();
+                    } else {
+                        return Poll::Ready(Ok(()));
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
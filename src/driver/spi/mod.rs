@@ -1,9 +1,11 @@
-use crate::api::arbitrator::Arbitrator;
-use crate::api::spi::{SpiBus, SpiError, SpiTransfer};
+pub mod dma;
+
+use crate::api::arbitrator::{Arbitrator, Bus};
+use crate::api::spi::{SpiBus, SpiConfig, SpiError, SpiRead, SpiTransfer, SpiWrite, SpiWriteRead};
 use crate::prelude::*;
 use core::fmt::Debug;
 use core::marker::PhantomData;
-use embedded_hal::blocking::spi::Transfer;
+use embedded_hal::blocking::spi::{Transfer, Write};
 
 // ------------------------------------------------------------------------
 // ------------------------------------------------------------------------
@@ -77,11 +79,25 @@ where
     type Configuration = ();
 }
 
-impl<SPI, W> SpiBus for SpiController<SPI, W>
+/// The blocking controller keeps the mode and frequency the peripheral was created with, so
+/// devices sharing it must agree on them. `DmaSpi` reconfigures the bus for each device.
+impl<SPI, W> Bus for SpiController<SPI, W>
 where
-    SPI::Error: Into<SpiError>,
     SPI: Transfer<W>,
-    W: Debug,
+{
+    type Config = SpiConfig;
+
+    fn configure(self, config: Self::Config) -> Response<Self, ()> {
+        Response::immediate(self, ())
+    }
+}
+
+impl<SPI, W> SpiBus for SpiController<SPI, W>
+where
+    SPI: Transfer<W> + Write<W>,
+    <SPI as Transfer<W>>::Error: Into<SpiError>,
+    <SPI as Write<W>>::Error: Into<SpiError>,
+    W: Debug + Default,
 {
     type Word = W;
 
@@ -92,4 +108,39 @@ where
         let result = self.spi.transfer(transfer.0).map_err(|e| e.into());
         Response::immediate(self, result.map(|_| ()))
     }
+
+    fn write(mut self, write: SpiWrite<Self::Word>) -> Response<Self, Result<(), SpiError>> {
+        let result = self.spi.write(write.0).map_err(|e| e.into());
+        Response::immediate(self, result)
+    }
+
+    fn read(mut self, read: SpiRead<Self::Word>) -> Response<Self, Result<(), SpiError>> {
+        let result = self.read_words(read.0);
+        Response::immediate(self, result)
+    }
+
+    fn write_read(
+        mut self,
+        write_read: SpiWriteRead<Self::Word>,
+    ) -> Response<Self, Result<(), SpiError>> {
+        let result = match self.spi.write(write_read.0) {
+            Ok(_) => self.read_words(write_read.1),
+            Err(e) => Err(e.into()),
+        };
+        Response::immediate(self, result)
+    }
+}
+
+impl<SPI, W> SpiController<SPI, W>
+where
+    SPI: Transfer<W>,
+    SPI::Error: Into<SpiError>,
+    W: Default,
+{
+    fn read_words(&mut self, buffer: &mut [W]) -> Result<(), SpiError> {
+        for word in buffer.iter_mut() {
+            *word = W::default();
+        }
+        self.spi.transfer(buffer).map(|_| ()).map_err(|e| e.into())
+    }
 }
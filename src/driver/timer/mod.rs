@@ -1,81 +1,217 @@
 use crate::api::delayer::{Delay, Delayer};
-use crate::api::scheduler::{Schedule, Scheduler};
+use crate::api::scheduler::{Schedule, ScheduleError, ScheduleId, Scheduler};
 use crate::arena::{Arena, Box};
 use crate::domain::time::duration::{Duration, Milliseconds};
-use crate::hal::timer::Timer as HalTimer;
+use crate::domain::time::{Clock, Instant};
+use crate::hal::timer::{Timer as HalTimer, WrappingClock};
 use crate::platform::with_critical_section;
 use crate::prelude::*;
-use crate::system::SystemArena;
+use crate::system::ActorArena;
 use core::cell::RefCell;
+use core::convert::TryFrom;
 use core::future::Future;
 use core::pin::Pin;
 use core::task::{Context, Poll, Waker};
 use cortex_m::interrupt::Nr;
+use heapless::consts::*;
 
-pub trait Schedulable {
+mod queue;
+
+pub use queue::{Capacity, TimeoutId};
+use queue::DeadlineQueue;
+
+pub trait Schedulable<C: Clock> {
+    fn id(&self) -> ScheduleId;
     fn run(&self);
-    fn get_expiration(&self) -> Milliseconds;
-    fn set_expiration(&mut self, expiration: Milliseconds);
+    fn get_period(&self) -> Option<Milliseconds>;
 }
 
-pub struct Shared {
-    current_deadline: RefCell<Option<Milliseconds>>,
-    delay_deadlines: RefCell<[Option<DelayDeadline>; 16]>,
-    schedule_deadlines: RefCell<[Option<Box<dyn Schedulable, SystemArena>>; 16]>,
+/// What a deadline of a timer stands for.
+pub enum Timeout<C: Clock + 'static> {
+    /// A pending delay, with the waker of its future once polled.
+    Delay(Option<Waker>),
+    Schedule(Box<dyn Schedulable<C>, ActorArena>),
 }
 
-impl Shared {
-    fn new() -> Self {
+pub struct Shared<C, N>
+where
+    C: Clock<T = u64> + 'static,
+    N: Capacity<Instant<C>, Timeout<C>> + 'static,
+{
+    clock: C,
+    current_deadline: RefCell<Option<Instant<C>>>,
+    deadlines: RefCell<DeadlineQueue<Instant<C>, Timeout<C>, N>>,
+}
+
+impl<C, N> Shared<C, N>
+where
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    fn new(clock: C) -> Self {
         Self {
+            clock,
             current_deadline: RefCell::new(None),
-            delay_deadlines: RefCell::new(Default::default()),
-            schedule_deadlines: RefCell::new(Default::default()),
+            deadlines: RefCell::new(DeadlineQueue::new()),
+        }
+    }
+
+    fn now(&self) -> Instant<C> {
+        self.clock.try_now().unwrap()
+    }
+
+    /// Whether the delay is over, which is when its deadline left the queue.
+    fn has_expired(&self, id: TimeoutId) -> bool {
+        !self.deadlines.borrow().contains(id)
+    }
+
+    fn register_waker(&self, id: TimeoutId, waker: Waker) {
+        if let Some(Timeout::Delay(current)) = self.deadlines.borrow_mut().get_mut(id) {
+            current.replace(waker);
+        }
+    }
+
+    fn add_delay(&self, expiration: Instant<C>) -> Option<TimeoutId> {
+        self.deadlines
+            .borrow_mut()
+            .insert(expiration, Timeout::Delay(None))
+            .ok()
+    }
+
+    fn remove_delay(&self, id: TimeoutId) {
+        self.deadlines.borrow_mut().remove(id);
+    }
+
+    /// Wake delays and run schedules due at `now`, soonest first.
+    fn expire(&self, now: Instant<C>) {
+        let mut deadlines = self.deadlines.borrow_mut();
+        while let Some((id, expiration)) = deadlines.peek() {
+            if expiration > now {
+                break;
+            }
+            let period = match deadlines.get_mut(id) {
+                Some(Timeout::Delay(waker)) => {
+                    if let Some(waker) = waker.take() {
+                        waker.wake();
+                    }
+                    None
+                }
+                Some(Timeout::Schedule(schedule)) => {
+                    schedule.run();
+                    schedule.get_period()
+                }
+                None => None,
+            };
+            match period {
+                Some(period) => {
+                    deadlines.set_deadline(id, next_firing(expiration, period, now));
+                }
+                None => {
+                    deadlines.remove(id);
+                }
+            }
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.deadlines.borrow().is_full()
+    }
+
+    fn add_schedule(
+        &self,
+        expiration: Instant<C>,
+        schedule: Box<dyn Schedulable<C>, ActorArena>,
+    ) -> Result<(), ScheduleError> {
+        self.deadlines
+            .borrow_mut()
+            .insert(expiration, Timeout::Schedule(schedule))
+            .map(|_| ())
+            .map_err(|_| ScheduleError::Full)
+    }
+
+    fn find_schedule(&self, id: ScheduleId) -> Option<TimeoutId> {
+        self.deadlines
+            .borrow()
+            .find(|timeout| matches!(timeout, Timeout::Schedule(s) if s.id() == id))
+    }
+
+    fn cancel(&self, id: ScheduleId) {
+        if let Some(id) = self.find_schedule(id) {
+            self.deadlines.borrow_mut().remove(id);
         }
     }
 
-    fn has_expired(&self, index: usize) -> bool {
-        let expired = self.delay_deadlines.borrow()[index]
-            .as_ref()
-            .unwrap()
-            .expiration
-            == Milliseconds(0u32);
-        if expired {
-            self.delay_deadlines.borrow_mut()[index].take();
+    fn reschedule(&self, id: ScheduleId, expiration: Instant<C>) {
+        if let Some(id) = self.find_schedule(id) {
+            self.deadlines.borrow_mut().set_deadline(id, expiration);
         }
-        expired
     }
 
-    fn register_waker(&self, index: usize, waker: Waker) {
-        self.delay_deadlines.borrow_mut()[index]
-            .as_mut()
-            .unwrap()
-            .waker
-            .replace(waker);
+    /// The soonest deadline still to come.
+    fn next_deadline(&self) -> Option<Instant<C>> {
+        self.deadlines
+            .borrow()
+            .peek()
+            .map(|(_, expiration)| expiration)
     }
 }
 
-impl Default for Shared {
-    fn default() -> Self {
-        Self::new()
+/// The deadline after `expiration` of a schedule firing every `period`, skipping the firings
+/// already missed at `now` rather than catching up on them in a burst.
+fn next_firing<C: Clock<T = u64>>(
+    expiration: Instant<C>,
+    period: Milliseconds,
+    now: Instant<C>,
+) -> Instant<C> {
+    let period = core::cmp::max(period, Milliseconds(1u32));
+    let mut next = expiration + period;
+    while next <= now {
+        next = next + period;
     }
+    next
 }
 
-pub struct Timer<T: HalTimer + 'static> {
-    actor: InterruptContext<TimerActor<T>>,
-    shared: Shared,
+/// A timer package, keeping deadlines as instants of the monotonic clock `C` and waking up
+/// with the hardware timer `T` as they come.
+///
+/// Delays and schedules share a queue of up to `N` deadlines, of which only the soonest runs
+/// the hardware timer, so there is no periodic tick.
+pub struct Timer<T, C, N = U32>
+where
+    T: HalTimer + 'static,
+    C: Clock<T = u64> + 'static,
+    N: Capacity<Instant<C>, Timeout<C>> + 'static,
+{
+    actor: InterruptContext<TimerActor<T, C, N>>,
+    shared: Shared<C, N>,
 }
 
-impl<T: HalTimer> Timer<T> {
-    pub fn new<IRQ: Nr>(timer: T, irq: IRQ) -> Self {
+impl<T, C, N> Timer<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    pub fn new<IRQ: Nr>(timer: T, clock: C, irq: IRQ) -> Self {
         Self {
             actor: InterruptContext::new(TimerActor::new(timer), irq).with_name("timer"),
-            shared: Shared::default(),
+            shared: Shared::new(clock),
         }
     }
+
+    /// The clock of the timer, to timestamp events and measure elapsed time.
+    pub fn clock(&'static self) -> &'static C {
+        &self.shared.clock
+    }
 }
 
-impl<T: HalTimer> Package for Timer<T> {
-    type Primary = TimerActor<T>;
+impl<T, C, N> Package for Timer<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    type Primary = TimerActor<T, C, N>;
     type Configuration = ();
 
     fn mount(
@@ -91,98 +227,164 @@ impl<T: HalTimer> Package for Timer<T> {
     }
 }
 
-pub struct TimerActor<T: HalTimer> {
+pub struct TimerActor<T, C, N = U32>
+where
+    T: HalTimer,
+    C: Clock<T = u64> + 'static,
+    N: Capacity<Instant<C>, Timeout<C>> + 'static,
+{
     timer: T,
-    shared: Option<&'static Shared>,
+    shared: Option<&'static Shared<C, N>>,
 }
 
-impl<T: HalTimer> TimerActor<T> {
+impl<T, C, N> TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
     fn new(timer: T) -> Self {
         Self {
             timer,
             shared: None,
         }
     }
+
+    /// Start the hardware timer for the soonest deadline, unless it already runs for that one.
+    fn arm(&mut self, now: Instant<C>) {
+        let shared = self.shared.unwrap();
+        let next_deadline = shared.next_deadline();
+        let mut current_deadline = shared.current_deadline.borrow_mut();
+        match next_deadline {
+            Some(deadline) if Some(deadline) != *current_deadline => {
+                current_deadline.replace(deadline);
+                // past what the hardware can count, it wakes up early and starts over
+                let duration = remaining(now, deadline);
+                self.timer
+                    .start(core::cmp::min(duration, self.timer.max_duration()));
+            }
+            Some(_) => {}
+            None => {
+                current_deadline.take();
+            }
+        }
+    }
+
+    fn delay_until(mut self, expiration: Instant<C>) -> Response<Self, ()> {
+        let shared = self.shared.unwrap();
+        if let Some(id) = shared.add_delay(expiration) {
+            self.arm(shared.now());
+            let future = DelayFuture::new(id, shared);
+            Response::immediate_future(self, future)
+        } else {
+            log::warn!("[{}] no free slot for delay", ActorInfo::name());
+            Response::immediate(self, ())
+        }
+    }
+}
+
+/// How long the hardware timer should run until `deadline`, at least a millisecond.
+fn remaining<C: Clock<T = u64>>(now: Instant<C>, deadline: Instant<C>) -> Milliseconds {
+    deadline
+        .checked_duration_since(&now)
+        .and_then(|duration| Milliseconds::<u32>::try_from(duration).ok())
+        .map_or(Milliseconds(1u32), |ms| {
+            core::cmp::max(ms, Milliseconds(1u32))
+        })
 }
 
-impl<T: HalTimer> Scheduler for TimerActor<T> {
-    fn schedule<A, DUR, E>(&mut self, message: Schedule<A, DUR, E>)
+impl<T, C, N> Scheduler for TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    fn schedule<A, DUR, E>(&mut self, message: Schedule<A, DUR, E>) -> Result<(), ScheduleError>
     where
         A: Actor + NotifyHandler<E> + 'static,
         DUR: Duration + Into<Milliseconds> + 'static,
         E: Clone + 'static,
     {
-        let ms: Milliseconds = message.delay.into();
-        // log::info!("schedule request {:?}", ms);
-        let mut deadlines = self.shared.unwrap().schedule_deadlines.borrow_mut();
-        let mut current_deadline = self.shared.unwrap().current_deadline.borrow_mut();
-
-        if let Some((index, slot)) = deadlines
-            .iter_mut()
-            .enumerate()
-            .find(|e| matches!(e, (_, None)))
-        {
-            deadlines[index].replace(Box::new(
-                SystemArena::alloc(ScheduleDeadline::new(ms, message)).unwrap(),
-            ));
-            if let Some(current) = &*current_deadline {
-                if *current > ms {
-                    current_deadline.replace(ms);
-                    self.timer.start(ms);
-                } else {
-                    //log::info!("timer already running for {:?}", current_deadline );
-                }
-            } else {
-                current_deadline.replace(ms);
-                //log::info!("start new timer for {:?}", ms);
-                self.timer.start(ms);
-            }
+        let shared = self.shared.unwrap();
+        if shared.is_full() {
+            return Err(ScheduleError::Full);
         }
+        let now = shared.now();
+        let ms: Milliseconds = message.delay.into();
+        let expiration = now + ms;
+        let schedule = ActorArena::try_alloc(ScheduleDeadline::new(message))
+            .map_err(ScheduleError::Exhausted)?;
+        shared.add_schedule(expiration, Box::new(schedule))?;
+        self.arm(now);
+        Ok(())
+    }
+
+    fn cancel(&mut self, id: ScheduleId) {
+        let shared = self.shared.unwrap();
+        shared.cancel(id);
+        self.arm(shared.now());
+    }
+
+    fn reschedule(&mut self, id: ScheduleId, delay: Milliseconds) {
+        let shared = self.shared.unwrap();
+        let now = shared.now();
+        shared.reschedule(id, now + delay);
+        self.arm(now);
     }
 }
 
-impl<T: HalTimer> Delayer for TimerActor<T> {
-    fn delay<DUR>(mut self, message: Delay<DUR>) -> Response<Self, ()>
+impl<T, C, N> Delayer for TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    fn delay<DUR>(self, message: Delay<DUR>) -> Response<Self, ()>
     where
         DUR: Duration + Into<Milliseconds> + 'static,
     {
         let ms: Milliseconds = message.0.into();
+        let expiration = self.shared.unwrap().now() + ms;
+        self.delay_until(expiration)
+    }
+}
 
-        let mut delay_deadlines = self.shared.unwrap().delay_deadlines.borrow_mut();
-        if let Some((index, slot)) = delay_deadlines
-            .iter_mut()
-            .enumerate()
-            .find(|e| matches!(e, (_, None)))
-        {
-            delay_deadlines[index].replace(DelayDeadline::new(ms));
-            let mut current_deadline = self.shared.unwrap().current_deadline.borrow_mut();
-            let (new_deadline, should_replace) = if let Some(current_deadline) = *current_deadline {
-                if current_deadline > ms {
-                    //log::info!("start shorter timer for {:?}", ms);
-                    (ms, true)
-                } else {
-                    //log::info!("timer already running for {:?}", current_deadline );
-                    (ms, false)
-                }
-            } else {
-                //log::info!("start new timer for {:?}", ms);
-                (ms, true)
-            };
+/// Delay until an instant of the clock of the timer.
+#[derive(Copy, Clone)]
+pub struct DelayUntil<C: Clock>(pub Instant<C>);
 
-            if should_replace {
-                current_deadline.replace(new_deadline);
-                self.timer.start(new_deadline);
-            }
-            let future = DelayFuture::new(index, self.shared.as_ref().unwrap());
-            Response::immediate_future(self, future)
-        } else {
-            Response::immediate(self, ())
-        }
+impl<T, C, N> RequestHandler<DelayUntil<C>> for TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    type Response = ();
+
+    fn on_request(self, message: DelayUntil<C>) -> Response<Self, Self::Response> {
+        self.delay_until(message.0)
     }
 }
 
-impl<T: HalTimer> Actor for TimerActor<T> {
-    type Configuration = &'static Shared;
+impl<T, C, N> Address<TimerActor<T, C, N>>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    /// Delay until `deadline`, which unlike a relative delay does not drift when repeated.
+    pub async fn delay_until(&self, deadline: Instant<C>) {
+        self.request(DelayUntil(deadline)).await
+    }
+}
+
+impl<T, C, N> Actor for TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    type Configuration = &'static Shared<C, N>;
 
     fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
     where
@@ -192,95 +394,55 @@ impl<T: HalTimer> Actor for TimerActor<T> {
     }
 }
 
-impl<T: HalTimer> Interrupt for TimerActor<T> {
+impl<T, C, N> Interrupt for TimerActor<T, C, N>
+where
+    T: HalTimer,
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
     fn on_interrupt(&mut self) {
         self.timer.clear_update_interrupt_flag();
-        let expired = self.shared.unwrap().current_deadline.borrow().unwrap();
-
-        let mut delay_deadlines = self.shared.unwrap().delay_deadlines.borrow_mut();
-
-        let mut next_deadline = None;
-        //log::info!("timer expired! {:?}", expired);
-        for slot in delay_deadlines.iter_mut() {
-            if let Some(deadline) = slot {
-                if deadline.expiration >= expired {
-                    deadline.expiration = deadline.expiration - expired;
-                } else {
-                    deadline.expiration = Milliseconds(0u32);
-                }
-
-                if deadline.expiration == Milliseconds(0u32) {
-                    if let Some(waker) = deadline.waker.take() {
-                        waker.wake();
-                    }
-                } else {
-                    match next_deadline {
-                        None => {
-                            next_deadline.replace(deadline.expiration);
-                        }
-                        Some(soonest) if soonest > deadline.expiration => {
-                            next_deadline.replace(deadline.expiration);
-                        }
-                        _ => { /* ignore */ }
-                    }
-                }
-            }
-        }
-
-        let mut schedule_deadlines = self.shared.unwrap().schedule_deadlines.borrow_mut();
-
-        for slot in schedule_deadlines.iter_mut() {
-            if let Some(deadline) = slot {
-                let expiration = deadline.get_expiration();
-                if expiration >= expired {
-                    deadline.set_expiration(expiration - expired);
-                } else {
-                    deadline.set_expiration(Milliseconds(0u32));
-                }
+        let shared = self.shared.unwrap();
+        let now = shared.now();
+        shared.expire(now);
+        shared.current_deadline.borrow_mut().take();
+        self.arm(now);
+    }
+}
 
-                if deadline.get_expiration() == Milliseconds(0u32) {
-                    deadline.run();
-                    slot.take();
-                } else {
-                    match next_deadline {
-                        None => {
-                            next_deadline.replace(deadline.get_expiration());
-                        }
-                        Some(soonest) if soonest > deadline.get_expiration() => {
-                            next_deadline.replace(deadline.get_expiration());
-                        }
-                        _ => { /* ignore */ }
-                    }
-                }
-            }
-        }
+/// Counts the wraps of a clock in the interrupt of its hardware, mounted with the clock of a
+/// timer after the timer itself, so that the clock does not lose them while unread.
+pub struct ClockInterrupt<C: WrappingClock + 'static> {
+    clock: Option<&'static C>,
+}
 
-        let mut current_deadline = self.shared.unwrap().current_deadline.borrow_mut();
-        //log::info!("next deadline {:?}", next_deadline );
+impl<C: WrappingClock> ClockInterrupt<C> {
+    pub fn new() -> Self {
+        Self { clock: None }
+    }
+}
 
-        if let Some(next_deadline) = next_deadline {
-            if next_deadline > Milliseconds(0u32) {
-                current_deadline.replace(next_deadline);
-                self.timer.start(next_deadline);
-            } else {
-                current_deadline.take();
-            }
-        } else {
-            current_deadline.take();
-        }
+impl<C: WrappingClock> Default for ClockInterrupt<C> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-struct DelayDeadline {
-    expiration: Milliseconds,
-    waker: Option<Waker>,
+impl<C: WrappingClock> Actor for ClockInterrupt<C> {
+    type Configuration = &'static C;
+
+    fn on_mount(&mut self, address: Address<Self>, config: Self::Configuration)
+    where
+        Self: Sized,
+    {
+        self.clock.replace(config);
+    }
 }
 
-impl DelayDeadline {
-    fn new(expiration: Milliseconds) -> Self {
-        Self {
-            expiration,
-            waker: None,
+impl<C: WrappingClock> Interrupt for ClockInterrupt<C> {
+    fn on_interrupt(&mut self) {
+        if let Some(clock) = self.clock {
+            clock.on_wrap();
         }
     }
 }
@@ -291,26 +453,26 @@ where
     DUR: Duration + Into<Milliseconds>,
     E: Clone + 'static,
 {
-    expiration: Milliseconds,
     schedule: Schedule<A, DUR, E>,
 }
 
-impl<A, DUR, E> Schedulable for ScheduleDeadline<A, DUR, E>
+impl<A, DUR, E, C> Schedulable<C> for ScheduleDeadline<A, DUR, E>
 where
     A: Actor + NotifyHandler<E> + 'static,
     DUR: Duration + Into<Milliseconds>,
     E: Clone + 'static,
+    C: Clock,
 {
-    fn run(&self) {
-        self.schedule.address.notify(self.schedule.event.clone());
+    fn id(&self) -> ScheduleId {
+        self.schedule.id()
     }
 
-    fn get_expiration(&self) -> Milliseconds {
-        self.expiration
+    fn run(&self) {
+        self.schedule.address.notify(self.schedule.event.clone());
     }
 
-    fn set_expiration(&mut self, expiration: Milliseconds) {
-        self.expiration = expiration;
+    fn get_period(&self) -> Option<Milliseconds> {
+        self.schedule.period
     }
 }
 
@@ -320,24 +482,29 @@ impl<
         E: Clone + 'static,
     > ScheduleDeadline<A, DUR, E>
 {
-    fn new(expiration: Milliseconds, schedule: Schedule<A, DUR, E>) -> Self {
-        Self {
-            expiration,
-            schedule,
-        }
+    fn new(schedule: Schedule<A, DUR, E>) -> Self {
+        Self { schedule }
     }
 }
 
-struct DelayFuture {
-    index: usize,
-    shared: &'static Shared,
+struct DelayFuture<C, N>
+where
+    C: Clock<T = u64> + 'static,
+    N: Capacity<Instant<C>, Timeout<C>> + 'static,
+{
+    id: TimeoutId,
+    shared: &'static Shared<C, N>,
     expired: bool,
 }
 
-impl DelayFuture {
-    fn new(index: usize, shared: &'static Shared) -> Self {
+impl<C, N> DelayFuture<C, N>
+where
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    fn new(id: TimeoutId, shared: &'static Shared<C, N>) -> Self {
         Self {
-            index,
+            id,
             shared,
             expired: false,
         }
@@ -346,31 +513,316 @@ impl DelayFuture {
     fn has_expired(&mut self) -> bool {
         if !self.expired {
             // critical section to avoid being trampled by the timer's own IRQ
-            self.expired = with_critical_section(|cs| self.shared.has_expired(self.index))
+            self.expired = with_critical_section(|cs| self.shared.has_expired(self.id))
         }
 
         self.expired
     }
 
     fn register_waker(&self, waker: &Waker) {
-        //unsafe {
-        //(&mut **self.timer.get()).register_waker(self.index, waker.clone());
-        //}
-        self.shared.register_waker(self.index, waker.clone());
+        with_critical_section(|cs| self.shared.register_waker(self.id, waker.clone()));
     }
 }
 
-impl Future for DelayFuture {
+impl<C, N> Drop for DelayFuture<C, N>
+where
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
+    /// A delay given up on, as a timeout that did not happen, frees its deadline right away.
+    fn drop(&mut self) {
+        if !self.expired {
+            with_critical_section(|cs| self.shared.remove_delay(self.id));
+        }
+    }
+}
+
+impl<C, N> Future for DelayFuture<C, N>
+where
+    C: Clock<T = u64>,
+    N: Capacity<Instant<C>, Timeout<C>>,
+{
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         if self.has_expired() {
-            //log::info!("delay poll - ready {}", self.index);
             Poll::Ready(())
         } else {
-            //log::info!("delay poll - pending {}", self.index);
             self.register_waker(cx.waker());
             Poll::Pending
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::domain::time::clock::Error;
+    use crate::domain::time::fraction::Fraction;
+    use core::cell::Cell;
+    use std::boxed::Box as StdBox;
+    use std::rc::Rc;
+    use std::sync::MutexGuard;
+    use std::vec::Vec;
+
+    /// A millisecond clock on the host, moved forward by the test.
+    struct TestClock(Cell<u64>);
+
+    impl TestClock {
+        fn advance(&self, ms: u64) {
+            self.0.set(self.0.get() + ms);
+        }
+    }
+
+    impl Clock for TestClock {
+        type T = u64;
+        const SCALING_FACTOR: Fraction = Fraction::new(1, 1_000);
+
+        fn try_now(&self) -> Result<Instant<Self>, Error> {
+            Ok(Instant::new(self.0.get()))
+        }
+    }
+
+    /// The test clock counts as though it were a 16-bit counter of milliseconds.
+    impl WrappingClock for TestClock {
+        fn on_wrap(&self) {
+            self.advance(1 << 16);
+        }
+    }
+
+    #[derive(Default)]
+    struct TestTimer {
+        started: Vec<Milliseconds>,
+    }
+
+    impl HalTimer for TestTimer {
+        fn start(&mut self, duration: Milliseconds) {
+            self.started.push(duration);
+        }
+
+        fn clear_update_interrupt_flag(&mut self) {}
+
+        fn max_duration(&self) -> Milliseconds {
+            Milliseconds(1_000)
+        }
+    }
+
+    fn setup<N: Capacity<Instant<TestClock>, Timeout<TestClock>>>() -> (
+        TimerActor<TestTimer, TestClock, N>,
+        &'static Shared<TestClock, N>,
+    ) {
+        let shared = StdBox::leak(StdBox::new(Shared::new(TestClock(Cell::new(1_000)))));
+        let mut actor = TimerActor::new(TestTimer::default());
+        actor.shared.replace(shared);
+        (actor, shared)
+    }
+
+    #[test]
+    fn test_deadlines_are_absolute() {
+        let (mut actor, shared) = setup::<U16>();
+        let now = shared.now();
+        let first = shared.add_delay(now + Milliseconds(100u32)).unwrap();
+        actor.arm(now);
+        let second = shared.add_delay(now + Milliseconds(50u32)).unwrap();
+        actor.arm(now);
+        assert_eq!(
+            actor.timer.started,
+            [Milliseconds(100u32), Milliseconds(50u32)]
+        );
+
+        // the interrupt comes late, which does not shift the remaining deadline
+        shared.clock.advance(60);
+        actor.on_interrupt();
+        assert!(shared.has_expired(second));
+        assert!(!shared.has_expired(first));
+        assert_eq!(actor.timer.started[2], Milliseconds(40u32));
+
+        shared.clock.advance(40);
+        actor.on_interrupt();
+        assert!(shared.has_expired(first));
+        assert_eq!(actor.timer.started.len(), 3);
+        assert!(shared.current_deadline.borrow().is_none());
+    }
+
+    #[test]
+    fn test_early_interrupt_rearms() {
+        let (mut actor, shared) = setup::<U16>();
+        let now = shared.now();
+        let delay = shared.add_delay(now + Milliseconds(10u32)).unwrap();
+        actor.arm(now);
+
+        shared.clock.advance(9);
+        actor.on_interrupt();
+        assert!(!shared.has_expired(delay));
+        assert_eq!(
+            actor.timer.started,
+            [Milliseconds(10u32), Milliseconds(1u32)]
+        );
+
+        shared.clock.advance(1);
+        actor.on_interrupt();
+        assert!(shared.has_expired(delay));
+    }
+
+    #[test]
+    fn test_long_deadlines_are_split() {
+        let (mut actor, shared) = setup::<U16>();
+        let now = shared.now();
+        let delay = shared.add_delay(now + Milliseconds(2_500u32)).unwrap();
+        actor.arm(now);
+
+        for _ in 0..2 {
+            shared.clock.advance(1_000);
+            actor.on_interrupt();
+            assert!(!shared.has_expired(delay));
+        }
+        assert_eq!(
+            actor.timer.started,
+            [
+                Milliseconds(1_000u32),
+                Milliseconds(1_000u32),
+                Milliseconds(500u32)
+            ]
+        );
+
+        shared.clock.advance(500);
+        actor.on_interrupt();
+        assert!(shared.has_expired(delay));
+    }
+
+    #[test]
+    fn test_dropped_delay_frees_deadline() {
+        let (_, shared) = setup::<U16>();
+        let id = shared.add_delay(shared.now() + Milliseconds(10u32)).unwrap();
+        let future = DelayFuture::new(id, shared);
+        assert_eq!(shared.deadlines.borrow().len(), 1);
+        drop(future);
+        assert!(shared.deadlines.borrow().is_empty());
+    }
+
+    /// Counts its firings instead of notifying an actor.
+    struct TestSchedule {
+        id: ScheduleId,
+        period: Option<Milliseconds>,
+        runs: Rc<Cell<usize>>,
+    }
+
+    impl Schedulable<TestClock> for TestSchedule {
+        fn id(&self) -> ScheduleId {
+            self.id
+        }
+
+        fn run(&self) {
+            self.runs.set(self.runs.get() + 1);
+        }
+
+        fn get_period(&self) -> Option<Milliseconds> {
+            self.period
+        }
+    }
+
+    /// The system arena holds the schedules, and is not meant to be shared between threads.
+    fn arena() -> MutexGuard<'static, ()> {
+        crate::system::mock::lock()
+    }
+
+    fn add_schedule<N: Capacity<Instant<TestClock>, Timeout<TestClock>>>(
+        shared: &Shared<TestClock, N>,
+        delay: u32,
+        period: Option<u32>,
+    ) -> Result<(ScheduleId, Rc<Cell<usize>>), ScheduleError> {
+        let id = ScheduleId::next();
+        let runs = Rc::new(Cell::new(0));
+        let schedule = ActorArena::alloc(TestSchedule {
+            id,
+            period: period.map(Milliseconds),
+            runs: runs.clone(),
+        })
+        .unwrap();
+        shared.add_schedule(shared.now() + Milliseconds(delay), Box::new(schedule))?;
+        Ok((id, runs))
+    }
+
+    #[test]
+    fn test_periodic_does_not_drift() {
+        let _arena = arena();
+        let (mut actor, shared) = setup::<U16>();
+        let (_, runs) = add_schedule(shared, 100, Some(100)).unwrap();
+        actor.arm(shared.now());
+
+        // a late interrupt keeps the next firing on the original grid
+        shared.clock.advance(130);
+        actor.on_interrupt();
+        assert_eq!(runs.get(), 1);
+        assert_eq!(actor.timer.started[1], Milliseconds(70u32));
+
+        // firings missed altogether are skipped rather than bunched up
+        shared.clock.advance(300);
+        actor.on_interrupt();
+        assert_eq!(runs.get(), 2);
+        assert_eq!(actor.timer.started[2], Milliseconds(70u32));
+        assert_eq!(shared.deadlines.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_cancel_and_reschedule() {
+        let _arena = arena();
+        let (mut actor, shared) = setup::<U16>();
+        let (once, once_runs) = add_schedule(shared, 50, None).unwrap();
+        let (periodic, periodic_runs) = add_schedule(shared, 20, Some(20)).unwrap();
+
+        actor.reschedule(once, Milliseconds(10));
+        actor.cancel(periodic);
+        assert_eq!(actor.timer.started, [Milliseconds(10u32)]);
+
+        shared.clock.advance(10);
+        actor.on_interrupt();
+        assert_eq!(once_runs.get(), 1);
+        assert_eq!(periodic_runs.get(), 0);
+        assert!(shared.deadlines.borrow().is_empty());
+        assert!(shared.current_deadline.borrow().is_none());
+
+        // cancelling what already fired does nothing
+        actor.cancel(once);
+    }
+
+    #[test]
+    fn test_full() {
+        let _arena = arena();
+        let (_, shared) = setup::<U2>();
+        // delays and schedules take from the same deadlines
+        assert!(shared.add_delay(shared.now()).is_some());
+        assert!(add_schedule(shared, 10, None).is_ok());
+        assert!(shared.is_full());
+        assert_eq!(
+            add_schedule(shared, 10, None).err(),
+            Some(ScheduleError::Full)
+        );
+    }
+
+    #[test]
+    fn test_remaining() {
+        let now = Instant::<TestClock>::new(1_000);
+        assert_eq!(
+            remaining(now, now + Milliseconds(25u32)),
+            Milliseconds(25u32)
+        );
+        assert_eq!(remaining(now, now), Milliseconds(1u32));
+        assert_eq!(remaining(now + Milliseconds(5u32), now), Milliseconds(1u32));
+    }
+
+    #[test]
+    fn test_clock_interrupt() {
+        let clock = StdBox::leak(StdBox::new(TestClock(Cell::new(1_000))));
+        // interrupts before the clock is mounted are ignored
+        ClockInterrupt::<TestClock>::new().on_interrupt();
+
+        let mut interrupt = ClockInterrupt {
+            clock: Some(&*clock),
+        };
+        interrupt.on_interrupt();
+        assert_eq!(clock.0.get(), 1_000 + (1 << 16));
+    }
+}
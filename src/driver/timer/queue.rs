@@ -0,0 +1,324 @@
+//! A queue of deadlines, ordered by a binary min-heap.
+//!
+//! Entries live in a slab of slots and the heap holds slot indices, each occupied slot knowing
+//! its place in the heap. Insertion, removal and moving a deadline are all `O(log n)`, and
+//! the soonest deadline is at hand in constant time.
+use heapless::{ArrayLength, Vec};
+
+/// Refers to an entry of a queue, for as long as the entry stays in it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeoutId {
+    index: u16,
+    generation: u16,
+}
+
+pub struct Slot<D, V> {
+    /// Bumped whenever the slot is freed, so stale ids do not refer to a new entry.
+    generation: u16,
+    state: State<D, V>,
+}
+
+enum State<D, V> {
+    Occupied {
+        deadline: D,
+        value: V,
+        position: u16,
+    },
+    Vacant {
+        next: Option<u16>,
+    },
+}
+
+/// Room for `N` entries of a queue.
+pub trait Capacity<D, V>: ArrayLength<Slot<D, V>> + ArrayLength<u16> {}
+
+impl<D, V, N> Capacity<D, V> for N where N: ArrayLength<Slot<D, V>> + ArrayLength<u16> {}
+
+pub struct DeadlineQueue<D, V, N>
+where
+    D: Ord + Copy,
+    N: Capacity<D, V>,
+{
+    slots: Vec<Slot<D, V>, N>,
+    heap: Vec<u16, N>,
+    free: Option<u16>,
+}
+
+impl<D, V, N> DeadlineQueue<D, V, N>
+where
+    D: Ord + Copy,
+    N: Capacity<D, V>,
+{
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            heap: Vec::new(),
+            free: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.heap.len() == self.heap.capacity()
+    }
+
+    /// Add `value` due at `deadline`, handing it back when the queue is full.
+    pub fn insert(&mut self, deadline: D, value: V) -> Result<TimeoutId, V> {
+        let position = self.heap.len() as u16;
+        let state = State::Occupied {
+            deadline,
+            value,
+            position,
+        };
+        let index = match self.free {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                if let State::Vacant { next } = slot.state {
+                    self.free = next;
+                }
+                slot.state = state;
+                index
+            }
+            None => {
+                let index = self.slots.len() as u16;
+                if let Err(slot) = self.slots.push(Slot {
+                    generation: 0,
+                    state,
+                }) {
+                    return match slot.state {
+                        State::Occupied { value, .. } => Err(value),
+                        State::Vacant { .. } => unreachable!(),
+                    };
+                }
+                index
+            }
+        };
+        self.heap.push(index).ok();
+        self.sift_up(position as usize);
+        Ok(TimeoutId {
+            index,
+            generation: self.slots[index as usize].generation,
+        })
+    }
+
+    pub fn contains(&self, id: TimeoutId) -> bool {
+        self.position(id).is_some()
+    }
+
+    pub fn get_mut(&mut self, id: TimeoutId) -> Option<&mut V> {
+        self.position(id)?;
+        match &mut self.slots[id.index as usize].state {
+            State::Occupied { value, .. } => Some(value),
+            State::Vacant { .. } => None,
+        }
+    }
+
+    /// The entry with a value matching `predicate`, searching all of them.
+    pub fn find<F: Fn(&V) -> bool>(&self, predicate: F) -> Option<TimeoutId> {
+        self.slots
+            .iter()
+            .enumerate()
+            .find_map(|(index, slot)| match &slot.state {
+                State::Occupied { value, .. } if predicate(value) => Some(TimeoutId {
+                    index: index as u16,
+                    generation: slot.generation,
+                }),
+                _ => None,
+            })
+    }
+
+    /// The soonest entry.
+    pub fn peek(&self) -> Option<(TimeoutId, D)> {
+        let index = *self.heap.first()?;
+        let slot = &self.slots[index as usize];
+        Some((
+            TimeoutId {
+                index,
+                generation: slot.generation,
+            },
+            self.deadline(index),
+        ))
+    }
+
+    pub fn remove(&mut self, id: TimeoutId) -> Option<V> {
+        let position = self.position(id)? as usize;
+        let last = self.heap.len() - 1;
+        self.swap(position, last);
+        self.heap.pop();
+        if position < last {
+            self.sift_down(position);
+            self.sift_up(position);
+        }
+
+        let slot = &mut self.slots[id.index as usize];
+        slot.generation = slot.generation.wrapping_add(1);
+        let state = core::mem::replace(&mut slot.state, State::Vacant { next: self.free });
+        self.free.replace(id.index);
+        match state {
+            State::Occupied { value, .. } => Some(value),
+            State::Vacant { .. } => None,
+        }
+    }
+
+    /// Move the entry to `deadline`, returning whether it is still in the queue.
+    pub fn set_deadline(&mut self, id: TimeoutId, deadline: D) -> bool {
+        let position = match self.position(id) {
+            Some(position) => position as usize,
+            None => return false,
+        };
+        if let State::Occupied { deadline: d, .. } = &mut self.slots[id.index as usize].state {
+            *d = deadline;
+        }
+        self.sift_down(position);
+        self.sift_up(position);
+        true
+    }
+
+    fn position(&self, id: TimeoutId) -> Option<u16> {
+        match self.slots.get(id.index as usize) {
+            Some(Slot {
+                generation,
+                state: State::Occupied { position, .. },
+            }) if *generation == id.generation => Some(*position),
+            _ => None,
+        }
+    }
+
+    fn deadline(&self, index: u16) -> D {
+        match &self.slots[index as usize].state {
+            State::Occupied { deadline, .. } => *deadline,
+            State::Vacant { .. } => unreachable!(),
+        }
+    }
+
+    fn less(&self, a: usize, b: usize) -> bool {
+        self.deadline(self.heap[a]) < self.deadline(self.heap[b])
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        for position in [a, b].iter() {
+            let index = self.heap[*position];
+            if let State::Occupied { position: p, .. } = &mut self.slots[index as usize].state {
+                *p = *position as u16;
+            }
+        }
+    }
+
+    fn sift_up(&mut self, mut position: usize) {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if !self.less(position, parent) {
+                break;
+            }
+            self.swap(position, parent);
+            position = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut position: usize) {
+        loop {
+            let mut smallest = position;
+            for child in [2 * position + 1, 2 * position + 2].iter() {
+                if *child < self.heap.len() && self.less(*child, smallest) {
+                    smallest = *child;
+                }
+            }
+            if smallest == position {
+                break;
+            }
+            self.swap(position, smallest);
+            position = smallest;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use heapless::consts::*;
+    use std::vec::Vec as StdVec;
+
+    fn drain(queue: &mut DeadlineQueue<u32, u32, U64>) -> StdVec<u32> {
+        let mut values = StdVec::new();
+        while let Some((id, _)) = queue.peek() {
+            values.push(queue.remove(id).unwrap());
+        }
+        values
+    }
+
+    #[test]
+    fn test_ordered_by_deadline() {
+        let mut queue = DeadlineQueue::<u32, u32, U64>::new();
+        for deadline in [50, 10, 40, 30, 20, 60, 5].iter() {
+            queue.insert(*deadline, *deadline).unwrap();
+        }
+        assert_eq!(queue.peek().map(|(_, deadline)| deadline), Some(5));
+        assert_eq!(drain(&mut queue), [5, 10, 20, 30, 40, 50, 60]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_remove_and_move() {
+        let mut queue = DeadlineQueue::<u32, u32, U64>::new();
+        let ids: StdVec<_> = (0..20)
+            .map(|value| queue.insert(value * 10, value).unwrap())
+            .collect();
+
+        assert_eq!(queue.remove(ids[7]), Some(7));
+        assert_eq!(queue.remove(ids[7]), None);
+        assert!(queue.set_deadline(ids[15], 1));
+        assert!(queue.set_deadline(ids[0], 1000));
+        assert!(!queue.set_deadline(ids[7], 5));
+        assert_eq!(queue.find(|value| *value == 3), Some(ids[3]));
+
+        let values = drain(&mut queue);
+        assert_eq!(values.len(), 19);
+        assert_eq!(values[0], 15);
+        assert_eq!(values[18], 0);
+    }
+
+    #[test]
+    fn test_many() {
+        let mut queue = DeadlineQueue::<u32, u32, U2048>::new();
+        let mut deadline = 1u32;
+        for value in 0..2048 {
+            // scattered, with plenty of equal deadlines
+            deadline = deadline.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            queue.insert(deadline % 5_000, value).unwrap();
+        }
+        assert!(queue.is_full());
+
+        let mut last = 0;
+        while let Some((id, deadline)) = queue.peek() {
+            assert!(deadline >= last);
+            last = deadline;
+            queue.remove(id);
+        }
+    }
+
+    #[test]
+    fn test_slots_are_reused() {
+        let mut queue = DeadlineQueue::<u32, u32, U2>::new();
+        let first = queue.insert(1, 1).unwrap();
+        queue.insert(2, 2).unwrap();
+        assert!(queue.is_full());
+        assert_eq!(queue.insert(3, 3), Err(3));
+
+        queue.remove(first);
+        let third = queue.insert(3, 3).unwrap();
+        // the slot is the same, the id is not
+        assert!(!queue.contains(first));
+        assert!(queue.contains(third));
+        assert_eq!(queue.get_mut(third), Some(&mut 3));
+    }
+}
@@ -0,0 +1,81 @@
+//! Framing of UART traffic, shared by drivers of line-oriented devices such as AT modems.
+
+use crate::api::uart::{Error, UartReader, UartWriter};
+use crate::prelude::*;
+use heapless::{ArrayLength, Vec};
+
+const CRLF: &[u8] = b"\r\n";
+
+/// Reads and writes lines ending in `\r\n` over a UART, into a buffer of `N` bytes.
+///
+/// Empty lines, which many modems send around their responses, are skipped when reading.
+pub struct LineCodec<U, N>
+where
+    U: UartReader + UartWriter + 'static,
+    N: ArrayLength<u8>,
+{
+    uart: Address<U>,
+    buffer: Vec<u8, N>,
+}
+
+impl<U, N> LineCodec<U, N>
+where
+    U: UartReader + UartWriter + 'static,
+    N: ArrayLength<u8>,
+{
+    pub fn new(uart: Address<U>) -> Self {
+        Self {
+            uart,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn uart(&self) -> Address<U> {
+        self.uart
+    }
+
+    /// Write `line`, followed by `\r\n`.
+    pub async fn write_line(&self, line: &[u8]) -> Result<(), Error> {
+        self.uart.write(line).await?;
+        self.uart.write(CRLF).await
+    }
+
+    /// Write `data` as is, such as the payload following a prompt.
+    pub async fn write(&self, data: &[u8]) -> Result<(), Error> {
+        self.uart.write(data).await
+    }
+
+    /// Read the next non-empty line, without its line ending. Fails with `RxBufferTooSmall`
+    /// if the line does not fit.
+    pub async fn read_line(&mut self) -> Result<&[u8], Error> {
+        loop {
+            self.fill();
+            let len = self.uart.read_line(&mut self.buffer).await?;
+            if len > 0 {
+                return Ok(&self.buffer[..len]);
+            }
+        }
+    }
+
+    /// Read up to and including `delimiter`, such as the `> ` prompt for data.
+    pub async fn read_until(&mut self, delimiter: &[u8]) -> Result<&[u8], Error> {
+        self.fill();
+        let len = self.uart.read_until(&mut self.buffer, delimiter).await?;
+        Ok(&self.buffer[..len])
+    }
+
+    /// Read exactly `len` bytes, such as a payload of known length.
+    pub async fn read_exact(&mut self, len: usize) -> Result<&[u8], Error> {
+        if len > self.buffer.capacity() {
+            return Err(Error::RxBufferTooSmall);
+        }
+        self.fill();
+        self.uart.read_exact(&mut self.buffer[..len]).await?;
+        Ok(&self.buffer[..len])
+    }
+
+    fn fill(&mut self) {
+        let capacity = self.buffer.capacity();
+        self.buffer.resize_default(capacity).ok();
+    }
+}
@@ -1,6 +1,6 @@
 use crate::api::uart::Error;
 use crate::synchronization::Signal;
-use crate::util::dma::async_bbqueue::*;
+use crate::util::dma::async_bbqueue::{Error as QueueError, *};
 use core::future::Future;
 use core::pin::Pin;
 use core::sync::atomic::AtomicBool;
@@ -88,26 +88,49 @@ where
     }
 }
 
-pub struct RxFuture<'a, RXN>
+/// A read from the rx queue, which a timeout may cut short.
+pub trait RxRead: Future<Output = Result<usize, QueueError>> + Unpin {
+    fn cancel(&mut self);
+}
+
+impl<RXN> RxRead for AsyncRead<RXN>
 where
     RXN: ArrayLength<u8> + 'static,
 {
-    future: AsyncRead<RXN>,
-    shared: &'a ActorState,
+    fn cancel(&mut self) {
+        AsyncRead::cancel(self)
+    }
 }
 
-impl<'a, RXN> RxFuture<'a, RXN>
+impl<RXN> RxRead for AsyncReadUntil<RXN>
 where
     RXN: ArrayLength<u8> + 'static,
 {
-    pub fn new(future: AsyncRead<RXN>, shared: &'a ActorState) -> Self {
+    fn cancel(&mut self) {
+        AsyncReadUntil::cancel(self)
+    }
+}
+
+pub struct RxFuture<'a, F>
+where
+    F: RxRead,
+{
+    future: F,
+    shared: &'a ActorState,
+}
+
+impl<'a, F> RxFuture<'a, F>
+where
+    F: RxRead,
+{
+    pub fn new(future: F, shared: &'a ActorState) -> Self {
         Self { future, shared }
     }
 }
 
-impl<'a, RXN> Future for RxFuture<'a, RXN>
+impl<'a, F> Future for RxFuture<'a, F>
 where
-    RXN: ArrayLength<u8> + 'static,
+    F: RxRead,
 {
     type Output = Result<usize, Error>;
 
@@ -119,9 +142,42 @@ where
         match Future::poll(Pin::new(&mut self.future), cx) {
             Poll::Ready(result) => {
                 self.shared.set_rx_ready();
-                return Poll::Ready(result.map_err(|_| Error::Receive));
+                return Poll::Ready(result.map_err(|e| match e {
+                    QueueError::DelimiterNotFound => Error::RxBufferTooSmall,
+                    _ => Error::Receive,
+                }));
             }
             Poll::Pending => Poll::Pending,
         }
     }
 }
+
+/// A read completing once the whole buffer is filled.
+pub struct RxExactFuture<'a, RXN>
+where
+    RXN: ArrayLength<u8> + 'static,
+{
+    future: RxFuture<'a, AsyncRead<RXN>>,
+}
+
+impl<'a, RXN> RxExactFuture<'a, RXN>
+where
+    RXN: ArrayLength<u8> + 'static,
+{
+    pub fn new(future: AsyncRead<RXN>, shared: &'a ActorState) -> Self {
+        Self {
+            future: RxFuture::new(future, shared),
+        }
+    }
+}
+
+impl<'a, RXN> Future for RxExactFuture<'a, RXN>
+where
+    RXN: ArrayLength<u8> + 'static,
+{
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Future::poll(Pin::new(&mut self.future), cx).map(|result| result.map(|_| ()))
+    }
+}
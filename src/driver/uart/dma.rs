@@ -3,7 +3,10 @@ use crate::prelude::*;
 pub use crate::api::uart::Error;
 use crate::api::{
     scheduler::*,
-    uart::{UartRead, UartReadWithTimeout, UartReader, UartWrite, UartWriter},
+    uart::{
+        UartRead, UartReadExact, UartReadUntil, UartReadWithTimeout, UartReader, UartWrite,
+        UartWriter,
+    },
 };
 use crate::domain::time::duration::{Duration, Milliseconds};
 use crate::hal::uart::dma::DmaUartHal;
@@ -217,6 +220,32 @@ where
             Response::immediate(self, Err(Error::RxInProgress))
         }
     }
+
+    /// Read bytes into the provided rx_buffer up to and including the delimiter. The memory pointed to by the buffers must be available until the return future is await'ed
+    fn read_until<'a>(self, message: UartReadUntil<'a>) -> Response<Self, Result<usize, Error>> {
+        let shared = self.shared.as_ref().unwrap();
+        if shared.try_rx_busy() {
+            let rx_consumer = self.rx_consumer.as_ref().unwrap();
+            let future = unsafe { rx_consumer.read_until(message.0, message.1) };
+            let future = RxFuture::new(future, shared);
+            Response::immediate_future(self, future)
+        } else {
+            Response::immediate(self, Err(Error::RxInProgress))
+        }
+    }
+
+    /// Fill the provided rx_buffer. The memory pointed to by the buffer must be available until the return future is await'ed
+    fn read_exact<'a>(self, message: UartReadExact<'a>) -> Response<Self, Result<(), Error>> {
+        let shared = self.shared.as_ref().unwrap();
+        if shared.try_rx_busy() {
+            let rx_consumer = self.rx_consumer.as_ref().unwrap();
+            let future = unsafe { rx_consumer.read(message.0) };
+            let future = RxExactFuture::new(future, shared);
+            Response::immediate_future(self, future)
+        } else {
+            Response::immediate(self, Err(Error::RxInProgress))
+        }
+    }
 }
 
 impl<T, TXN, RXN> NotifyHandler<ReadTimeout> for UartActor<T, TXN, RXN>
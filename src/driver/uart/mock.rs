@@ -0,0 +1,229 @@
+//! A UART playing the other end of a scripted session, for tests of drivers talking over one.
+
+extern crate std;
+
+use crate::api::uart::{
+    Error, UartRead, UartReadExact, UartReadUntil, UartReadWithTimeout, UartReader, UartWrite,
+    UartWriter,
+};
+use crate::domain::time::duration::{Duration, Milliseconds};
+use crate::prelude::*;
+use core::cell::Cell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::boxed::Box as StdBox;
+
+/// A step of a session.
+#[derive(Copy, Clone)]
+pub enum Step {
+    /// Bytes the driver is expected to write next, possibly in several writes.
+    Send(&'static [u8]),
+    /// Bytes the other end answers with, readable once the steps before are done.
+    Receive(&'static [u8]),
+    /// Nothing more happens until the test calls `Transcript::resume`.
+    Pause,
+}
+
+struct Script {
+    steps: &'static [Step],
+    step: Cell<usize>,
+    offset: Cell<usize>,
+    reader: Cell<Option<Waker>>,
+}
+
+impl Script {
+    fn advance(&self, len: usize, step_len: usize) {
+        let offset = self.offset.get() + len;
+        if offset == step_len {
+            self.step.set(self.step.get() + 1);
+            self.offset.set(0);
+        } else {
+            self.offset.set(offset);
+        }
+    }
+
+    fn write(&self, mut data: &[u8]) {
+        while !data.is_empty() {
+            match self.steps.get(self.step.get()) {
+                Some(Step::Send(expected)) => {
+                    let expected = &expected[self.offset.get()..];
+                    let len = expected.len().min(data.len());
+                    assert_eq!(
+                        core::str::from_utf8(&data[..len]),
+                        core::str::from_utf8(&expected[..len]),
+                        "unexpected write"
+                    );
+                    self.advance(len, self.offset.get() + expected.len());
+                    data = &data[len..];
+                }
+                _ => panic!("unexpected write: {:?}", core::str::from_utf8(data)),
+            }
+        }
+        if let Some(reader) = self.reader.take() {
+            reader.wake();
+        }
+    }
+
+    fn read(&self) -> Option<u8> {
+        match self.steps.get(self.step.get()) {
+            Some(Step::Receive(data)) => {
+                let b = data[self.offset.get()];
+                self.advance(1, data.len());
+                Some(b)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Checks on the session of a `MockUart`, kept by the test once the UART is mounted.
+#[derive(Copy, Clone)]
+pub struct Transcript(&'static Script);
+
+impl Transcript {
+    /// Continue after a `Pause`.
+    pub fn resume(&self) {
+        assert!(
+            matches!(self.0.steps.get(self.0.step.get()), Some(Step::Pause)),
+            "not paused"
+        );
+        self.0.step.set(self.0.step.get() + 1);
+        if let Some(reader) = self.0.reader.take() {
+            reader.wake();
+        }
+    }
+
+    /// Check that the whole session was played.
+    pub fn finish(&self) {
+        assert_eq!(
+            self.0.step.get(),
+            self.0.steps.len(),
+            "transcript not finished"
+        );
+    }
+}
+
+/// Answers reads with the `Receive` steps of its script, once the driver wrote all the `Send`
+/// steps before them, and panics on writes deviating from the script. Reads with a timeout
+/// time out as soon as nothing is left to receive.
+pub struct MockUart {
+    script: &'static Script,
+}
+
+impl MockUart {
+    pub fn new(steps: &'static [Step]) -> Self {
+        Self {
+            script: StdBox::leak(StdBox::new(Script {
+                steps,
+                step: Cell::new(0),
+                offset: Cell::new(0),
+                reader: Cell::new(None),
+            })),
+        }
+    }
+
+    pub fn transcript(&self) -> Transcript {
+        Transcript(self.script)
+    }
+
+    fn read(self, buffer: &mut [u8], until: Until) -> Response<Self, Result<usize, Error>> {
+        // The requests are awaited before their buffers go away, as with the other UARTs.
+        let buffer = unsafe { core::mem::transmute::<&mut [u8], &'static mut [u8]>(buffer) };
+        let future = MockRead {
+            script: self.script,
+            buffer,
+            pos: 0,
+            until,
+        };
+        Response::immediate_future(self, future)
+    }
+}
+
+impl Actor for MockUart {
+    type Configuration = ();
+}
+
+impl UartWriter for MockUart {
+    fn write<'a>(self, message: UartWrite<'a>) -> Response<Self, Result<(), Error>> {
+        self.script.write(message.0);
+        Response::immediate(self, Ok(()))
+    }
+}
+
+impl UartReader for MockUart {
+    fn read<'a>(self, message: UartRead<'a>) -> Response<Self, Result<usize, Error>> {
+        self.read(message.0, Until::Full)
+    }
+
+    fn read_with_timeout<'a, DUR>(
+        self,
+        message: UartReadWithTimeout<'a, DUR>,
+    ) -> Response<Self, Result<usize, Error>>
+    where
+        DUR: Duration + Into<Milliseconds> + 'static,
+    {
+        self.read(message.0, Until::Idle)
+    }
+
+    fn read_until<'a>(self, message: UartReadUntil<'a>) -> Response<Self, Result<usize, Error>> {
+        let delimiter = unsafe { core::mem::transmute::<&[u8], &'static [u8]>(message.1) };
+        self.read(message.0, Until::Delimiter(delimiter))
+    }
+
+    fn read_exact<'a>(self, message: UartReadExact<'a>) -> Response<Self, Result<(), Error>> {
+        let buffer = unsafe { core::mem::transmute::<&mut [u8], &'static mut [u8]>(message.0) };
+        let future = MockRead {
+            script: self.script,
+            buffer,
+            pos: 0,
+            until: Until::Full,
+        };
+        Response::immediate_future(self, async move { future.await.map(|_| ()) })
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Until {
+    Full,
+    Delimiter(&'static [u8]),
+    Idle,
+}
+
+struct MockRead {
+    script: &'static Script,
+    buffer: &'static mut [u8],
+    pos: usize,
+    until: Until,
+}
+
+impl Future for MockRead {
+    type Output = Result<usize, Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            let pos = self.pos;
+            match self.until {
+                Until::Delimiter(delimiter) if self.buffer[..pos].ends_with(delimiter) => {
+                    return Poll::Ready(Ok(pos));
+                }
+                Until::Delimiter(_) if pos == self.buffer.len() => {
+                    return Poll::Ready(Err(Error::RxBufferTooSmall));
+                }
+                _ if pos == self.buffer.len() => return Poll::Ready(Ok(pos)),
+                _ => {}
+            }
+            match self.script.read() {
+                Some(b) => {
+                    self.buffer[pos] = b;
+                    self.pos += 1;
+                }
+                None if matches!(self.until, Until::Idle) => return Poll::Ready(Ok(pos)),
+                None => {
+                    self.script.reader.set(Some(cx.waker().clone()));
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
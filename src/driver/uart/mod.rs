@@ -1,3 +1,8 @@
+pub mod codec;
 mod common;
 pub mod dma;
+#[cfg(test)]
+pub(crate) mod mock;
 pub mod serial;
+#[cfg(feature = "usb")]
+pub mod usb;
@@ -230,6 +230,30 @@ where
             Response::immediate(self, Err(Error::RxInProgress))
         }
     }
+
+    fn read_until<'a>(self, message: UartReadUntil<'a>) -> Response<Self, Result<usize, Error>> {
+        let state = self.state.as_ref().unwrap();
+        if state.try_rx_busy() {
+            let rx_consumer = self.rx_consumer.as_ref().unwrap();
+            let future = unsafe { rx_consumer.read_until(message.0, message.1) };
+            let future = RxFuture::new(future, state);
+            Response::immediate_future(self, future)
+        } else {
+            Response::immediate(self, Err(Error::RxInProgress))
+        }
+    }
+
+    fn read_exact<'a>(self, message: UartReadExact<'a>) -> Response<Self, Result<(), Error>> {
+        let state = self.state.as_ref().unwrap();
+        if state.try_rx_busy() {
+            let rx_consumer = self.rx_consumer.as_ref().unwrap();
+            let future = unsafe { rx_consumer.read(message.0) };
+            let future = RxExactFuture::new(future, state);
+            Response::immediate_future(self, future)
+        } else {
+            Response::immediate(self, Err(Error::RxInProgress))
+        }
+    }
 }
 
 impl<TX, S> NotifyHandler<ReadTimeout> for SerialActor<TX, S>
@@ -0,0 +1,308 @@
+//! A UART over USB, as a CDC-ACM serial port.
+//!
+//! The port behaves like the other UARTs, so actors generic over `UartReader` and `UartWriter`
+//! work over USB as they are, such as the echo of the `stm32l4/usb-serial` example.
+use crate::prelude::*;
+
+use crate::api::scheduler::*;
+use crate::domain::time::duration::Milliseconds;
+use crate::hal::usb::UsbBus;
+
+use core::cell::UnsafeCell;
+use cortex_m::interrupt::Nr;
+use usb_device::bus::UsbBusAllocator;
+use usb_device::prelude::*;
+use usbd_serial::{SerialPort, USB_CLASS_CDC};
+
+use super::common::*;
+use super::dma::UartActor;
+
+use crate::util::dma::async_bbqueue::{Error as QueueError, *};
+
+/// How the device presents itself to the host.
+#[derive(Copy, Clone)]
+pub struct UsbConfig {
+    pub vid: u16,
+    pub pid: u16,
+    pub manufacturer: &'static str,
+    pub product: &'static str,
+    pub serial_number: &'static str,
+}
+
+impl Default for UsbConfig {
+    /// The test VID/PID pair of pid.codes, fine for development only.
+    fn default() -> Self {
+        Self {
+            vid: 0x1209,
+            pid: 0x0001,
+            manufacturer: "Drogue IoT",
+            product: "drogue-device serial",
+            serial_number: "0",
+        }
+    }
+}
+
+/// How often the port checks for written bytes to send, and for room to take the received
+/// bytes it left with the host.
+const POLL_INTERVAL: Milliseconds = Milliseconds(10);
+
+/// Largest packet of a full-speed bulk endpoint.
+const PACKET_SIZE: usize = 64;
+
+pub struct UsbSerial<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8> + 'static,
+    RXN: ArrayLength<u8> + 'static,
+{
+    allocator: UsbBusAllocator<B::Bus>,
+    config: UsbConfig,
+    actor: ActorContext<UartActor<T, TXN, RXN>>,
+    interrupt: InterruptContext<UsbInterrupt<B, T, TXN, RXN>>,
+    shared: ActorState,
+
+    rx_buffer: UnsafeCell<AsyncBBBuffer<'static, RXN>>,
+    tx_buffer: UnsafeCell<AsyncBBBuffer<'static, TXN>>,
+}
+
+impl<B, T, TXN, RXN> UsbSerial<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    pub fn new<IRQ>(bus: B, config: UsbConfig, irq: IRQ) -> Self
+    where
+        IRQ: Nr,
+    {
+        Self {
+            allocator: bus.into_allocator(),
+            config,
+            actor: ActorContext::new(UartActor::new()).with_name("usb_serial_actor"),
+            interrupt: InterruptContext::new(UsbInterrupt::new(), irq)
+                .with_name("usb_serial_interrupt"),
+            shared: ActorState::new(),
+            rx_buffer: UnsafeCell::new(AsyncBBBuffer::new()),
+            tx_buffer: UnsafeCell::new(AsyncBBBuffer::new()),
+        }
+    }
+}
+
+impl<B, T, TXN, RXN> Package for UsbSerial<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    type Primary = UartActor<T, TXN, RXN>;
+    type Configuration = Address<T>;
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let (rx_prod, rx_cons) = unsafe { (&mut *self.rx_buffer.get()).split() };
+        let (tx_prod, tx_cons) = unsafe { (&mut *self.tx_buffer.get()).split() };
+
+        // the class allocates its endpoints before the device is built
+        let serial = SerialPort::new(&self.allocator);
+        let device =
+            UsbDeviceBuilder::new(&self.allocator, UsbVidPid(self.config.vid, self.config.pid))
+                .manufacturer(self.config.manufacturer)
+                .product(self.config.product)
+                .serial_number(self.config.serial_number)
+                .device_class(USB_CLASS_CDC)
+                .build();
+
+        let addr = self
+            .actor
+            .mount((&self.shared, config, tx_prod, rx_cons), supervisor);
+        self.interrupt
+            .mount((device, serial, config, tx_cons, rx_prod), supervisor);
+
+        addr
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.actor.address()
+    }
+}
+
+/// Runs the USB stack as the peripheral interrupts, moving received bytes to the actor and
+/// written bytes to the host.
+pub struct UsbInterrupt<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8> + 'static,
+    RXN: ArrayLength<u8> + 'static,
+{
+    me: Option<Address<Self>>,
+    scheduler: Option<Address<T>>,
+    device: Option<UsbDevice<'static, B::Bus>>,
+    serial: Option<SerialPort<'static, B::Bus>>,
+    tx_consumer: Option<AsyncBBConsumer<TXN>>,
+    rx_producer: Option<AsyncBBProducer<RXN>>,
+    /// Whether received bytes were left with the host, for lack of room.
+    rx_blocked: bool,
+}
+
+impl<B, T, TXN, RXN> UsbInterrupt<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    pub fn new() -> Self {
+        Self {
+            me: None,
+            scheduler: None,
+            device: None,
+            serial: None,
+            tx_consumer: None,
+            rx_producer: None,
+            rx_blocked: false,
+        }
+    }
+
+    /// Whether a terminal on the host has the port open.
+    fn connected(&self) -> bool {
+        self.device.as_ref().unwrap().state() == UsbDeviceState::Configured
+            && self.serial.as_ref().unwrap().dtr()
+    }
+
+    fn receive(&mut self) {
+        let serial = self.serial.as_mut().unwrap();
+        self.rx_blocked = false;
+        loop {
+            match self
+                .rx_producer
+                .as_ref()
+                .unwrap()
+                .prepare_write(PACKET_SIZE)
+            {
+                Ok(mut grant) => match serial.read(grant.buf()) {
+                    Ok(len) if len > 0 => grant.commit(len),
+                    _ => {
+                        grant.commit(0);
+                        return;
+                    }
+                },
+                Err(QueueError::BufferFull) => {
+                    // left with the host, which holds off, until a poll finds room for them
+                    self.rx_blocked = true;
+                    return;
+                }
+                Err(e) => {
+                    log::error!("Error preparing receive buffer: {:?}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn transmit(&mut self) {
+        let connected = self.connected();
+        let serial = self.serial.as_mut().unwrap();
+        let tx_consumer = self.tx_consumer.as_ref().unwrap();
+        while let Ok(grant) = tx_consumer.prepare_read() {
+            if !connected {
+                // nobody listens, so drop the output rather than blocking writers
+                let len = grant.len();
+                grant.release(len);
+                continue;
+            }
+            match serial.write(grant.buf()) {
+                Ok(len) if len > 0 => grant.release(len),
+                Ok(_) | Err(UsbError::WouldBlock) => {
+                    grant.release(0);
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("Error writing to USB: {:?}", e);
+                    grant.release(0);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+impl<B, T, TXN, RXN> Actor for UsbInterrupt<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    type Configuration = (
+        UsbDevice<'static, B::Bus>,
+        SerialPort<'static, B::Bus>,
+        Address<T>,
+        AsyncBBConsumer<TXN>,
+        AsyncBBProducer<RXN>,
+    );
+
+    fn on_mount(&mut self, me: Address<Self>, config: Self::Configuration) {
+        self.me.replace(me);
+        self.device.replace(config.0);
+        self.serial.replace(config.1);
+        self.scheduler.replace(config.2);
+        self.tx_consumer.replace(config.3);
+        self.rx_producer.replace(config.4);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        self.scheduler
+            .as_ref()
+            .unwrap()
+            .schedule(POLL_INTERVAL, Poll, *self.me.as_ref().unwrap());
+        Completion::immediate(self)
+    }
+}
+
+impl<B, T, TXN, RXN> NotifyHandler<Poll> for UsbInterrupt<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    fn on_notify(mut self, _: Poll) -> Completion<Self> {
+        // the host sends no more, nor interrupts, until the bytes left with it are read
+        if self.rx_blocked {
+            self.receive();
+        }
+        self.transmit();
+        self.scheduler
+            .as_ref()
+            .unwrap()
+            .schedule(POLL_INTERVAL, Poll, *self.me.as_ref().unwrap());
+        Completion::immediate(self)
+    }
+}
+
+impl<B, T, TXN, RXN> Interrupt for UsbInterrupt<B, T, TXN, RXN>
+where
+    B: UsbBus + 'static,
+    T: Scheduler + 'static,
+    TXN: ArrayLength<u8>,
+    RXN: ArrayLength<u8>,
+{
+    fn on_interrupt(&mut self) {
+        let device = self.device.as_mut().unwrap();
+        let serial = self.serial.as_mut().unwrap();
+        if device.poll(&mut [serial]) {
+            self.receive();
+        }
+        self.transmit();
+    }
+}
+
+#[derive(Clone)]
+struct Poll;
@@ -0,0 +1,419 @@
+//! The commands and URCs of the Espressif AT firmware.
+
+use crate::api::ip::{IpAddress, IpAddressV4, IpProtocol, SocketAddress};
+use crate::api::wifi::JoinError;
+use crate::domain::time::duration::Milliseconds;
+use crate::util::at::{AtError, Command, DataHeader, Urc};
+use crate::util::nom::{parse_u8, parse_usize};
+use core::fmt::Write;
+use heapless::{consts::*, Vec};
+use nom::branch::alt;
+use nom::bytes::streaming::{tag, take, take_until};
+use nom::character::streaming::{char, one_of};
+use nom::combinator::{flat_map, map};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+/// Most data received with one `AT+CIPRECVDATA`.
+pub type ChunkLength = U192;
+
+/// `AT+CWMODE=1`, to act as a station.
+pub struct SetStationMode;
+
+impl Command for SetStationMode {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CWMODE=1")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CIPMUX=1`, to use several links at once.
+pub struct SetMultipleConnections;
+
+impl Command for SetMultipleConnections {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CIPMUX=1")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CIPRECVMODE=1`, to keep received data until asked for it, rather than sending it along
+/// with `+IPD`.
+pub struct SetPassiveReceive;
+
+impl Command for SetPassiveReceive {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CIPRECVMODE=1")
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CWJAP`, to join an access point. Ends in `FAIL` rather than `ERROR` if joining failed.
+pub struct JoinAccessPoint<'a> {
+    pub ssid: &'a str,
+    pub password: &'a str,
+}
+
+impl<'a> Command for JoinAccessPoint<'a> {
+    type Response = Result<(), JoinError>;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+CWJAP=\"{}\",\"{}\"", self.ssid, self.password)
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(20_000)
+    }
+
+    fn final_result(&self, line: &[u8]) -> Option<Result<(), AtError>> {
+        match line {
+            b"FAIL\r\n" => Some(Ok(())),
+            _ => crate::util::at::final_result(line),
+        }
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        match join_failure(response) {
+            Ok((_, error)) => Ok(Err(error)),
+            Err(_) => Ok(Ok(())),
+        }
+    }
+}
+
+/// `AT+CIFSR`, for the address of the station.
+pub struct GetAddress;
+
+impl Command for GetAddress {
+    type Response = IpAddress;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        w.write_str("+CIFSR")
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        station_address(response)
+            .map(|(_, ip)| IpAddress::V4(ip))
+            .map_err(|_| AtError::Parse)
+    }
+}
+
+/// `AT+CIPSTART`, to connect a link.
+pub struct StartConnection {
+    pub link: u8,
+    pub proto: IpProtocol,
+    pub dst: SocketAddress,
+}
+
+impl Command for StartConnection {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        let proto = match self.proto {
+            IpProtocol::Tcp => "TCP",
+            IpProtocol::Udp => "UDP",
+        };
+        write!(
+            w,
+            "+CIPSTART={},\"{}\",\"{}\",{}",
+            self.link,
+            proto,
+            self.dst.ip(),
+            self.dst.port()
+        )
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(10_000)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CIPSEND`, to send data over a link once prompted. Ends in `SEND OK`, after the `OK`
+/// preceding the prompt.
+pub struct SendData<'a> {
+    pub link: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> Command for SendData<'a> {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+CIPSEND={},{}", self.link, self.data.len())
+    }
+
+    fn timeout(&self) -> Milliseconds {
+        Milliseconds(5_000)
+    }
+
+    fn payload(&self) -> Option<&[u8]> {
+        Some(self.data)
+    }
+
+    fn final_result(&self, line: &[u8]) -> Option<Result<(), AtError>> {
+        match line {
+            b"SEND OK\r\n" => Some(Ok(())),
+            b"SEND FAIL\r\n" | b"ERROR\r\n" => Some(Err(AtError::Error)),
+            _ => None,
+        }
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `AT+CIPRECVDATA`, to receive up to `len` bytes kept for a link.
+pub struct ReceiveData {
+    pub link: u8,
+    pub len: usize,
+}
+
+impl Command for ReceiveData {
+    type Response = Vec<u8, ChunkLength>;
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+CIPRECVDATA={},{}", self.link, self.len)
+    }
+
+    fn data_header(&self) -> Option<DataHeader> {
+        Some(received_data_length)
+    }
+
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+        let (_, data) = received_data(response).map_err(|_| AtError::Parse)?;
+        let mut chunk = Vec::new();
+        chunk
+            .extend_from_slice(data)
+            .map_err(|_| AtError::ResponseTooLong)?;
+        Ok(chunk)
+    }
+}
+
+/// `AT+CIPCLOSE`, to close a link.
+pub struct CloseConnection {
+    pub link: u8,
+}
+
+impl Command for CloseConnection {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "+CIPCLOSE={}", self.link)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// The URCs of the firmware, in multiple connection and passive receive mode.
+#[derive(Debug, PartialEq)]
+pub enum EspUrc {
+    /// `ready`, after the module (re)started.
+    Ready,
+    WifiConnected,
+    WifiGotIp,
+    WifiDisconnected,
+    /// `<link>,CONNECT`
+    Connected(u8),
+    /// `<link>,CLOSED`
+    Closed(u8),
+    /// `+IPD,<link>,<len>`, as `len` more bytes are kept for the link.
+    DataAvailable(u8, usize),
+}
+
+impl Urc for EspUrc {
+    fn parse(line: &[u8]) -> Option<Self> {
+        match line {
+            b"ready\r\n" => Some(EspUrc::Ready),
+            b"WIFI CONNECTED\r\n" => Some(EspUrc::WifiConnected),
+            b"WIFI GOT IP\r\n" => Some(EspUrc::WifiGotIp),
+            b"WIFI DISCONNECT\r\n" => Some(EspUrc::WifiDisconnected),
+            _ => urc(line).ok().map(|(_, urc)| urc),
+        }
+    }
+}
+
+fn connected(input: &[u8]) -> IResult<&[u8], EspUrc> {
+    map(terminated(parse_u8, tag(",CONNECT\r\n")), EspUrc::Connected)(input)
+}
+
+fn closed(input: &[u8]) -> IResult<&[u8], EspUrc> {
+    map(terminated(parse_u8, tag(",CLOSED\r\n")), EspUrc::Closed)(input)
+}
+
+fn data_available(input: &[u8]) -> IResult<&[u8], EspUrc> {
+    map(
+        delimited(
+            tag("+IPD,"),
+            separated_pair(parse_u8, char(','), parse_usize),
+            tag("\r\n"),
+        ),
+        |(link, len)| EspUrc::DataAvailable(link, len),
+    )(input)
+}
+
+fn urc(input: &[u8]) -> IResult<&[u8], EspUrc> {
+    alt((connected, closed, data_available))(input)
+}
+
+// +CWJAP:1
+fn join_failure(input: &[u8]) -> IResult<&[u8], JoinError> {
+    map(
+        preceded(pair(take_until("+CWJAP:"), tag("+CWJAP:")), parse_u8),
+        |code| match code {
+            2 => JoinError::InvalidPassword,
+            3 => JoinError::InvalidSsid,
+            1 | 4 => JoinError::UnableToAssociate,
+            _ => JoinError::Unknown,
+        },
+    )(input)
+}
+
+fn ip_addr(input: &[u8]) -> IResult<&[u8], IpAddressV4> {
+    map(
+        tuple((
+            parse_u8,
+            preceded(char('.'), parse_u8),
+            preceded(char('.'), parse_u8),
+            preceded(char('.'), parse_u8),
+        )),
+        |(a, b, c, d)| IpAddressV4::new(a, b, c, d),
+    )(input)
+}
+
+// +CIFSR:STAIP,"192.168.1.10"
+fn station_address(input: &[u8]) -> IResult<&[u8], IpAddressV4> {
+    let prefix = "+CIFSR:STAIP,\"";
+    delimited(pair(take_until(prefix), tag(prefix)), ip_addr, char('"'))(input)
+}
+
+// +CIPRECVDATA,5: (ESP8266) or +CIPRECVDATA:5, (ESP32)
+fn received_data_header(input: &[u8]) -> IResult<&[u8], usize> {
+    delimited(
+        pair(tag("+CIPRECVDATA"), one_of(",:")),
+        parse_usize,
+        one_of(":,"),
+    )(input)
+}
+
+fn received_data_length(line: &[u8]) -> Option<(usize, usize)> {
+    received_data_header(line)
+        .ok()
+        .map(|(data, len)| (line.len() - data.len(), len))
+}
+
+fn received_data(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    flat_map(received_data_header, take)(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::uart::mock::Step::*;
+    use crate::util::at::replay::Replay;
+    use heapless::String;
+
+    #[test]
+    fn test_join() {
+        let mut modem: Replay<EspUrc> = Replay::new(&[
+            Send(b"AT+CWJAP=\"drogue\",\"rodney\"\r\n"),
+            Receive(b"WIFI CONNECTED\r\nWIFI GOT IP\r\n\r\nOK\r\n"),
+            Send(b"AT+CIFSR\r\n"),
+            Receive(b"+CIFSR:STAIP,\"192.168.1.174\"\r\n+CIFSR:STAMAC,\"5c:cf:7f:00:00:01\"\r\n\r\nOK\r\n"),
+            Send(b"AT+CWJAP=\"drogue\",\"wrong\"\r\n"),
+            Receive(b"+CWJAP:2\r\n\r\nFAIL\r\n"),
+        ]);
+        let join = JoinAccessPoint {
+            ssid: "drogue",
+            password: "rodney",
+        };
+        assert!(matches!(modem.execute(join), Ok(Ok(()))));
+        assert_eq!(modem.urc(), Some(EspUrc::WifiConnected));
+        assert_eq!(modem.urc(), Some(EspUrc::WifiGotIp));
+        let mut ip: String<U16> = String::new();
+        write!(ip, "{}", modem.execute(GetAddress).unwrap()).unwrap();
+        assert_eq!(ip.as_str(), "192.168.1.174");
+
+        let join = JoinAccessPoint {
+            ssid: "drogue",
+            password: "wrong",
+        };
+        assert!(matches!(
+            modem.execute(join),
+            Ok(Err(JoinError::InvalidPassword))
+        ));
+        modem.finish();
+    }
+
+    #[test]
+    fn test_connect_send_close() {
+        let mut modem: Replay<EspUrc> = Replay::new(&[
+            Send(b"AT+CIPSTART=0,\"TCP\",\"192.168.1.2\",8080\r\n"),
+            Receive(b"0,CONNECT\r\n\r\nOK\r\n"),
+            Send(b"AT+CIPSEND=0,5\r\n"),
+            Receive(b"\r\nOK\r\n> "),
+            Send(b"hello"),
+            Receive(b"\r\nRecv 5 bytes\r\n\r\nSEND OK\r\n"),
+            Receive(b"+IPD,0,7\r\n"),
+            Send(b"AT+CIPCLOSE=0\r\n"),
+            Receive(b"0,CLOSED\r\n\r\nOK\r\n"),
+        ]);
+        let connect = StartConnection {
+            link: 0,
+            proto: IpProtocol::Tcp,
+            dst: SocketAddress::new(IpAddress::new_v4(192, 168, 1, 2), 8080),
+        };
+        assert!(modem.execute(connect).is_ok());
+        assert_eq!(modem.urc(), Some(EspUrc::Connected(0)));
+        let send = SendData {
+            link: 0,
+            data: b"hello",
+        };
+        assert!(modem.execute(send).is_ok());
+        modem.idle();
+        assert_eq!(modem.urc(), Some(EspUrc::DataAvailable(0, 7)));
+        assert!(modem.execute(CloseConnection { link: 0 }).is_ok());
+        assert_eq!(modem.urc(), Some(EspUrc::Closed(0)));
+        modem.finish();
+    }
+
+    #[test]
+    fn test_receive_data() {
+        let mut modem: Replay<EspUrc> = Replay::new(&[
+            Send(b"AT+CIPRECVDATA=0,192\r\n"),
+            Receive(b"+CIPRECVDATA,14:HTTP/1.0\r\n\r\nhi\r\nOK\r\n"),
+            Send(b"AT+CIPRECVDATA=1,192\r\n"),
+            Receive(b"+CIPRECVDATA:3,abc\r\nOK\r\n"),
+            Send(b"AT+CIPRECVDATA=0,192\r\n"),
+            Receive(b"+CIPRECVDATA,13:OK\r\n\r\nERROR\r\n\r\nOK\r\n"),
+        ]);
+        let data = modem.execute(ReceiveData { link: 0, len: 192 }).unwrap();
+        assert_eq!(&data[..], b"HTTP/1.0\r\n\r\nhi");
+        let data = modem.execute(ReceiveData { link: 1, len: 192 }).unwrap();
+        assert_eq!(&data[..], b"abc");
+        // data looking like a final result is still data
+        let data = modem.execute(ReceiveData { link: 0, len: 192 }).unwrap();
+        assert_eq!(&data[..], b"OK\r\n\r\nERROR\r\n");
+        modem.finish();
+    }
+}
@@ -0,0 +1,758 @@
+//! A driver for ESP8266 and ESP32 modules running the Espressif AT firmware over a UART.
+
+mod commands;
+
+use commands::*;
+
+use crate::driver::socket_pool::SocketPool;
+
+use crate::api::ip::tcp::{TcpError, TcpStack};
+use crate::api::ip::{IpAddress, IpProtocol, SocketAddress};
+use crate::api::scheduler::{Schedule, Scheduler};
+use crate::api::uart::{UartReader, UartWriter};
+use crate::api::wifi::{Join, JoinError, WifiSupplicant};
+use crate::domain::time::duration::Milliseconds;
+use crate::prelude::*;
+use crate::synchronization::Signal;
+use crate::util::at::{AtController, AtError, AtModem, Attention, SetEcho};
+use core::cell::{Cell, RefCell};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use heapless::spsc::Queue;
+
+pub use commands::EspUrc;
+
+/// Most data written with one `AT+CIPSEND`.
+const MAX_SEND: usize = 2048;
+
+/// How long a read waits for data to arrive.
+const RECEIVE_TIMEOUT: Milliseconds = Milliseconds(10_000);
+
+/// What is known of a link, updated from URCs as well as by the controller.
+struct Link {
+    connected: bool,
+    /// Bytes kept by the module, according to `+IPD`.
+    available: usize,
+    /// Bytes received from the module, but not read yet.
+    buffer: Queue<u8, ChunkLength>,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self {
+            connected: false,
+            available: 0,
+            buffer: Queue::new(),
+        }
+    }
+}
+
+pub struct Shared {
+    socket_pool: SocketPool,
+    links: RefCell<[Link; 4]>,
+    /// Signaled by the monitor as links change, or a wait for them expires.
+    changed: Signal<()>,
+    /// The last wait that expired.
+    expired: Cell<u32>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            socket_pool: SocketPool::new(),
+            links: Default::default(),
+            changed: Signal::new(),
+            expired: Cell::new(0),
+        }
+    }
+}
+
+/// An ESP8266 or ESP32 over the UART `U`, timing out commands with the scheduler `S`.
+///
+/// Received data is kept by the module until read, in passive receive mode, so it never
+/// interleaves with responses.
+pub struct Esp8266<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    shared: Shared,
+    modem: AtModem<U, S, EspUrc, Esp8266Monitor>,
+    monitor: ActorContext<Esp8266Monitor>,
+    controller: ActorContext<Esp8266Controller<U, S>>,
+}
+
+impl<U, S> Esp8266<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            shared: Shared::new(),
+            modem: AtModem::new(),
+            monitor: ActorContext::new(Esp8266Monitor::new()).with_name("esp8266_monitor"),
+            controller: ActorContext::new(Esp8266Controller::new()).with_name("esp8266"),
+        }
+    }
+}
+
+impl<U, S> Package for Esp8266<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    type Primary = Esp8266Controller<U, S>;
+    type Configuration = (Address<U>, Address<S>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let monitor = self.monitor.mount(&self.shared, supervisor);
+        let modem = self.modem.mount((config.0, config.1, monitor), supervisor);
+        self.controller
+            .mount((&self.shared, modem, config.1, monitor), supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.controller.address()
+    }
+}
+
+enum State {
+    Uninitialized,
+    Ready,
+}
+
+pub struct Esp8266Controller<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    shared: Option<&'static Shared>,
+    modem: Option<Address<AtController<U, S>>>,
+    scheduler: Option<Address<S>>,
+    monitor: Option<Address<Esp8266Monitor>>,
+    state: State,
+    wait: u32,
+}
+
+impl<U, S> Esp8266Controller<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            shared: None,
+            modem: None,
+            scheduler: None,
+            monitor: None,
+            state: State::Uninitialized,
+            wait: 0,
+        }
+    }
+
+    async fn start(mut self) -> Self {
+        log::info!("[{}] start", ActorInfo::name());
+        match self.initialize().await {
+            Ok(_) => {
+                self.state = State::Ready;
+                log::info!("[{}] ESP8266 adapter is ready", ActorInfo::name());
+            }
+            Err(e) => {
+                log::info!("[{}] failed to initialize {:?}", ActorInfo::name(), e);
+            }
+        }
+        self
+    }
+
+    async fn initialize(&mut self) -> Result<(), AtError> {
+        let modem = self.modem.unwrap();
+        let mut attempts = 0;
+        while let Err(e) = modem.execute(Attention).await {
+            attempts += 1;
+            if attempts == 5 {
+                return Err(e);
+            }
+        }
+        modem.execute(SetEcho(false)).await?;
+        modem.execute(SetStationMode).await?;
+        modem.execute(SetMultipleConnections).await?;
+        modem.execute(SetPassiveReceive).await
+    }
+
+    async fn join_wpa(&mut self, ssid: &str, password: &str) -> Result<IpAddress, JoinError> {
+        let modem = self.modem.unwrap();
+        modem
+            .execute(JoinAccessPoint { ssid, password })
+            .await
+            .map_err(|_| JoinError::UnableToAssociate)??;
+        modem
+            .execute(GetAddress)
+            .await
+            .map_err(|_| JoinError::Unknown)
+    }
+
+    /// Whether data was received for `link`, buffered or kept by the module.
+    fn has_data(&self, link: u8) -> bool {
+        let links = self.shared.unwrap().links.borrow();
+        let link = &links[link as usize];
+        !link.buffer.is_empty() || link.available > 0
+    }
+
+    /// Wait for data to be received for `link`, failing if the link closes or no data arrives
+    /// within the `RECEIVE_TIMEOUT`.
+    async fn wait_for_data(&mut self, link: u8) -> Result<(), TcpError> {
+        let shared = self.shared.unwrap();
+        self.wait = self.wait.wrapping_add(1);
+        let wait = self.wait;
+        let mut timeout = None;
+        let result = loop {
+            if self.has_data(link) {
+                break Ok(());
+            }
+            if !shared.links.borrow()[link as usize].connected {
+                break Err(TcpError::SocketClosed);
+            }
+            if shared.expired.get() == wait {
+                break Err(TcpError::ReadError);
+            }
+            if timeout.is_none() {
+                shared.changed.reset();
+                let schedule = Schedule::new(RECEIVE_TIMEOUT, Expire(wait), self.monitor.unwrap());
+                match self.scheduler.unwrap().try_schedule(schedule).await {
+                    Ok(handle) => timeout = Some(handle),
+                    Err(e) => {
+                        log::warn!("[{}] unable to wait for data: {:?}", ActorInfo::name(), e);
+                        break Err(TcpError::ReadError);
+                    }
+                }
+            }
+            Changed(&shared.changed).await;
+        };
+        // an unexpired timeout would hold on to a slot of the scheduler for as long
+        if let Some(timeout) = timeout {
+            timeout.cancel();
+        }
+        result
+    }
+
+    /// Take up to `buf.len()` bytes received for `link`, fetching them from the module if none
+    /// are buffered, and waiting for some to arrive if the module has none either.
+    async fn receive(&mut self, link: u8, buf: &mut [u8]) -> Result<usize, TcpError> {
+        let shared = self.shared.unwrap();
+        self.wait_for_data(link).await?;
+        let fetch = {
+            let links = shared.links.borrow();
+            let link = &links[link as usize];
+            if link.buffer.is_empty() {
+                Some(core::cmp::min(link.available, link.buffer.capacity()))
+            } else {
+                None
+            }
+        };
+
+        if let Some(len) = fetch {
+            let chunk = self
+                .modem
+                .unwrap()
+                .execute(ReceiveData { link, len })
+                .await
+                .map_err(|_| TcpError::ReadError)?;
+            let mut links = shared.links.borrow_mut();
+            let link = &mut links[link as usize];
+            // the module may report less than announced, if the link closed meanwhile
+            link.available = if chunk.len() < len {
+                0
+            } else {
+                link.available.saturating_sub(chunk.len())
+            };
+            for b in chunk {
+                link.buffer.enqueue(b).ok();
+            }
+        }
+
+        let mut links = shared.links.borrow_mut();
+        let link = &mut links[link as usize];
+        let mut pos = 0;
+        while pos < buf.len() {
+            match link.buffer.dequeue() {
+                Some(b) => {
+                    buf[pos] = b;
+                    pos += 1;
+                }
+                None => break,
+            }
+        }
+        Ok(pos)
+    }
+}
+
+impl<U, S> WifiSupplicant for Esp8266Controller<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    fn join(mut self, join_info: Join) -> Response<Self, Result<IpAddress, JoinError>> {
+        Response::defer(async move {
+            let result = match join_info {
+                Join::Open => self.join_wpa("", "").await,
+                Join::Wpa { ssid, password } => {
+                    self.join_wpa(ssid.as_ref(), password.as_ref()).await
+                }
+            };
+
+            (self, result)
+        })
+    }
+}
+
+impl<U, S> TcpStack for Esp8266Controller<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    type SocketHandle = u8;
+
+    fn open(self) -> Response<Self, Self::SocketHandle> {
+        let open_future = self.shared.unwrap().socket_pool.open();
+        Response::immediate_future(self, open_future)
+    }
+
+    fn connect(
+        self,
+        handle: Self::SocketHandle,
+        proto: IpProtocol,
+        dst: SocketAddress,
+    ) -> Response<Self, Result<(), TcpError>> {
+        Response::defer(async move {
+            let command = StartConnection {
+                link: handle,
+                proto,
+                dst,
+            };
+            let result = match self.modem.unwrap().execute(command).await {
+                Ok(_) => {
+                    let mut links = self.shared.unwrap().links.borrow_mut();
+                    links[handle as usize] = Link {
+                        connected: true,
+                        ..Default::default()
+                    };
+                    Ok(())
+                }
+                Err(_) => Err(TcpError::ConnectError),
+            };
+            (self, result)
+        })
+    }
+
+    fn write(
+        self,
+        handle: Self::SocketHandle,
+        buf: &[u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        unsafe {
+            Response::defer_unchecked(async move {
+                let result = async {
+                    if !self.shared.unwrap().links.borrow()[handle as usize].connected {
+                        return Err(TcpError::SocketClosed);
+                    }
+                    let len = core::cmp::min(buf.len(), MAX_SEND);
+                    let command = SendData {
+                        link: handle,
+                        data: &buf[..len],
+                    };
+                    self.modem
+                        .unwrap()
+                        .execute(command)
+                        .await
+                        .map_err(|_| TcpError::WriteError)?;
+                    Ok(len)
+                }
+                .await;
+                (self, result)
+            })
+        }
+    }
+
+    fn read(
+        mut self,
+        handle: Self::SocketHandle,
+        buf: &mut [u8],
+    ) -> Response<Self, Result<usize, TcpError>> {
+        unsafe {
+            Response::defer_unchecked(async move {
+                let mut pos = 0;
+                loop {
+                    match self.receive(handle, &mut buf[pos..]).await {
+                        Ok(len) => {
+                            pos += len;
+                            if pos == buf.len() || !self.has_data(handle) {
+                                return (self, Ok(pos));
+                            }
+                        }
+                        Err(e) => {
+                            if pos == 0 {
+                                return (self, Err(e));
+                            } else {
+                                return (self, Ok(pos));
+                            }
+                        }
+                    }
+                }
+            })
+        }
+    }
+
+    fn close(self, handle: Self::SocketHandle) -> Completion<Self> {
+        Completion::defer(async move {
+            let shared = self.shared.unwrap();
+            let connected = shared.links.borrow()[handle as usize].connected;
+            if connected {
+                if let Err(e) = self
+                    .modem
+                    .unwrap()
+                    .execute(CloseConnection { link: handle })
+                    .await
+                {
+                    log::info!("[{}] error closing link {:?}", ActorInfo::name(), e);
+                }
+            }
+            shared.links.borrow_mut()[handle as usize] = Link::default();
+            shared.socket_pool.close(handle);
+            self
+        })
+    }
+}
+
+impl<U, S> Actor for Esp8266Controller<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    type Configuration = (
+        &'static Shared,
+        Address<AtController<U, S>>,
+        Address<S>,
+        Address<Esp8266Monitor>,
+    );
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config.0);
+        self.modem.replace(config.1);
+        self.scheduler.replace(config.2);
+        self.monitor.replace(config.3);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        Completion::defer(self.start())
+    }
+}
+
+/// Keeps track of links as the module reports on them, while the controller may be busy.
+pub struct Esp8266Monitor {
+    shared: Option<&'static Shared>,
+}
+
+impl Esp8266Monitor {
+    pub fn new() -> Self {
+        Self { shared: None }
+    }
+}
+
+impl Actor for Esp8266Monitor {
+    type Configuration = &'static Shared;
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config);
+    }
+}
+
+impl NotifyHandler<EspUrc> for Esp8266Monitor {
+    fn on_notify(self, message: EspUrc) -> Completion<Self> {
+        log::debug!("[{}] {:?}", ActorInfo::name(), message);
+        let mut links = self.shared.unwrap().links.borrow_mut();
+        match message {
+            EspUrc::DataAvailable(link, len) => {
+                if let Some(link) = links.get_mut(link as usize) {
+                    link.available += len;
+                }
+            }
+            EspUrc::Closed(link) => {
+                if let Some(link) = links.get_mut(link as usize) {
+                    link.connected = false;
+                }
+            }
+            EspUrc::Ready => {
+                log::info!("[{}] module restarted", ActorInfo::name());
+                for link in links.iter_mut() {
+                    link.connected = false;
+                    link.available = 0;
+                }
+            }
+            _ => {}
+        }
+        drop(links);
+        self.shared.unwrap().changed.signal(());
+        Completion::immediate(self)
+    }
+}
+
+/// Resolves when the monitor signals a change.
+struct Changed<'a>(&'a Signal<()>);
+
+impl<'a> Future for Changed<'a> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_wait(cx)
+    }
+}
+
+/// The event the scheduler notifies the monitor with, when the controller waited too long.
+#[derive(Copy, Clone)]
+pub struct Expire(u32);
+
+impl NotifyHandler<Expire> for Esp8266Monitor {
+    fn on_notify(self, message: Expire) -> Completion<Self> {
+        let shared = self.shared.unwrap();
+        shared.expired.set(message.0);
+        shared.changed.signal(());
+        Completion::immediate(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::api::ip::tcp::TcpSocket;
+    use crate::driver::uart::mock::{MockUart, Step, Step::*, Transcript};
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use core::fmt::Write;
+    use heapless::{consts::*, String};
+    use std::boxed::Box as StdBox;
+    use std::sync::MutexGuard;
+    use std::vec::Vec;
+
+    type Controller = Esp8266Controller<MockUart, MockTimer>;
+
+    const STARTUP: &[Step] = &[
+        Send(b"AT\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"ATE0\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CWMODE=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CIPMUX=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+        Send(b"AT+CIPRECVMODE=1\r\n"),
+        Receive(b"\r\nOK\r\n"),
+    ];
+
+    const CONNECT: &[Step] = &[
+        Send(b"AT+CIPSTART=0,\"TCP\",\"192.168.1.2\",8080\r\n"),
+        Receive(b"0,CONNECT\r\n\r\nOK\r\n"),
+    ];
+
+    const CLOSE: &[Step] = &[
+        Send(b"AT+CIPCLOSE=0\r\n"),
+        Receive(b"0,CLOSED\r\n\r\nOK\r\n"),
+    ];
+
+    struct TestDevice {
+        runtime: MockRuntime,
+        esp: Address<Controller>,
+        transcript: Transcript,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    /// An initialized ESP8266 over a module playing `steps` after the startup.
+    fn start(steps: &[&'static [Step]]) -> TestDevice {
+        let lock = mock::lock();
+        let mut script: Vec<Step> = STARTUP.to_vec();
+        for steps in steps {
+            script.extend_from_slice(steps);
+        }
+        let mut runtime = MockRuntime::new();
+        let uart = MockUart::new(StdBox::leak(script.into_boxed_slice()));
+        let transcript = uart.transcript();
+        let uart = runtime.mount(ActorContext::new(uart), ());
+        let timer = runtime.timer();
+        let esp = runtime.mount_package(Esp8266::new(), (uart, timer));
+        runtime.start();
+        runtime.run();
+        TestDevice {
+            runtime,
+            esp,
+            transcript,
+            _lock: lock,
+        }
+    }
+
+    fn connect(device: &mut TestDevice) -> TcpSocket<Controller> {
+        let esp = device.esp;
+        device.runtime.block_on(async move {
+            let mut socket = esp.tcp_open().await;
+            let dst = SocketAddress::new(IpAddress::new_v4(192, 168, 1, 2), 8080);
+            assert!(socket.connect(IpProtocol::Tcp, dst).await.is_ok());
+            socket
+        })
+    }
+
+    #[test]
+    fn test_join() {
+        let mut device = start(&[&[
+            Send(b"AT+CWJAP=\"drogue\",\"rodney\"\r\n"),
+            Receive(b"WIFI CONNECTED\r\nWIFI GOT IP\r\n\r\nOK\r\n"),
+            Send(b"AT+CIFSR\r\n"),
+            Receive(b"+CIFSR:STAIP,\"192.168.1.174\"\r\n\r\nOK\r\n"),
+        ]]);
+        let esp = device.esp;
+        let join = Join::Wpa {
+            ssid: String::from("drogue"),
+            password: String::from("rodney"),
+        };
+        let ip = device.runtime.block_on(esp.wifi_join(join)).unwrap();
+        let mut s: String<U16> = String::new();
+        write!(s, "{}", ip).unwrap();
+        assert_eq!(s.as_str(), "192.168.1.174");
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_socket() {
+        let mut device = start(&[
+            CONNECT,
+            &[
+                Send(b"AT+CIPSEND=0,5\r\n"),
+                Receive(b"\r\nOK\r\n> "),
+                Send(b"hello"),
+                Receive(b"\r\nRecv 5 bytes\r\n\r\nSEND OK\r\n"),
+                Receive(b"+IPD,0,6\r\n"),
+                Send(b"AT+CIPRECVDATA=0,6\r\n"),
+                Receive(b"+CIPRECVDATA,6:\x00\r\nOK\r\n\r\nOK\r\n"),
+            ],
+            CLOSE,
+        ]);
+        let mut socket = connect(&mut device);
+        let written = device.runtime.block_on(socket.write(b"hello"));
+        assert!(matches!(written, Ok(5)));
+        device.runtime.run();
+
+        let mut buf = [0; 16];
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Ok(6)));
+        assert_eq!(&buf[..6], b"\x00\r\nOK\r");
+
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_read_waits_for_data() {
+        let mut device = start(&[
+            CONNECT,
+            &[
+                Pause,
+                Receive(b"+IPD,0,3\r\n"),
+                Send(b"AT+CIPRECVDATA=0,3\r\n"),
+                Receive(b"+CIPRECVDATA,3:abc\r\nOK\r\n"),
+            ],
+            CLOSE,
+        ]);
+        let mut socket = connect(&mut device);
+        let timer = device.runtime.timer();
+        let transcript = device.transcript;
+        let mut buf = [0; 16];
+        let resume = async {
+            timer.delay(Milliseconds(500)).await;
+            transcript.resume();
+        };
+        let (len, _) = device
+            .runtime
+            .block_on(futures::future::join(socket.read(&mut buf), resume));
+        assert!(matches!(len, Ok(3)));
+        assert_eq!(&buf[..3], b"abc");
+        assert_eq!(device.runtime.elapsed(), Milliseconds(500u32));
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_read_timeouts_cancelled() {
+        const WAIT: &[Step] = &[
+            Pause,
+            Receive(b"+IPD,0,1\r\n"),
+            Send(b"AT+CIPRECVDATA=0,1\r\n"),
+            Receive(b"+CIPRECVDATA,1:a\r\nOK\r\n"),
+        ];
+        // more reads waiting for data than the timer has slots
+        let mut steps = Vec::new();
+        steps.push(CONNECT);
+        steps.resize(41, WAIT);
+        steps.push(CLOSE);
+        let mut device = start(&steps);
+        let mut socket = connect(&mut device);
+        let timer = device.runtime.timer();
+        let transcript = device.transcript;
+        for _ in 0..40 {
+            let mut buf = [0; 16];
+            let resume = async {
+                timer.delay(Milliseconds(10)).await;
+                transcript.resume();
+            };
+            let (len, _) = device
+                .runtime
+                .block_on(futures::future::join(socket.read(&mut buf), resume));
+            assert!(matches!(len, Ok(1)));
+        }
+
+        // their timeouts were dropped, leaving room for one that expires
+        let mut buf = [0; 16];
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Err(TcpError::ReadError)));
+        assert_eq!(
+            device.runtime.elapsed(),
+            Milliseconds(400 + RECEIVE_TIMEOUT.0)
+        );
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let mut device = start(&[CONNECT, CLOSE]);
+        let mut socket = connect(&mut device);
+        let mut buf = [0; 16];
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Err(TcpError::ReadError)));
+        assert_eq!(device.runtime.elapsed(), RECEIVE_TIMEOUT);
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+
+    #[test]
+    fn test_read_closed() {
+        let mut device = start(&[CONNECT, &[Receive(b"0,CLOSED\r\n")]]);
+        let mut socket = connect(&mut device);
+        device.runtime.run();
+        let mut buf = [0; 16];
+        let len = device.runtime.block_on(socket.read(&mut buf));
+        assert!(matches!(len, Err(TcpError::SocketClosed)));
+
+        // the module closed the link already
+        drop(socket);
+        device.runtime.run();
+        device.transcript.finish();
+    }
+}
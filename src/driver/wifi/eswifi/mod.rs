@@ -1,16 +1,16 @@
 mod parser;
 mod ready;
-mod socket_pool;
 
-use socket_pool::SocketPool;
+use crate::driver::socket_pool::SocketPool;
 
-use crate::api::arbitrator::BusArbitrator;
+use crate::api::arbitrator::{BusArbitrator, BusTransaction};
 use crate::api::delayer::Delayer;
 use crate::api::ip::tcp::{TcpError, TcpStack};
 use crate::api::ip::{IpAddress, IpAddressV4, IpProtocol, SocketAddress};
-use crate::api::spi::{ChipSelect, SpiBus, SpiError};
+use crate::api::spi::{ChipSelect, SpiBus, SpiConfig, SpiError, MODE_0};
 use crate::api::wifi::{Join, JoinError, WifiSupplicant};
 use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::rate::Hertz;
 use crate::driver::wifi::eswifi::parser::{
     CloseResponse, ConnectResponse, JoinResponse, ReadResponse, WriteResponse,
 };
@@ -45,6 +45,7 @@ where
     WAKEUP: OutputPin + 'static,
 {
     shared: Shared,
+    cs: ChipSelect<CS, T>,
     controller: ActorContext<EsWifiController<SPI, T, CS, RESET, WAKEUP>>,
     ready: EsWifiReady<READY>,
 }
@@ -68,7 +69,8 @@ where
     ) -> Self {
         Self {
             shared: Shared::new(),
-            controller: ActorContext::new(EsWifiController::new(cs, reset, wakeup))
+            cs: ChipSelect::new(cs, Milliseconds(2u32)),
+            controller: ActorContext::new(EsWifiController::new(reset, wakeup))
                 .with_name("es-wifi"),
             ready: EsWifiReady::new(ready, ready_irq),
         }
@@ -93,8 +95,11 @@ where
         supervisor: &mut Supervisor,
     ) -> Address<Self::Primary> {
         let ready_addr = self.ready.mount((), supervisor);
-        self.controller
-            .mount((&self.shared, config.0, config.1, ready_addr), supervisor)
+        self.cs.set_delayer(config.1);
+        self.controller.mount(
+            (&self.shared, &self.cs, config.0, config.1, ready_addr),
+            supervisor,
+        )
     }
 
     fn primary(&'static self) -> Address<Self::Primary> {
@@ -120,7 +125,7 @@ where
     spi: Option<Address<BusArbitrator<SPI>>>,
     delayer: Option<Address<T>>,
     ready: Option<Address<EsWifiReadyPin>>,
-    cs: ChipSelect<CS, T>,
+    cs: Option<&'static ChipSelect<CS, T>>,
     reset: RESET,
     wakeup: WAKEUP,
     state: State,
@@ -145,13 +150,13 @@ where
     RESET: OutputPin + 'static,
     WAKEUP: OutputPin + 'static,
 {
-    pub fn new(cs: CS, reset: RESET, wakeup: WAKEUP) -> Self {
+    pub fn new(reset: RESET, wakeup: WAKEUP) -> Self {
         Self {
             address: None,
             spi: None,
             delayer: None,
             ready: None,
-            cs: ChipSelect::new(cs, Milliseconds(2u32)),
+            cs: None,
             reset,
             wakeup,
             state: State::Uninitialized,
@@ -173,6 +178,13 @@ where
         self.delayer.unwrap().delay(Milliseconds(50u32)).await;
     }
 
+    async fn begin_transaction(&self) -> BusTransaction<SPI> {
+        self.spi
+            .unwrap()
+            .begin_selected_transaction(self.cs.unwrap(), SPI_CONFIG)
+            .await
+    }
+
     async fn await_data_ready(&self) {
         self.ready.unwrap().request(AwaitReady {}).await
     }
@@ -191,8 +203,7 @@ where
 
         self.await_data_ready().await;
         {
-            let spi = self.spi.unwrap().begin_transaction().await;
-            let cs = self.cs.select().await;
+            let spi = self.begin_transaction().await;
 
             loop {
                 if !self.is_data_ready().await {
@@ -252,8 +263,7 @@ where
 
         self.await_data_ready().await;
         {
-            let spi = self.spi.unwrap().begin_transaction().await;
-            let _cs = self.cs.select().await;
+            let spi = self.begin_transaction().await;
 
             for chunk in command.chunks(2) {
                 let mut xfer: [u8; 2] = [0; 2];
@@ -264,7 +274,7 @@ where
                     xfer[0] = 0x0A
                 }
 
-                spi.spi_transfer(&mut xfer).await?;
+                spi.spi_write(&xfer).await?;
             }
         }
         self.receive(response).await
@@ -274,8 +284,7 @@ where
         self.await_data_ready().await;
         let mut pos = 0;
 
-        let spi = self.spi.unwrap().begin_transaction().await;
-        let _cs = self.cs.select().await;
+        let spi = self.begin_transaction().await;
 
         while self.is_data_ready().await {
             let mut xfer: [u8; 2] = [0x0A, 0x0A];
@@ -459,8 +468,7 @@ where
 
                     self.await_data_ready().await;
                     {
-                        let spi = self.spi.unwrap().begin_transaction().await;
-                        let _cs = self.cs.select().await;
+                        let spi = self.begin_transaction().await;
 
                         for chunk in prefix.chunks(2) {
                             let mut xfer: [u8; 2] = [0; 2];
@@ -472,7 +480,7 @@ where
                                 xfer[0] = 0x0A
                             }
 
-                            spi.spi_transfer(&mut xfer).await.unwrap();
+                            spi.spi_write(&xfer).await.unwrap();
                         }
 
                         for chunk in remainder.chunks(2) {
@@ -485,7 +493,7 @@ where
                             }
 
                             //log::info!("transfer {:?}", xfer);
-                            spi.spi_transfer(&mut xfer).await.unwrap();
+                            spi.spi_write(&xfer).await.unwrap();
                         }
                     }
 
@@ -544,14 +552,13 @@ where
 
                         self.await_data_ready().await;
                         {
-                            let spi = self.spi.unwrap().begin_transaction().await;
-                            let _cs = self.cs.select().await;
+                            let spi = self.begin_transaction().await;
 
                             let mut xfer = [b'0', b'R'];
-                            spi.spi_transfer(&mut xfer).await.unwrap();
+                            spi.spi_write(&xfer).await.unwrap();
 
                             xfer = [b'\n', b'\r'];
-                            spi.spi_transfer(&mut xfer).await.unwrap();
+                            spi.spi_write(&xfer).await.unwrap();
                         }
 
                         self.await_data_ready().await;
@@ -614,6 +621,7 @@ where
                 }
             }
             .await;
+            self.shared.unwrap().socket_pool.close(handle);
             self
         })
     }
@@ -621,6 +629,9 @@ where
 
 const NAK: u8 = 0x15;
 
+/// The ISM43362 module runs SPI in mode 0, at up to 20 MHz.
+const SPI_CONFIG: SpiConfig = SpiConfig::new(MODE_0, Hertz(20_000_000));
+
 impl<SPI, T, CS, RESET, WAKEUP> Actor for EsWifiController<SPI, T, CS, RESET, WAKEUP>
 where
     SPI: SpiBus<Word = u8>,
@@ -631,6 +642,7 @@ where
 {
     type Configuration = (
         &'static Shared,
+        &'static ChipSelect<CS, T>,
         Address<BusArbitrator<SPI>>,
         Address<T>,
         Address<EsWifiReadyPin>,
@@ -642,10 +654,10 @@ where
     {
         self.shared.replace(config.0);
         self.address.replace(address);
-        self.spi.replace(config.1);
-        self.delayer.replace(config.2);
-        self.ready.replace(config.3);
-        self.cs.set_delayer(config.2);
+        self.cs.replace(config.1);
+        self.spi.replace(config.2);
+        self.delayer.replace(config.3);
+        self.ready.replace(config.4);
     }
 
     fn on_start(self) -> Completion<Self>
@@ -1 +1,2 @@
+pub mod esp8266;
 pub mod eswifi;
@@ -0,0 +1,66 @@
+//! A pin whose level tests set and follow, for drivers of devices wired to GPIOs.
+
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// Clones of a pin share its level, so a test keeps one to drive or watch the pin the driver
+/// owns.
+#[derive(Clone, Default)]
+pub struct MockPin {
+    level: Rc<Cell<bool>>,
+    history: Rc<RefCell<Vec<bool>>>,
+}
+
+impl MockPin {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the pin is high.
+    pub fn level(&self) -> bool {
+        self.level.get()
+    }
+
+    /// Drive the pin, as the device on the other end would.
+    pub fn set(&self, high: bool) {
+        self.level.set(high);
+    }
+
+    /// The levels the driver set so far, oldest first, clearing them.
+    pub fn take_history(&self) -> Vec<bool> {
+        self.history.replace(Vec::new())
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.level.set(false);
+        self.history.borrow_mut().push(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.level.set(true);
+        self.history.borrow_mut().push(true);
+        Ok(())
+    }
+}
+
+impl InputPin for MockPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.level.get())
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.level.get())
+    }
+}
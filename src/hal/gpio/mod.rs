@@ -1,5 +1,8 @@
 use embedded_hal::digital::v2::OutputPin;
 
+#[cfg(test)]
+pub(crate) mod mock;
+
 pub trait ActiveOutput {
     fn set_active<P: OutputPin>(pin: &mut P) -> Result<(), P::Error>;
     fn set_inactive<P: OutputPin>(pin: &mut P) -> Result<(), P::Error>;
@@ -0,0 +1,31 @@
+use crate::api::i2c::I2cError;
+
+/// Trait for devices that support I2C as a interrupt-driven DMA peripheral.
+pub trait DmaI2cHal {
+    /// Prepare a transfer to `address`, writing `tx_buffer` and then reading into `rx_buffer`
+    /// after a repeated start. Either buffer may be empty, but not both. Implementations can
+    /// return TxBufferTooLong or RxBufferTooLong if a buffer is too big.
+    fn prepare_transfer(
+        &self,
+        address: u8,
+        tx_buffer: &[u8],
+        rx_buffer: &mut [u8],
+    ) -> Result<(), I2cError>;
+
+    /// Start the prepared transfer.
+    fn start_transfer(&self);
+
+    /// Complete a transfer, after the stop condition has been sent.
+    fn finish_transfer(&self) -> Result<(), I2cError>;
+
+    /// Abort a transfer in progress.
+    fn cancel_transfer(&self);
+
+    /// Bring the bus back to idle after an error, clocking SCL until a device holding SDA low
+    /// releases it, and issuing a stop condition.
+    fn recover(&self);
+
+    /// Process interrupts for the peripheral. Returns true when the transfer has ended,
+    /// successfully or not.
+    fn process_interrupts(&self) -> bool;
+}
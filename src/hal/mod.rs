@@ -1,8 +1,14 @@
 //! General HAL types and traits.
 
 pub mod gpio;
+pub mod i2c;
+pub mod pwm;
+pub mod rtc;
+pub mod spi;
 pub mod timer;
 pub mod uart;
+#[cfg(feature = "usb")]
+pub mod usb;
 
 /// Enum for denoting active-high or active-low.
 pub enum Active {
@@ -0,0 +1,52 @@
+/// The share of each period a PWM output is high, from [`Duty::OFF`] to [`Duty::FULL`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duty(pub u16);
+
+impl Duty {
+    pub const OFF: Duty = Duty(0);
+    pub const FULL: Duty = Duty(u16::MAX);
+
+    pub fn from_percent(percent: u8) -> Self {
+        let percent = if percent > 100 { 100 } else { percent };
+        Self((percent as u32 * u16::MAX as u32 / 100) as u16)
+    }
+
+    /// The duty at which an LED looks `brightness` out of 255 bright.
+    ///
+    /// The eye tells dim levels apart much better than bright ones, so brightness is squared,
+    /// near enough to how it is perceived for fades to look even.
+    pub fn perceived(brightness: u8) -> Self {
+        let brightness = brightness as u64;
+        Self((brightness * brightness * u16::MAX as u64 / (255 * 255)) as u16)
+    }
+
+    /// The duty of the same output, active-low.
+    pub fn inverted(self) -> Self {
+        Self(u16::MAX - self.0)
+    }
+
+    /// The duty as a compare value of a timer counting up to `max`.
+    pub fn scale(self, max: u32) -> u32 {
+        (self.0 as u64 * max as u64 / u16::MAX as u64) as u32
+    }
+}
+
+/// A PWM channel, enabled and running at the frequency it is to be driven at.
+pub trait Pwm {
+    fn set_duty(&mut self, duty: Duty);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duty() {
+        assert_eq!(Duty::perceived(0), Duty::OFF);
+        assert_eq!(Duty::perceived(255), Duty::FULL);
+        assert!(Duty::perceived(128) < Duty::from_percent(50));
+        assert_eq!(Duty::from_percent(100).scale(1_000), 1_000);
+        assert_eq!(Duty::from_percent(25).scale(1_000), 249);
+        assert_eq!(Duty::FULL.inverted(), Duty::OFF);
+    }
+}
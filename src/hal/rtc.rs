@@ -0,0 +1,7 @@
+use crate::domain::time::datetime::DateTime;
+
+/// A calendar clock, counting on while the rest of the device sleeps.
+pub trait Rtc {
+    fn get_datetime(&mut self) -> DateTime;
+    fn set_datetime(&mut self, datetime: DateTime);
+}
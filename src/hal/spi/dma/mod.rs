@@ -0,0 +1,27 @@
+use crate::api::spi::{SpiConfig, SpiError};
+
+/// Trait for devices that support SPI as a interrupt-driven DMA peripheral.
+pub trait DmaSpiHal {
+    /// Apply the mode, frequency and bit order of a device. Only called between transfers, and
+    /// when the settings differ from the previous device.
+    fn configure(&self, config: &SpiConfig);
+
+    /// Prepare a full-duplex transfer, replacing the contents of `buffer` with the bytes
+    /// received. Implementations can return TransferTooLong if the buffer is too big.
+    fn prepare_transfer(&self, buffer: &mut [u8]) -> Result<(), SpiError>;
+
+    /// Prepare a transfer of `tx_buffer`, discarding the bytes received.
+    fn prepare_write(&self, tx_buffer: &[u8]) -> Result<(), SpiError>;
+
+    /// Prepare a transfer receiving into `rx_buffer`, while clocking out filler bytes.
+    fn prepare_read(&self, rx_buffer: &mut [u8]) -> Result<(), SpiError>;
+
+    /// Start the prepared transfer.
+    fn start_transfer(&self);
+
+    /// Complete a transfer.
+    fn finish_transfer(&self) -> Result<(), SpiError>;
+
+    /// Process interrupts for the peripheral. Returns true when the transfer has ended.
+    fn process_interrupts(&self) -> bool;
+}
@@ -1,6 +1,17 @@
 use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::Clock;
 
 pub trait Timer {
     fn start(&mut self, duration: Milliseconds);
     fn clear_update_interrupt_flag(&mut self);
+
+    /// The longest duration the timer can count in one go.
+    fn max_duration(&self) -> Milliseconds;
+}
+
+/// A clock whose hardware counter wraps, interrupting as it does, so that the wraps are
+/// counted however long the clock goes unread.
+pub trait WrappingClock: Clock {
+    /// Count the wrap of the counter the interrupt is for, if it was not counted yet.
+    fn on_wrap(&self);
 }
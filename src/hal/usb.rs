@@ -0,0 +1,11 @@
+//! USB device peripherals, driven by the `usb-device` stack.
+
+use usb_device::bus::UsbBusAllocator;
+
+/// A USB device peripheral, which platforms hand over to the `usb-device` stack.
+pub trait UsbBus {
+    type Bus: usb_device::bus::UsbBus + 'static;
+
+    /// Give up the peripheral to the stack, for classes and the device to allocate endpoints.
+    fn into_allocator(self) -> UsbBusAllocator<Self::Bus>;
+}
@@ -16,14 +16,14 @@ mod future;
 pub mod hal;
 pub mod platform;
 pub mod system;
-pub(crate) mod util;
+pub mod util;
 
 /// Easy imports for common types and traits.
 pub mod prelude {
     pub use crate::device;
     pub use crate::system::{
         actor::{Actor, ActorContext, ActorInfo, Configurable},
-        address::Address,
+        address::{Address, SendError},
         bus::EventBus,
         device::{Device, DeviceConfiguration},
         handler::{Completion, EventHandler, NotifyHandler, RequestHandler, Response},
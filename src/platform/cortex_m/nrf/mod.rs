@@ -1,3 +1,9 @@
 pub mod gpiote;
+pub mod pwm;
+#[cfg(feature = "ble-nrf-advertising")]
+pub mod radio;
+pub mod rtc;
+pub mod spim;
 pub mod timer;
+pub mod twim;
 pub mod uarte;
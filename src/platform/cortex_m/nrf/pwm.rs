@@ -0,0 +1,25 @@
+//! PWM channels for nRF series.
+#[cfg(feature = "nrf52833")]
+use nrf52833_hal as hal;
+
+use crate::hal::pwm::{Duty, Pwm as HalPwm};
+use hal::pwm::{Channel, Instance, Pwm};
+
+/// One of the four channels of a PWM peripheral, which the others may share.
+pub struct PwmChannel<T: Instance> {
+    pwm: &'static Pwm<T>,
+    channel: Channel,
+}
+
+impl<T: Instance> PwmChannel<T> {
+    pub fn new(pwm: &'static Pwm<T>, channel: Channel) -> Self {
+        Self { pwm, channel }
+    }
+}
+
+impl<T: Instance> HalPwm for PwmChannel<T> {
+    fn set_duty(&mut self, duty: Duty) {
+        let max = self.pwm.max_duty() as u32;
+        self.pwm.set_duty_off(self.channel, duty.scale(max) as u16);
+    }
+}
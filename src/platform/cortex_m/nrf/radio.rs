@@ -0,0 +1,169 @@
+//! A broadcast-only BLE backend, using the RADIO of the nRF series.
+//!
+//! It sends non-connectable advertisements, so centrals pick up the state of a device from its
+//! advertising data, such as service data, without connecting. There is no link layer for
+//! connections: connectable advertising, `notify` and `disconnect` answer
+//! `BleError::Unsupported`, `poll` never reports a central, and `respond` has nobody to answer.
+//! The GATT services of a peripheral over this backend are kept, but never served.
+//!
+//! The radio needs the high-frequency clock to run from the external crystal, for instance
+//! using `Clocks::enable_ext_hfosc` of the HAL.
+#[cfg(feature = "nrf52833")]
+use nrf52833_hal as hal;
+
+use hal::pac::{FICR, RADIO};
+
+use crate::api::ble::{AdvertisingData, BleError, CharacteristicHandle, Connection};
+use crate::driver::ble::{Transport, TransportEvent};
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+/// The access address of advertising channel PDUs.
+const ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+
+/// ADV_NONCONN_IND, in the header of an advertising channel PDU.
+const ADV_NONCONN_IND: u8 = 0x02;
+
+/// Header, advertiser address and up to 31 bytes of advertising data.
+const MAX_PDU: usize = 2 + 6 + 31;
+
+/// Polls of the DISABLED event before giving up on a PDU, well above the 600 or so
+/// microseconds it takes to send one.
+const SEND_ATTEMPTS: u32 = 100_000;
+
+/// Advertising channels, with their frequency as an offset from 2400 MHz.
+const CHANNELS: [(u8, u8); 3] = [(37, 2), (38, 26), (39, 80)];
+
+pub struct Radio {
+    radio: RADIO,
+    address: [u8; 6],
+    random: bool,
+    pdu: [u8; MAX_PDU],
+}
+
+impl Radio {
+    /// Set up the radio for BLE 1M, advertising with the device address programmed in `FICR`.
+    pub fn new(radio: RADIO, ficr: &FICR) -> Self {
+        let low = ficr.deviceaddr[0].read().bits().to_le_bytes();
+        let high = (ficr.deviceaddr[1].read().bits() as u16).to_le_bytes();
+        let random = ficr.deviceaddrtype.read().deviceaddrtype().is_random();
+        let mut address = [low[0], low[1], low[2], low[3], high[0], high[1]];
+        if random {
+            // a random static address has both most significant bits set
+            address[5] |= 0xc0;
+        }
+
+        radio.mode.write(|w| w.mode().ble_1mbit());
+        radio.txpower.write(|w| w.txpower()._0d_bm());
+        // an 8-bit S0 holds the PDU header, and LENGTH the length of the payload
+        radio
+            .pcnf0
+            .write(|w| unsafe { w.lflen().bits(8).s0len().set_bit().s1len().bits(0) });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(37)
+                .statlen()
+                .bits(0)
+                .balen()
+                .bits(3)
+                .endian()
+                .little()
+                .whiteen()
+                .enabled()
+        });
+        radio
+            .base0
+            .write(|w| unsafe { w.bits(ACCESS_ADDRESS << 8) });
+        radio
+            .prefix0
+            .write(|w| unsafe { w.ap0().bits((ACCESS_ADDRESS >> 24) as u8) });
+        radio.txaddress.write(|w| unsafe { w.txaddress().bits(0) });
+        radio.crccnf.write(|w| w.len().three().skipaddr().skip());
+        radio
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(0x0000_065B) });
+        radio
+            .crcinit
+            .write(|w| unsafe { w.crcinit().bits(0x0055_5555) });
+        radio
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+
+        Self {
+            radio,
+            address,
+            random,
+            pdu: [0; MAX_PDU],
+        }
+    }
+
+    /// Send the PDU on `channel`, waiting until the radio is done with it, unless it gets
+    /// stuck.
+    fn send(&mut self, channel: u8, frequency: u8) -> Result<(), BleError> {
+        self.radio
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(frequency) });
+        self.radio
+            .datawhiteiv
+            .write(|w| unsafe { w.datawhiteiv().bits(channel) });
+        self.radio
+            .packetptr
+            .write(|w| unsafe { w.bits(self.pdu.as_ptr() as u32) });
+
+        self.radio.events_disabled.reset();
+        compiler_fence(SeqCst);
+        self.radio.tasks_txen.write(|w| unsafe { w.bits(1) });
+        let mut attempts = SEND_ATTEMPTS;
+        while self.radio.events_disabled.read().bits() == 0 && attempts > 0 {
+            attempts -= 1;
+        }
+        compiler_fence(SeqCst);
+        if attempts > 0 {
+            return Ok(());
+        }
+
+        // such as when the high-frequency clock does not run from the crystal
+        self.radio.tasks_disable.write(|w| unsafe { w.bits(1) });
+        attempts = 1000;
+        while self.radio.events_disabled.read().bits() == 0 && attempts > 0 {
+            attempts -= 1;
+        }
+        self.radio.events_disabled.reset();
+        compiler_fence(SeqCst);
+        Err(BleError::Transport)
+    }
+}
+
+impl Transport for Radio {
+    fn advertise(&mut self, data: &AdvertisingData, connectable: bool) -> Result<(), BleError> {
+        if connectable {
+            return Err(BleError::Unsupported);
+        }
+        let data = data.as_bytes();
+        self.pdu[0] = ADV_NONCONN_IND | if self.random { 0x40 } else { 0 };
+        self.pdu[1] = (6 + data.len()) as u8;
+        self.pdu[2..8].copy_from_slice(&self.address);
+        self.pdu[8..8 + data.len()].copy_from_slice(data);
+
+        for (channel, frequency) in CHANNELS.iter() {
+            self.send(*channel, *frequency)?;
+        }
+        Ok(())
+    }
+
+    fn notify(&mut self, _: Connection, _: CharacteristicHandle, _: &[u8]) -> Result<(), BleError> {
+        Err(BleError::Unsupported)
+    }
+
+    fn respond(&mut self, _: Connection, _: Result<&[u8], BleError>) {
+        // without connections, no central ever waits on a response
+    }
+
+    fn disconnect(&mut self, _: Connection) -> Result<(), BleError> {
+        Err(BleError::Unsupported)
+    }
+
+    fn poll(&mut self) -> Option<TransportEvent> {
+        // without connections, no central ever does anything
+        None
+    }
+}
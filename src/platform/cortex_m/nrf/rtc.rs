@@ -0,0 +1,70 @@
+//! A monotonic clock for nRF series, counting with an RTC.
+#[cfg(feature = "nrf52833")]
+use nrf52833_hal as hal;
+
+use crate::domain::time::{clock, fraction::Fraction, Clock, Instant};
+use crate::hal::timer::WrappingClock;
+use crate::platform::with_critical_section;
+use core::sync::atomic::{AtomicU32, Ordering};
+use hal::rtc::{Instance, Rtc, RtcInterrupt};
+
+/// Divides the 32.768 kHz low-frequency clock down to 1024 ticks per second.
+const PRESCALER: u32 = 31;
+
+/// The RTC counts 24 bits, which wrap every 4.5 hours at 1024 Hz. The RTC interrupts as it
+/// wraps, so mount a [`ClockInterrupt`](crate::driver::timer::ClockInterrupt) on its IRQ to
+/// count the wraps.
+pub struct RtcClock<T: Instance> {
+    rtc: Rtc<T>,
+    overflows: AtomicU32,
+}
+
+impl<T: Instance> RtcClock<T> {
+    /// Start counting. The low-frequency clock must be running.
+    pub fn new(rtc: T) -> Self {
+        let mut rtc = Rtc::new(rtc, PRESCALER).unwrap();
+        rtc.reset_event(RtcInterrupt::Overflow);
+        rtc.enable_interrupt(RtcInterrupt::Overflow, None);
+        rtc.enable_counter();
+        Self {
+            rtc,
+            overflows: AtomicU32::new(0),
+        }
+    }
+
+    /// Count a wrap if the overflow event is set, within a critical section.
+    fn count_wrap(&self) -> bool {
+        if self.rtc.is_event_triggered(RtcInterrupt::Overflow) {
+            self.rtc.reset_event(RtcInterrupt::Overflow);
+            self.overflows.fetch_add(1, Ordering::SeqCst);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<T: Instance> Clock for RtcClock<T> {
+    type T = u64;
+    const SCALING_FACTOR: Fraction = Fraction::new(1, 1024);
+
+    fn try_now(&self) -> Result<Instant<Self>, clock::Error> {
+        let ticks = with_critical_section(|_| {
+            let mut counter = self.rtc.get_counter();
+            // the interrupt may be pending, and the counter read just before it wrapped
+            if self.count_wrap() {
+                counter = self.rtc.get_counter();
+            }
+            (self.overflows.load(Ordering::SeqCst) as u64) << 24 | counter as u64
+        });
+        Ok(Instant::new(ticks))
+    }
+}
+
+impl<T: Instance> WrappingClock for RtcClock<T> {
+    fn on_wrap(&self) {
+        with_critical_section(|_| {
+            self.count_wrap();
+        });
+    }
+}
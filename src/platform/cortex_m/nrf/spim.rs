@@ -0,0 +1,160 @@
+//! SPI implementation for nRF series
+#[cfg(feature = "nrf52833")]
+use nrf52833_hal as hal;
+
+use crate::api::spi::{BitOrder, Phase, Polarity, SpiConfig, SpiError};
+use crate::platform::cortex_m::nrf::uarte::slice_in_ram_or;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+pub use hal::spim::{Frequency, Instance, Mode, Pins};
+
+pub struct Spim<T>
+where
+    T: Instance,
+{
+    spim: T,
+}
+
+impl<T> Spim<T>
+where
+    T: Instance,
+{
+    /// `orc` is the filler byte clocked out while only reading.
+    pub fn new(spim: T, pins: Pins, frequency: Frequency, mode: Mode, orc: u8) -> Self {
+        let spim = hal::spim::Spim::new(spim, pins, frequency, mode, orc).free();
+        spim.intenset.write(|w| w.end().set());
+
+        Self { spim }
+    }
+
+    fn prepare(
+        &self,
+        tx: *const u8,
+        tx_len: usize,
+        rx: *mut u8,
+        rx_len: usize,
+    ) -> Result<(), SpiError> {
+        if tx_len > hal::target_constants::EASY_DMA_SIZE
+            || rx_len > hal::target_constants::EASY_DMA_SIZE
+        {
+            return Err(SpiError::TransferTooLong);
+        }
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started.
+        compiler_fence(SeqCst);
+
+        self.spim
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(tx as u32) });
+        self.spim
+            .txd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(tx_len as _) });
+        self.spim
+            .rxd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(rx as u32) });
+        self.spim
+            .rxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(rx_len as _) });
+        Ok(())
+    }
+}
+
+impl<T> crate::hal::spi::dma::DmaSpiHal for Spim<T>
+where
+    T: Instance,
+{
+    fn configure(&self, config: &SpiConfig) {
+        // Use the fastest supported rate not above the one of the device.
+        let frequency = match config.frequency.0 {
+            f if f >= 8_000_000 => Frequency::M8,
+            f if f >= 4_000_000 => Frequency::M4,
+            f if f >= 2_000_000 => Frequency::M2,
+            f if f >= 1_000_000 => Frequency::M1,
+            f if f >= 500_000 => Frequency::K500,
+            f if f >= 250_000 => Frequency::K250,
+            _ => Frequency::K125,
+        };
+
+        self.spim.enable.write(|w| w.enable().disabled());
+        self.spim
+            .frequency
+            .write(|w| w.frequency().variant(frequency));
+        self.spim.config.write(|w| {
+            let w = match config.bit_order {
+                BitOrder::MsbFirst => w.order().msb_first(),
+                BitOrder::LsbFirst => w.order().lsb_first(),
+            };
+            let w = match config.mode.polarity {
+                Polarity::IdleLow => w.cpol().active_high(),
+                Polarity::IdleHigh => w.cpol().active_low(),
+            };
+            match config.mode.phase {
+                Phase::CaptureOnFirstTransition => w.cpha().leading(),
+                Phase::CaptureOnSecondTransition => w.cpha().trailing(),
+            }
+        });
+        self.spim.enable.write(|w| w.enable().enabled());
+    }
+
+    fn prepare_transfer(&self, buffer: &mut [u8]) -> Result<(), SpiError> {
+        // Each byte is transmitted before the byte received in its place is written.
+        let ptr = buffer.as_mut_ptr();
+        self.prepare(ptr, buffer.len(), ptr, buffer.len())
+    }
+
+    fn prepare_write(&self, tx_buffer: &[u8]) -> Result<(), SpiError> {
+        // We can only DMA out of RAM.
+        slice_in_ram_or(tx_buffer, SpiError::BufferNotInRAM)?;
+        self.prepare(
+            tx_buffer.as_ptr(),
+            tx_buffer.len(),
+            core::ptr::null_mut(),
+            0,
+        )
+    }
+
+    fn prepare_read(&self, rx_buffer: &mut [u8]) -> Result<(), SpiError> {
+        // With nothing to transmit, the ORC byte is clocked out.
+        self.prepare(
+            core::ptr::null(),
+            0,
+            rx_buffer.as_mut_ptr(),
+            rx_buffer.len(),
+        )
+    }
+
+    fn start_transfer(&self) {
+        self.spim.events_end.reset();
+        self.spim.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
+    fn finish_transfer(&self) -> Result<(), SpiError> {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed.
+        compiler_fence(SeqCst);
+
+        if self.spim.txd.amount.read().bits() != self.spim.txd.maxcnt.read().bits()
+            || self.spim.rxd.amount.read().bits() != self.spim.rxd.maxcnt.read().bits()
+        {
+            Err(SpiError::Unknown)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn process_interrupts(&self) -> bool {
+        if self.spim.events_end.read().bits() != 0 {
+            self.spim.events_end.reset();
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -46,4 +46,9 @@ where
         self.timer.task_stop().write(|w| unsafe { w.bits(1) });
         self.timer.event_compare_cc0().write(|w| w);
     }
+
+    /// As long as the deadline rate, in millihertz, stays above zero.
+    fn max_duration(&self) -> Milliseconds {
+        Milliseconds(1_000_000)
+    }
 }
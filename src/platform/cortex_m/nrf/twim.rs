@@ -0,0 +1,272 @@
+//! I2C implementation for nRF series
+#[cfg(feature = "nrf52833")]
+use nrf52833_hal as hal;
+
+#[cfg(feature = "nrf9160")]
+use hal::pac::{p0_ns as p0, P0_NS as P0};
+
+#[cfg(not(feature = "nrf9160"))]
+use hal::pac::{p0, P0};
+
+#[cfg(any(feature = "nrf52833", feature = "nrf52840"))]
+use hal::pac::P1;
+
+use crate::api::i2c::I2cError;
+use crate::platform::cortex_m::nrf::uarte::slice_in_ram_or;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+pub use hal::twim::{Frequency, Instance, Pins};
+
+const ERROR_OVERRUN: u32 = 0b001;
+const ERROR_ANACK: u32 = 0b010;
+const ERROR_DNACK: u32 = 0b100;
+
+// Half a clock period at 100kHz, at 64MHz.
+const HALF_PERIOD_CYCLES: u32 = 320;
+
+pub struct Twim<T>
+where
+    T: Instance,
+{
+    twim: T,
+    scl: u32,
+    sda: u32,
+}
+
+impl<T> Twim<T>
+where
+    T: Instance,
+{
+    pub fn new(twim: T, pins: Pins, frequency: Frequency) -> Self {
+        let scl = pins.scl.psel_bits();
+        let sda = pins.sda.psel_bits();
+        // Configure the pins as TWIM would, and hold on to the numbers for bus recovery.
+        let twim = hal::twim::Twim::new(twim, pins, frequency).free();
+        twim.intenset.write(|w| w.stopped().set().error().set());
+
+        Self { twim, scl, sda }
+    }
+}
+
+impl<T> crate::hal::i2c::dma::DmaI2cHal for Twim<T>
+where
+    T: Instance,
+{
+    fn prepare_transfer(
+        &self,
+        address: u8,
+        tx_buffer: &[u8],
+        rx_buffer: &mut [u8],
+    ) -> Result<(), I2cError> {
+        if tx_buffer.len() > hal::target_constants::EASY_DMA_SIZE {
+            return Err(I2cError::TxBufferTooLong);
+        }
+        if rx_buffer.len() > hal::target_constants::EASY_DMA_SIZE {
+            return Err(I2cError::RxBufferTooLong);
+        }
+
+        // We can only DMA out of RAM.
+        if !tx_buffer.is_empty() {
+            slice_in_ram_or(tx_buffer, I2cError::BufferNotInRAM)?;
+        }
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started.
+        compiler_fence(SeqCst);
+
+        self.twim
+            .address
+            .write(|w| unsafe { w.address().bits(address) });
+
+        self.twim
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(tx_buffer.as_ptr() as u32) });
+        self.twim
+            .txd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(tx_buffer.len() as _) });
+
+        self.twim
+            .rxd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(rx_buffer.as_ptr() as u32) });
+        self.twim
+            .rxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(rx_buffer.len() as _) });
+
+        self.twim.shorts.write(|w| {
+            if rx_buffer.is_empty() {
+                w.lasttx_stop().enabled()
+            } else if tx_buffer.is_empty() {
+                w.lastrx_stop().enabled()
+            } else {
+                w.lasttx_startrx().enabled().lastrx_stop().enabled()
+            }
+        });
+
+        self.twim.events_stopped.reset();
+        self.twim.events_error.reset();
+        self.twim
+            .errorsrc
+            .write(|w| unsafe { w.bits(ERROR_OVERRUN | ERROR_ANACK | ERROR_DNACK) });
+        Ok(())
+    }
+
+    fn start_transfer(&self) {
+        if self.twim.txd.maxcnt.read().bits() == 0 {
+            self.twim.tasks_startrx.write(|w| unsafe { w.bits(1) });
+        } else {
+            self.twim.tasks_starttx.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    fn finish_transfer(&self) -> Result<(), I2cError> {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed.
+        compiler_fence(SeqCst);
+
+        let errors = self.twim.errorsrc.read().bits();
+        if errors & ERROR_ANACK != 0 {
+            Err(I2cError::AddressNack)
+        } else if errors & ERROR_DNACK != 0 {
+            Err(I2cError::DataNack)
+        } else if errors & ERROR_OVERRUN != 0 {
+            Err(I2cError::Overrun)
+        } else if self.twim.txd.amount.read().bits() != self.twim.txd.maxcnt.read().bits()
+            || self.twim.rxd.amount.read().bits() != self.twim.rxd.maxcnt.read().bits()
+        {
+            Err(I2cError::Bus)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn cancel_transfer(&self) {
+        self.twim.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        // Wait for the stop condition, unless the bus is stuck.
+        let mut attempts = 1000;
+        while self.twim.events_stopped.read().bits() == 0 && attempts > 0 {
+            attempts -= 1;
+        }
+
+        self.twim.events_stopped.reset();
+        self.twim.events_error.reset();
+        compiler_fence(SeqCst);
+    }
+
+    fn recover(&self) {
+        // Hand the pins over to GPIO while clocking the bus.
+        self.twim.enable.write(|w| w.enable().disabled());
+
+        let scl = BusPin::new(self.scl);
+        let sda = BusPin::new(self.sda);
+        scl.release();
+        sda.release();
+
+        // A device in the middle of sending a byte releases SDA within 9 clocks.
+        for _ in 0..9 {
+            if sda.is_high() {
+                break;
+            }
+            scl.pull_low();
+            cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+            scl.release();
+            cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+        }
+
+        // Stop condition: SDA rising while SCL is high.
+        sda.pull_low();
+        cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+        sda.release();
+        cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+
+        scl.restore();
+        sda.restore();
+        self.twim.enable.write(|w| w.enable().enabled());
+    }
+
+    fn process_interrupts(&self) -> bool {
+        if self.twim.events_error.read().bits() != 0 {
+            self.twim.events_error.reset();
+            // Errors do not end the transfer by themselves.
+            self.twim.tasks_stop.write(|w| unsafe { w.bits(1) });
+        }
+
+        if self.twim.events_stopped.read().bits() != 0 {
+            self.twim.events_stopped.reset();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A bus line driven directly through GPIO during recovery.
+struct BusPin {
+    port: &'static p0::RegisterBlock,
+    pin: usize,
+}
+
+impl BusPin {
+    fn new(psel: u32) -> Self {
+        #[cfg(any(feature = "nrf52833", feature = "nrf52840"))]
+        let port = if psel & 0x20 != 0 {
+            unsafe { &*P1::ptr() }
+        } else {
+            unsafe { &*P0::ptr() }
+        };
+        #[cfg(not(any(feature = "nrf52833", feature = "nrf52840")))]
+        let port = unsafe { &*P0::ptr() };
+
+        Self {
+            port,
+            pin: (psel & 0x1F) as usize,
+        }
+    }
+
+    /// Open-drain output with the line released.
+    fn release(&self) {
+        self.port.outset.write(|w| unsafe { w.bits(1 << self.pin) });
+        self.port.pin_cnf[self.pin].write(|w| {
+            w.dir()
+                .output()
+                .input()
+                .connect()
+                .pull()
+                .pullup()
+                .drive()
+                .s0d1()
+                .sense()
+                .disabled()
+        });
+    }
+
+    fn pull_low(&self) {
+        self.port.outclr.write(|w| unsafe { w.bits(1 << self.pin) });
+    }
+
+    fn is_high(&self) -> bool {
+        self.port.in_.read().bits() & (1 << self.pin) != 0
+    }
+
+    /// The configuration TWIM expects of its pins.
+    fn restore(&self) {
+        self.port.pin_cnf[self.pin].write(|w| {
+            w.dir()
+                .input()
+                .input()
+                .connect()
+                .pull()
+                .pullup()
+                .drive()
+                .s0d1()
+                .sense()
+                .disabled()
+        });
+    }
+}
@@ -0,0 +1,99 @@
+//! A monotonic clock, counting with a free-running 32-bit timer.
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+use crate::stm32::TIM5;
+use stm32l4xx_hal::pac::RCC;
+use stm32l4xx_hal::stm32::TIM2;
+
+use stm32l4xx_hal::rcc::{Clocks, APB1R1};
+
+use crate::domain::time::{clock, fraction::Fraction, Clock, Instant};
+use crate::hal::timer::WrappingClock;
+use crate::platform::with_critical_section;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Ticks per second.
+const FREQUENCY: u32 = 10_000;
+
+/// A clock ticking at 10 kHz. The 32 bits of the timer wrap every 5 days. The timer
+/// interrupts as it wraps, so mount a [`ClockInterrupt`](crate::driver::timer::ClockInterrupt)
+/// on its IRQ to count the wraps.
+pub struct MonotonicTimer<TIM> {
+    tim: TIM,
+    overflows: AtomicU32,
+}
+
+macro_rules! hal {
+    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident, $apb:ident, $apbenr:ident, $apbrstr:ident),)+) => {
+        $(
+            impl MonotonicTimer<$TIM> {
+                pub fn $tim(tim: $TIM, clocks: Clocks, apb: &mut $apb) -> Self {
+                    unsafe {
+                        (&(*RCC::ptr()).$apbenr).modify(|_,w| w.$timXen().set_bit());
+                        (&(*RCC::ptr()).$apbrstr).modify(|_,w| w.$timXrst().set_bit());
+                        (&(*RCC::ptr()).$apbrstr).modify(|_,w| w.$timXrst().clear_bit());
+                    }
+
+                    let psc = clocks.pclk1().0 / FREQUENCY - 1;
+                    tim.psc.write(|w| unsafe { w.psc().bits(psc as u16) });
+                    tim.arr.write(|w| unsafe { w.bits(u32::MAX) });
+                    // load the prescaler, and forget the update event doing so
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.sr.modify(|_, w| w.uif().clear_bit());
+                    tim.dier.modify(|_, w| w.uie().set_bit());
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    Self {
+                        tim,
+                        overflows: AtomicU32::new(0),
+                    }
+                }
+
+                /// Count a wrap if the update flag is set, within a critical section.
+                fn count_wrap(&self) -> bool {
+                    if self.tim.sr.read().uif().bit_is_set() {
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                        self.overflows.fetch_add(1, Ordering::SeqCst);
+                        true
+                    } else {
+                        false
+                    }
+                }
+            }
+
+            impl WrappingClock for MonotonicTimer<$TIM> {
+                fn on_wrap(&self) {
+                    with_critical_section(|_| {
+                        self.count_wrap();
+                    });
+                }
+            }
+
+            impl Clock for MonotonicTimer<$TIM> {
+                type T = u64;
+                const SCALING_FACTOR: Fraction = Fraction::new(1, FREQUENCY);
+
+                fn try_now(&self) -> Result<Instant<Self>, clock::Error> {
+                    let ticks = with_critical_section(|_| {
+                        let mut counter = self.tim.cnt.read().bits();
+                        // the interrupt may be pending, and the counter read just before it wrapped
+                        if self.count_wrap() {
+                            counter = self.tim.cnt.read().bits();
+                        }
+                        (self.overflows.load(Ordering::SeqCst) as u64) << 32 | counter as u64
+                    });
+                    Ok(Instant::new(ticks))
+                }
+            }
+        )+
+    }
+}
+
+hal! {
+    TIM2: (tim2, tim2en, tim2rst, APB1R1, apb1enr1, apb1rstr1),
+}
+
+#[cfg(any(feature = "stm32l4x5", feature = "stm32l4x6",))]
+hal! {
+    TIM5: (tim5, tim5en, tim5rst, APB1R1, apb1enr1, apb1rstr1),
+}
@@ -0,0 +1,325 @@
+//! Interrupt-driven I2C
+
+use crate::api::i2c::I2cError;
+use core::cell::Cell;
+use core::ops::Deref;
+use stm32l4xx_hal::gpio::{gpioa, gpiob, gpioc};
+use stm32l4xx_hal::i2c::I2c as HalI2c;
+use stm32l4xx_hal::pac::gpioa::RegisterBlock as GpioRegisterBlock;
+use stm32l4xx_hal::pac::{i2c1, GPIOA, GPIOB, GPIOC, I2C1, I2C2, I2C3};
+
+// Without reload, a transfer is limited to NBYTES.
+const MAX_TRANSFER: usize = 255;
+
+// Half a clock period at 100kHz, at 80MHz.
+const HALF_PERIOD_CYCLES: u32 = 400;
+
+pub trait Instance: Deref<Target = i2c1::RegisterBlock> {}
+
+impl Instance for I2C1 {}
+impl Instance for I2C2 {}
+impl Instance for I2C3 {}
+
+/// A pin that can be driven directly through GPIO during bus recovery.
+pub trait BusLine {
+    fn line() -> (*const GpioRegisterBlock, usize);
+}
+
+macro_rules! bus_lines {
+    ($($PORT:ident: $port:ident, [$($PIN:ident: $n:expr,)+],)+) => {
+        $(
+            $(
+                impl<MODE> BusLine for $port::$PIN<MODE> {
+                    fn line() -> (*const GpioRegisterBlock, usize) {
+                        ($PORT::ptr() as *const _, $n)
+                    }
+                }
+            )+
+        )+
+    };
+}
+
+bus_lines! {
+    GPIOA: gpioa, [PA7: 7, PA9: 9, PA10: 10,],
+    GPIOB: gpiob, [PB4: 4, PB6: 6, PB7: 7, PB8: 8, PB9: 9, PB10: 10, PB11: 11, PB13: 13, PB14: 14,],
+    GPIOC: gpioc, [PC0: 0, PC1: 1,],
+}
+
+struct BusPin {
+    port: *const GpioRegisterBlock,
+    pin: usize,
+}
+
+impl BusPin {
+    fn of<P: BusLine>() -> Self {
+        let (port, pin) = P::line();
+        Self { port, pin }
+    }
+
+    fn port(&self) -> &GpioRegisterBlock {
+        unsafe { &*self.port }
+    }
+
+    fn set_mode(&self, mode: u32) {
+        let shift = self.pin * 2;
+        self.port()
+            .moder
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(0b11 << shift)) | (mode << shift)) });
+    }
+
+    /// Open-drain output with the line released. I2C pins are already configured open-drain.
+    fn release(&self) {
+        self.port().bsrr.write(|w| unsafe { w.bits(1 << self.pin) });
+        self.set_mode(0b01);
+    }
+
+    fn pull_low(&self) {
+        self.port()
+            .bsrr
+            .write(|w| unsafe { w.bits(1 << (self.pin + 16)) });
+    }
+
+    fn is_high(&self) -> bool {
+        self.port().idr.read().bits() & (1 << self.pin) != 0
+    }
+
+    /// Hand the pin back to the peripheral.
+    fn restore(&self) {
+        self.set_mode(0b10);
+    }
+}
+
+/// Bytes are moved by the interrupt handler as the peripheral asks for them.
+pub struct I2c<I2C, SCL, SDA>
+where
+    I2C: Instance,
+{
+    i2c: I2C,
+    pins: (SCL, SDA),
+    scl: BusPin,
+    sda: BusPin,
+    address: Cell<u8>,
+    tx: Cell<(*const u8, usize)>,
+    rx: Cell<(*mut u8, usize)>,
+    position: Cell<usize>,
+    error: Cell<Option<I2cError>>,
+}
+
+impl<I2C, SCL, SDA> I2c<I2C, SCL, SDA>
+where
+    I2C: Instance,
+    SCL: BusLine,
+    SDA: BusLine,
+{
+    /// Take over an I2C peripheral configured by the HAL.
+    pub fn new(i2c: HalI2c<I2C, (SCL, SDA)>) -> Self {
+        let (i2c, pins) = i2c.free();
+        i2c.cr1.modify(|_, w| {
+            w.txie()
+                .set_bit()
+                .rxie()
+                .set_bit()
+                .tcie()
+                .set_bit()
+                .stopie()
+                .set_bit()
+                .nackie()
+                .set_bit()
+                .errie()
+                .set_bit()
+                .pe()
+                .set_bit()
+        });
+
+        Self {
+            i2c,
+            pins,
+            scl: BusPin::of::<SCL>(),
+            sda: BusPin::of::<SDA>(),
+            address: Cell::new(0),
+            tx: Cell::new((core::ptr::null(), 0)),
+            rx: Cell::new((core::ptr::null_mut(), 0)),
+            position: Cell::new(0),
+            error: Cell::new(None),
+        }
+    }
+}
+
+impl<I2C, SCL, SDA> I2c<I2C, SCL, SDA>
+where
+    I2C: Instance,
+{
+    fn start(&self, len: usize, read: bool, autoend: bool) {
+        self.position.set(0);
+        self.i2c.cr2.write(|w| unsafe {
+            w.sadd()
+                .bits((self.address.get() as u16) << 1)
+                .rd_wrn()
+                .bit(read)
+                .nbytes()
+                .bits(len as u8)
+                .autoend()
+                .bit(autoend)
+                .start()
+                .set_bit()
+        });
+    }
+
+    fn reset(&self) {
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        // PE must be kept low for at least 3 APB clock cycles.
+        cortex_m::asm::delay(3);
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+    }
+}
+
+impl<I2C, SCL, SDA> crate::hal::i2c::dma::DmaI2cHal for I2c<I2C, SCL, SDA>
+where
+    I2C: Instance,
+{
+    fn prepare_transfer(
+        &self,
+        address: u8,
+        tx_buffer: &[u8],
+        rx_buffer: &mut [u8],
+    ) -> Result<(), I2cError> {
+        if tx_buffer.len() > MAX_TRANSFER {
+            return Err(I2cError::TxBufferTooLong);
+        }
+        if rx_buffer.len() > MAX_TRANSFER {
+            return Err(I2cError::RxBufferTooLong);
+        }
+
+        self.address.set(address);
+        self.tx.set((tx_buffer.as_ptr(), tx_buffer.len()));
+        self.rx.set((rx_buffer.as_mut_ptr(), rx_buffer.len()));
+        self.error.set(None);
+        self.i2c.icr.write(|w| {
+            w.stopcf()
+                .set_bit()
+                .nackcf()
+                .set_bit()
+                .berrcf()
+                .set_bit()
+                .arlocf()
+                .set_bit()
+                .ovrcf()
+                .set_bit()
+        });
+        Ok(())
+    }
+
+    fn start_transfer(&self) {
+        let (_, tx_len) = self.tx.get();
+        let (_, rx_len) = self.rx.get();
+        if tx_len > 0 {
+            // Without AUTOEND, TC is raised at the end of the write to issue a repeated start.
+            self.start(tx_len, false, rx_len == 0);
+        } else {
+            self.start(rx_len, true, true);
+        }
+    }
+
+    fn finish_transfer(&self) -> Result<(), I2cError> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn cancel_transfer(&self) {
+        self.i2c.cr2.modify(|_, w| w.stop().set_bit());
+        self.reset();
+    }
+
+    fn recover(&self) {
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        self.scl.release();
+        self.sda.release();
+
+        // A device in the middle of sending a byte releases SDA within 9 clocks.
+        for _ in 0..9 {
+            if self.sda.is_high() {
+                break;
+            }
+            self.scl.pull_low();
+            cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+            self.scl.release();
+            cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+        }
+
+        // Stop condition: SDA rising while SCL is high.
+        self.sda.pull_low();
+        cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+        self.sda.release();
+        cortex_m::asm::delay(HALF_PERIOD_CYCLES);
+
+        self.scl.restore();
+        self.sda.restore();
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+    }
+
+    fn process_interrupts(&self) -> bool {
+        let isr = self.i2c.isr.read();
+
+        if isr.berr().bit_is_set() {
+            self.i2c.icr.write(|w| w.berrcf().set_bit());
+            self.error.set(Some(I2cError::Bus));
+        }
+
+        if isr.ovr().bit_is_set() {
+            self.i2c.icr.write(|w| w.ovrcf().set_bit());
+            self.error.set(Some(I2cError::Overrun));
+        }
+
+        if isr.arlo().bit_is_set() {
+            // The bus is released without a stop condition.
+            self.i2c.icr.write(|w| w.arlocf().set_bit());
+            self.error.set(Some(I2cError::Bus));
+            return true;
+        }
+
+        if isr.nackf().bit_is_set() {
+            // A stop condition follows automatically. Nothing moved yet in the current
+            // direction means the address itself was not acknowledged.
+            self.i2c.icr.write(|w| w.nackcf().set_bit());
+            if self.position.get() == 0 {
+                self.error.set(Some(I2cError::AddressNack));
+            } else {
+                self.error.set(Some(I2cError::DataNack));
+            }
+        }
+
+        if isr.txis().bit_is_set() {
+            let (tx, len) = self.tx.get();
+            let position = self.position.get();
+            if position < len {
+                let byte = unsafe { *tx.add(position) };
+                self.i2c.txdr.write(|w| w.txdata().bits(byte));
+                self.position.set(position + 1);
+            }
+        }
+
+        if isr.rxne().bit_is_set() {
+            let (rx, len) = self.rx.get();
+            let position = self.position.get();
+            let byte = self.i2c.rxdr.read().rxdata().bits();
+            if position < len {
+                unsafe { *rx.add(position) = byte };
+                self.position.set(position + 1);
+            }
+        }
+
+        if isr.tc().bit_is_set() {
+            let (_, rx_len) = self.rx.get();
+            self.start(rx_len, true, true);
+        }
+
+        if isr.stopf().bit_is_set() {
+            self.i2c.icr.write(|w| w.stopcf().set_bit());
+            return true;
+        }
+
+        false
+    }
+}
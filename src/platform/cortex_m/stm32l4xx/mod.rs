@@ -1,4 +1,10 @@
+pub mod clock;
 pub mod gpio;
+pub mod i2c;
+pub mod pwm;
+pub mod rtc;
 pub mod spi;
 pub mod timer;
 pub mod serial;
+#[cfg(feature = "stm32l4xx-usb")]
+pub mod usb;
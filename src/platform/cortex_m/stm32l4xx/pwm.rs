@@ -0,0 +1,18 @@
+//! PWM channels of the STM32L4 timers.
+use crate::hal::pwm::{Duty, Pwm as HalPwm};
+use core::convert::TryFrom;
+use embedded_hal::PwmPin;
+use stm32l4xx_hal::pwm::Pwm;
+
+impl<TIM, CHANNEL> HalPwm for Pwm<TIM, CHANNEL>
+where
+    Self: PwmPin,
+    <Self as PwmPin>::Duty: Into<u32> + TryFrom<u32>,
+{
+    fn set_duty(&mut self, duty: Duty) {
+        let max = self.get_max_duty().into();
+        if let Ok(duty) = <Self as PwmPin>::Duty::try_from(duty.scale(max)) {
+            PwmPin::set_duty(self, duty);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! The calendar RTC of the STM32L4.
+//!
+//! The RTC keeps counting from the LSE through resets, and in standby when backed by `VBAT`,
+//! so it needs setting only after power was lost. Summer time is left alone: the clock runs
+//! on UTC.
+use crate::domain::time::datetime::DateTime;
+use crate::hal::rtc::Rtc as HalRtc;
+use stm32l4xx_hal::datetime::{Date, DateInMonth, Day, Hour, Minute, Month, Second, Time, Year};
+use stm32l4xx_hal::rtc::Rtc;
+
+impl HalRtc for Rtc {
+    fn get_datetime(&mut self) -> DateTime {
+        let (date, time) = self.get_date_time();
+        DateTime::new(
+            date.year as u16,
+            date.month as u8,
+            date.date as u8,
+            time.hours as u8,
+            time.minutes as u8,
+            time.seconds as u8,
+        )
+        // registers that hold no valid date, as before the RTC was ever set
+        .unwrap_or(DateTime::UNIX_EPOCH)
+    }
+
+    fn set_datetime(&mut self, datetime: DateTime) {
+        let date = Date::new(
+            Day(datetime.weekday().number_from_monday() as u32),
+            DateInMonth(datetime.day() as u32),
+            Month(datetime.month() as u32),
+            Year(datetime.year() as u32),
+        );
+        let time = Time::new(
+            Hour(datetime.hour() as u32),
+            Minute(datetime.minute() as u32),
+            Second(datetime.second() as u32),
+            false,
+        );
+        self.set_date_time(date, time);
+    }
+}
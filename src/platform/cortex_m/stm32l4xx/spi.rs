@@ -1,5 +1,10 @@
-use crate::api::spi::SpiError;
-use stm32l4xx_hal::spi::Error;
+use crate::api::spi::{BitOrder, Phase, Polarity, SpiConfig, SpiError};
+use core::cell::Cell;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+use stm32l4xx_hal::dma::dma2;
+use stm32l4xx_hal::pac::{dma1, DMA2, SPI3};
+use stm32l4xx_hal::rcc::Clocks;
+use stm32l4xx_hal::spi::{Error, Spi as HalSpi};
 
 impl Into<SpiError> for Error {
     fn into(self) -> SpiError {
@@ -11,3 +16,227 @@ impl Into<SpiError> for Error {
         }
     }
 }
+
+// Reception needs a destination even when the bytes received are discarded, and
+// transmission a source when only reading.
+const FILLER: u8 = 0;
+
+// A single DMA transfer is limited to CNDTR.
+const MAX_TRANSFER: usize = 0xFFFF;
+
+// DMA request 3 routes SPI3 RX to DMA2 channel 1, and SPI3 TX to DMA2 channel 2.
+const SPI3_REQUEST: u8 = 0b0011;
+
+/// SPI3 driven by DMA2, with channel 1 receiving and channel 2 transmitting. Completion is
+/// signalled on the `DMA2_CH1` interrupt.
+pub struct DmaSpi3<PINS> {
+    spi: SPI3,
+    pins: PINS,
+    pclk: u32,
+    rx_channel: dma2::C1,
+    tx_channel: dma2::C2,
+    sink: Cell<u8>,
+    dma_error: Cell<bool>,
+}
+
+impl<PINS> DmaSpi3<PINS> {
+    /// Take over SPI3 configured by the HAL for 8-bit words. The `clocks` are needed to derive
+    /// the baud rate prescaler of each device.
+    pub fn new(
+        spi: HalSpi<SPI3, PINS>,
+        rx_channel: dma2::C1,
+        tx_channel: dma2::C2,
+        clocks: Clocks,
+    ) -> Self {
+        let (spi, pins) = spi.free();
+        let dma = Self::dma();
+        dma.cselr
+            .modify(|_, w| unsafe { w.c1s().bits(SPI3_REQUEST).c2s().bits(SPI3_REQUEST) });
+        spi.cr2.modify(|_, w| w.frxth().set_bit());
+        spi.cr1.modify(|_, w| w.spe().set_bit());
+
+        Self {
+            spi,
+            pins,
+            pclk: clocks.pclk1().0,
+            rx_channel,
+            tx_channel,
+            sink: Cell::new(0),
+            dma_error: Cell::new(false),
+        }
+    }
+
+    fn dma() -> &'static dma1::RegisterBlock {
+        unsafe { &*DMA2::ptr() }
+    }
+
+    fn prepare(
+        &self,
+        tx: *const u8,
+        tx_increment: bool,
+        rx: *mut u8,
+        rx_increment: bool,
+        len: usize,
+    ) -> Result<(), SpiError> {
+        if len > MAX_TRANSFER {
+            return Err(SpiError::TransferTooLong);
+        }
+
+        let dma = Self::dma();
+        let dr = &self.spi.dr as *const _ as u32;
+
+        dma.ccr1.modify(|_, w| w.en().clear_bit());
+        dma.ccr2.modify(|_, w| w.en().clear_bit());
+        dma.ifcr.write(|w| w.cgif1().set_bit().cgif2().set_bit());
+        self.dma_error.set(false);
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started.
+        compiler_fence(SeqCst);
+
+        unsafe {
+            dma.cpar1.write(|w| w.bits(dr));
+            dma.cmar1.write(|w| w.bits(rx as u32));
+            dma.cndtr1.write(|w| w.bits(len as u32));
+            dma.ccr1.write(|w| {
+                w.dir()
+                    .clear_bit()
+                    .minc()
+                    .bit(rx_increment)
+                    .psize()
+                    .bits(0b00)
+                    .msize()
+                    .bits(0b00)
+                    .tcie()
+                    .set_bit()
+                    .teie()
+                    .set_bit()
+            });
+
+            dma.cpar2.write(|w| w.bits(dr));
+            dma.cmar2.write(|w| w.bits(tx as u32));
+            dma.cndtr2.write(|w| w.bits(len as u32));
+            dma.ccr2.write(|w| {
+                w.dir()
+                    .set_bit()
+                    .minc()
+                    .bit(tx_increment)
+                    .psize()
+                    .bits(0b00)
+                    .msize()
+                    .bits(0b00)
+                    .teie()
+                    .set_bit()
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<PINS> crate::hal::spi::dma::DmaSpiHal for DmaSpi3<PINS> {
+    fn configure(&self, config: &SpiConfig) {
+        // BR divides PCLK1 by 2^(BR + 1), use the fastest rate not above the one of the device.
+        let br = (0..7)
+            .find(|br| self.pclk >> (br + 1) <= config.frequency.0)
+            .unwrap_or(7);
+
+        // The clock settings may only change while the peripheral is disabled.
+        self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+        self.spi.cr1.modify(|_, w| {
+            w.br()
+                .bits(br as u8)
+                .cpol()
+                .bit(config.mode.polarity == Polarity::IdleHigh)
+                .cpha()
+                .bit(config.mode.phase == Phase::CaptureOnSecondTransition)
+                .lsbfirst()
+                .bit(config.bit_order == BitOrder::LsbFirst)
+                .spe()
+                .set_bit()
+        });
+    }
+
+    fn prepare_transfer(&self, buffer: &mut [u8]) -> Result<(), SpiError> {
+        // Each byte is transmitted before the byte received in its place is written.
+        let ptr = buffer.as_mut_ptr();
+        self.prepare(ptr, true, ptr, true, buffer.len())
+    }
+
+    fn prepare_write(&self, tx_buffer: &[u8]) -> Result<(), SpiError> {
+        self.prepare(
+            tx_buffer.as_ptr(),
+            true,
+            self.sink.as_ptr(),
+            false,
+            tx_buffer.len(),
+        )
+    }
+
+    fn prepare_read(&self, rx_buffer: &mut [u8]) -> Result<(), SpiError> {
+        self.prepare(
+            &FILLER,
+            false,
+            rx_buffer.as_mut_ptr(),
+            true,
+            rx_buffer.len(),
+        )
+    }
+
+    fn start_transfer(&self) {
+        let dma = Self::dma();
+        // Reception must be ready before the first byte is transmitted.
+        self.spi.cr2.modify(|_, w| w.rxdmaen().set_bit());
+        dma.ccr1.modify(|_, w| w.en().set_bit());
+        dma.ccr2.modify(|_, w| w.en().set_bit());
+        self.spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+    }
+
+    fn finish_transfer(&self) -> Result<(), SpiError> {
+        let dma = Self::dma();
+        dma.ccr1.modify(|_, w| w.en().clear_bit());
+        dma.ccr2.modify(|_, w| w.en().clear_bit());
+        while self.spi.sr.read().bsy().bit_is_set() {}
+        self.spi
+            .cr2
+            .modify(|_, w| w.txdmaen().clear_bit().rxdmaen().clear_bit());
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed.
+        compiler_fence(SeqCst);
+
+        let sr = self.spi.sr.read();
+        if sr.ovr().bit_is_set() {
+            // Cleared by reading DR, then SR.
+            let _ = self.spi.dr.read();
+            let _ = self.spi.sr.read();
+            Err(SpiError::Overrun)
+        } else if sr.modf().bit_is_set() {
+            Err(SpiError::ModeFault)
+        } else if self.dma_error.get() {
+            Err(SpiError::Unknown)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn process_interrupts(&self) -> bool {
+        let dma = Self::dma();
+        let isr = dma.isr.read();
+
+        if isr.teif1().bit_is_set() || isr.teif2().bit_is_set() {
+            self.dma_error.set(true);
+            dma.ifcr.write(|w| w.cgif1().set_bit().cgif2().set_bit());
+            return true;
+        }
+
+        if isr.tcif1().bit_is_set() {
+            // The last byte has been received, so the last byte has also been sent.
+            dma.ifcr.write(|w| w.cgif1().set_bit().cgif2().set_bit());
+            return true;
+        }
+
+        false
+    }
+}
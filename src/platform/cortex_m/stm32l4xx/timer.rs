@@ -99,6 +99,13 @@ macro_rules! hal {
                     self.tim.sr.modify(|_, w| w.uif().clear_bit());
                 }
 
+                /// As far as the 16-bit prescaler and counter go, and no longer than the
+                /// deadline rate, in millihertz, stays above zero.
+                fn max_duration(&self) -> Milliseconds {
+                    let ms = (1u64 << 32) * 1_000 / self.clocks.pclk1().0 as u64;
+                    Milliseconds(core::cmp::min(ms, 1_000_000) as u32)
+                }
+
             }
         )+
     }
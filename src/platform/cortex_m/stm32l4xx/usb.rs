@@ -0,0 +1,16 @@
+//! The USB FS device peripheral of the STM32L4.
+//!
+//! Before the peripheral is handed over, the 48 MHz clock must run, for instance from the
+//! HSI48 or the MSI with the LSE, and USB supply must be declared valid with `USV` in
+//! `PWR_CR2`.
+use crate::hal::usb::UsbBus;
+use stm32l4xx_hal::usb::{Peripheral, UsbBus as Stm32UsbBus};
+use usb_device::bus::UsbBusAllocator;
+
+impl UsbBus for Peripheral {
+    type Bus = Stm32UsbBus<Peripheral>;
+
+    fn into_allocator(self) -> UsbBusAllocator<Self::Bus> {
+        Stm32UsbBus::new(self)
+    }
+}
@@ -0,0 +1,50 @@
+//! A clock for running on a host with the standard library, such as in simulations.
+extern crate std;
+
+use crate::domain::time::{clock, fraction::Fraction, Clock, Instant};
+
+/// A clock counting microseconds since it was created, from the monotonic clock of the host.
+pub struct StdClock {
+    start: std::time::Instant,
+}
+
+impl StdClock {
+    pub fn new() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Default for StdClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for StdClock {
+    type T = u64;
+    const SCALING_FACTOR: Fraction = Fraction::new(1, 1_000_000);
+
+    fn try_now(&self) -> Result<Instant<Self>, clock::Error> {
+        Ok(Instant::new(self.start.elapsed().as_micros() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::time::duration::Milliseconds;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_elapsed() {
+        let clock = StdClock::new();
+        let start = clock.try_now().unwrap();
+        thread::sleep(Duration::from_millis(20));
+        let end = clock.try_now().unwrap();
+
+        assert!(end >= start + Milliseconds(20u32));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod cortex_m;
+#[cfg(feature = "std")]
+pub mod host;
 
 pub use self::cortex_m::{with_critical_section, CriticalSection, Mutex};
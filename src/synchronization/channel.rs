@@ -0,0 +1,188 @@
+//! A bounded async channel, for passing values between actors and interrupts.
+
+use super::lock::Lock;
+use super::wait_queue::{Priority, WaitQueue, Waiter};
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use heapless::{consts::*, spsc::Queue, ArrayLength};
+
+/// A queue of up to `N` values, with any number of senders and receivers. Actors wait for room
+/// or for values, taking turns, while interrupts use `try_send` and `try_recv`.
+pub struct Channel<T, N>
+where
+    N: ArrayLength<T>,
+{
+    queue: UnsafeCell<Queue<T, N>>,
+    lock: Lock,
+    senders: WaitQueue<U8>,
+    receivers: WaitQueue<U8>,
+}
+
+unsafe impl<T: Send, N> Send for Channel<T, N> where N: ArrayLength<T> {}
+unsafe impl<T: Send, N> Sync for Channel<T, N> where N: ArrayLength<T> {}
+
+impl<T, N> Channel<T, N>
+where
+    N: ArrayLength<T>,
+{
+    pub fn new() -> Self {
+        Self {
+            queue: UnsafeCell::new(Queue::new()),
+            lock: Lock::new(),
+            senders: WaitQueue::new(),
+            receivers: WaitQueue::new(),
+        }
+    }
+
+    /// Send `value`, waiting for room if the channel is full.
+    pub fn send(&self, value: T) -> SendFuture<'_, T, N> {
+        SendFuture {
+            channel: self,
+            value: Some(value),
+            waiter: self.senders.waiter(Priority::default()),
+        }
+    }
+
+    /// Send `value` if there is room, or else hand it back.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        self.lock
+            .with(|| unsafe { &mut *self.queue.get() }.enqueue(value))?;
+        self.receivers.wake_one();
+        Ok(())
+    }
+
+    /// Receive the oldest value, waiting for one if the channel is empty.
+    pub fn recv(&self) -> RecvFuture<'_, T, N> {
+        RecvFuture {
+            channel: self,
+            waiter: self.receivers.waiter(Priority::default()),
+        }
+    }
+
+    /// Receive the oldest value, if any.
+    pub fn try_recv(&self) -> Option<T> {
+        let value = self
+            .lock
+            .with(|| unsafe { &mut *self.queue.get() }.dequeue())?;
+        self.senders.wake_one();
+        Some(value)
+    }
+
+    /// Number of values waiting to be received.
+    pub fn len(&self) -> usize {
+        self.lock.with(|| unsafe { &*self.queue.get() }.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, N> Default for Channel<T, N>
+where
+    N: ArrayLength<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct SendFuture<'c, T, N>
+where
+    N: ArrayLength<T>,
+{
+    channel: &'c Channel<T, N>,
+    value: Option<T>,
+    waiter: Waiter<'c, U8>,
+}
+
+// The value is moved in and out, but never pinned.
+impl<'c, T, N> Unpin for SendFuture<'c, T, N> where N: ArrayLength<T> {}
+
+impl<'c, T, N> Future for SendFuture<'c, T, N>
+where
+    N: ArrayLength<T>,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let (channel, value) = (this.channel, &mut this.value);
+        this.waiter.poll_acquire(cx, || match value.take() {
+            Some(v) => match channel.try_send(v) {
+                Ok(_) => Some(()),
+                Err(v) => {
+                    value.replace(v);
+                    None
+                }
+            },
+            None => Some(()),
+        })
+    }
+}
+
+pub struct RecvFuture<'c, T, N>
+where
+    N: ArrayLength<T>,
+{
+    channel: &'c Channel<T, N>,
+    waiter: Waiter<'c, U8>,
+}
+
+impl<'c, T, N> Future for RecvFuture<'c, T, N>
+where
+    N: ArrayLength<T>,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let channel = self.channel;
+        self.waiter.poll_acquire(cx, || channel.try_recv())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_send_recv() {
+        let channel: Channel<u8, U2> = Channel::new();
+        block_on(channel.send(1));
+        block_on(channel.send(2));
+        assert_eq!(channel.len(), 2);
+        assert_eq!(channel.try_send(3), Err(3));
+        assert_eq!(block_on(channel.recv()), 1);
+        assert_eq!(channel.try_recv(), Some(2));
+        assert!(channel.recv().now_or_never().is_none());
+    }
+
+    #[test]
+    fn test_waiting_sender() {
+        let channel: Channel<u8, U1> = Channel::new();
+        channel.try_send(1).unwrap();
+        let sending = channel.send(2);
+        futures::pin_mut!(sending);
+        assert!(sending.as_mut().now_or_never().is_none());
+
+        assert_eq!(channel.try_recv(), Some(1));
+        assert!(sending.as_mut().now_or_never().is_some());
+        assert_eq!(channel.try_recv(), Some(2));
+    }
+
+    #[test]
+    fn test_waiting_receiver() {
+        let channel: Channel<u8, U1> = Channel::new();
+        let receiving = channel.recv();
+        futures::pin_mut!(receiving);
+        assert!(receiving.as_mut().now_or_never().is_none());
+
+        // as sent from an interrupt
+        channel.try_send(1).unwrap();
+        assert_eq!(receiving.as_mut().now_or_never(), Some(1));
+    }
+}
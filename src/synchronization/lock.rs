@@ -0,0 +1,36 @@
+//! Mutual exclusion of state shared between actors and interrupts.
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+extern crate std;
+
+/// Runs closures in a critical section, or under a mutex when hosted.
+pub(crate) struct Lock {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    lock: std::sync::Mutex<()>,
+}
+
+impl Lock {
+    pub(crate) const fn new() -> Self {
+        Self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    pub(crate) fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        let guard = self.lock.lock().unwrap();
+        f()
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    pub(crate) fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        crate::platform::with_critical_section(|_| f())
+    }
+}
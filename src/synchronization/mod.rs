@@ -1,8 +1,17 @@
-//! Synchronization primitive actors.
+//! Synchronization primitives, and actors built on them.
 
+mod channel;
+mod lock;
+mod mutex;
 mod sempahore;
 mod signal;
+mod wait_queue;
+mod watch;
 
+pub use channel::{Channel, RecvFuture, SendFuture};
+pub use mutex::{LockFuture, Mutex, MutexGuard};
 pub use signal::Signal;
+pub use wait_queue::{Entry, Priority, WaitQueue, Waiter};
+pub use watch::{ChangedFuture, Receiver, Watch};
 
 pub use sempahore::{Permit, SemaphoreActor};
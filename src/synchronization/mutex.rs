@@ -0,0 +1,147 @@
+//! An async mutex, for state shared between actors and interrupts.
+
+use super::lock::Lock;
+use super::wait_queue::{Priority, WaitQueue, Waiter};
+use core::cell::{Cell, UnsafeCell};
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use heapless::consts::*;
+
+/// A value accessed by one holder at a time. Actors wait for their turn with `lock`, while
+/// interrupts, which can not wait, use `try_lock`.
+pub struct Mutex<T> {
+    value: UnsafeCell<T>,
+    locked: Cell<bool>,
+    lock: Lock,
+    waiters: WaitQueue<U8>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+            locked: Cell::new(false),
+            lock: Lock::new(),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Wait for exclusive access to the value, in turn with other tasks.
+    pub fn lock(&self) -> LockFuture<'_, T> {
+        self.lock_with_priority(Priority::default())
+    }
+
+    /// Wait for exclusive access to the value, ahead of tasks of a lower `priority`.
+    pub fn lock_with_priority(&self, priority: Priority) -> LockFuture<'_, T> {
+        LockFuture {
+            mutex: self,
+            waiter: self.waiters.waiter(priority),
+        }
+    }
+
+    /// Access the value if nobody else is.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.lock.with(|| self.locked.replace(true)) {
+            None
+        } else {
+            Some(MutexGuard { mutex: self })
+        }
+    }
+
+    /// Access the value through exclusive ownership of the mutex.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    fn unlock(&self) {
+        self.lock.with(|| self.locked.set(false));
+        self.waiters.wake_one();
+    }
+}
+
+impl<T: Default> Default for Mutex<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+/// Access to the value of a `Mutex`, released when dropped.
+pub struct MutexGuard<'m, T> {
+    mutex: &'m Mutex<T>,
+}
+
+impl<'m, T> Deref for MutexGuard<'m, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'m, T> DerefMut for MutexGuard<'m, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'m, T> Drop for MutexGuard<'m, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+pub struct LockFuture<'m, T> {
+    mutex: &'m Mutex<T>,
+    waiter: Waiter<'m, U8>,
+}
+
+impl<'m, T> Future for LockFuture<'m, T> {
+    type Output = MutexGuard<'m, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mutex = self.mutex;
+        self.waiter.poll_acquire(cx, || mutex.try_lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_lock() {
+        let mutex = Mutex::new(1);
+        {
+            let mut guard = block_on(mutex.lock());
+            *guard += 1;
+            assert!(mutex.try_lock().is_none());
+            assert!(mutex.lock().now_or_never().is_none());
+        }
+        assert_eq!(*mutex.try_lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_waiter_is_served_first() {
+        let mutex = Mutex::new(0);
+        let guard = mutex.try_lock().unwrap();
+        let waiting = mutex.lock();
+        futures::pin_mut!(waiting);
+        assert!(waiting.as_mut().now_or_never().is_none());
+        drop(guard);
+
+        // a newcomer waits for its turn, behind the task already waiting
+        assert!(mutex.lock().now_or_never().is_none());
+        assert!(waiting.as_mut().now_or_never().is_some());
+    }
+}
@@ -1,12 +1,13 @@
 //! A semaphore actor and supporting types.
 
 use crate::prelude::*;
+use crate::synchronization::{Priority, WaitQueue, Waiter};
 use core::cell::RefCell;
 use core::future::Future;
 use core::pin::Pin;
-use core::task::{Context, Poll, Waker};
+use core::task::{Context, Poll};
 
-use heapless::{consts::*, spsc::Queue};
+use heapless::consts::*;
 
 #[derive(Debug)]
 pub struct Acquire;
@@ -14,14 +15,14 @@ pub struct Release;
 
 pub struct Shared {
     permits: RefCell<usize>,
-    waiters: RefCell<Queue<Waker, U16>>,
+    waiters: WaitQueue<U16>,
 }
 
 impl Shared {
     fn new(permits: usize) -> Self {
         Self {
             permits: RefCell::new(permits),
-            waiters: RefCell::new(Queue::new()),
+            waiters: WaitQueue::new(),
         }
     }
 
@@ -38,13 +39,7 @@ impl Shared {
     fn release(&self) {
         let permits = *self.permits.borrow();
         *self.permits.borrow_mut() = permits + 1;
-        if let Some(next) = self.waiters.borrow_mut().dequeue() {
-            next.wake()
-        }
-    }
-
-    fn waiting(&self, waker: &Waker) {
-        self.waiters.borrow_mut().enqueue(waker.clone()).ok();
+        self.waiters.wake_one();
     }
 }
 
@@ -107,8 +102,13 @@ impl SemaphoreActor {
     }
 
     pub async fn acquire(&self) -> Permit {
+        self.acquire_with_priority(Priority::default()).await
+    }
+
+    /// Acquire a permit ahead of waiters of a lower `priority`.
+    pub async fn acquire_with_priority(&self, priority: Priority) -> Permit {
         struct Acquire {
-            waiting: bool,
+            waiter: Waiter<'static, U16>,
             address: Address<SemaphoreActor>,
             shared: &'static Shared,
         }
@@ -117,24 +117,22 @@ impl SemaphoreActor {
             type Output = Permit;
 
             fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                if self.shared.acquire() {
-                    return Poll::Ready(Permit {
-                        address: self.address,
-                    });
-                }
-                if !self.waiting {
-                    self.shared.waiting(cx.waker());
-                    self.waiting = true;
-                }
-
-                Poll::Pending
+                let (shared, address) = (self.shared, self.address);
+                self.waiter.poll_acquire(cx, || {
+                    if shared.acquire() {
+                        Some(Permit { address })
+                    } else {
+                        None
+                    }
+                })
             }
         }
 
+        let shared = self.shared.unwrap();
         Acquire {
-            waiting: false,
+            waiter: shared.waiters.waiter(priority),
             address: self.address.unwrap(),
-            shared: self.shared.unwrap(),
+            shared,
         }
         .await
     }
@@ -0,0 +1,417 @@
+//! A queue of tasks taking turns at a shared resource.
+
+use super::lock::Lock;
+use core::cell::UnsafeCell;
+use core::task::{Context, Poll, Waker};
+use heapless::{ArrayLength, Vec};
+
+/// The precedence of a waiter. Waiters of the same priority are served in arrival order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Priority(pub u8);
+
+impl Priority {
+    pub const LOW: Priority = Priority(0);
+    pub const NORMAL: Priority = Priority(128);
+    pub const HIGH: Priority = Priority(255);
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+/// A task waiting in a `WaitQueue`.
+pub struct Entry {
+    ticket: u32,
+    priority: Priority,
+    waker: Waker,
+    woken: bool,
+}
+
+struct State<N>
+where
+    N: ArrayLength<Entry>,
+{
+    entries: Vec<Entry, N>,
+    next_ticket: u32,
+}
+
+impl<N> State<N>
+where
+    N: ArrayLength<Entry>,
+{
+    fn position(&self, ticket: u32) -> Option<usize> {
+        self.entries.iter().position(|e| e.ticket == ticket)
+    }
+
+    /// The entry first in line among those not woken yet: of the highest priority, and the
+    /// oldest among those. Age is counted back from the next ticket, so tickets may wrap.
+    fn next(&self) -> Option<usize> {
+        let next_ticket = self.next_ticket;
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| !e.woken)
+            .max_by_key(|(_, e)| (e.priority, next_ticket.wrapping_sub(e.ticket)))
+            .map(|(index, _)| index)
+    }
+}
+
+/// Tasks waiting for a resource, woken one at a time in order of priority, then arrival.
+///
+/// A task waits through a `Waiter`, which only tries to take the resource when its turn has
+/// come, so that newcomers can not overtake those already waiting. Dropping a `Waiter` leaves
+/// the queue, handing its turn on if it had been woken.
+///
+/// Up to `N` tasks wait in line. Tasks beyond that are polled again until there is room,
+/// rather than failing.
+pub struct WaitQueue<N>
+where
+    N: ArrayLength<Entry>,
+{
+    state: UnsafeCell<State<N>>,
+    lock: Lock,
+}
+
+unsafe impl<N> Send for WaitQueue<N> where N: ArrayLength<Entry> {}
+unsafe impl<N> Sync for WaitQueue<N> where N: ArrayLength<Entry> {}
+
+impl<N> WaitQueue<N>
+where
+    N: ArrayLength<Entry>,
+{
+    pub fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                entries: Vec::new(),
+                next_ticket: 0,
+            }),
+            lock: Lock::new(),
+        }
+    }
+
+    fn with_state<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut State<N>) -> R,
+    {
+        self.lock.with(|| f(unsafe { &mut *self.state.get() }))
+    }
+
+    /// A new waiter, not yet in line.
+    pub fn waiter(&self, priority: Priority) -> Waiter<'_, N> {
+        Waiter {
+            queue: self,
+            priority,
+            ticket: None,
+        }
+    }
+
+    /// Wake the task first in line, returning whether there was one. To be called whenever
+    /// the resource becomes available.
+    pub fn wake_one(&self) -> bool {
+        self.with_state(|state| {
+            if let Some(index) = state.next() {
+                let entry = &mut state.entries[index];
+                entry.woken = true;
+                entry.waker.wake_by_ref();
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Wake every task in line.
+    pub fn wake_all(&self) {
+        self.with_state(|state| {
+            for entry in state.entries.iter_mut().filter(|e| !e.woken) {
+                entry.woken = true;
+                entry.waker.wake_by_ref();
+            }
+        })
+    }
+
+    /// Number of tasks in line.
+    pub fn len(&self) -> usize {
+        self.with_state(|state| state.entries.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn enqueue(&self, priority: Priority, waker: &Waker) -> Option<u32> {
+        self.with_state(|state| {
+            let ticket = state.next_ticket;
+            let entry = Entry {
+                ticket,
+                priority,
+                waker: waker.clone(),
+                woken: false,
+            };
+            state.entries.push(entry).ok()?;
+            state.next_ticket = ticket.wrapping_add(1);
+            Some(ticket)
+        })
+    }
+
+    /// Whether `ticket` has been woken, clearing it, or else refresh its waker.
+    fn take_turn(&self, ticket: u32, waker: &Waker) -> bool {
+        self.with_state(|state| {
+            if let Some(index) = state.position(ticket) {
+                let entry = &mut state.entries[index];
+                if entry.woken {
+                    entry.woken = false;
+                    return true;
+                }
+                if !entry.waker.will_wake(waker) {
+                    entry.waker = waker.clone();
+                }
+            }
+            false
+        })
+    }
+
+    /// Remove `ticket` from the line, returning whether it had been woken.
+    fn remove(&self, ticket: u32) -> bool {
+        self.with_state(|state| {
+            if let Some(index) = state.position(ticket) {
+                // Entries are ordered by their tickets, not their position.
+                state.entries.swap_remove(index).woken
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl<N> Default for WaitQueue<N>
+where
+    N: ArrayLength<Entry>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A task's place in a `WaitQueue`, to be polled by the future waiting for the resource.
+pub struct Waiter<'q, N>
+where
+    N: ArrayLength<Entry>,
+{
+    queue: &'q WaitQueue<N>,
+    priority: Priority,
+    ticket: Option<u32>,
+}
+
+impl<'q, N> Waiter<'q, N>
+where
+    N: ArrayLength<Entry>,
+{
+    /// Try to take the resource with `acquire` when it is this waiter's turn, or else get in
+    /// line to be woken.
+    pub fn poll_acquire<T, F>(&mut self, cx: &mut Context<'_>, mut acquire: F) -> Poll<T>
+    where
+        F: FnMut() -> Option<T>,
+    {
+        let turn = match self.ticket {
+            Some(ticket) => self.queue.take_turn(ticket, cx.waker()),
+            None => self.queue.is_empty(),
+        };
+
+        if turn {
+            if let Some(value) = acquire() {
+                self.leave();
+                return Poll::Ready(value);
+            }
+        }
+
+        if self.ticket.is_none() {
+            self.enqueue(cx);
+            // The resource may have been released, by an interrupt, before getting in line.
+            if turn && self.ticket.is_some() {
+                if let Some(value) = acquire() {
+                    self.leave();
+                    return Poll::Ready(value);
+                }
+            }
+        }
+        Poll::Pending
+    }
+
+    /// Wait for `ready` to succeed, checking it on every poll rather than in turn. For
+    /// conditions of interest to every waiter, such as a new value, rather than a resource
+    /// taken by one of them.
+    pub fn poll_ready<T, F>(&mut self, cx: &mut Context<'_>, ready: F) -> Poll<T>
+    where
+        F: FnOnce() -> Option<T>,
+    {
+        // Get in line before checking, so that a wake in between is not missed.
+        match self.ticket {
+            Some(ticket) => {
+                self.queue.take_turn(ticket, cx.waker());
+            }
+            None => self.enqueue(cx),
+        }
+
+        if let Some(value) = ready() {
+            self.leave();
+            Poll::Ready(value)
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn enqueue(&mut self, cx: &mut Context<'_>) {
+        if self.ticket.is_none() {
+            self.ticket = self.queue.enqueue(self.priority, cx.waker());
+            if self.ticket.is_none() {
+                log::warn!("wait queue full, polling");
+                cx.waker().wake_by_ref();
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        if let Some(ticket) = self.ticket.take() {
+            self.queue.remove(ticket);
+        }
+    }
+}
+
+impl<'q, N> Drop for Waiter<'q, N>
+where
+    N: ArrayLength<Entry>,
+{
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket.take() {
+            if self.queue.remove(ticket) {
+                // Woken but no longer interested, the turn goes to the next in line.
+                self.queue.wake_one();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::task::{RawWaker, RawWakerVTable};
+    use heapless::consts::*;
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |data| RawWaker::new(data, &VTABLE),
+        |data| unsafe {
+            (*(data as *const Cell<usize>)).set((*(data as *const Cell<usize>)).get() + 1)
+        },
+        |data| unsafe {
+            (*(data as *const Cell<usize>)).set((*(data as *const Cell<usize>)).get() + 1)
+        },
+        |_| {},
+    );
+
+    /// A waker counting its wakes.
+    fn waker(wakes: &Cell<usize>) -> Waker {
+        unsafe { Waker::from_raw(RawWaker::new(wakes as *const _ as *const (), &VTABLE)) }
+    }
+
+    fn poll<N: ArrayLength<Entry>>(
+        waiter: &mut Waiter<'_, N>,
+        waker: &Waker,
+        available: &Cell<bool>,
+    ) -> Poll<()> {
+        let mut cx = Context::from_waker(waker);
+        waiter.poll_acquire(&mut cx, || {
+            if available.replace(false) {
+                Some(())
+            } else {
+                None
+            }
+        })
+    }
+
+    #[test]
+    fn test_fifo() {
+        let queue: WaitQueue<U4> = WaitQueue::new();
+        let available = Cell::new(false);
+        let wakes = [Cell::new(0), Cell::new(0), Cell::new(0)];
+        let wakers = [waker(&wakes[0]), waker(&wakes[1]), waker(&wakes[2])];
+        let mut first = queue.waiter(Priority::NORMAL);
+        let mut second = queue.waiter(Priority::NORMAL);
+
+        assert!(poll(&mut first, &wakers[0], &available).is_pending());
+        assert!(poll(&mut second, &wakers[1], &available).is_pending());
+
+        available.set(true);
+        assert!(queue.wake_one());
+        assert_eq!(wakes[0].get(), 1);
+        assert_eq!(wakes[1].get(), 0);
+
+        // a newcomer can not overtake the woken waiter
+        let mut newcomer = queue.waiter(Priority::NORMAL);
+        assert!(poll(&mut newcomer, &wakers[2], &available).is_pending());
+        assert!(poll(&mut first, &wakers[0], &available).is_ready());
+        assert_eq!(queue.len(), 2);
+
+        available.set(true);
+        queue.wake_one();
+        assert_eq!(wakes[1].get(), 1);
+        assert!(poll(&mut second, &wakers[1], &available).is_ready());
+    }
+
+    #[test]
+    fn test_priority() {
+        let queue: WaitQueue<U4> = WaitQueue::new();
+        let available = Cell::new(false);
+        let wakes = [Cell::new(0), Cell::new(0)];
+        let wakers = [waker(&wakes[0]), waker(&wakes[1])];
+        let mut low = queue.waiter(Priority::LOW);
+        let mut high = queue.waiter(Priority::HIGH);
+
+        assert!(poll(&mut low, &wakers[0], &available).is_pending());
+        assert!(poll(&mut high, &wakers[1], &available).is_pending());
+
+        available.set(true);
+        queue.wake_one();
+        assert_eq!(wakes[0].get(), 0);
+        assert_eq!(wakes[1].get(), 1);
+        assert!(poll(&mut high, &wakers[1], &available).is_ready());
+    }
+
+    #[test]
+    fn test_cancel_passes_turn_on() {
+        let queue: WaitQueue<U4> = WaitQueue::new();
+        let available = Cell::new(false);
+        let wakes = [Cell::new(0), Cell::new(0)];
+        let wakers = [waker(&wakes[0]), waker(&wakes[1])];
+        let mut first = queue.waiter(Priority::NORMAL);
+        let mut second = queue.waiter(Priority::NORMAL);
+
+        assert!(poll(&mut first, &wakers[0], &available).is_pending());
+        assert!(poll(&mut second, &wakers[1], &available).is_pending());
+
+        available.set(true);
+        queue.wake_one();
+        drop(first);
+        assert_eq!(wakes[1].get(), 1);
+        assert!(poll(&mut second, &wakers[1], &available).is_ready());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_full_queue_polls_again() {
+        let queue: WaitQueue<U1> = WaitQueue::new();
+        let available = Cell::new(false);
+        let wakes = [Cell::new(0), Cell::new(0)];
+        let wakers = [waker(&wakes[0]), waker(&wakes[1])];
+        let mut first = queue.waiter(Priority::NORMAL);
+        let mut second = queue.waiter(Priority::NORMAL);
+
+        assert!(poll(&mut first, &wakers[0], &available).is_pending());
+        assert!(poll(&mut second, &wakers[1], &available).is_pending());
+        assert_eq!(queue.len(), 1);
+        assert_eq!(wakes[1].get(), 1);
+    }
+}
@@ -0,0 +1,179 @@
+//! A cell broadcasting its latest value, for state observed by several actors.
+
+use super::lock::Lock;
+use super::wait_queue::{Priority, WaitQueue, Waiter};
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use heapless::consts::*;
+
+struct State<T> {
+    value: Option<T>,
+    version: u32,
+}
+
+/// Holds the latest value set, which every `Receiver` sees at least once. Values set in quick
+/// succession may be skipped by a receiver, which only gets the last one. Setting never waits,
+/// so interrupts may set values as well.
+pub struct Watch<T>
+where
+    T: Clone,
+{
+    state: UnsafeCell<State<T>>,
+    lock: Lock,
+    waiters: WaitQueue<U8>,
+}
+
+unsafe impl<T: Clone + Send> Send for Watch<T> {}
+unsafe impl<T: Clone + Send> Sync for Watch<T> {}
+
+impl<T> Watch<T>
+where
+    T: Clone,
+{
+    /// A watch without a value yet.
+    pub fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                value: None,
+                version: 0,
+            }),
+            lock: Lock::new(),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    fn with_state<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut State<T>) -> R,
+    {
+        self.lock.with(|| f(unsafe { &mut *self.state.get() }))
+    }
+
+    /// Replace the value, waking every receiver.
+    pub fn set(&self, value: T) {
+        self.with_state(|state| {
+            state.value.replace(value);
+            state.version = state.version.wrapping_add(1);
+        });
+        self.waiters.wake_all();
+    }
+
+    /// The latest value, if any.
+    pub fn get(&self) -> Option<T> {
+        self.with_state(|state| state.value.clone())
+    }
+
+    /// A receiver of values set from now on.
+    pub fn receiver(&self) -> Receiver<'_, T> {
+        Receiver {
+            watch: self,
+            version: self.with_state(|state| state.version),
+        }
+    }
+
+    /// The value, if a version other than `seen` has been set since.
+    fn changed_since(&self, seen: u32) -> Option<(u32, T)> {
+        self.with_state(|state| match &state.value {
+            Some(value) if state.version != seen => Some((state.version, value.clone())),
+            _ => None,
+        })
+    }
+}
+
+impl<T> Default for Watch<T>
+where
+    T: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Follows the values of a `Watch`.
+pub struct Receiver<'w, T>
+where
+    T: Clone,
+{
+    watch: &'w Watch<T>,
+    version: u32,
+}
+
+impl<'w, T> Receiver<'w, T>
+where
+    T: Clone,
+{
+    /// Wait for a value not seen by this receiver yet.
+    pub fn changed(&mut self) -> ChangedFuture<'_, 'w, T> {
+        ChangedFuture {
+            waiter: self.watch.waiters.waiter(Priority::default()),
+            receiver: self,
+        }
+    }
+
+    /// The value, if not seen by this receiver yet.
+    pub fn try_changed(&mut self) -> Option<T> {
+        let (version, value) = self.watch.changed_since(self.version)?;
+        self.version = version;
+        Some(value)
+    }
+}
+
+pub struct ChangedFuture<'r, 'w, T>
+where
+    T: Clone,
+{
+    receiver: &'r mut Receiver<'w, T>,
+    waiter: Waiter<'w, U8>,
+}
+
+impl<'r, 'w, T> Future for ChangedFuture<'r, 'w, T>
+where
+    T: Clone,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let receiver = &mut this.receiver;
+        this.waiter.poll_ready(cx, || receiver.try_changed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn test_receivers_see_latest() {
+        let watch = Watch::new();
+        let mut first = watch.receiver();
+        let mut second = watch.receiver();
+        assert!(first.changed().now_or_never().is_none());
+
+        watch.set(1);
+        watch.set(2);
+        assert_eq!(watch.get(), Some(2));
+        assert_eq!(first.changed().now_or_never(), Some(2));
+        assert_eq!(second.changed().now_or_never(), Some(2));
+        assert!(first.changed().now_or_never().is_none());
+    }
+
+    #[test]
+    fn test_waiting_receivers_are_woken() {
+        let watch = Watch::new();
+        let mut first = watch.receiver();
+        let mut second = watch.receiver();
+        let first_changed = first.changed();
+        let second_changed = second.changed();
+        futures::pin_mut!(first_changed, second_changed);
+        assert!(first_changed.as_mut().now_or_never().is_none());
+        assert!(second_changed.as_mut().now_or_never().is_none());
+
+        watch.set(1);
+        assert_eq!(first_changed.as_mut().now_or_never(), Some(1));
+        assert_eq!(second_changed.as_mut().now_or_never(), Some(1));
+    }
+}
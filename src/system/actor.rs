@@ -1,7 +1,7 @@
 //! Actor-related types and traits.
 
 use crate::arena::Arena;
-use core::cell::{RefCell, UnsafeCell};
+use core::cell::{Cell, RefCell, UnsafeCell};
 use core::future::Future;
 use core::mem::transmute;
 use core::pin::Pin;
@@ -11,13 +11,14 @@ use core::task::{Context, Poll, Waker};
 use heapless::spsc::{Consumer, Producer};
 use heapless::{consts::*, spsc::Queue, String};
 
-use crate::arena::{Box, Rc};
+use crate::arena::{self, footprint, ArenaRef, Box, Rc, RcBox};
 use crate::platform::with_critical_section;
 use crate::prelude::*;
+use crate::system::address::SendError;
 use crate::system::device::Lifecycle;
 use crate::system::supervisor::actor_executor::ActiveActor;
 use crate::system::supervisor::{actor_executor::ActorState, Supervisor};
-use crate::system::SystemArena;
+use crate::system::{ActorArena, SystemArena};
 
 pub trait Configurable {
     type Configuration;
@@ -85,6 +86,7 @@ pub trait Actor: Sized {
 /// Global methods for acquiring the current actor's infomation.
 pub struct ActorInfo {
     pub(crate) name: Option<&'static str>,
+    pub(crate) arena: Option<ArenaRef>,
 }
 
 impl ActorInfo {
@@ -92,28 +94,38 @@ impl ActorInfo {
     pub fn name() -> &'static str {
         unsafe { CURRENT.name.unwrap_or("<unnamed>") }
     }
+
+    /// Retrieve the arena the current actor allocates from, the system arena unless it was
+    /// given its own.
+    pub fn arena() -> ArenaRef {
+        unsafe { CURRENT.arena }.unwrap_or_else(ArenaRef::of::<SystemArena>)
+    }
 }
 
-pub(crate) static mut CURRENT: ActorInfo = ActorInfo { name: None };
+pub(crate) static mut CURRENT: ActorInfo = ActorInfo {
+    name: None,
+    arena: None,
+};
 
 type ItemsProducer<A> =
-    RefCell<Option<Producer<'static, Box<dyn ActorFuture<A>, SystemArena>, U64>>>;
+    RefCell<Option<Producer<'static, Box<dyn ActorFuture<A>, ActorArena>, U64>>>;
 type ItemsConsumer<A> =
-    RefCell<Option<Consumer<'static, Box<dyn ActorFuture<A>, SystemArena>, U64>>>;
+    RefCell<Option<Consumer<'static, Box<dyn ActorFuture<A>, ActorArena>, U64>>>;
 
 /// Struct which is capable of holding an `Actor` instance
 /// and connects it to the actor system.
 pub struct ActorContext<A: Actor + 'static> {
     pub(crate) actor: RefCell<Option<A>>,
-    pub(crate) current: RefCell<Option<Box<dyn ActorFuture<A>, SystemArena>>>,
+    pub(crate) current: RefCell<Option<Box<dyn ActorFuture<A>, ActorArena>>>,
     // Only an UnsafeCell instead of RefCell in order to maintain it's 'static nature when borrowed.
-    pub(crate) items: UnsafeCell<Queue<Box<dyn ActorFuture<A>, SystemArena>, U64>>,
+    pub(crate) items: UnsafeCell<Queue<Box<dyn ActorFuture<A>, ActorArena>, U64>>,
     pub(crate) items_producer: ItemsProducer<A>,
     pub(crate) items_consumer: ItemsConsumer<A>,
     //pub(crate) items: FutureQueue<A>,
     pub(crate) state_flag_handle: RefCell<Option<*const ()>>,
     pub(crate) in_flight: AtomicBool,
     name: Option<&'static str>,
+    arena: Cell<Option<ArenaRef>>,
 }
 
 impl<A: Actor + 'static> ActorContext<A> {
@@ -131,6 +143,7 @@ impl<A: Actor + 'static> ActorContext<A> {
             state_flag_handle: RefCell::new(None),
             in_flight: AtomicBool::new(false),
             name: None,
+            arena: Cell::new(None),
         }
     }
 
@@ -145,6 +158,45 @@ impl<A: Actor + 'static> ActorContext<A> {
         self.name.unwrap_or("<unnamed>")
     }
 
+    /// Have the actor's messages, and whatever it allocates while handling them, come from
+    /// arena `AR` rather than the system arena, so it cannot starve other actors of memory.
+    pub fn with_arena<AR: Arena>(self) -> Self {
+        let arena = ArenaRef::of::<AR>();
+        arena::register(arena);
+        self.arena.set(Some(arena));
+        self
+    }
+
+    /// Retrieve the arena the actor allocates from.
+    pub fn arena(&self) -> ArenaRef {
+        self.arena.get().unwrap_or_else(ArenaRef::of::<SystemArena>)
+    }
+
+    fn alloc<'o, T: 'o>(&self, val: T) -> &'o mut T {
+        self.arena()
+            .try_alloc(val)
+            .unwrap_or_else(|e| panic!("[{}] arena exhausted: {:?}", self.name(), e))
+    }
+
+    /// Bytes of arena each notification of type `M` takes while queued or handled, not
+    /// counting any future its handler defers to.
+    pub fn notify_footprint<M>() -> usize
+    where
+        A: NotifyHandler<M>,
+    {
+        footprint::<OnNotify<A, M>>()
+    }
+
+    /// Bytes of arena each request of type `M` takes until answered, not counting any future
+    /// its handler defers to.
+    pub fn request_footprint<M>() -> usize
+    where
+        A: RequestHandler<M>,
+    {
+        footprint::<OnRequest<A, M>>()
+            + footprint::<RcBox<CompletionHandle<A::Response>, ActorArena>>()
+    }
+
     fn take_actor(&self) -> Option<A> {
         self.actor.borrow_mut().take()
     }
@@ -165,6 +217,9 @@ impl<A: Actor + 'static> ActorContext<A> {
         supervisor: &mut Supervisor,
     ) -> Address<A> {
         let addr = Address::new(self);
+        if self.arena.get().is_none() {
+            self.arena.set(supervisor.arena());
+        }
         let (actor_index, state_flag_handle) = supervisor.activate_actor(self);
         log::trace!("[{}] == {:x}", self.name(), state_flag_handle as u32);
         self.state_flag_handle
@@ -187,8 +242,8 @@ impl<A: Actor + 'static> ActorContext<A> {
     pub(crate) fn lifecycle(&'static self, event: Lifecycle) {
         log::trace!("[{}].lifecycle({:?})", self.name(), event);
 
-        let lifecycle = SystemArena::alloc(OnLifecycle::new(self, event)).unwrap();
-        let lifecycle: Box<dyn ActorFuture<A>, SystemArena> = Box::new(lifecycle);
+        let lifecycle = self.alloc(OnLifecycle::new(self, event));
+        let lifecycle: Box<dyn ActorFuture<A>, ActorArena> = Box::new(lifecycle);
 
         with_critical_section(|cs| {
             self.items_producer
@@ -214,8 +269,8 @@ impl<A: Actor + 'static> ActorContext<A> {
         M: 'static,
     {
         log::trace!("[{}].notify(...)", self.name());
-        let notify = SystemArena::alloc(OnNotify::new(self, message)).unwrap();
-        let notify: Box<dyn ActorFuture<A>, SystemArena> = Box::new(notify);
+        let notify = self.alloc(OnNotify::new(self, message));
+        let notify: Box<dyn ActorFuture<A>, ActorArena> = Box::new(notify);
         with_critical_section(|cs| {
             self.items_producer
                 .borrow_mut()
@@ -232,6 +287,34 @@ impl<A: Actor + 'static> ActorContext<A> {
         }
     }
 
+    /// Dispatch a notification, unless the actor's queue or the arena has no room for it.
+    pub(crate) fn try_notify<M>(&'static self, message: M) -> Result<(), (M, SendError)>
+    where
+        A: NotifyHandler<M>,
+        M: 'static,
+    {
+        log::trace!("[{}].try_notify(...)", self.name());
+        with_critical_section(|cs| {
+            let mut producer = self.items_producer.borrow_mut();
+            let producer = producer.as_mut().unwrap();
+            if !producer.ready() {
+                return Err((message, SendError::QueueFull));
+            }
+            let notify: &mut dyn ActorFuture<A> = self
+                .arena()
+                .try_alloc_with(message, |message| OnNotify::new(self, message))
+                .map_err(|(message, e)| (message, SendError::Exhausted(e)))?;
+            producer.enqueue(Box::new(notify)).ok();
+            Ok(())
+        })?;
+
+        let flag_ptr = self.state_flag_handle.borrow_mut().unwrap() as *const AtomicU8;
+        unsafe {
+            (*flag_ptr).store(ActorState::READY.into(), Ordering::Release);
+        }
+        Ok(())
+    }
+
     /// Dispatch an async request.
     pub(crate) async fn request<M>(&'static self, message: M) -> <A as RequestHandler<M>>::Response
     where
@@ -241,12 +324,11 @@ impl<A: Actor + 'static> ActorContext<A> {
         let signal = Rc::new(CompletionHandle::new());
         let sender = CompletionSender::new(signal.clone());
         let receiver = CompletionReceiver::new(signal);
-        let request: &mut dyn ActorFuture<A> =
-            SystemArena::alloc(OnRequest::new(self, message, sender)).unwrap();
+        let request: &mut dyn ActorFuture<A> = self.alloc(OnRequest::new(self, message, sender));
         let response = RequestResponseFuture::new(receiver);
 
         unsafe {
-            let request: Box<dyn ActorFuture<A>, SystemArena> = Box::new(request);
+            let request: Box<dyn ActorFuture<A>, ActorArena> = Box::new(request);
             with_critical_section(|cs| {
                 self.items_producer
                     .borrow_mut()
@@ -264,6 +346,43 @@ impl<A: Actor + 'static> ActorContext<A> {
         response.await
     }
 
+    /// Dispatch an async request, unless the actor's queue or the arena has no room for it.
+    pub(crate) async fn try_request<M>(
+        &'static self,
+        message: M,
+    ) -> Result<<A as RequestHandler<M>>::Response, (M, SendError)>
+    where
+        A: RequestHandler<M>,
+        M: 'static,
+    {
+        let response = with_critical_section(|cs| {
+            let mut producer = self.items_producer.borrow_mut();
+            let producer = producer.as_mut().unwrap();
+            if !producer.ready() {
+                return Err((message, SendError::QueueFull));
+            }
+            let signal = match Rc::try_new(CompletionHandle::new()) {
+                Ok(signal) => signal,
+                Err(e) => return Err((message, SendError::Exhausted(e))),
+            };
+            let sender = CompletionSender::new(signal.clone());
+            let receiver = CompletionReceiver::new(signal);
+            let request: &mut dyn ActorFuture<A> = self
+                .arena()
+                .try_alloc_with(message, |message| OnRequest::new(self, message, sender))
+                .map_err(|(message, e)| (message, SendError::Exhausted(e)))?;
+            producer.enqueue(Box::new(request)).ok();
+            Ok(RequestResponseFuture::new(receiver))
+        })?;
+
+        let flag_ptr = self.state_flag_handle.borrow_mut().unwrap() as *const AtomicU8;
+        unsafe {
+            (*flag_ptr).store(ActorState::READY.into(), Ordering::Release);
+        }
+
+        Ok(response.await)
+    }
+
     /// Dispatch an async request.
     pub(crate) async fn request_cancellable<M>(
         &'static self,
@@ -275,13 +394,12 @@ impl<A: Actor + 'static> ActorContext<A> {
         let signal = Rc::new(CompletionHandle::new());
         let sender = CompletionSender::new(signal.clone());
         let receiver = CompletionReceiver::new(signal);
-        let request: &mut dyn ActorFuture<A> =
-            SystemArena::alloc(OnRequest::new(self, message, sender)).unwrap();
+        let request: &mut dyn ActorFuture<A> = self.alloc(OnRequest::new(self, message, sender));
         let response = RequestResponseFuture::new(receiver);
 
         unsafe {
             let request = transmute::<_, &mut (dyn ActorFuture<A> + 'static)>(request);
-            let request: Box<dyn ActorFuture<A>, SystemArena> = Box::new(request);
+            let request: Box<dyn ActorFuture<A>, ActorArena> = Box::new(request);
             with_critical_section(|cs| {
                 self.items_producer
                     .borrow_mut()
@@ -314,13 +432,12 @@ impl<A: Actor + 'static> ActorContext<A> {
         let debug = "unknown".into();
         //write!(debug, "{:?}", type_name::<M>()).unwrap();
 
-        let request: &mut dyn ActorFuture<A> =
-            SystemArena::alloc(OnRequest::new(self, message, sender)).unwrap();
+        let request: &mut dyn ActorFuture<A> = self.alloc(OnRequest::new(self, message, sender));
         let response = RequestResponseFuture::new_panicking(receiver, debug);
 
         unsafe {
             let request = transmute::<_, &mut (dyn ActorFuture<A> + 'static)>(request);
-            let request: Box<dyn ActorFuture<A>, SystemArena> = Box::new(request);
+            let request: Box<dyn ActorFuture<A>, ActorArena> = Box::new(request);
             with_critical_section(|cs| {
                 self.items_producer
                     .borrow_mut()
@@ -670,7 +787,7 @@ struct CompletionHandle<T> {
 
 enum CompletionValue<T> {
     Immediate(T),
-    Future(Box<dyn Future<Output = T>, SystemArena>),
+    Future(Box<dyn Future<Output = T>, ActorArena>),
 }
 
 impl<T> CompletionHandle<T> {
@@ -698,7 +815,7 @@ impl<T: 'static> CompletionHandle<T> {
         }
     }
 
-    pub fn send_future(&self, value: Box<dyn Future<Output = T>, SystemArena>) {
+    pub fn send_future(&self, value: Box<dyn Future<Output = T>, ActorArena>) {
         self.value
             .borrow_mut()
             .replace(CompletionValue::Future(value));
@@ -734,12 +851,12 @@ impl<T: 'static> CompletionHandle<T> {
 }
 
 struct CompletionSender<T: 'static> {
-    handle: Rc<CompletionHandle<T>, SystemArena>,
+    handle: Rc<CompletionHandle<T>, ActorArena>,
     sent: AtomicBool,
 }
 
 impl<T: 'static> CompletionSender<T> {
-    pub(crate) fn new(handle: Rc<CompletionHandle<T>, SystemArena>) -> Self {
+    pub(crate) fn new(handle: Rc<CompletionHandle<T>, ActorArena>) -> Self {
         Self {
             handle,
             sent: AtomicBool::new(false),
@@ -754,7 +871,7 @@ impl<T: 'static> CompletionSender<T> {
         self.handle.send_value(response);
     }
 
-    pub(crate) fn send_future(&self, response: Box<dyn Future<Output = T>, SystemArena>)
+    pub(crate) fn send_future(&self, response: Box<dyn Future<Output = T>, ActorArena>)
     where
         T: 'static,
     {
@@ -764,12 +881,12 @@ impl<T: 'static> CompletionSender<T> {
 }
 
 struct CompletionReceiver<T: 'static> {
-    handle: Rc<CompletionHandle<T>, SystemArena>,
+    handle: Rc<CompletionHandle<T>, ActorArena>,
     received: bool,
 }
 
 impl<T: 'static> CompletionReceiver<T> {
-    pub(crate) fn new(handle: Rc<CompletionHandle<T>, SystemArena>) -> Self {
+    pub(crate) fn new(handle: Rc<CompletionHandle<T>, ActorArena>) -> Self {
         Self {
             handle,
             received: false,
@@ -793,3 +910,147 @@ impl<T: 'static> CompletionReceiver<T> {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::arena::AllocError;
+    use crate::system::mock::{self, MockRuntime};
+    use core::sync::atomic::AtomicUsize;
+    use std::rc::Rc as StdRc;
+    use std::sync::Once;
+    use std::vec::Vec;
+
+    crate::define_arena!(ActorTestArena);
+
+    /// Set up the arena of the actors under test, the first time.
+    fn init_arena() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            crate::init_arena!(crate::system::actor::tests | ActorTestArena => 1024);
+        });
+    }
+
+    /// Sums up what it is sent.
+    #[derive(Clone, Default)]
+    struct Counter(StdRc<Cell<usize>>);
+
+    impl Actor for Counter {
+        type Configuration = ();
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Add(usize);
+
+    impl NotifyHandler<Add> for Counter {
+        fn on_notify(self, message: Add) -> Completion<Self> {
+            self.0.set(self.0.get() + message.0);
+            Completion::immediate(self)
+        }
+    }
+
+    impl RequestHandler<Add> for Counter {
+        type Response = usize;
+
+        fn on_request(self, message: Add) -> Response<Self, Self::Response> {
+            self.0.set(self.0.get() + message.0);
+            let sum = self.0.get();
+            Response::immediate(self, sum)
+        }
+    }
+
+    /// Take all the room left in the arena, for it to be given back with `release()`.
+    fn fill<AR: Arena>() -> Vec<*mut u8> {
+        let mut taken = Vec::new();
+        while let Ok(byte) = AR::try_alloc(0u8) {
+            taken.push(byte as *mut u8);
+        }
+        taken
+    }
+
+    fn release<AR: Arena>(taken: Vec<*mut u8>) {
+        for ptr in taken {
+            AR::dealloc(ptr);
+        }
+    }
+
+    static EXHAUSTED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_exhaustion(error: &AllocError) {
+        EXHAUSTED.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_try_notify_queue_full() {
+        let _lock = mock::lock();
+        let mut runtime = MockRuntime::new();
+        let counter = Counter::default();
+        let address = runtime.mount(ActorContext::new(counter.clone()), ());
+        runtime.start();
+
+        // nothing runs in between, so the queue fills up
+        let mut sent = 0;
+        let error = loop {
+            match address.try_notify(Add(1)) {
+                Ok(_) => sent += 1,
+                Err(error) => break error,
+            }
+        };
+        assert_eq!(error, (Add(1), SendError::QueueFull));
+        assert!(sent > 0);
+
+        runtime.run();
+        assert_eq!(counter.0.get(), sent);
+        assert!(address.try_notify(Add(1)).is_ok());
+    }
+
+    #[test]
+    fn test_try_notify_exhausted() {
+        let _lock = mock::lock();
+        init_arena();
+        let mut runtime = MockRuntime::new();
+        let counter = Counter::default();
+        let context = ActorContext::new(counter.clone()).with_arena::<ActorTestArena>();
+        let address = runtime.mount(context, ());
+        runtime.start();
+        runtime.run();
+
+        let taken = fill::<ActorTestArena>();
+        EXHAUSTED.store(0, Ordering::SeqCst);
+        arena::on_exhaustion(count_exhaustion);
+        let (message, error) = address.try_notify(Add(2)).unwrap_err();
+        arena::clear_exhaustion_handler();
+        assert_eq!(message, Add(2));
+        assert!(matches!(error, SendError::Exhausted(e) if e.arena == "ActorTestArena"));
+        assert_eq!(EXHAUSTED.load(Ordering::SeqCst), 1);
+
+        // the queue was left as it was, and the actor goes on once there is room again
+        release::<ActorTestArena>(taken);
+        address.try_notify(message).unwrap();
+        runtime.run();
+        assert_eq!(counter.0.get(), 2);
+    }
+
+    #[test]
+    fn test_try_request() {
+        let _lock = mock::lock();
+        init_arena();
+        let mut runtime = MockRuntime::new();
+        let context = ActorContext::new(Counter::default()).with_arena::<ActorTestArena>();
+        let address = runtime.mount(context, ());
+        runtime.start();
+
+        assert_eq!(runtime.block_on(address.try_request(Add(3))), Ok(3));
+
+        let taken = fill::<ActorTestArena>();
+        let (message, error) = runtime.block_on(address.try_request(Add(4))).unwrap_err();
+        assert_eq!(message, Add(4));
+        assert!(matches!(error, SendError::Exhausted(_)));
+
+        release::<ActorTestArena>(taken);
+        assert_eq!(runtime.block_on(address.try_request(message)), Ok(7));
+        assert_eq!(ActorTestArena::info().used, 0);
+    }
+}
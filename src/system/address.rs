@@ -1,7 +1,17 @@
 //! Actor addresses
 
+use crate::arena::AllocError;
 use crate::prelude::*;
 
+/// Why a message could not be sent to an actor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SendError {
+    /// The actor has as many messages queued as it can hold.
+    QueueFull,
+    /// The arena had no room for the message.
+    Exhausted(AllocError),
+}
+
 /// A handle to another actor for dispatching notifications and requests.
 ///
 /// Individual actor implementations may augment the `Address` object
@@ -37,6 +47,16 @@ impl<A: Actor + 'static> Address<A> {
         self.actor.notify(message)
     }
 
+    /// Like `notify(...)`, but handing the message back when the actor cannot take it,
+    /// rather than panicking.
+    pub fn try_notify<M>(&self, message: M) -> Result<(), (M, SendError)>
+    where
+        A: NotifyHandler<M>,
+        M: 'static,
+    {
+        self.actor.try_notify(message)
+    }
+
     /// Perform an _async_ request to the actor behind this address.
     ///
     /// To accept the request and provide a response, the target must implement
@@ -49,6 +69,19 @@ impl<A: Actor + 'static> Address<A> {
         self.actor.request(message).await
     }
 
+    /// Like `request(...)`, but handing the message back when the actor cannot take it,
+    /// rather than panicking.
+    pub async fn try_request<M>(
+        &self,
+        message: M,
+    ) -> Result<<A as RequestHandler<M>>::Response, (M, SendError)>
+    where
+        A: RequestHandler<M> + 'static,
+        M: 'static,
+    {
+        self.actor.try_request(message).await
+    }
+
     /// Perform an unsafe _async_ request to the actor behind this address.
     ///
     /// To accept the request and provide a response, the target must implement
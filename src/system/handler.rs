@@ -2,9 +2,9 @@
 
 use core::future::Future;
 
-use crate::arena::{Arena, Box};
+use crate::arena::{AllocError, Arena, Box};
 use crate::prelude::Actor;
-use crate::system::SystemArena;
+use crate::system::{alloc, ActorArena};
 use core::mem::transmute;
 
 /// Return value from a `RequestHandler` to allow for synchronous or
@@ -18,10 +18,10 @@ pub enum Response<A: Actor + 'static, T: 'static> {
     Immediate(A, T),
 
     /// See `defer(future)`.
-    Defer(Box<dyn Future<Output = (A, T)>, SystemArena>),
+    Defer(Box<dyn Future<Output = (A, T)>, ActorArena>),
 
     /// See `immediate_future(future)`.
-    ImmediateFuture(A, Box<dyn Future<Output = T>, SystemArena>),
+    ImmediateFuture(A, Box<dyn Future<Output = T>, ActorArena>),
 }
 
 impl<T, A: Actor + 'static> Response<A, T> {
@@ -37,7 +37,27 @@ impl<T, A: Actor + 'static> Response<A, T> {
     where
         T: 'static,
     {
-        Self::Defer(Box::new(SystemArena::alloc(f).unwrap()))
+        Self::Defer(Box::new(alloc(f)))
+    }
+
+    /// Like `defer(...)`, but building the future from the actor only once there is room
+    /// for it in the arena. Otherwise the actor is handed back along with the error, to
+    /// respond some other way.
+    ///
+    /// ```ignore
+    /// Response::try_defer(self, |this| async move {
+    ///     let value = this.compute().await;
+    ///     (this, Ok(value))
+    /// })
+    /// .unwrap_or_else(|(this, e)| Response::immediate(this, Err(e.into())))
+    /// ```
+    pub fn try_defer<F, B>(actor: A, build: B) -> Result<Self, (A, AllocError)>
+    where
+        F: Future<Output = (A, T)> + 'static,
+        B: FnOnce(A) -> F,
+    {
+        let f: &mut F = ActorArena::try_alloc_with(actor, build)?;
+        Ok(Self::Defer(Box::new(f)))
     }
 
     /// Return a _non-static_-containing future,
@@ -55,7 +75,7 @@ impl<T, A: Actor + 'static> Response<A, T> {
     where
         T: 'static,
     {
-        let f: &mut dyn Future<Output = (A, T)> = SystemArena::alloc(f).unwrap();
+        let f: &mut dyn Future<Output = (A, T)> = alloc(f);
         let f = transmute::<_, &mut (dyn Future<Output = (A, T)> + 'static)>(f);
         Self::Defer(Box::new(f))
     }
@@ -67,7 +87,7 @@ impl<T, A: Actor + 'static> Response<A, T> {
     where
         T: 'static,
     {
-        Self::ImmediateFuture(actor, Box::new(SystemArena::alloc(f).unwrap()))
+        Self::ImmediateFuture(actor, Box::new(alloc(f)))
     }
 }
 
@@ -94,7 +114,7 @@ pub enum Completion<A: Actor> {
     Immediate(A),
 
     /// See `defer(future)`
-    Defer(Box<dyn Future<Output = A>, SystemArena>),
+    Defer(Box<dyn Future<Output = A>, ActorArena>),
 }
 
 impl<A: Actor + 'static> Completion<A> {
@@ -106,7 +126,18 @@ impl<A: Actor + 'static> Completion<A> {
     /// Provide a future for asynchronous handling of the notification
     /// within this actor's context.
     pub fn defer<F: Future<Output = A> + 'static>(f: F) -> Self {
-        Self::Defer(Box::new(SystemArena::alloc(f).unwrap()))
+        Self::Defer(Box::new(alloc(f)))
+    }
+
+    /// Like `defer(...)`, but building the future from the actor only once there is room
+    /// for it in the arena. Otherwise the actor is handed back along with the error.
+    pub fn try_defer<F, B>(actor: A, build: B) -> Result<Self, (A, AllocError)>
+    where
+        F: Future<Output = A> + 'static,
+        B: FnOnce(A) -> F,
+    {
+        let f: &mut F = ActorArena::try_alloc_with(actor, build)?;
+        Ok(Self::Defer(Box::new(f)))
     }
 
     /*
@@ -122,7 +153,7 @@ impl<A: Actor + 'static> Completion<A> {
     /// undefined behaviour by panicking if the caller drops the request future
     /// before completion.
     pub unsafe fn defer_unchecked<F: Future<Output = A>>(f: F) -> Self {
-        let f: &mut dyn Future<Output = A> = SystemArena::alloc(f).unwrap();
+        let f: &mut dyn Future<Output = A> = ActorArena::alloc(f).unwrap();
         let f = transmute::<_, &mut (dyn Future<Output = A> + 'static)>(f);
         Self::Defer(Box::new(f))
     }
@@ -154,3 +185,125 @@ pub trait EventHandler<E> {
     /// The default implementation simply drops the event.
     fn on_event(&'static self, event: E) {}
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::system::mock::{self, MockRuntime};
+    use core::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::Once;
+
+    crate::define_arena!(DeferArena);
+
+    /// Set up the arena of the actors under test, the first time.
+    fn init_arena() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            crate::init_arena!(crate::system::handler::tests | DeferArena => 1024);
+        });
+    }
+
+    /// Larger than the arenas of the tests, so that no future holding it fits.
+    const LARGE: usize = 2048;
+
+    /// Defers its work to a future when there is room for it, and otherwise makes do without.
+    #[derive(Clone, Default)]
+    struct Worker {
+        deferred: Rc<Cell<usize>>,
+        skipped: Rc<Cell<usize>>,
+    }
+
+    impl Actor for Worker {
+        type Configuration = ();
+    }
+
+    struct Small;
+    struct Large;
+
+    impl NotifyHandler<Small> for Worker {
+        fn on_notify(self, message: Small) -> Completion<Self> {
+            Completion::try_defer(self, |this| async move {
+                this.deferred.set(this.deferred.get() + 1);
+                this
+            })
+            .unwrap_or_else(|(this, _)| {
+                this.skipped.set(this.skipped.get() + 1);
+                Completion::immediate(this)
+            })
+        }
+    }
+
+    impl NotifyHandler<Large> for Worker {
+        fn on_notify(self, message: Large) -> Completion<Self> {
+            let buf = [1u8; LARGE];
+            Completion::try_defer(self, move |this| async move {
+                this.deferred.set(this.deferred.get() + buf[0] as usize);
+                this
+            })
+            .unwrap_or_else(|(this, _)| {
+                this.skipped.set(this.skipped.get() + 1);
+                Completion::immediate(this)
+            })
+        }
+    }
+
+    impl RequestHandler<Small> for Worker {
+        type Response = Result<usize, AllocError>;
+
+        fn on_request(self, message: Small) -> Response<Self, Self::Response> {
+            Response::try_defer(self, |this| async move { (this, Ok(1)) })
+                .unwrap_or_else(|(this, e)| Response::immediate(this, Err(e)))
+        }
+    }
+
+    impl RequestHandler<Large> for Worker {
+        type Response = Result<usize, AllocError>;
+
+        fn on_request(self, message: Large) -> Response<Self, Self::Response> {
+            let buf = [1u8; LARGE];
+            Response::try_defer(self, move |this| async move { (this, Ok(buf.len())) })
+                .unwrap_or_else(|(this, e)| Response::immediate(this, Err(e)))
+        }
+    }
+
+    #[test]
+    fn test_completion_try_defer() {
+        let _lock = mock::lock();
+        init_arena();
+        let mut runtime = MockRuntime::new();
+        let worker = Worker::default();
+        let context = ActorContext::new(worker.clone()).with_arena::<DeferArena>();
+        let address = runtime.mount(context, ());
+        runtime.start();
+
+        address.notify(Small);
+        address.notify(Large);
+        address.notify(Small);
+        runtime.run();
+        assert_eq!(worker.deferred.get(), 2);
+        assert_eq!(worker.skipped.get(), 1);
+        assert_eq!(DeferArena::info().used, 0);
+    }
+
+    #[test]
+    fn test_response_try_defer() {
+        let _lock = mock::lock();
+        init_arena();
+        let mut runtime = MockRuntime::new();
+        let context = ActorContext::new(Worker::default()).with_arena::<DeferArena>();
+        let address = runtime.mount(context, ());
+        runtime.start();
+
+        assert_eq!(runtime.block_on(address.request(Small)), Ok(1));
+        let error = runtime.block_on(address.request(Large)).unwrap_err();
+        assert_eq!(error.arena, "DeferArena");
+        assert!(error.requested > LARGE);
+        // the actor is still there to take requests
+        assert_eq!(runtime.block_on(address.request(Small)), Ok(1));
+        assert_eq!(DeferArena::info().used, 0);
+    }
+}
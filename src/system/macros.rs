@@ -7,6 +7,14 @@
 ///
 /// Additionally, the size of the async pool, in bytes, should be provided.
 ///
+/// Every queued message, deferred future and schedule takes room in the pool until it is
+/// done with. `arena::footprint::<T>()`, `ActorContext::notify_footprint::<M>()` and
+/// `ActorContext::request_footprint::<M>()` give the bytes each of these takes, and the pool's
+/// use is logged once the device has started. `arena::on_exhaustion(...)` reports allocations
+/// the pool had no room for. Actors and packages may be given arenas of their own with
+/// `ActorContext::with_arena()` and `Supervisor::with_arena()`, keeping them from starving
+/// each other.
+///
 /// Usage:
 /// ```ignore
 /// use cortex_m_rt::exception;
@@ -0,0 +1,200 @@
+//! Running actors on the host, for tests.
+//!
+//! A `MockRuntime` mounts actors the way a device does, and runs them until the future under
+//! test completes. Its timer runs on virtual time, which only moves on once no actor has
+//! anything left to do, so timeouts happen exactly when nothing answers.
+
+extern crate std;
+
+use crate::domain::time::clock::Error;
+use crate::domain::time::duration::Milliseconds;
+use crate::domain::time::fraction::Fraction;
+use crate::domain::time::{Clock, Instant};
+use crate::driver::timer::{Timer, TimerActor};
+use crate::hal::timer::Timer as HalTimer;
+use crate::prelude::*;
+use crate::system::device::DeviceContext;
+use core::cell::Cell;
+use core::future::Future;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+use cortex_m::interrupt::Nr;
+use std::boxed::Box as StdBox;
+use std::sync::{Arc, Mutex, MutexGuard, Once};
+use std::task::Wake;
+
+/// The interrupt of the mock timer.
+const IRQ: u8 = 0xFF;
+
+/// Serialize the tests using the actor system, which keeps its state in statics, setting up
+/// the system arena the first time.
+pub(crate) fn lock() -> MutexGuard<'static, ()> {
+    static INIT: Once = Once::new();
+    static LOCK: Mutex<()> = Mutex::new(());
+    INIT.call_once(|| {
+        crate::init_arena!(crate::system | SystemArena => 65536);
+    });
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn leak<T>(value: T) -> &'static mut T {
+    StdBox::leak(StdBox::new(value))
+}
+
+/// Milliseconds of virtual time, and when the timer is due to interrupt next.
+#[derive(Default)]
+struct VirtualTime {
+    now: Cell<u64>,
+    alarm: Cell<Option<u64>>,
+}
+
+pub struct MockClock(&'static VirtualTime);
+
+impl Clock for MockClock {
+    type T = u64;
+    const SCALING_FACTOR: Fraction = Fraction::new(1, 1_000);
+
+    fn try_now(&self) -> Result<Instant<Self>, Error> {
+        Ok(Instant::new(self.0.now.get()))
+    }
+}
+
+pub struct MockHardwareTimer(&'static VirtualTime);
+
+impl HalTimer for MockHardwareTimer {
+    fn start(&mut self, duration: Milliseconds) {
+        self.0.alarm.set(Some(self.0.now.get() + duration.0 as u64));
+    }
+
+    fn clear_update_interrupt_flag(&mut self) {}
+
+    fn max_duration(&self) -> Milliseconds {
+        Milliseconds(u32::MAX)
+    }
+}
+
+struct MockIrq;
+
+unsafe impl Nr for MockIrq {
+    fn nr(&self) -> u8 {
+        IRQ
+    }
+}
+
+pub type MockTimer = TimerActor<MockHardwareTimer, MockClock>;
+
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Actors mounted on the host, with a timer on virtual time. Tests hold the `lock()` while
+/// using one.
+pub struct MockRuntime {
+    supervisor: &'static mut Supervisor,
+    time: &'static VirtualTime,
+    timer: Address<MockTimer>,
+}
+
+impl MockRuntime {
+    pub fn new() -> Self {
+        // the executor hands out pointers into itself as wakers, so it must not move
+        let supervisor = leak(Supervisor::new());
+        let time = leak(VirtualTime::default());
+        let timer = leak(Timer::new(
+            MockHardwareTimer(time),
+            MockClock(time),
+            MockIrq,
+        ))
+        .mount((), supervisor);
+        Self {
+            supervisor,
+            time,
+            timer,
+        }
+    }
+
+    pub fn timer(&self) -> Address<MockTimer> {
+        self.timer
+    }
+
+    /// Virtual time passed since the runtime was created.
+    pub fn elapsed(&self) -> Milliseconds {
+        Milliseconds(self.time.now.get() as u32)
+    }
+
+    pub fn supervisor(&mut self) -> &mut Supervisor {
+        self.supervisor
+    }
+
+    pub fn mount<A: Actor>(
+        &mut self,
+        context: ActorContext<A>,
+        config: A::Configuration,
+    ) -> Address<A> {
+        leak(context).mount(config, self.supervisor)
+    }
+
+    pub fn mount_package<P: Package + 'static>(
+        &mut self,
+        package: P,
+        config: P::Configuration,
+    ) -> Address<P::Primary> {
+        leak(package).mount(config, self.supervisor)
+    }
+
+    /// The event bus of `device`, which handles whatever actors publish on it.
+    pub fn event_bus<D: Device>(&mut self, device: D) -> Address<EventBus<D>> {
+        let device = leak(DeviceContext::new(device));
+        self.mount(ActorContext::new(EventBus::new(device)), ())
+    }
+
+    /// Initialize and start the actors mounted so far.
+    pub fn start(&mut self) {
+        self.supervisor.start();
+    }
+
+    /// Run the actors until they all wait on something, without moving time on.
+    pub fn run(&mut self) {
+        self.supervisor.run_until_quiescence();
+    }
+
+    /// Run the actors, and the timer, until `future` completes.
+    ///
+    /// # Panics
+    /// When the future can never complete, with nothing left to run and no timer pending.
+    pub fn block_on<F: Future>(&mut self, future: F) -> F::Output {
+        let woken = Arc::new(Woken(AtomicBool::new(true)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut future = StdBox::pin(future);
+        loop {
+            if woken.0.swap(false, Ordering::AcqRel) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+            }
+            self.supervisor.run_until_quiescence();
+            if !woken.0.load(Ordering::Acquire) {
+                let alarm = self
+                    .time
+                    .alarm
+                    .take()
+                    .expect("stalled, with nothing to run and no timer pending");
+                if alarm > self.time.now.get() {
+                    self.time.now.set(alarm);
+                }
+                self.supervisor.on_interrupt(IRQ as i16);
+            }
+        }
+    }
+
+    /// Run the actors for `duration` of virtual time.
+    pub fn sleep(&mut self, duration: Milliseconds) {
+        let timer = self.timer;
+        self.block_on(timer.delay(duration));
+    }
+}
@@ -5,10 +5,173 @@ pub(crate) mod device;
 pub(crate) mod handler;
 pub(crate) mod interrupt;
 pub(crate) mod macros;
+#[cfg(test)]
+pub(crate) mod mock;
 pub(crate) mod package;
 pub(crate) mod supervisor;
 
 pub use device::{Device, DeviceConfiguration, DeviceContext};
 
+use crate::arena::{self, AllocError, Arena, ArenaRef, Info};
 use crate::define_arena;
+use actor::ActorInfo;
+use core::ptr::NonNull;
+
 define_arena!(SystemArena);
+
+/// The arena of the actor being run: its own, if it was given one, or else the system arena.
+///
+/// Memory goes back to whichever arena it came from, regardless of the actor it is freed by.
+pub struct ActorArena;
+
+impl Arena for ActorArena {
+    fn name() -> &'static str {
+        ActorInfo::arena().name()
+    }
+
+    fn id() -> usize {
+        ActorInfo::arena().id()
+    }
+
+    fn try_alloc_raw(size: usize, align: usize) -> Result<NonNull<u8>, AllocError> {
+        ActorInfo::arena().try_alloc_raw(size, align)
+    }
+
+    fn dealloc(ptr: *mut u8) {
+        owner(ptr).dealloc(ptr)
+    }
+
+    fn contains(ptr: *const u8) -> bool {
+        ActorInfo::arena().contains(ptr)
+            || SystemArena::contains(ptr)
+            || arena::owner(ptr).is_some()
+    }
+
+    fn info() -> Info {
+        ActorInfo::arena().info()
+    }
+}
+
+/// The arena `ptr` came from. Most memory goes back from the actor that took it, or to the
+/// system arena, so those are asked before searching the registered arenas.
+fn owner(ptr: *const u8) -> ArenaRef {
+    let current = ActorInfo::arena();
+    if current.contains(ptr) {
+        current
+    } else if SystemArena::contains(ptr) {
+        ArenaRef::of::<SystemArena>()
+    } else {
+        arena::owner(ptr).unwrap_or_else(ArenaRef::of::<SystemArena>)
+    }
+}
+
+/// Move `val` into the current actor's arena, panicking with the actor's name and the
+/// arena's state when there is no room.
+pub(crate) fn alloc<'o, T: 'o>(val: T) -> &'o mut T {
+    ActorArena::try_alloc(val)
+        .unwrap_or_else(|e| panic!("[{}] arena exhausted: {:?}", ActorInfo::name(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::arena::Box;
+    use crate::domain::time::duration::Milliseconds;
+    use crate::prelude::*;
+    use crate::system::mock::{self, MockRuntime, MockTimer};
+    use std::boxed::Box as StdBox;
+
+    define_arena!(DriverArena);
+    define_arena!(ActorOwnArena);
+    define_arena!(PackageArena);
+
+    /// Waits a while for each notification, in a future deferred to.
+    struct Sleeper {
+        timer: Option<Address<MockTimer>>,
+    }
+
+    impl Actor for Sleeper {
+        type Configuration = Address<MockTimer>;
+
+        fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+            self.timer.replace(config);
+        }
+    }
+
+    struct Sleep;
+
+    impl NotifyHandler<Sleep> for Sleeper {
+        fn on_notify(self, message: Sleep) -> Completion<Self> {
+            Completion::defer(async move {
+                self.timer.unwrap().delay(Milliseconds(100)).await;
+                self
+            })
+        }
+    }
+
+    /// Bytes of its arena a sleeper takes while it sleeps, after which it takes none.
+    fn sleep<AR: Arena>(runtime: &mut MockRuntime, sleeper: Address<Sleeper>) -> usize {
+        runtime.start();
+        runtime.run();
+        assert_eq!(AR::info().used, 0);
+        sleeper.notify(Sleep);
+        runtime.run();
+        let used = AR::info().used;
+        runtime.sleep(Milliseconds(150u32));
+        assert_eq!(AR::info().used, 0);
+        used
+    }
+
+    #[test]
+    fn test_freed_into_owner() {
+        let _lock = crate::system::mock::lock();
+        crate::init_arena!(crate::system::tests | DriverArena => 512);
+        let driver = ArenaRef::of::<DriverArena>();
+        arena::register(driver);
+
+        let value: Box<[u8; 100], ActorArena> = Box::new(driver.try_alloc([1u8; 100]).unwrap());
+        assert!(DriverArena::info().used > 0);
+        assert!(ActorArena::contains(&value[0]));
+        assert_eq!(arena::owner(&value[0]), Some(driver));
+
+        drop(value);
+        assert_eq!(DriverArena::info().used, 0);
+    }
+
+    #[test]
+    fn test_actor_arena() {
+        let _lock = mock::lock();
+        crate::init_arena!(crate::system::tests | ActorOwnArena => 1024);
+        let mut runtime = MockRuntime::new();
+        let timer = runtime.timer();
+        let context = ActorContext::new(Sleeper { timer: None }).with_arena::<ActorOwnArena>();
+        let sleeper = runtime.mount(context, timer);
+
+        // the notification, and the future it defers to, come from the actor's arena
+        let used = sleep::<ActorOwnArena>(&mut runtime, sleeper);
+        assert!(used > ActorContext::<Sleeper>::notify_footprint::<Sleep>());
+    }
+
+    #[test]
+    fn test_supervisor_arena() {
+        let _lock = mock::lock();
+        crate::init_arena!(crate::system::tests | PackageArena => 1024);
+        let mut runtime = MockRuntime::new();
+        let timer = runtime.timer();
+        let inner: &'static _ =
+            StdBox::leak(StdBox::new(ActorContext::new(Sleeper { timer: None })));
+        let outer: &'static _ =
+            StdBox::leak(StdBox::new(ActorContext::new(Sleeper { timer: None })));
+        let sleeper = runtime
+            .supervisor()
+            .with_arena::<PackageArena, _>(move |supervisor| inner.mount(timer, supervisor));
+        outer.mount(timer, runtime.supervisor());
+        assert_eq!(inner.arena(), ArenaRef::of::<PackageArena>());
+        assert_eq!(outer.arena(), ArenaRef::of::<SystemArena>());
+
+        let used = sleep::<PackageArena>(&mut runtime, sleeper);
+        assert!(used > ActorContext::<Sleeper>::notify_footprint::<Sleep>());
+    }
+}
@@ -1,7 +1,9 @@
 use heapless::{consts::*, Vec};
 
+use crate::arena::{self, ArenaRef};
 use crate::system::actor::{Actor, ActorContext, CURRENT};
 use crate::system::device::Lifecycle;
+use crate::system::SystemArena;
 use core::cmp::PartialEq;
 use core::sync::atomic::{AtomicU8, Ordering};
 use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
@@ -90,6 +92,7 @@ impl<A: Actor> ActiveActor for ActorContext<A> {
         }
         unsafe {
             CURRENT.name.replace(self.name());
+            CURRENT.arena.replace(self.arena());
         }
         if self.name() == "uart_actor" {
             log::trace!("[{}] Replaced name", self.name());
@@ -149,6 +152,7 @@ impl<A: Actor> ActiveActor for ActorContext<A> {
 
         unsafe {
             CURRENT.name.take();
+            CURRENT.arena.take();
         }
 
         Poll::Pending
@@ -207,10 +211,29 @@ impl ActorExecutor {
         }
     }
 
-    pub fn run_forever(&mut self) -> ! {
+    /// Initialize and then start every actor, running them until they wait on something.
+    pub(crate) fn start(&mut self) {
         self.dispatch_lifecycle_event(Lifecycle::Initialize);
         self.run_until_quiescence();
         self.dispatch_lifecycle_event(Lifecycle::Start);
+        self.run_until_quiescence();
+        for arena in Some(ArenaRef::of::<SystemArena>())
+            .iter()
+            .chain(arena::registered().iter())
+        {
+            let info = arena.info();
+            log::info!(
+                "[{}] started using {} of {} bytes, at most {}",
+                arena.name(),
+                info.used,
+                info.used + info.free,
+                info.high_watermark
+            );
+        }
+    }
+
+    pub fn run_forever(&mut self) -> ! {
+        self.start();
         loop {
             self.run_until_quiescence();
             // self.dispatch_lifecycle_event( Lifecycle::Sleep );
@@ -1,5 +1,6 @@
 //! Opaque supervisor for internal operation.
 
+use crate::arena::{self, Arena, ArenaRef};
 use crate::platform::with_critical_section;
 
 use crate::system::supervisor::actor_executor::{ActiveActor, ActorExecutor};
@@ -14,6 +15,7 @@ pub(crate) mod interrupt_dispatcher;
 pub struct Supervisor {
     executor: RefCell<ActorExecutor>,
     dispatcher: RefCell<InterruptDispatcher>,
+    arena: Option<ArenaRef>,
 }
 
 impl Supervisor {
@@ -22,9 +24,32 @@ impl Supervisor {
         Self {
             executor: RefCell::new(ActorExecutor::new()),
             dispatcher: RefCell::new(InterruptDispatcher::new()),
+            arena: None,
         }
     }
 
+    /// Mount with `mount`, giving the actors it mounts arena `AR` to allocate from unless
+    /// they have one of their own already. This way, a package and all of its actors share
+    /// a memory budget:
+    ///
+    /// ```ignore
+    /// let wifi = supervisor.with_arena::<NetworkArena, _>(|supervisor| {
+    ///     self.wifi.mount(config, supervisor)
+    /// });
+    /// ```
+    pub fn with_arena<AR: Arena, R>(&mut self, mount: impl FnOnce(&mut Supervisor) -> R) -> R {
+        let arena = ArenaRef::of::<AR>();
+        arena::register(arena);
+        let outer = self.arena.replace(arena);
+        let result = mount(self);
+        self.arena = outer;
+        result
+    }
+
+    pub(crate) fn arena(&self) -> Option<ArenaRef> {
+        self.arena
+    }
+
     pub(crate) fn activate_actor<S: ActiveActor>(
         &mut self,
         actor: &'static S,
@@ -49,6 +74,16 @@ impl Supervisor {
         self.executor.borrow_mut().run_forever()
     }
 
+    /// Initialize and start the actors mounted so far, without running forever after.
+    pub(crate) fn start(&self) {
+        self.executor.borrow_mut().start()
+    }
+
+    /// Run the actors until none of them has anything left to do for now.
+    pub(crate) fn run_until_quiescence(&self) {
+        self.executor.borrow_mut().run_until_quiescence()
+    }
+
     pub(crate) fn on_interrupt(&self, irqn: i16) {
         //log::info!("[supervisor] on IRQ {}", irqn);
         self.dispatcher.borrow().on_interrupt(irqn);
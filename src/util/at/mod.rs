@@ -0,0 +1,528 @@
+//! A framework for modems driven by AT commands.
+//!
+//! A driver describes each of its commands as a `Command`, which encodes the command and parses
+//! its response, and the unsolicited result codes (URCs) of its modem as a `Urc`. The `AtModem`
+//! package then takes care of writing commands, collecting their responses, enforcing timeouts
+//! and routing URCs to the driver.
+
+mod modem;
+#[cfg(test)]
+pub(crate) mod replay;
+
+pub use modem::{AtController, AtModem};
+
+use crate::api::scheduler::ScheduleError;
+use crate::api::uart::Error as UartError;
+use crate::domain::time::duration::Milliseconds;
+use crate::util::nom::parse_u16;
+use core::fmt::Write;
+use heapless::{consts::*, String, Vec};
+use nom::branch::alt;
+use nom::bytes::streaming::tag;
+use nom::combinator::{map, value};
+use nom::sequence::delimited;
+use nom::IResult;
+
+/// Longest command, including the leading `AT`.
+pub type CommandLength = U128;
+/// Longest line received. Longer lines are dropped.
+pub type LineLength = U256;
+/// Longest response to a command, not counting its final result.
+pub type ResponseLength = U512;
+
+/// The prompt for the payload of a command.
+pub const PROMPT: &[u8] = b"> ";
+
+/// Parses the header of raw data at the start of a line, returning the length of the header
+/// and the length of the data following it.
+pub type DataHeader = fn(&[u8]) -> Option<(usize, usize)>;
+
+const DEFAULT_TIMEOUT: Milliseconds = Milliseconds(1000);
+
+#[derive(Debug, Clone)]
+pub enum AtError {
+    /// No final result arrived within the timeout of the command.
+    Timeout,
+    /// The modem answered `ERROR`.
+    Error,
+    /// The modem answered `+CME ERROR: <code>`.
+    CmeError(u16),
+    /// The modem answered `+CMS ERROR: <code>`.
+    CmsError(u16),
+    Uart(UartError),
+    /// The timeout of the command could not be scheduled, so it was not waited for.
+    Schedule(ScheduleError),
+    /// The response could not be parsed.
+    Parse,
+    CommandTooLong,
+    ResponseTooLong,
+}
+
+impl From<UartError> for AtError {
+    fn from(error: UartError) -> Self {
+        AtError::Uart(error)
+    }
+}
+
+impl From<ScheduleError> for AtError {
+    fn from(error: ScheduleError) -> Self {
+        AtError::Schedule(error)
+    }
+}
+
+/// A command understood by a modem.
+///
+/// Lines handed to `final_result` and `parse`, as well as to `Urc::parse`, keep their line
+/// endings, so parsers can match on `\r\n` just like the parsers of other drivers do.
+pub trait Command {
+    type Response: 'static;
+
+    /// Write the command following the leading `AT`, such as `+CSQ`.
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result;
+
+    /// How long to wait for the final result.
+    fn timeout(&self) -> Milliseconds {
+        DEFAULT_TIMEOUT
+    }
+
+    /// Data to write once the modem prompts for it with `> `.
+    fn payload(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// For responses carrying raw data, such as `+CIPRECVDATA,<len>:<data>`, the parser of the
+    /// header announcing the data. The data following the header is read as is, rather than
+    /// split into lines, and handed to `parse` along with the rest of the response.
+    fn data_header(&self) -> Option<DataHeader> {
+        None
+    }
+
+    /// Whether `line` ends the response, and how. Commands whose responses end differently,
+    /// such as in `SEND OK`, override this.
+    fn final_result(&self, line: &[u8]) -> Option<Result<(), AtError>> {
+        final_result(line)
+    }
+
+    /// Parse the lines received before a successful final result.
+    fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError>;
+}
+
+/// An unsolicited result code, which the modem sends at any time rather than in response to a
+/// command.
+pub trait Urc: Sized {
+    /// Parse `line`, if it is one of these codes.
+    fn parse(line: &[u8]) -> Option<Self>;
+}
+
+/// Plain `AT`, to check that the modem is responsive.
+pub struct Attention;
+
+impl Command for Attention {
+    type Response = ();
+
+    fn encode<W: Write>(&self, _: &mut W) -> core::fmt::Result {
+        Ok(())
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+/// `ATE0` or `ATE1`, to turn the echo of commands off or on.
+pub struct SetEcho(pub bool);
+
+impl Command for SetEcho {
+    type Response = ();
+
+    fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+        write!(w, "E{}", self.0 as u8)
+    }
+
+    fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+        Ok(())
+    }
+}
+
+fn ok(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    tag("OK\r\n")(input)
+}
+
+fn error(input: &[u8]) -> IResult<&[u8], AtError> {
+    value(AtError::Error, tag("ERROR\r\n"))(input)
+}
+
+fn cme_error(input: &[u8]) -> IResult<&[u8], AtError> {
+    map(
+        delimited(tag("+CME ERROR: "), parse_u16, tag("\r\n")),
+        AtError::CmeError,
+    )(input)
+}
+
+fn cms_error(input: &[u8]) -> IResult<&[u8], AtError> {
+    map(
+        delimited(tag("+CMS ERROR: "), parse_u16, tag("\r\n")),
+        AtError::CmsError,
+    )(input)
+}
+
+fn error_result(input: &[u8]) -> IResult<&[u8], AtError> {
+    alt((error, cme_error, cms_error))(input)
+}
+
+/// The standard final results: `OK`, `ERROR`, and numeric `+CME ERROR` and `+CMS ERROR`.
+pub fn final_result(line: &[u8]) -> Option<Result<(), AtError>> {
+    if ok(line).is_ok() {
+        Some(Ok(()))
+    } else {
+        error_result(line).ok().map(|(_, error)| Err(error))
+    }
+}
+
+/// `line` without its line ending.
+pub fn trim(line: &[u8]) -> &[u8] {
+    let mut end = line.len();
+    while end > 0 && (line[end - 1] == b'\r' || line[end - 1] == b'\n') {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+/// Encode `command`, without a line ending.
+pub(crate) fn encode<C: Command>(command: &C) -> Result<String<CommandLength>, AtError> {
+    let mut line = String::new();
+    line.push_str("AT").map_err(|_| AtError::CommandTooLong)?;
+    command
+        .encode(&mut line)
+        .map_err(|_| AtError::CommandTooLong)?;
+    Ok(line)
+}
+
+/// Collects the response to a command, line by line.
+pub(crate) struct Exchange<'c, C>
+where
+    C: Command,
+{
+    command: &'c C,
+    response: Vec<u8, ResponseLength>,
+    started: bool,
+    overflow: bool,
+}
+
+impl<'c, C> Exchange<'c, C>
+where
+    C: Command,
+{
+    pub(crate) fn new(command: &'c C) -> Self {
+        Self {
+            command,
+            response: Vec::new(),
+            started: false,
+            overflow: false,
+        }
+    }
+
+    /// Digest a line, returning the outcome once the final result arrived. Empty lines and the
+    /// echo of the command are skipped until the response starts, and kept after, so that
+    /// responses carrying data arrive intact.
+    pub(crate) fn digest(&mut self, line: &[u8]) -> Option<Result<C::Response, AtError>> {
+        let trimmed = trim(line);
+        if !self.started && (trimmed.is_empty() || trimmed.starts_with(b"AT")) {
+            return None;
+        }
+        if let Some(result) = self.command.final_result(line) {
+            return Some(match result {
+                Ok(_) if self.overflow => Err(AtError::ResponseTooLong),
+                Ok(_) => self.command.parse(&self.response),
+                Err(e) => Err(e),
+            });
+        }
+        self.data(line);
+        None
+    }
+
+    /// Digest raw data, or the header announcing it.
+    pub(crate) fn data(&mut self, data: &[u8]) {
+        self.started = true;
+        if self.response.extend_from_slice(data).is_err() {
+            self.overflow = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::replay::Replay;
+    use super::*;
+    use crate::driver::uart::mock::Step::*;
+    use crate::util::nom::parse_u8;
+    use nom::character::streaming::char;
+    use nom::sequence::separated_pair;
+
+    #[derive(Debug, PartialEq)]
+    struct SignalQuality {
+        rssi: u8,
+        ber: u8,
+    }
+
+    struct GetSignalQuality;
+
+    fn signal_quality(input: &[u8]) -> IResult<&[u8], SignalQuality> {
+        map(
+            delimited(
+                tag("+CSQ: "),
+                separated_pair(parse_u8, char(','), parse_u8),
+                tag("\r\n"),
+            ),
+            |(rssi, ber)| SignalQuality { rssi, ber },
+        )(input)
+    }
+
+    impl Command for GetSignalQuality {
+        type Response = SignalQuality;
+
+        fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+            w.write_str("+CSQ")
+        }
+
+        fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+            signal_quality(response)
+                .map(|(_, quality)| quality)
+                .map_err(|_| AtError::Parse)
+        }
+    }
+
+    /// `+COPN`, listing the names of all operators, one per line.
+    struct ListOperators;
+
+    impl Command for ListOperators {
+        type Response = usize;
+
+        fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+            w.write_str("+COPN")
+        }
+
+        fn parse(&self, response: &[u8]) -> Result<Self::Response, AtError> {
+            Ok(response
+                .split(|b| *b == b'\n')
+                .filter(|l| l.starts_with(b"+COPN: "))
+                .count())
+        }
+    }
+
+    struct SendSms(&'static [u8]);
+
+    impl Command for SendSms {
+        type Response = ();
+
+        fn encode<W: Write>(&self, w: &mut W) -> core::fmt::Result {
+            w.write_str("+CMGS=\"+4712345678\"")
+        }
+
+        fn payload(&self) -> Option<&[u8]> {
+            Some(self.0)
+        }
+
+        fn parse(&self, _: &[u8]) -> Result<Self::Response, AtError> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Ring,
+        Registered(u8),
+    }
+
+    fn registered(input: &[u8]) -> IResult<&[u8], Event> {
+        map(
+            delimited(tag("+CREG: "), parse_u8, tag("\r\n")),
+            Event::Registered,
+        )(input)
+    }
+
+    impl Urc for Event {
+        fn parse(line: &[u8]) -> Option<Self> {
+            match line {
+                b"RING\r\n" => Some(Event::Ring),
+                _ => registered(line).ok().map(|(_, event)| event),
+            }
+        }
+    }
+
+    #[test]
+    fn test_final_result() {
+        assert!(matches!(final_result(b"OK\r\n"), Some(Ok(()))));
+        assert!(matches!(
+            final_result(b"ERROR\r\n"),
+            Some(Err(AtError::Error))
+        ));
+        assert!(matches!(
+            final_result(b"+CME ERROR: 10\r\n"),
+            Some(Err(AtError::CmeError(10)))
+        ));
+        assert!(matches!(
+            final_result(b"+CMS ERROR: 304\r\n"),
+            Some(Err(AtError::CmsError(304)))
+        ));
+        assert!(final_result(b"+CSQ: 20,99\r\n").is_none());
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&Attention).unwrap().as_str(), "AT");
+        assert_eq!(encode(&SetEcho(false)).unwrap().as_str(), "ATE0");
+        assert_eq!(encode(&GetSignalQuality).unwrap().as_str(), "AT+CSQ");
+    }
+
+    #[test]
+    fn test_response() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"AT+CSQ\r\r\n+CSQ: 20,99\r\n\r\nOK\r\n"),
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"\r\n+CME ERROR: 10\r\n"),
+        ]);
+        assert_eq!(
+            modem.execute(GetSignalQuality).unwrap(),
+            SignalQuality { rssi: 20, ber: 99 }
+        );
+        assert!(matches!(
+            modem.execute(GetSignalQuality),
+            Err(AtError::CmeError(10))
+        ));
+        modem.finish();
+    }
+
+    #[test]
+    fn test_long_response() {
+        // more lines than the controller queues up while the ingress reads on
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+COPN\r\n"),
+            Receive(b"+COPN: \"24201\",\"TELENOR\"\r\n+COPN: \"24202\",\"TELIA\"\r\n"),
+            Receive(b"+COPN: \"24205\",\"ICE\"\r\n+COPN: \"24206\",\"ICE\"\r\n"),
+            Receive(b"+COPN: \"24207\",\"PHONERO\"\r\n+COPN: \"24208\",\"TDC\"\r\nOK\r\n"),
+        ]);
+        assert_eq!(modem.execute(ListOperators).unwrap(), 6);
+        modem.finish();
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"+CSQ: 20,99\r\n"),
+            Send(b"AT\r\n"),
+            Receive(b"OK\r\n"),
+        ]);
+        assert!(matches!(
+            modem.execute(GetSignalQuality),
+            Err(AtError::Timeout)
+        ));
+        assert_eq!(modem.elapsed(), DEFAULT_TIMEOUT);
+
+        // the timeout of the first command does not end the next
+        assert!(modem.execute(Attention).is_ok());
+        assert_eq!(modem.elapsed(), DEFAULT_TIMEOUT);
+        modem.finish();
+    }
+
+    #[test]
+    fn test_timeouts_cancelled() {
+        // more commands than the timer has slots, each answered right away
+        let mut script = std::vec::Vec::new();
+        for _ in 0..40 {
+            script.push(Send(b"AT\r\n"));
+            script.push(Receive(b"OK\r\n"));
+        }
+        script.push(Send(b"AT+CSQ\r\n"));
+        let mut modem: Replay<Event> =
+            Replay::new(std::boxed::Box::leak(script.into_boxed_slice()));
+        for _ in 0..40 {
+            assert!(modem.execute(Attention).is_ok());
+        }
+
+        // their timeouts were dropped, leaving room for that of a command never answered
+        assert!(matches!(
+            modem.execute(GetSignalQuality),
+            Err(AtError::Timeout)
+        ));
+        assert_eq!(modem.elapsed(), DEFAULT_TIMEOUT);
+        modem.finish();
+    }
+
+    #[test]
+    fn test_late_response_is_dropped() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"+CSQ: 20,99\r\n"),
+            Send(b"AT\r\n"),
+            // the rest of the response to the first command arrives first
+            Receive(b"\r\nOK\r\n"),
+            Receive(b"OK\r\n"),
+        ]);
+        assert!(matches!(
+            modem.execute(GetSignalQuality),
+            Err(AtError::Timeout)
+        ));
+        assert!(modem.execute(Attention).is_ok());
+        modem.finish();
+    }
+
+    #[test]
+    fn test_payload() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+CMGS=\"+4712345678\"\r\n"),
+            Receive(b"\r\n> "),
+            Send(b"hello\x1a"),
+            Receive(b"\r\n+CMGS: 1\r\n\r\nOK\r\n"),
+        ]);
+        assert!(modem.execute(SendSms(b"hello\x1a")).is_ok());
+        modem.finish();
+    }
+
+    #[test]
+    fn test_payload_after_echo() {
+        // the prompt follows the echo of the command
+        let mut modem: Replay<Event> = Replay::new(&[
+            Send(b"AT+CMGS=\"+4712345678\"\r\n"),
+            Receive(b"AT+CMGS=\"+4712345678\"\r\r\n> "),
+            Send(b"hello\x1a"),
+            Receive(b"\r\nOK\r\n"),
+        ]);
+        assert!(modem.execute(SendSms(b"hello\x1a")).is_ok());
+        modem.finish();
+    }
+
+    #[test]
+    fn test_urcs_are_routed() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Receive(b"\r\nRING\r\n"),
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"+CREG: 5\r\n+CSQ: 20,99\r\nOK\r\n"),
+        ]);
+        modem.idle();
+        assert_eq!(modem.urc(), Some(Event::Ring));
+        assert!(modem.execute(GetSignalQuality).is_ok());
+        assert_eq!(modem.urc(), Some(Event::Registered(5)));
+        assert_eq!(modem.urc(), None);
+        modem.finish();
+    }
+
+    #[test]
+    fn test_lines_while_idle_are_dropped() {
+        let mut modem: Replay<Event> = Replay::new(&[
+            Receive(b"+CSQ: 1,1\r\nOK\r\n"),
+            Send(b"AT+CSQ\r\n"),
+            Receive(b"+CSQ: 20,99\r\nOK\r\n"),
+        ]);
+        modem.idle();
+        assert_eq!(
+            modem.execute(GetSignalQuality).unwrap(),
+            SignalQuality { rssi: 20, ber: 99 }
+        );
+        modem.finish();
+    }
+}
@@ -0,0 +1,407 @@
+//! The actors running an AT modem over a UART.
+
+use super::{encode, trim, AtError, Command, DataHeader, Exchange, LineLength, Urc, PROMPT};
+use crate::api::scheduler::{Schedule, Scheduler};
+use crate::api::uart::{Error as UartError, UartReader, UartWriter};
+use crate::driver::uart::codec::LineCodec;
+use crate::prelude::*;
+use crate::synchronization::Channel;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+use heapless::{consts::*, Vec};
+
+enum Event {
+    Line(Vec<u8, LineLength>),
+    /// Raw data, or the header announcing it.
+    Data(Vec<u8, LineLength>),
+    Prompt,
+    Timeout(u32),
+}
+
+/// State shared by the actors of an `AtModem`.
+pub struct Shared {
+    events: Channel<Event, U4>,
+    pending: AtomicBool,
+    expect_prompt: AtomicBool,
+    data_header: Cell<Option<DataHeader>>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            events: Channel::new(),
+            pending: AtomicBool::new(false),
+            expect_prompt: AtomicBool::new(false),
+            data_header: Cell::new(None),
+        }
+    }
+}
+
+/// A modem driven by AT commands over the UART `U`, timing commands out with the scheduler `S`.
+///
+/// Its primary actor, the `AtController`, executes one command at a time. The `AtIngress`
+/// reads lines from the UART, notifying the handler `H` of those parsing as `URC`, and passing
+/// the others on to the command being executed.
+///
+/// The ingress reads a line at a time, and only looks for the `> ` prompt after the line being
+/// read when the command was written, such as its echo, or the `OK` some modems send first.
+/// While a command expecting raw data is executed, a line starting with the header announcing
+/// the data is cut short after the data, reading the rest of the data as is.
+pub struct AtModem<U, S, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    shared: Shared,
+    controller: ActorContext<AtController<U, S>>,
+    ingress: ActorContext<AtIngress<U, URC, H>>,
+    timeout: ActorContext<AtTimeout>,
+}
+
+impl<U, S, URC, H> AtModem<U, S, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            shared: Shared::new(),
+            controller: ActorContext::new(AtController::new()).with_name("at_controller"),
+            ingress: ActorContext::new(AtIngress::new()).with_name("at_ingress"),
+            timeout: ActorContext::new(AtTimeout::new()).with_name("at_timeout"),
+        }
+    }
+}
+
+impl<U, S, URC, H> Package for AtModem<U, S, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    type Primary = AtController<U, S>;
+    type Configuration = (Address<U>, Address<S>, Address<H>);
+
+    fn mount(
+        &'static self,
+        config: Self::Configuration,
+        supervisor: &mut Supervisor,
+    ) -> Address<Self::Primary> {
+        let timeout = self.timeout.mount(&self.shared, supervisor);
+        self.ingress
+            .mount((&self.shared, config.0, config.2), supervisor);
+        self.controller
+            .mount((&self.shared, config.0, config.1, timeout), supervisor)
+    }
+
+    fn primary(&'static self) -> Address<Self::Primary> {
+        self.controller.address()
+    }
+}
+
+/// Executes commands, one at a time.
+pub struct AtController<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    shared: Option<&'static Shared>,
+    uart: Option<Address<U>>,
+    scheduler: Option<Address<S>>,
+    timeout: Option<Address<AtTimeout>>,
+    id: u32,
+}
+
+impl<U, S> AtController<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            shared: None,
+            uart: None,
+            scheduler: None,
+            timeout: None,
+            id: 0,
+        }
+    }
+
+    async fn execute<C: Command>(&mut self, command: &C) -> Result<C::Response, AtError> {
+        let shared = self.shared.unwrap();
+        // Whatever is left over, such as the rest of a response that timed out, is stale.
+        while shared.events.try_recv().is_some() {}
+
+        let line = encode(command)?;
+        log::debug!("[AT] {}", line.as_str());
+        self.id = self.id.wrapping_add(1);
+        shared
+            .expect_prompt
+            .store(command.payload().is_some(), Ordering::Release);
+        shared.data_header.set(command.data_header());
+        shared.pending.store(true, Ordering::Release);
+        let result = self.exchange(command, line.as_bytes()).await;
+        shared.pending.store(false, Ordering::Release);
+        shared.data_header.set(None);
+        shared.expect_prompt.store(false, Ordering::Release);
+        result
+    }
+
+    async fn exchange<C: Command>(
+        &mut self,
+        command: &C,
+        line: &[u8],
+    ) -> Result<C::Response, AtError> {
+        let uart = self.uart.unwrap();
+        uart.write(line).await?;
+        uart.write(b"\r\n").await?;
+        let timeout = self
+            .scheduler
+            .unwrap()
+            .try_schedule(Schedule::new(
+                command.timeout(),
+                Expire(self.id),
+                self.timeout.unwrap(),
+            ))
+            .await?;
+        let result = self.respond(command).await;
+        // so that it does not hold on to a slot of the scheduler for as long as it would wait
+        timeout.cancel();
+        result
+    }
+
+    /// Collect the response to `command`, until its final result or timeout.
+    async fn respond<C: Command>(&self, command: &C) -> Result<C::Response, AtError> {
+        let shared = self.shared.unwrap();
+        let uart = self.uart.unwrap();
+        let mut exchange = Exchange::new(command);
+        let mut payload = command.payload();
+        loop {
+            match shared.events.recv().await {
+                Event::Line(line) => {
+                    if let Some(result) = exchange.digest(&line) {
+                        return result;
+                    }
+                }
+                Event::Data(data) => exchange.data(&data),
+                Event::Prompt => {
+                    if let Some(payload) = payload.take() {
+                        uart.write(payload).await?;
+                    }
+                }
+                Event::Timeout(id) if id == self.id => {
+                    return Err(AtError::Timeout);
+                }
+                Event::Timeout(_) => {}
+            }
+        }
+    }
+}
+
+impl<U, S> Actor for AtController<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    type Configuration = (&'static Shared, Address<U>, Address<S>, Address<AtTimeout>);
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config.0);
+        self.uart.replace(config.1);
+        self.scheduler.replace(config.2);
+        self.timeout.replace(config.3);
+    }
+}
+
+impl<U, S, C> RequestHandler<C> for AtController<U, S>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+    C: Command,
+{
+    type Response = Result<C::Response, AtError>;
+
+    fn on_request(mut self, command: C) -> Response<Self, Self::Response> {
+        unsafe {
+            Response::defer_unchecked(async move {
+                let result = self.execute(&command).await;
+                (self, result)
+            })
+        }
+    }
+}
+
+impl<U, S> Address<AtController<U, S>>
+where
+    U: UartReader + UartWriter + 'static,
+    S: Scheduler + 'static,
+{
+    /// Execute `command`, waiting for its response or timeout.
+    ///
+    /// # Panics
+    ///
+    /// While the command may borrow non-static data, such as its payload, the future must
+    /// be awaited to completion, or else the system will panic.
+    pub async fn execute<C: Command>(&self, command: C) -> Result<C::Response, AtError> {
+        self.request_panicking(command).await
+    }
+}
+
+/// Reads lines from the UART, routing URCs to the handler and other lines to the command being
+/// executed. Lines arriving while no command is executed are dropped.
+pub struct AtIngress<U, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    shared: Option<&'static Shared>,
+    uart: Option<Address<U>>,
+    handler: Option<Address<H>>,
+    _urc: PhantomData<URC>,
+}
+
+impl<U, URC, H> AtIngress<U, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    pub fn new() -> Self {
+        Self {
+            shared: None,
+            uart: None,
+            handler: None,
+            _urc: PhantomData,
+        }
+    }
+
+    async fn route(&self, line: &[u8]) {
+        let shared = self.shared.unwrap();
+        let empty = trim(line).is_empty();
+        if !empty {
+            if let Some(urc) = URC::parse(line) {
+                self.handler.unwrap().notify(urc);
+                return;
+            }
+        }
+        if shared.pending.load(Ordering::Acquire) {
+            let mut buf = Vec::new();
+            // Lines are no longer than the buffer of the codec.
+            buf.extend_from_slice(line).ok();
+            shared.events.send(Event::Line(buf)).await;
+        } else if !empty {
+            log::warn!("[AT] dropping line {:?}", core::str::from_utf8(line));
+        }
+    }
+
+    /// Pass on the header at the start of `line` and the `len` bytes of raw data following it,
+    /// reading those not in `line` yet, and route the rest of `line` after the data.
+    async fn read_data(&self, line: &[u8], header: usize, len: usize) -> Result<(), UartError> {
+        let shared = self.shared.unwrap();
+        let end = line.len().min(header + len);
+        let mut data = Vec::new();
+        data.extend_from_slice(&line[..end]).ok();
+        shared.events.send(Event::Data(data)).await;
+
+        let mut remaining = header + len - end;
+        while remaining > 0 {
+            let mut data: Vec<u8, LineLength> = Vec::new();
+            data.resize_default(remaining.min(data.capacity())).ok();
+            self.uart.unwrap().read_exact(&mut data).await?;
+            remaining -= data.len();
+            shared.events.send(Event::Data(data)).await;
+        }
+        if end < line.len() {
+            self.route(&line[end..]).await;
+        }
+        Ok(())
+    }
+}
+
+impl<U, URC, H> Actor for AtIngress<U, URC, H>
+where
+    U: UartReader + UartWriter + 'static,
+    URC: Urc + 'static,
+    H: NotifyHandler<URC> + 'static,
+{
+    type Configuration = (&'static Shared, Address<U>, Address<H>);
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config.0);
+        self.uart.replace(config.1);
+        self.handler.replace(config.2);
+    }
+
+    fn on_start(self) -> Completion<Self> {
+        Completion::defer(async move {
+            let shared = self.shared.unwrap();
+            let mut codec: LineCodec<U, LineLength> = LineCodec::new(self.uart.unwrap());
+            loop {
+                if shared.expect_prompt.load(Ordering::Acquire) {
+                    match codec.read_until(PROMPT).await {
+                        Ok(_) => {
+                            shared.expect_prompt.store(false, Ordering::Release);
+                            shared.events.send(Event::Prompt).await;
+                        }
+                        Err(e) => log::warn!("[AT] error waiting for prompt: {:?}", e),
+                    }
+                } else {
+                    match codec.read_until(b"\n").await {
+                        Ok(line) => match shared.data_header.get().and_then(|h| h(line)) {
+                            Some((header, len)) => {
+                                if let Err(e) = self.read_data(line, header, len).await {
+                                    log::warn!("[AT] error reading data: {:?}", e);
+                                }
+                            }
+                            None => self.route(line).await,
+                        },
+                        Err(e) => log::warn!("[AT] error reading line: {:?}", e),
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// The event the scheduler notifies `AtTimeout` with, when a command times out.
+#[derive(Copy, Clone)]
+pub struct Expire(u32);
+
+/// Receives timeouts from the scheduler on behalf of the busy `AtController`.
+pub struct AtTimeout {
+    shared: Option<&'static Shared>,
+}
+
+impl AtTimeout {
+    pub fn new() -> Self {
+        Self { shared: None }
+    }
+}
+
+impl Actor for AtTimeout {
+    type Configuration = &'static Shared;
+
+    fn on_mount(&mut self, _: Address<Self>, config: Self::Configuration) {
+        self.shared.replace(config);
+    }
+}
+
+impl NotifyHandler<Expire> for AtTimeout {
+    fn on_notify(self, message: Expire) -> Completion<Self> {
+        // Timeouts of commands that completed meanwhile are dropped by the controller, or
+        // here if its queue is full.
+        self.shared
+            .unwrap()
+            .events
+            .try_send(Event::Timeout(message.0))
+            .ok();
+        Completion::immediate(self)
+    }
+}
@@ -0,0 +1,94 @@
+//! Replays the transcript of a modem session to an `AtModem`, to test commands and URCs
+//! through the actors a device runs.
+
+extern crate std;
+
+use super::{AtController, AtError, AtModem, Command, Urc};
+use crate::domain::time::duration::Milliseconds;
+use crate::driver::uart::mock::{MockUart, Step, Transcript};
+use crate::prelude::*;
+use crate::system::mock::{self, MockRuntime, MockTimer};
+use core::cell::RefCell;
+use std::boxed::Box as StdBox;
+use std::collections::VecDeque;
+use std::sync::MutexGuard;
+
+/// Collects the URCs routed by the modem.
+struct Urcs<URC: 'static>(&'static RefCell<VecDeque<URC>>);
+
+impl<URC> Actor for Urcs<URC> {
+    type Configuration = ();
+}
+
+impl<URC> NotifyHandler<URC> for Urcs<URC> {
+    fn on_notify(self, message: URC) -> Completion<Self> {
+        self.0.borrow_mut().push_back(message);
+        Completion::immediate(self)
+    }
+}
+
+/// An `AtModem` over a `MockUart` playing the modem of a transcript, which panics where the
+/// driver deviates from it. Holds the `mock::lock()` while in use.
+pub(crate) struct Replay<URC>
+where
+    URC: Urc + 'static,
+{
+    runtime: MockRuntime,
+    modem: Address<AtController<MockUart, MockTimer>>,
+    transcript: Transcript,
+    urcs: &'static RefCell<VecDeque<URC>>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl<URC> Replay<URC>
+where
+    URC: Urc + 'static,
+{
+    pub(crate) fn new(script: &'static [Step]) -> Self {
+        let lock = mock::lock();
+        let mut runtime = MockRuntime::new();
+        let uart = MockUart::new(script);
+        let transcript = uart.transcript();
+        let uart = runtime.mount(ActorContext::new(uart), ());
+        let urcs = StdBox::leak(StdBox::new(RefCell::new(VecDeque::new())));
+        let handler = runtime.mount(ActorContext::new(Urcs(urcs)), ());
+        let timer = runtime.timer();
+        let modem: AtModem<MockUart, MockTimer, URC, Urcs<URC>> = AtModem::new();
+        let modem = runtime.mount_package(modem, (uart, timer, handler));
+        runtime.start();
+        Self {
+            runtime,
+            modem,
+            transcript,
+            urcs,
+            _lock: lock,
+        }
+    }
+
+    /// Execute `command`, failing with `Timeout` if the modem stops answering before the final
+    /// result.
+    pub(crate) fn execute<C: Command>(&mut self, command: C) -> Result<C::Response, AtError> {
+        let modem = self.modem;
+        self.runtime.block_on(modem.execute(command))
+    }
+
+    /// Let the modem receive what it is sent while no command is pending.
+    pub(crate) fn idle(&mut self) {
+        self.runtime.run();
+    }
+
+    /// The oldest URC received, if any.
+    pub(crate) fn urc(&mut self) -> Option<URC> {
+        self.urcs.borrow_mut().pop_front()
+    }
+
+    /// Virtual time passed since the session started.
+    pub(crate) fn elapsed(&self) -> Milliseconds {
+        self.runtime.elapsed()
+    }
+
+    /// Check that the whole transcript was played.
+    pub(crate) fn finish(&self) {
+        self.transcript.finish();
+    }
+}
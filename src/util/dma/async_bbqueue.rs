@@ -135,6 +135,8 @@ where
 pub enum Error {
     BufferFull,
     BufferEmpty,
+    /// The read buffer filled up before the delimiter was read.
+    DelimiterNotFound,
     Other,
 }
 
@@ -324,6 +326,91 @@ where
     }
 }
 
+pub struct AsyncReadUntil<N>
+where
+    N: ArrayLength<u8> + 'static,
+{
+    inner: &'static Inner<'static, N>,
+    buffer: &'static mut [u8],
+    delimiter: &'static [u8],
+    pos: usize,
+    cancelled: bool,
+}
+
+impl<N> AsyncReadUntil<N>
+where
+    N: ArrayLength<u8> + 'static,
+{
+    unsafe fn new<'a>(inner: &'static Inner<N>, buffer: &'a mut [u8], delimiter: &'a [u8]) -> Self {
+        Self {
+            inner,
+            buffer: core::mem::transmute::<&'a mut [u8], &'static mut [u8]>(buffer),
+            delimiter: core::mem::transmute::<&'a [u8], &'static [u8]>(delimiter),
+            pos: 0,
+            cancelled: false,
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelled = true;
+    }
+
+    /// Copy bytes up to the delimiter, returning how many were taken and whether the
+    /// delimiter was found.
+    fn copy(&mut self, data: &[u8]) -> (usize, bool) {
+        let mut taken = 0;
+        for b in data {
+            if self.pos == self.buffer.len() {
+                break;
+            }
+            self.buffer[self.pos] = *b;
+            self.pos += 1;
+            taken += 1;
+            if self.buffer[..self.pos].ends_with(self.delimiter) {
+                return (taken, true);
+            }
+        }
+        (taken, false)
+    }
+}
+
+impl<N> Future for AsyncReadUntil<N>
+where
+    N: ArrayLength<u8> + 'static,
+{
+    type Output = Result<usize, Error>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = self.inner;
+        let mut consumer = inner.consumer.as_ref().unwrap().borrow_mut();
+        if self.cancelled {
+            return Poll::Ready(Ok(self.pos));
+        }
+        loop {
+            match consumer.read() {
+                Ok(grant) => {
+                    // Bytes past the delimiter are left in the queue for the next read.
+                    let (taken, found) = self.copy(grant.buf());
+                    grant.release(taken);
+                    inner.notify_producer();
+                    if found {
+                        return Poll::Ready(Ok(self.pos));
+                    } else if self.pos == self.buffer.len() {
+                        return Poll::Ready(Err(Error::DelimiterNotFound));
+                    } else if let Poll::Pending = inner.poll_consumer(cx) {
+                        return Poll::Pending;
+                    }
+                }
+                Err(BBQueueError::InsufficientSize) => {
+                    if let Poll::Pending = inner.poll_consumer(cx) {
+                        return Poll::Pending;
+                    }
+                }
+                Err(e) => return Poll::Ready(Err(Error::Other)),
+            }
+        }
+    }
+}
+
 impl<N> AsyncBBConsumer<N>
 where
     N: ArrayLength<u8> + 'static,
@@ -347,6 +434,20 @@ where
     pub unsafe fn read<'a>(&self, buffer: &'a mut [u8]) -> AsyncRead<N> {
         AsyncRead::new(self.inner, buffer)
     }
+
+    /// Read from the consumer into the provided buffer, up to and including `delimiter`. The
+    /// returned future completes with the number of bytes read once the delimiter has been
+    /// read, or fails if the buffer fills up first.
+    ///
+    /// Safety: the returned future must be awaited before the provided buffer or delimiter
+    /// are dropped or reused.
+    pub unsafe fn read_until<'a>(
+        &self,
+        buffer: &'a mut [u8],
+        delimiter: &'a [u8],
+    ) -> AsyncReadUntil<N> {
+        AsyncReadUntil::new(self.inner, buffer, delimiter)
+    }
 }
 
 #[cfg(test)]
@@ -354,6 +455,7 @@ mod tests {
     extern crate std;
     use super::*;
     use futures::executor::block_on;
+    use futures::FutureExt;
     use std::sync::Once;
 
     static INIT: Once = Once::new();
@@ -393,6 +495,63 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_read_until() {
+        setup();
+        let mut queue: AsyncBBBuffer<consts::U32> = AsyncBBBuffer::new();
+        let (prod, cons) = split(&mut queue);
+
+        unsafe {
+            block_on(prod.write(b"OK\r\n> ")).unwrap();
+
+            let mut rx_buf = [0; 8];
+            let len = block_on(cons.read_until(&mut rx_buf, b"\r\n")).unwrap();
+            assert_eq!(b"OK\r\n", &rx_buf[..len]);
+
+            // the rest stays queued
+            let len = block_on(cons.read_until(&mut rx_buf, b"> ")).unwrap();
+            assert_eq!(b"> ", &rx_buf[..len]);
+        }
+    }
+
+    #[test]
+    fn test_read_until_across_writes() {
+        setup();
+        let mut queue: AsyncBBBuffer<consts::U32> = AsyncBBBuffer::new();
+        let (prod, cons) = split(&mut queue);
+
+        unsafe {
+            let mut rx_buf = [0; 16];
+            let mut rx_future = cons.read_until(&mut rx_buf, b"\r\n");
+            block_on(prod.write(b"+CSQ: 2")).unwrap();
+            assert!((&mut rx_future).now_or_never().is_none());
+            block_on(prod.write(b"0,99\r")).unwrap();
+            assert!((&mut rx_future).now_or_never().is_none());
+            block_on(prod.write(b"\n")).unwrap();
+            let len = block_on(rx_future).unwrap();
+            assert_eq!(b"+CSQ: 20,99\r\n", &rx_buf[..len]);
+        }
+    }
+
+    #[test]
+    fn test_read_until_buffer_too_small() {
+        setup();
+        let mut queue: AsyncBBBuffer<consts::U32> = AsyncBBBuffer::new();
+        let (prod, cons) = split(&mut queue);
+
+        unsafe {
+            block_on(prod.write(b"ERROR\r\n")).unwrap();
+            let mut rx_buf = [0; 4];
+            let result = block_on(cons.read_until(&mut rx_buf, b"\r\n"));
+            assert!(matches!(result, Err(Error::DelimiterNotFound)));
+            assert_eq!(b"ERRO", &rx_buf);
+
+            // the rest of the line is read next
+            let len = block_on(cons.read_until(&mut rx_buf, b"\r\n")).unwrap();
+            assert_eq!(b"R\r\n", &rx_buf[..len]);
+        }
+    }
+
     #[test]
     fn test_interrupt_queue() {
         setup();
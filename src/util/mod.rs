@@ -1,2 +1,3 @@
-pub(crate) mod nom;
+pub mod at;
 pub(crate) mod dma;
+pub(crate) mod nom;
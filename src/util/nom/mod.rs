@@ -5,6 +5,11 @@ pub fn parse_u8(input: &[u8]) -> IResult<&[u8], u8> {
     IResult::Ok((input, atoi_u8(digits).unwrap()))
 }
 
+pub fn parse_u16(input: &[u8]) -> IResult<&[u8], u16> {
+    let (input, digits) = digit1(input)?;
+    IResult::Ok((input, atoi_usize(digits).unwrap() as u16))
+}
+
 pub fn parse_usize(input: &[u8]) -> IResult<&[u8], usize> {
     let (input, digits) = digit1(input)?;
     let num = atoi_usize(digits).unwrap();